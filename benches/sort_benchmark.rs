@@ -1,6 +1,8 @@
 use criterion::{black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
 use rand::prelude::*;
-use tilesort::{tilesort, tilesort_by_key};
+use tilesort::{
+    tilesort, tilesort_by_key, tilesort_by_key_with_config, tilesort_integers, SortConfig,
+};
 
 /// Generate data with sorted tiles of varying sizes
 fn generate_tiled_data(total_size: usize, tile_sizes: &[usize]) -> Vec<i32> {
@@ -223,6 +225,136 @@ fn bench_with_key_function(c: &mut Criterion) {
     group.finish();
 }
 
+fn bench_large_elements(c: &mut Criterion) {
+    let mut group = c.benchmark_group("large_elements");
+
+    // 256-byte records keyed by their first word
+    let size = 10_000;
+    let tile_sizes = vec![100, 1000, 5000];
+    let generate = || -> Vec<[u64; 32]> {
+        generate_tiled_data(size, &tile_sizes)
+            .into_iter()
+            .map(|key| [key as u64; 32])
+            .collect()
+    };
+
+    group.bench_function("buffered", |b| {
+        b.iter_batched(
+            generate,
+            |mut data| tilesort_by_key(black_box(&mut data), |record| record[0]),
+            BatchSize::LargeInput,
+        )
+    });
+
+    group.bench_function("indirect", |b| {
+        let config = SortConfig::new().indirect(true);
+        b.iter_batched(
+            generate,
+            |mut data| {
+                tilesort_by_key_with_config(black_box(&mut data), |record| record[0], &config)
+            },
+            BatchSize::LargeInput,
+        )
+    });
+
+    group.finish();
+}
+
+fn bench_mixed_random_segments(c: &mut Criterion) {
+    let mut group = c.benchmark_group("mixed_random_segments");
+
+    // Alternating sorted tiles and stretches of random values
+    let size = 100_000;
+    let generate = || -> Vec<i32> {
+        let tiles = generate_tiled_data(size / 2, &[5000]);
+        let random = generate_random_data(size / 2);
+        tiles
+            .chunks(5000)
+            .zip(random.chunks(5000))
+            .flat_map(|(tile, noise)| tile.iter().chain(noise).copied())
+            .collect()
+    };
+
+    group.bench_function("tilesort", |b| {
+        b.iter_batched(
+            generate,
+            |mut data| tilesort(black_box(&mut data)),
+            BatchSize::LargeInput,
+        )
+    });
+
+    group.bench_function("tilesort_integers", |b| {
+        b.iter_batched(
+            generate,
+            |mut data| tilesort_integers(black_box(&mut data)),
+            BatchSize::LargeInput,
+        )
+    });
+
+    group.finish();
+}
+
+fn bench_fragmented_index(c: &mut Criterion) {
+    let mut group = c.benchmark_group("fragmented_index");
+
+    // Two interleaved ascending runs: every element ends up in its own tile,
+    // so this measures tile insertion into a very large index
+    for size in [10_000, 100_000, 1_000_000].iter() {
+        let half = *size / 2;
+        let data: Vec<i32> = (0..half)
+            .map(|i| i * 2)
+            .chain((0..half).map(|i| i * 2 + 1))
+            .collect();
+
+        group.bench_with_input(BenchmarkId::new("tilesort", size), &data, |b, data| {
+            b.iter_batched(
+                || data.clone(),
+                |mut data| tilesort(black_box(&mut data)),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_duplicate_heavy(c: &mut Criterion) {
+    let mut group = c.benchmark_group("duplicate_heavy");
+
+    // Status codes, mostly 200: runs are short and the index fills with long
+    // stretches of tiles sharing a key
+    let codes = [200u16, 200, 200, 200, 200, 301, 404, 500];
+    for size in [10_000, 100_000, 1_000_000].iter() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let data: Vec<u16> = (0..*size)
+            .map(|_| codes[rng.random_range(0..codes.len())])
+            .collect();
+
+        for (name, config) in [
+            ("tilesort", SortConfig::new()),
+            ("tilesort_unstable", SortConfig::new().unstable()),
+        ] {
+            group.bench_with_input(BenchmarkId::new(name, size), &data, |b, data| {
+                b.iter_batched(
+                    || data.clone(),
+                    |mut data| tilesort_by_key_with_config(black_box(&mut data), |&c| c, &config),
+                    BatchSize::LargeInput,
+                )
+            });
+        }
+
+        group.bench_with_input(BenchmarkId::new("std_sort", size), &data, |b, data| {
+            b.iter_batched(
+                || data.clone(),
+                |mut data| data.sort(),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
 fn bench_realistic_workload(c: &mut Criterion) {
     let mut group = c.benchmark_group("realistic_workload");
 
@@ -256,6 +388,10 @@ criterion_group!(
     bench_hybrid_tiles,
     bench_random_data,
     bench_with_key_function,
+    bench_large_elements,
+    bench_mixed_random_segments,
+    bench_fragmented_index,
+    bench_duplicate_heavy,
     bench_realistic_workload,
 );
 
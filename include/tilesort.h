@@ -0,0 +1,36 @@
+/* C interface to tilesort, built with `cargo build --release --features ffi`.
+ * Regenerate with `cbindgen --config cbindgen.toml --output include/tilesort.h`. */
+
+#ifndef TILESORT_H
+#define TILESORT_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Comparison callback: negative, zero or positive as `a` sorts before, with,
+ * or after `b`. `context` is passed through unchanged. */
+typedef int (*TilesortCompare)(const void *a, const void *b, void *context);
+
+/* Sort `len` 64-bit integers at `data` in ascending order. */
+void tilesort_i64(int64_t *data, size_t len);
+
+/* Sort `len` doubles at `data` in ascending IEEE 754 total order. */
+void tilesort_f64_total(double *data, size_t len);
+
+/* Stably sort `len` records of `size` bytes each at `base` with `compare`.
+ * `compare` must not unwind (throw) and must define a total order. */
+void tilesort_by_comparator(void *base,
+                            size_t len,
+                            size_t size,
+                            TilesortCompare compare,
+                            void *context);
+
+#ifdef __cplusplus
+} /* extern "C" */
+#endif
+
+#endif /* TILESORT_H */
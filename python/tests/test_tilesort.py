@@ -1,5 +1,9 @@
 """Tests for the tilesort Python package."""
 
+import array
+
+import pytest
+
 import tilesort
 
 
@@ -217,3 +221,74 @@ class TestEdgeCases:
         data = [3, None, 1, None, 2]
         tilesort.sort(data, key=lambda x: float("inf") if x is None else x)
         assert data == [1, 2, 3, None, None]
+
+
+class TestKeyErrors:
+    """Exceptions raised by the key function."""
+
+    def test_sort_key_error_propagates(self):
+        data = ["3", "4", "x", "1"]
+        with pytest.raises(ValueError):
+            tilesort.sort(data, key=int)
+        assert data == ["3", "4", "x", "1"]  # Original order kept
+
+    def test_sorted_key_error_propagates(self):
+        with pytest.raises(KeyError):
+            tilesort.sorted([{"a": 1}, {"b": 2}], key=lambda d: d["a"])
+
+
+class TestSortArrays:
+    """In-place sorting of arrays through the buffer protocol."""
+
+    def test_int_array(self):
+        data = array.array("q", [5, 6, 7, 1, 2, 3])
+        tilesort.sort(data)
+        assert data.tolist() == [1, 2, 3, 5, 6, 7]
+
+    def test_unsigned_array_reverse(self):
+        data = array.array("B", [1, 2, 3, 7, 8, 9])
+        tilesort.sort(data, reverse=True)
+        assert data.tolist() == [9, 8, 7, 3, 2, 1]
+
+    def test_float_array_total_order(self):
+        data = array.array("d", [2.5, float("inf"), -1.0, 0.0, -0.0])
+        tilesort.sort(data)
+        assert [str(x) for x in data] == ["-1.0", "-0.0", "0.0", "2.5", "inf"]
+
+    def test_array_with_key_rejected(self):
+        data = array.array("i", [3, 1, 2])
+        with pytest.raises(TypeError):
+            tilesort.sort(data, key=abs)
+        assert data.tolist() == [3, 1, 2]
+
+    def test_read_only_buffer_rejected(self):
+        with pytest.raises(TypeError):
+            tilesort.sort(b"cab")
+
+    def test_unsupported_type_rejected(self):
+        with pytest.raises(TypeError):
+            tilesort.sort((3, 1, 2))
+
+    def test_numpy_array_sorted_in_place(self):
+        np = pytest.importorskip("numpy")
+        data = np.concatenate([np.arange(100, 200), np.arange(0, 100)])
+        view = data[:]
+        tilesort.sort(data)
+        assert (view == np.arange(200)).all()
+
+    def test_numpy_float_array(self):
+        np = pytest.importorskip("numpy")
+        data = np.array([3.0, 4.0, 1.0, 2.0], dtype=np.float32)
+        tilesort.sort(data, reverse=True)
+        assert data.tolist() == [4.0, 3.0, 2.0, 1.0]
+
+    def test_numpy_non_contiguous_rejected(self):
+        np = pytest.importorskip("numpy")
+        data = np.arange(10)[::-2]
+        with pytest.raises(TypeError):
+            tilesort.sort(data)
+
+    def test_numpy_two_dimensional_rejected(self):
+        np = pytest.importorskip("numpy")
+        with pytest.raises(ValueError):
+            tilesort.sort(np.zeros((2, 2)))
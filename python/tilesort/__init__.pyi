@@ -1,6 +1,6 @@
 """Type stubs for tilesort package."""
 
-from typing import Callable, TypeVar, overload
+from typing import Any, Callable, TypeVar, overload
 
 _T = TypeVar("_T")
 _KT = TypeVar("_KT")
@@ -20,6 +20,13 @@ def sort(
     reverse: bool = False,
 ) -> None: ...
 @overload
+def sort(
+    list: Any,
+    *,
+    key: None = None,
+    reverse: bool = False,
+) -> None: ...
+@overload
 def sorted(
     list: list[_T],
     *,
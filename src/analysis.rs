@@ -0,0 +1,299 @@
+//! Presortedness statistics.
+
+use std::ops::RangeInclusive;
+
+use crate::config::SortConfig;
+use crate::key_extractor::KeyExtractor;
+use crate::opcount;
+use crate::tile_index::precedes;
+
+/// Count the pairs of elements whose keys are out of ascending order.
+///
+/// An inversion is a pair of positions `i < j` whose keys compare
+/// `key(i) > key(j)`; equal keys are not inverted. Sorted input has none and
+/// strictly descending input has `n * (n - 1) / 2`. Each key is extracted
+/// once (keys [`IdentityKey`](crate::IdentityKey) can lend are not extracted
+/// at all), and the count is found by merging the input's ascending runs, so
+/// presorted input is cheap to measure.
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::{count_inversions, IdentityKey};
+///
+/// assert_eq!(count_inversions(&[1, 2, 3, 4], IdentityKey), 0);
+/// assert_eq!(count_inversions(&[3, 1, 2], IdentityKey), 2);
+/// assert_eq!(count_inversions(&["bb", "a", "ccc"], |s: &&str| s.len()), 1);
+/// ```
+pub fn count_inversions<T, K, E>(data: &[T], key_extractor: E) -> u64
+where
+    K: Ord,
+    E: KeyExtractor<T, K>,
+{
+    match key_extractor.borrowed_keys(data) {
+        Some(keys) => count_key_inversions(keys),
+        None => {
+            let keys: Vec<K> = data
+                .iter()
+                .map(|element| opcount::extract_key(&key_extractor, element))
+                .collect();
+            count_key_inversions(&keys)
+        }
+    }
+}
+
+/// Inversions of `keys`, by a natural merge sort of their positions.
+fn count_key_inversions<K: Ord>(keys: &[K]) -> u64 {
+    // Ends of the ascending runs, each of which has no inversions
+    let mut run_ends: Vec<usize> = (1..keys.len())
+        .filter(|&idx| precedes(&keys[idx], &keys[idx - 1], false))
+        .collect();
+    if run_ends.is_empty() {
+        return 0;
+    }
+    run_ends.push(keys.len());
+
+    let mut positions: Vec<usize> = (0..keys.len()).collect();
+    let mut merged = Vec::with_capacity(keys.len());
+    let mut inversions = 0;
+    while run_ends.len() > 1 {
+        let mut start = 0;
+        let mut next_ends = Vec::with_capacity((run_ends.len() + 1) / 2);
+        for pair in run_ends.chunks(2) {
+            let end = *pair.last().expect("chunks are never empty");
+            if let [mid, end] = *pair {
+                inversions += merge(
+                    keys,
+                    &positions[start..mid],
+                    &positions[mid..end],
+                    &mut merged,
+                );
+                positions[start..end].copy_from_slice(&merged);
+                merged.clear();
+            }
+            next_ends.push(end);
+            start = end;
+        }
+        run_ends = next_ends;
+    }
+    inversions
+}
+
+/// Merge two runs of positions sorted by key into `merged`, returning the
+/// number of pairs across the two that are inverted.
+fn merge<K: Ord>(keys: &[K], left: &[usize], right: &[usize], merged: &mut Vec<usize>) -> u64 {
+    let (mut l, mut r) = (0, 0);
+    let mut inversions = 0;
+    while l < left.len() && r < right.len() {
+        if precedes(&keys[right[r]], &keys[left[l]], false) {
+            // Precedes every remaining element of the left run
+            inversions += (left.len() - l) as u64;
+            merged.push(right[r]);
+            r += 1;
+        } else {
+            merged.push(left[l]);
+            l += 1;
+        }
+    }
+    merged.extend_from_slice(&left[l..]);
+    merged.extend_from_slice(&right[r..]);
+    inversions
+}
+
+/// Distribution of the lengths of a slice's runs, as found by the scan.
+///
+/// Created by [`run_length_histogram`]. Runs are counted in power-of-two
+/// buckets: bucket `i` holds the runs of `2^i` to `2^(i+1) - 1` elements.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Histogram {
+    buckets: Vec<usize>,
+    runs: usize,
+    elements: usize,
+    longest: usize,
+}
+
+impl Histogram {
+    fn record(&mut self, len: usize) {
+        let bucket = (usize::BITS - 1 - len.leading_zeros()) as usize;
+        if self.buckets.len() <= bucket {
+            self.buckets.resize(bucket + 1, 0);
+        }
+        self.buckets[bucket] += 1;
+        self.runs += 1;
+        self.elements += len;
+        self.longest = self.longest.max(len);
+    }
+
+    /// Number of runs; `1` means the slice is already sorted.
+    pub fn runs(&self) -> usize {
+        self.runs
+    }
+
+    /// Number of elements scanned.
+    pub fn elements(&self) -> usize {
+        self.elements
+    }
+
+    /// Length of the longest run, or `0` for an empty slice.
+    pub fn longest(&self) -> usize {
+        self.longest
+    }
+
+    /// Mean run length, or `0.0` for an empty slice.
+    pub fn mean(&self) -> f64 {
+        if self.runs == 0 {
+            0.0
+        } else {
+            self.elements as f64 / self.runs as f64
+        }
+    }
+
+    /// The non-empty buckets in increasing order of length, each as the
+    /// range of run lengths it covers and the number of runs in it.
+    pub fn buckets(&self) -> impl Iterator<Item = (RangeInclusive<usize>, usize)> + '_ {
+        self.buckets
+            .iter()
+            .enumerate()
+            .filter(|&(_, &count)| count > 0)
+            .map(|(bucket, &count)| {
+                let low = 1usize << bucket;
+                (low..=low.saturating_mul(2) - 1, count)
+            })
+    }
+}
+
+/// Measure the lengths of the ascending runs of `data`.
+///
+/// These are the tiles the scan starts from, before any splitting. Each key
+/// is extracted once and only the previous one is kept, so measuring needs
+/// no memory proportional to the input.
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::{run_length_histogram, IdentityKey};
+///
+/// let data = [1, 2, 3, 4, 0, 1, 0, 5, 6, 7, 8, 9, 10, 11];
+/// let histogram = run_length_histogram(&data, IdentityKey);
+/// assert_eq!(histogram.runs(), 3);
+/// assert_eq!(histogram.longest(), 8);
+/// let buckets: Vec<_> = histogram.buckets().collect();
+/// assert_eq!(buckets, vec![(2..=3, 1), (4..=7, 1), (8..=15, 1)]);
+/// ```
+pub fn run_length_histogram<T, K, E>(data: &[T], key_extractor: E) -> Histogram
+where
+    K: Ord,
+    E: KeyExtractor<T, K>,
+{
+    run_length_histogram_with_config(data, key_extractor, &SortConfig::new())
+}
+
+/// Like [`run_length_histogram`], for runs in the direction given by
+/// `config`.
+pub fn run_length_histogram_with_config<T, K, E>(
+    data: &[T],
+    key_extractor: E,
+    config: &SortConfig,
+) -> Histogram
+where
+    K: Ord,
+    E: KeyExtractor<T, K>,
+{
+    let reverse = config.order.is_descending();
+    let mut histogram = Histogram::default();
+    let mut run_start = 0;
+    let mut record_boundary = |idx: usize| {
+        histogram.record(idx - run_start);
+        run_start = idx;
+    };
+
+    match key_extractor.borrowed_keys(data) {
+        Some(keys) => {
+            for idx in 1..keys.len() {
+                if precedes(&keys[idx], &keys[idx - 1], reverse) {
+                    record_boundary(idx);
+                }
+            }
+        }
+        None => {
+            let mut keys = data
+                .iter()
+                .map(|element| opcount::extract_key(&key_extractor, element));
+            if let Some(mut prev) = keys.next() {
+                for (idx, key) in (1..).zip(keys) {
+                    if precedes(&key, &prev, reverse) {
+                        record_boundary(idx);
+                    }
+                    prev = key;
+                }
+            }
+        }
+    }
+    if !data.is_empty() {
+        record_boundary(data.len());
+    }
+    histogram
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn brute_force(keys: &[u32]) -> u64 {
+        let mut count = 0;
+        for i in 0..keys.len() {
+            for j in i + 1..keys.len() {
+                if keys[i] > keys[j] {
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+
+    #[test]
+    fn test_matches_brute_force() {
+        let mut state = 7u32;
+        for len in 0..60 {
+            let keys: Vec<u32> = (0..len)
+                .map(|_| {
+                    state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                    (state >> 16) % 10
+                })
+                .collect();
+            assert_eq!(
+                count_key_inversions(&keys),
+                brute_force(&keys),
+                "{:?}",
+                keys
+            );
+        }
+    }
+
+    #[test]
+    fn test_histogram_buckets() {
+        let mut histogram = Histogram::default();
+        for len in [1, 1, 2, 3, 4, 9, 1000] {
+            histogram.record(len);
+        }
+        let buckets: Vec<_> = histogram.buckets().collect();
+        assert_eq!(
+            buckets,
+            vec![
+                (1..=1, 2),
+                (2..=3, 2),
+                (4..=7, 1),
+                (8..=15, 1),
+                (512..=1023, 1)
+            ]
+        );
+        assert_eq!(histogram.runs(), 7);
+        assert_eq!(histogram.elements(), 1020);
+    }
+
+    #[test]
+    fn test_descending() {
+        let keys: Vec<u32> = (0..100).rev().collect();
+        assert_eq!(count_key_inversions(&keys), 100 * 99 / 2);
+    }
+}
@@ -0,0 +1,123 @@
+//! Sorting fixed-size arrays without heap allocation.
+
+use crate::config::SortConfig;
+use crate::container::apply_permutation;
+use crate::opcount;
+use crate::sorter;
+
+/// Largest array sorted with scratch space on the stack. Longer arrays use the
+/// regular tile index, whose cost per element is lower once there are many
+/// tiles.
+pub(crate) const STACK_SORT_MAX_LEN: usize = 64;
+
+/// Stable ascending sort of `arr`, allocating nothing when
+/// `N <= STACK_SORT_MAX_LEN`.
+pub(crate) fn sort_array<T: Ord, const N: usize>(arr: &mut [T; N]) {
+    if N <= 1 {
+        return;
+    }
+    if N > STACK_SORT_MAX_LEN {
+        sorter::tilesort_container_impl(arr.as_mut_slice(), &SortConfig::new());
+        return;
+    }
+
+    // Start of every tile, in input order, then the next unmerged position of
+    // each. A tile ends where the next one starts.
+    let mut positions = [0usize; N];
+    let mut tiles = 1;
+    for idx in 1..N {
+        opcount::comparisons(1);
+        if arr[idx] < arr[idx - 1] {
+            positions[tiles] = idx;
+            tiles += 1;
+        }
+    }
+    if tiles == 1 {
+        return;
+    }
+    let mut ends = [N; N];
+    ends[..tiles - 1].copy_from_slice(&positions[1..tiles]);
+
+    // Merge the tile heads; ties go to the earliest tile, keeping the sort
+    // stable
+    let mut sources = [0usize; N];
+    for source in sources.iter_mut() {
+        let mut best: Option<usize> = None;
+        for tile in 0..tiles {
+            if positions[tile] == ends[tile] {
+                continue;
+            }
+            best = match best {
+                Some(current) => {
+                    opcount::comparisons(1);
+                    if arr[positions[tile]] < arr[positions[current]] {
+                        Some(tile)
+                    } else {
+                        Some(current)
+                    }
+                }
+                None => Some(tile),
+            };
+        }
+        let tile = best.expect("every output position has an unmerged element");
+        *source = positions[tile];
+        positions[tile] += 1;
+    }
+
+    apply_permutation(arr.as_mut_slice(), sources);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sort_array_tiles() {
+        let mut arr = [7, 8, 9, 1, 2, 3, 4, 5, 6];
+        sort_array(&mut arr);
+        assert_eq!(arr, [1, 2, 3, 4, 5, 6, 7, 8, 9]);
+    }
+
+    /// Ordered by the key alone, with a tag recording input position.
+    #[derive(Debug, Clone, Copy)]
+    struct Tagged(u8, char);
+
+    impl PartialEq for Tagged {
+        fn eq(&self, other: &Self) -> bool {
+            self.0 == other.0
+        }
+    }
+
+    impl Eq for Tagged {}
+
+    impl PartialOrd for Tagged {
+        fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Tagged {
+        fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+            self.0.cmp(&other.0)
+        }
+    }
+
+    #[test]
+    fn test_sort_array_stable() {
+        let mut arr = [(2, 'a'), (1, 'b'), (2, 'c'), (1, 'd')].map(|(k, t)| Tagged(k, t));
+        sort_array(&mut arr);
+        assert_eq!(arr.map(|t| t.1), ['b', 'd', 'a', 'c']);
+    }
+
+    #[test]
+    fn test_sort_array_above_stack_limit() {
+        let mut arr = [0u32; STACK_SORT_MAX_LEN + 16];
+        for (value, slot) in (0..).zip(arr.iter_mut()) {
+            *slot = (value * 37) % 101;
+        }
+        let mut expected = arr;
+        expected.sort();
+        sort_array(&mut arr);
+        assert_eq!(arr, expected);
+    }
+}
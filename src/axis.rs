@@ -0,0 +1,117 @@
+//! Sorting the lanes of `ndarray` arrays along an axis.
+
+use std::mem;
+
+use ndarray::{ArrayBase, ArrayView, Axis, Data, DataMut, Ix2, RemoveAxis, Zip};
+
+use crate::config::SortConfig;
+use crate::container::permute_with;
+use crate::sorter;
+
+/// Index along `axis` of the subview at each sorted position, ordering the
+/// subviews by the key `key_fn` computes from each.
+///
+/// For an `Array2` and `Axis(0)` the subviews are the rows, so this is the
+/// order of the rows sorted by key; the array is left untouched. Rows that
+/// arrive nearly in order, such as samples with slightly shuffled timestamps,
+/// form long tiles and are ordered with few comparisons. The order is stable.
+///
+/// # Panics
+///
+/// Panics if `axis` is out of bounds.
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::{array, Axis};
+///
+/// let samples = array![[20, 7], [10, 3], [30, 9]];
+/// let order = tilesort::sorted_axis_indices(&samples, Axis(0), |row| row[0]);
+/// assert_eq!(order, vec![1, 0, 2]);
+/// assert_eq!(samples.select(Axis(0), &order), array![[10, 3], [20, 7], [30, 9]]);
+/// ```
+pub fn sorted_axis_indices<A, S, D, K, F>(
+    array: &ArrayBase<S, D>,
+    axis: Axis,
+    key_fn: F,
+) -> Vec<usize>
+where
+    S: Data<Elem = A>,
+    D: RemoveAxis,
+    K: Ord,
+    F: Fn(ArrayView<'_, A, D::Smaller>) -> K,
+{
+    let lanes: Vec<ArrayView<'_, A, D::Smaller>> = array.axis_iter(axis).collect();
+    let key = |lane: &ArrayView<'_, A, D::Smaller>| key_fn(lane.view());
+    sorter::sorted_sources(&lanes, &key, &SortConfig::new())
+}
+
+/// Sort the subviews of `array` along `axis` in place, by the key `key_fn`
+/// computes from each.
+///
+/// The order is found as by [`sorted_axis_indices`] and applied by swapping
+/// whole subviews, so elements need not be `Clone`.
+///
+/// # Panics
+///
+/// Panics if `axis` is out of bounds.
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::{array, Axis};
+///
+/// // Sort the columns by their sum
+/// let mut matrix = array![[3, 1, 2], [3, 1, 0]];
+/// tilesort::sort_axis_by_key(&mut matrix, Axis(1), |column| column.sum());
+/// assert_eq!(matrix, array![[1, 2, 3], [1, 0, 3]]);
+/// ```
+pub fn sort_axis_by_key<A, S, D, K, F>(array: &mut ArrayBase<S, D>, axis: Axis, key_fn: F)
+where
+    S: DataMut<Elem = A>,
+    D: RemoveAxis,
+    K: Ord,
+    F: Fn(ArrayView<'_, A, D::Smaller>) -> K,
+{
+    let sources = sorted_axis_indices(array, axis, key_fn);
+    permute_with(sources, |a, b| swap_subviews(array, axis, a, b));
+}
+
+/// Sort the rows of a matrix in place by the key `key_fn` computes from the
+/// element in `column`.
+///
+/// # Panics
+///
+/// Panics if `column` is out of bounds and the matrix has any rows.
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::array;
+///
+/// // Readings keyed by timestamp, two of them out of order
+/// let mut readings = array![[100, 5], [101, 6], [103, 8], [102, 7]];
+/// tilesort::sort_rows_by_column(&mut readings, 0, |&time| time);
+/// assert_eq!(readings, array![[100, 5], [101, 6], [102, 7], [103, 8]]);
+/// ```
+pub fn sort_rows_by_column<A, S, K, F>(array: &mut ArrayBase<S, Ix2>, column: usize, key_fn: F)
+where
+    S: DataMut<Elem = A>,
+    K: Ord,
+    F: Fn(&A) -> K,
+{
+    sort_axis_by_key(array, Axis(0), |row| key_fn(&row[column]));
+}
+
+/// Exchange the subviews at `a` and `b` along `axis`.
+fn swap_subviews<A, S, D>(array: &mut ArrayBase<S, D>, axis: Axis, a: usize, b: usize)
+where
+    S: DataMut<Elem = A>,
+    D: RemoveAxis,
+{
+    let (low, high) = (a.min(b), a.max(b));
+    let (mut front, mut back) = array.view_mut().split_at(axis, high);
+    Zip::from(front.index_axis_mut(axis, low))
+        .and(back.index_axis_mut(axis, 0))
+        .for_each(mem::swap);
+}
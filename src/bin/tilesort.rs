@@ -0,0 +1,918 @@
+//! The `tilesort` command: sort lines of text files as `sort` does, spilling
+//! sorted runs to temporary files so that inputs larger than memory sort
+//! too.
+
+use std::cell::{Cell, RefCell};
+use std::cmp::Reverse;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::process::{self, ExitCode};
+
+use clap::{value_parser, Arg, ArgAction, ArgMatches, Command};
+use tilesort::keys::{
+    parse_human_size, EpochMillis, FieldKey, HumanNumeric, Ip, Rfc3339, SizeUnits,
+};
+use tilesort::{
+    merge_files, merge_files_by_key, sort_file_with_progress, EtaEstimator, ExternalSortConfig,
+    KeyExtractor, KeyExtractorDyn, MergeConfig, Progress, SortConfig,
+};
+
+/// Exit status for errors, as `sort` uses.
+const TROUBLE: u8 = 2;
+
+fn command() -> Command {
+    Command::new("tilesort")
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("Sort lines of text files, spilling to disk when they do not fit in memory")
+        // `-h` is `--human-numeric-sort`, as for `sort`
+        .disable_help_flag(true)
+        .arg(
+            Arg::new("files")
+                .value_name("FILE")
+                .num_args(0..)
+                .value_parser(value_parser!(PathBuf))
+                .help("Files to sort, as if concatenated; `-` or none reads standard input"),
+        )
+        .arg(
+            Arg::new("output")
+                .short('o')
+                .long("output")
+                .value_name("FILE")
+                .value_parser(value_parser!(PathBuf))
+                .help("Write the result to FILE instead of standard output"),
+        )
+        .arg(
+            Arg::new("buffer-size")
+                .short('S')
+                .long("buffer-size")
+                .value_name("SIZE")
+                .value_parser(parse_buffer_size)
+                .help("Memory for each sorted run, such as 512M; a bare number is in KiB"),
+        )
+        .arg(
+            Arg::new("temporary-directory")
+                .short('T')
+                .long("temporary-directory")
+                .value_name("DIR")
+                .value_parser(value_parser!(PathBuf))
+                .help("Directory for temporary files instead of the system's"),
+        )
+        .arg(
+            Arg::new("checkpoint-dir")
+                .long("checkpoint-dir")
+                .value_name("DIR")
+                .value_parser(value_parser!(PathBuf))
+                .conflicts_with_all(["merge", "check", "check-silent"])
+                .help("Keep the sorted runs in DIR until the sort succeeds, so it can be resumed"),
+        )
+        .arg(
+            Arg::new("resume")
+                .long("resume")
+                .action(ArgAction::SetTrue)
+                .requires("checkpoint-dir")
+                .help("Reuse the runs left in --checkpoint-dir by an interrupted sort"),
+        )
+        .arg(
+            Arg::new("key-type")
+                .long("key-type")
+                .value_name("TYPE")
+                .value_parser(parse_key_type)
+                .default_value("text")
+                .help(
+                    "Compare lines as text, by the IPv4 or IPv6 address they start with (ip), or by \
+                     the timestamp they start with (datetime[:rfc3339|epoch-millis])",
+                ),
+        )
+        .arg(
+            Arg::new("human-numeric-sort")
+                .short('h')
+                .long("human-numeric-sort")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("key-type")
+                .help("Compare sizes such as 2K and 1G that lines start with, suffix first, as `sort -h`"),
+        )
+        .arg(
+            Arg::new("key")
+                .short('k')
+                .long("key")
+                .value_name("KEYDEF")
+                .action(ArgAction::Append)
+                .help("Sort by the fields POS1[,POS2], which may end with modifiers n, f, r and h"),
+        )
+        .arg(
+            Arg::new("field-separator")
+                .short('t')
+                .long("field-separator")
+                .value_name("SEP")
+                .value_parser(parse_separator)
+                .help("Separate fields at SEP instead of at runs of blanks"),
+        )
+        .arg(
+            Arg::new("numeric-sort")
+                .short('n')
+                .long("numeric-sort")
+                .action(ArgAction::SetTrue)
+                .help("Compare the numbers keys start with"),
+        )
+        .arg(
+            Arg::new("ignore-case")
+                .short('f')
+                .long("ignore-case")
+                .action(ArgAction::SetTrue)
+                .help("Fold lowercase letters to uppercase"),
+        )
+        .arg(
+            Arg::new("reverse")
+                .short('r')
+                .long("reverse")
+                .action(ArgAction::SetTrue)
+                .help("Reverse the order"),
+        )
+        .arg(
+            Arg::new("unique")
+                .short('u')
+                .long("unique")
+                .action(ArgAction::SetTrue)
+                .help("Output only the first of lines with equal keys"),
+        )
+        .arg(
+            Arg::new("count")
+                .long("count")
+                .action(ArgAction::SetTrue)
+                .requires("unique")
+                .help(
+                    "With --unique, precede each line by the number of lines equal to it and a tab",
+                ),
+        )
+        .arg(
+            Arg::new("merge")
+                .short('m')
+                .long("merge")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["check", "check-silent"])
+                .help("Merge files already sorted by the keys given, without sorting them"),
+        )
+        .arg(
+            Arg::new("check")
+                .short('c')
+                .long("check")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["output", "count"])
+                .help("Check that the input is sorted, reporting the first line out of order"),
+        )
+        .arg(
+            Arg::new("check-silent")
+                .short('C')
+                .long("check-silent")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["check", "output", "count"])
+                .help("Like -c, but report nothing"),
+        )
+        .arg(
+            Arg::new("progress")
+                .long("progress")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["merge", "check", "check-silent"])
+                .help("Report how far the sort is and the time it has left on standard error"),
+        )
+        .arg(
+            Arg::new("help")
+                .long("help")
+                .action(ArgAction::Help)
+                .help("Print help"),
+        )
+}
+
+/// The keys a line is sorted by, earlier keys taking precedence.
+type Keys = Vec<Box<dyn KeyExtractorDyn<String>>>;
+
+/// What `--key-type` compares keys as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KeyType {
+    Text,
+    Ip,
+    Rfc3339,
+    EpochMillis,
+}
+
+/// Parse a `--key-type`: `text`, `ip`, or `datetime` with an optional
+/// `:FORMAT`, RFC 3339 by default.
+fn parse_key_type(s: &str) -> Result<KeyType, String> {
+    match s {
+        "text" => Ok(KeyType::Text),
+        "ip" => Ok(KeyType::Ip),
+        "datetime" | "datetime:rfc3339" => Ok(KeyType::Rfc3339),
+        "datetime:epoch-millis" => Ok(KeyType::EpochMillis),
+        _ => Err("expected text, ip, datetime:rfc3339 or datetime:epoch-millis".to_string()),
+    }
+}
+
+/// The options that apply to every key without modifiers of its own.
+struct Globals {
+    /// The modifier letters of `-n`, `-f`, `-r` and `-h`.
+    modifiers: String,
+    key_type: KeyType,
+    separator: Option<char>,
+}
+
+impl Globals {
+    fn new(matches: &ArgMatches) -> Self {
+        let modifiers = [
+            ("numeric-sort", 'n'),
+            ("ignore-case", 'f'),
+            ("reverse", 'r'),
+            ("human-numeric-sort", 'h'),
+        ]
+        .iter()
+        .filter(|&&(flag, _)| matches.get_flag(flag))
+        .map(|&(_, modifier)| modifier)
+        .collect();
+        Globals {
+            modifiers,
+            key_type: matches
+                .get_one::<KeyType>("key-type")
+                .copied()
+                .unwrap_or(KeyType::Text),
+            separator: matches.get_one::<char>("field-separator").copied(),
+        }
+    }
+}
+
+/// A key comparing lines by a value parsed from the text of `field`.
+fn parsed_key<K: Ord + 'static>(
+    field: FieldKey,
+    reverse: bool,
+    parse: impl Fn(&str) -> K + 'static,
+) -> Box<dyn KeyExtractorDyn<String>> {
+    let key = move |line: &String| parse(field.text(line));
+    if reverse {
+        key.reversed().boxed()
+    } else {
+        key.boxed()
+    }
+}
+
+/// Build the key for one `-k` specification. As in `sort`, the global
+/// options apply only to keys without modifiers of their own.
+fn field_key(spec: &str, globals: &Globals) -> Result<Box<dyn KeyExtractorDyn<String>>, String> {
+    let own: String = spec.chars().filter(char::is_ascii_alphabetic).collect();
+    let positions: String = spec.chars().filter(|c| !c.is_ascii_alphabetic()).collect();
+    let (modifiers, key_type) = if own.is_empty() {
+        (globals.modifiers.clone(), globals.key_type)
+    } else {
+        (own, KeyType::Text)
+    };
+    let human = modifiers.contains('h');
+    let typed = human || key_type != KeyType::Text;
+    if typed && modifiers.contains('n') {
+        return Err("-n cannot be combined with -h or --key-type".to_string());
+    }
+
+    // `FieldKey` handles the other modifiers itself
+    let field_spec = if typed {
+        positions + &modifiers.replace(['h', 'r'], "")
+    } else {
+        positions + &modifiers
+    };
+    let mut field = FieldKey::parse(&field_spec).map_err(|e| format!("-k {}: {}", spec, e))?;
+    if let Some(separator) = globals.separator {
+        field = field.separator(separator);
+    }
+    let reverse = modifiers.contains('r');
+    let blanks = [' ', '\t'];
+    Ok(match key_type {
+        _ if human => parsed_key(field, reverse, |text| HumanNumeric.extract_key(&text)),
+        KeyType::Text => field.boxed(),
+        KeyType::Ip => parsed_key(field, reverse, move |text| {
+            Ip.extract_key(&text.trim_start_matches(blanks))
+        }),
+        KeyType::Rfc3339 => parsed_key(field, reverse, move |text| {
+            Rfc3339.extract_key(&text.trim_start_matches(blanks))
+        }),
+        KeyType::EpochMillis => parsed_key(field, reverse, move |text| {
+            EpochMillis.extract_key(&text.trim_start_matches(blanks))
+        }),
+    })
+}
+
+/// The keys the options ask for, or `None` to sort lines by their bytes.
+fn keys(matches: &ArgMatches) -> Result<Option<Keys>, String> {
+    let globals = Globals::new(matches);
+    let specs: Vec<&str> = match matches.get_many::<String>("key") {
+        Some(specs) => specs.map(String::as_str).collect(),
+        // `-r` alone reverses the byte order
+        None if globals.modifiers.replace('r', "").is_empty()
+            && globals.key_type == KeyType::Text =>
+        {
+            return Ok(None)
+        }
+        // The whole line
+        None => vec!["1"],
+    };
+    let keys = specs
+        .iter()
+        .map(|spec| field_key(spec, &globals))
+        .collect::<Result<_, _>>()?;
+    Ok(Some(keys))
+}
+
+fn parse_separator(s: &str) -> Result<char, String> {
+    let mut chars = s.chars();
+    match (chars.next(), chars.next()) {
+        (Some(separator), None) => Ok(separator),
+        _ => Err("the separator must be one character".to_string()),
+    }
+}
+
+/// Parse a `--buffer-size` such as `512M` or `1.5GiB`. As for `sort -S`, a
+/// bare number is in KiB and a trailing `b` means bytes.
+fn parse_buffer_size(s: &str) -> Result<usize, String> {
+    let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    if digits(s) {
+        return s
+            .parse::<usize>()
+            .ok()
+            .and_then(|kib| kib.checked_mul(1024))
+            .ok_or_else(|| "size out of range".to_string());
+    }
+    if let Some(bytes) = s.strip_suffix('b').filter(|bytes| digits(bytes)) {
+        return bytes.parse().map_err(|_| "size out of range".to_string());
+    }
+    let bytes = parse_human_size(s, SizeUnits::Binary)
+        .map_err(|e| e.to_string())?
+        .bytes();
+    if bytes < 0.0 || bytes >= usize::MAX as f64 {
+        return Err("size out of range".to_string());
+    }
+    Ok(bytes as usize)
+}
+
+/// Temporary files of this process, removed when dropped.
+struct Scratch {
+    dir: PathBuf,
+    files: Vec<PathBuf>,
+}
+
+impl Scratch {
+    fn new(dir: PathBuf) -> Self {
+        Scratch {
+            dir,
+            files: Vec::new(),
+        }
+    }
+
+    /// A new temporary file path, removed with the others.
+    fn file(&mut self, name: &str) -> PathBuf {
+        let path = self
+            .dir
+            .join(format!("tilesort-{}-{}", process::id(), name));
+        self.files.push(path.clone());
+        path
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        for path in &self.files {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// Add the path an I/O error concerns to its message.
+fn with_path(path: &Path, e: io::Error) -> io::Error {
+    io::Error::new(e.kind(), format!("{}: {}", path.display(), e))
+}
+
+fn is_stdin(path: &Path) -> bool {
+    path == Path::new("-")
+}
+
+/// Copy the inputs, standard input for `-`, one after another into `output`.
+fn concatenate(inputs: &[PathBuf], output: &Path) -> io::Result<()> {
+    let mut out = File::create(output).map_err(|e| with_path(output, e))?;
+    for input in inputs {
+        if is_stdin(input) {
+            io::copy(&mut io::stdin().lock(), &mut out)?;
+        } else {
+            let mut file = File::open(input).map_err(|e| with_path(input, e))?;
+            io::copy(&mut file, &mut out).map_err(|e| with_path(input, e))?;
+        }
+    }
+    out.flush()
+}
+
+/// Read the next run of lines from `reader`, holding at most about
+/// `run_size` bytes as `sort_file` counts them. Returns the lines, without
+/// their terminators, and whether the input is exhausted.
+fn read_run<R: BufRead>(reader: &mut R, run_size: usize) -> io::Result<(Vec<Vec<u8>>, bool)> {
+    let mut run = Vec::new();
+    let mut size = 0;
+    loop {
+        let mut line = Vec::new();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            return Ok((run, true));
+        }
+        if line.last() == Some(&b'\n') {
+            line.pop();
+        }
+        size += line.len() + mem::size_of::<Vec<u8>>();
+        run.push(line);
+        if size >= run_size {
+            return Ok((run, false));
+        }
+    }
+}
+
+/// Prints the progress of a sort to standard error, as `--progress` asks: a
+/// line such as `sorted 42% (eta 1m20s)` each time a phase reaches another
+/// percent.
+struct Reporter {
+    eta: EtaEstimator,
+    shown: Option<(&'static str, u32)>,
+}
+
+impl Reporter {
+    fn new() -> Self {
+        Reporter {
+            eta: EtaEstimator::new(),
+            shown: None,
+        }
+    }
+
+    fn report(&mut self, progress: Progress) {
+        let phase = match progress {
+            Progress::Merging { .. } => "merged",
+            _ => "sorted",
+        };
+        let remaining = self.eta.update(&progress);
+        let shown = (phase, progress.percent() as u32);
+        if self.shown == Some(shown) {
+            return;
+        }
+        self.shown = Some(shown);
+        match remaining {
+            Some(eta) => eprintln!("{} {}% (eta {})", phase, shown.1, eta),
+            None => eprintln!("{} {}%", phase, shown.1),
+        }
+    }
+}
+
+/// Number of passes merging `batch_size` files at a time takes to leave at
+/// most `batch_size` of `runs`, as `sort_file` merges them.
+fn merge_passes(mut runs: usize, batch_size: usize) -> u64 {
+    let mut passes = 0;
+    while runs > batch_size {
+        runs = (runs + batch_size - 1) / batch_size;
+        passes += 1;
+    }
+    passes
+}
+
+/// What to do with lines whose keys are equal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Duplicates {
+    Keep,
+    /// Keep the first, as `--unique` asks.
+    Drop,
+    /// Keep the first, after the number of them and a tab, as `--unique
+    /// --count` asks.
+    Count,
+}
+
+/// Write `lines`, each after its count and a tab if `counted`.
+fn write_lines<'a>(
+    lines: impl IntoIterator<Item = (usize, &'a [u8])>,
+    counted: bool,
+    path: &Path,
+    buffer_size: usize,
+) -> io::Result<()> {
+    let file = File::create(path).map_err(|e| with_path(path, e))?;
+    let mut out = BufWriter::with_capacity(buffer_size, file);
+    for (count, line) in lines {
+        if counted {
+            write!(out, "{}\t", count)?;
+        }
+        out.write_all(line)?;
+        out.write_all(b"\n")?;
+    }
+    out.flush()
+}
+
+/// Split a line written with its count into the count and the line.
+fn split_count(record: &[u8]) -> (usize, &[u8]) {
+    match record.iter().position(|&b| b == b'\t') {
+        Some(tab) => {
+            let count = std::str::from_utf8(&record[..tab])
+                .ok()
+                .and_then(|count| count.parse().ok())
+                .unwrap_or(1);
+            (count, &record[tab + 1..])
+        }
+        None => (1, record),
+    }
+}
+
+/// Collapse the sorted lines of `input` with equal keys into the first of
+/// them, adding up their counts if the lines are `counted`.
+fn collapse<K: Ord>(
+    input: &Path,
+    output: &Path,
+    counted: bool,
+    duplicates: Duplicates,
+    buffer_size: usize,
+    key: impl Fn(&[u8]) -> K,
+) -> io::Result<()> {
+    let mut reader = BufReader::with_capacity(buffer_size, File::open(input)?);
+    let file = File::create(output).map_err(|e| with_path(output, e))?;
+    let mut out = BufWriter::with_capacity(buffer_size, file);
+    let mut write = |count: usize, line: &[u8]| -> io::Result<()> {
+        if duplicates == Duplicates::Count {
+            write!(out, "{}\t", count)?;
+        }
+        out.write_all(line)?;
+        out.write_all(b"\n")
+    };
+    let mut group: Option<(usize, Vec<u8>, K)> = None;
+    let mut record = Vec::new();
+    loop {
+        record.clear();
+        if reader.read_until(b'\n', &mut record)? == 0 {
+            break;
+        }
+        if record.last() == Some(&b'\n') {
+            record.pop();
+        }
+        let (count, line) = if counted {
+            split_count(&record)
+        } else {
+            (1, record.as_slice())
+        };
+        let line_key = key(line);
+        match &mut group {
+            Some((total, _, group_key)) if *group_key == line_key => *total += count,
+            _ => {
+                if let Some((total, first, _)) = group.replace((count, line.to_vec(), line_key)) {
+                    write(total, &first)?;
+                }
+            }
+        }
+    }
+    if let Some((total, first, _)) = group {
+        write(total, &first)?;
+    }
+    out.flush()
+}
+
+/// Sort the lines of `input` into `output` by `key`, as `sort_file` sorts
+/// by bytes: runs are sorted in memory and spilled to temporary files, then
+/// merged by key, at most `batch_size` at a time.
+///
+/// Without [`Duplicates::Keep`], each run is reduced to its groups of equal
+/// keys, spilled with their counts, and the groups left equal across runs
+/// are collapsed after the merge. Progress is reported to `report` as
+/// `sort_file_with_progress` reports it.
+fn sort_by_key<K: Ord>(
+    input: &Path,
+    output: &Path,
+    config: &ExternalSortConfig,
+    duplicates: Duplicates,
+    scratch: &mut Scratch,
+    report: &dyn Fn(Progress),
+    key: impl Fn(&[u8]) -> K,
+) -> io::Result<()> {
+    let file = File::open(input).map_err(|e| with_path(input, e))?;
+    let total_bytes = file.metadata()?.len();
+    let mut reader = BufReader::with_capacity(config.buffer_size, file);
+    let mut runs = Vec::new();
+    let mut bytes_read = 0;
+    report(Progress::Spilling {
+        bytes_read,
+        total_bytes,
+    });
+    loop {
+        let (mut run, done) = read_run(&mut reader, config.run_size)?;
+        bytes_read += run.iter().map(|line| line.len() as u64 + 1).sum::<u64>();
+        report(Progress::Spilling {
+            // A final line without a terminator is counted with one
+            bytes_read: bytes_read.min(total_bytes),
+            total_bytes,
+        });
+        let groups = if duplicates == Duplicates::Keep {
+            tilesort::tilesort_by_key(&mut run, |line| key(line));
+            (0..run.len()).map(|i| i..i + 1).collect()
+        } else {
+            tilesort::tilesort_grouped_by_extractor(&mut run, |line: &Vec<u8>| key(line))
+        };
+        let lines = groups
+            .iter()
+            .map(|group| (group.len(), run[group.start].as_slice()));
+        // An input that fits in one run needs no merge
+        if done && runs.is_empty() {
+            let counted = duplicates == Duplicates::Count;
+            return write_lines(lines, counted, output, config.buffer_size);
+        }
+        if !run.is_empty() {
+            let path = scratch.file(&format!("run-{}", runs.len()));
+            let counted = duplicates != Duplicates::Keep;
+            write_lines(lines, counted, &path, config.buffer_size)?;
+            runs.push(path);
+        }
+        if done {
+            break;
+        }
+    }
+
+    let merge = MergeConfig::new().buffer_size(config.buffer_size);
+    let batch_size = config.batch_size.max(2);
+    // Each record merged has its key read once, which counts it
+    let total_bytes = total_bytes * (merge_passes(runs.len(), batch_size) + 1);
+    let bytes_merged = Cell::new(0);
+    let records = Cell::new(0_usize);
+    let merging = || Progress::Merging {
+        bytes_merged: bytes_merged.get().min(total_bytes),
+        total_bytes,
+    };
+    report(merging());
+    let merge_key = |record: &[u8]| {
+        bytes_merged.set(bytes_merged.get() + record.len() as u64 + 1);
+        records.set(records.get() + 1);
+        if records.get() % 4096 == 0 {
+            report(merging());
+        }
+        match duplicates {
+            Duplicates::Keep => key(record),
+            _ => key(split_count(record).1),
+        }
+    };
+    let mut pass = 0;
+    while runs.len() > batch_size {
+        let mut merged = Vec::new();
+        for batch in runs.chunks(batch_size) {
+            let path = scratch.file(&format!("merge-{}-{}", pass, merged.len()));
+            merge_files_by_key(batch, &path, &merge, merge_key)?;
+            for run in batch {
+                fs::remove_file(run)?;
+            }
+            merged.push(path);
+        }
+        runs = merged;
+        pass += 1;
+    }
+    if duplicates == Duplicates::Keep {
+        merge_files_by_key(&runs, output, &merge, merge_key)?;
+    } else {
+        let merged = scratch.file("merged");
+        merge_files_by_key(&runs, &merged, &merge, merge_key)?;
+        collapse(&merged, output, true, duplicates, config.buffer_size, &key)?;
+    }
+    bytes_merged.set(total_bytes);
+    report(merging());
+    Ok(())
+}
+
+/// Merge `inputs`, each already sorted by `key`, into `output`, as `-m`
+/// asks. Standard input is first copied to a file, and so is the output if
+/// it is also an input.
+fn merge<K: Ord>(
+    inputs: &[PathBuf],
+    output: &Path,
+    config: &ExternalSortConfig,
+    bytes: bool,
+    duplicates: Duplicates,
+    scratch: &mut Scratch,
+    key: impl Fn(&[u8]) -> K,
+) -> io::Result<()> {
+    let mut files = Vec::new();
+    for input in inputs {
+        if is_stdin(input) {
+            let path = scratch.file(&format!("input-{}", files.len()));
+            concatenate(std::slice::from_ref(input), &path)?;
+            files.push(path);
+        } else {
+            File::open(input).map_err(|e| with_path(input, e))?;
+            files.push(input.clone());
+        }
+    }
+    let replaces_input = fs::canonicalize(output).is_ok_and(|output| {
+        files
+            .iter()
+            .any(|file| fs::canonicalize(file).is_ok_and(|file| file == output))
+    });
+    let merged = if replaces_input || duplicates != Duplicates::Keep {
+        scratch.file("merged")
+    } else {
+        output.to_path_buf()
+    };
+
+    let merge = MergeConfig::new()
+        .order(config.order)
+        .buffer_size(config.buffer_size);
+    if bytes {
+        merge_files(&files, &merged, &merge)?;
+    } else {
+        merge_files_by_key(&files, &merged, &merge, &key)?;
+    }
+    if duplicates != Duplicates::Keep {
+        collapse(&merged, output, false, duplicates, config.buffer_size, &key)?;
+    } else if merged != output {
+        fs::copy(&merged, output).map_err(|e| with_path(output, e))?;
+    }
+    Ok(())
+}
+
+/// Check that the lines of `reader` are sorted by `key`, strictly if
+/// `unique`, reading at most about `run_size` bytes of lines at a time.
+/// Returns the number, counted from 1, and the text of the first line out of
+/// order.
+fn check<K: Ord>(
+    mut reader: impl BufRead,
+    run_size: usize,
+    unique: bool,
+    key: impl Fn(&[u8]) -> K,
+) -> io::Result<Option<(usize, Vec<u8>)>> {
+    let extractor = |line: &Vec<u8>| key(line);
+    // The last line of the previous run is checked against the next one
+    let mut previous: Option<Vec<u8>> = None;
+    let mut checked = 0;
+    loop {
+        let (run, done) = read_run(&mut reader, run_size)?;
+        let carried = usize::from(previous.is_some());
+        let mut lines: Vec<Vec<u8>> = previous.take().into_iter().chain(run).collect();
+        let mut sorted = tilesort::sorted_until_by_extractor(&lines, extractor, &SortConfig::new());
+        if unique {
+            if let Some(i) = lines[..sorted]
+                .windows(2)
+                .position(|pair| key(&pair[0]) == key(&pair[1]))
+            {
+                sorted = i + 1;
+            }
+        }
+        if sorted < lines.len() {
+            return Ok(Some((
+                checked + sorted - carried + 1,
+                lines.swap_remove(sorted),
+            )));
+        }
+        checked += lines.len() - carried;
+        if done {
+            return Ok(None);
+        }
+        previous = lines.pop();
+    }
+}
+
+/// Sort or check the input by `key`; `bytes` says the key is the whole
+/// line, compared as `sort_file` compares it.
+fn sort<K: Ord>(
+    matches: &ArgMatches,
+    bytes: bool,
+    key: impl Fn(&[u8]) -> K,
+) -> Result<ExitCode, Box<dyn Error>> {
+    let reverse = matches.get_flag("reverse");
+    let duplicates = match (matches.get_flag("unique"), matches.get_flag("count")) {
+        (false, _) => Duplicates::Keep,
+        (true, false) => Duplicates::Drop,
+        (true, true) => Duplicates::Count,
+    };
+    let mut config = ExternalSortConfig::new();
+    if bytes && reverse {
+        config = config.descending();
+    }
+    if let Some(&run_size) = matches.get_one::<usize>("buffer-size") {
+        config = config.run_size(run_size);
+    }
+    if let Some(dir) = matches.get_one::<PathBuf>("temporary-directory") {
+        config = config.temp_dir(dir);
+    }
+    if let Some(dir) = matches.get_one::<PathBuf>("checkpoint-dir") {
+        // Only `sort_file` keeps a manifest of its runs
+        if !bytes || duplicates != Duplicates::Keep {
+            return Err("--checkpoint-dir cannot be combined with keys or --unique".into());
+        }
+        config = config
+            .checkpoint_dir(dir)
+            .resume(matches.get_flag("resume"));
+    }
+    let mut scratch = Scratch::new(config.temp_dir.clone().unwrap_or_else(std::env::temp_dir));
+    let reporter = RefCell::new(matches.get_flag("progress").then(Reporter::new));
+    let report = |progress| {
+        if let Some(reporter) = reporter.borrow_mut().as_mut() {
+            reporter.report(progress);
+        }
+    };
+
+    let mut inputs: Vec<PathBuf> = matches
+        .get_many::<PathBuf>("files")
+        .map_or_else(Vec::new, |files| files.cloned().collect());
+    if inputs.is_empty() {
+        inputs.push(PathBuf::from("-"));
+    }
+
+    let quiet = matches.get_flag("check-silent");
+    if quiet || matches.get_flag("check") {
+        let input = match inputs.as_slice() {
+            [input] => input,
+            _ => return Err("only one file can be checked".into()),
+        };
+        let unique = duplicates != Duplicates::Keep;
+        let disorder = if is_stdin(input) {
+            check(io::stdin().lock(), config.run_size, unique, key)?
+        } else {
+            let file = File::open(input).map_err(|e| with_path(input, e))?;
+            let reader = BufReader::with_capacity(config.buffer_size, file);
+            check(reader, config.run_size, unique, key).map_err(|e| with_path(input, e))?
+        };
+        return Ok(match disorder {
+            None => ExitCode::SUCCESS,
+            Some((number, line)) => {
+                if !quiet {
+                    eprintln!(
+                        "tilesort: {}:{}: disorder: {}",
+                        input.display(),
+                        number,
+                        String::from_utf8_lossy(&line)
+                    );
+                }
+                ExitCode::FAILURE
+            }
+        });
+    }
+
+    let named_output = matches.get_one::<PathBuf>("output");
+    let output = match named_output {
+        Some(output) => output.clone(),
+        None => scratch.file("output"),
+    };
+    if matches.get_flag("merge") {
+        merge(
+            &inputs,
+            &output,
+            &config,
+            bytes,
+            duplicates,
+            &mut scratch,
+            key,
+        )?;
+    } else {
+        // `sort_file` reads one file, so standard input and several files
+        // are first copied into one
+        let input = match inputs.as_slice() {
+            [input] if !is_stdin(input) => {
+                File::open(input).map_err(|e| with_path(input, e))?;
+                input.clone()
+            }
+            _ => {
+                let input = scratch.file("input");
+                concatenate(&inputs, &input)?;
+                input
+            }
+        };
+        if bytes && duplicates == Duplicates::Keep {
+            sort_file_with_progress(&input, &output, &config, report)?;
+        } else {
+            sort_by_key(
+                &input,
+                &output,
+                &config,
+                duplicates,
+                &mut scratch,
+                &report,
+                key,
+            )?;
+        }
+    }
+    if named_output.is_none() {
+        let mut stdout = io::stdout().lock();
+        io::copy(&mut File::open(&output)?, &mut stdout)?;
+        stdout.flush()?;
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn run(matches: &ArgMatches) -> Result<ExitCode, Box<dyn Error>> {
+    match keys(matches)? {
+        Some(keys) => sort(matches, false, |line: &[u8]| {
+            keys.extract_key(&String::from_utf8_lossy(line).into_owned())
+        }),
+        None if matches.get_flag("reverse") => {
+            sort(matches, true, |line: &[u8]| Reverse(line.to_vec()))
+        }
+        None => sort(matches, true, <[u8]>::to_vec),
+    }
+}
+
+fn main() -> ExitCode {
+    // Usage errors exit with status 2 too
+    let matches = command().get_matches();
+    match run(&matches) {
+        Ok(status) => status,
+        Err(e) => {
+            eprintln!("tilesort: {}", e);
+            ExitCode::from(TROUBLE)
+        }
+    }
+}
@@ -0,0 +1,134 @@
+//! Fluent builder for multi-key sorts.
+
+use std::marker::PhantomData;
+
+use crate::config::SortConfig;
+use crate::key_extractor::{KeyExtractor, Reversed, Then};
+use crate::sorter;
+
+/// Builder for sorting by several keys, each with its own direction.
+///
+/// Start with [`Tilesort::by`] (or [`Tilesort::by_desc`]) and add tie-breaking
+/// levels with [`then_by`](Tilesort::then_by) and
+/// [`then_by_desc`](Tilesort::then_by_desc). The levels are composed into a
+/// single tuple key, so the sort itself is an ordinary tilesort by key.
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::Tilesort;
+///
+/// #[derive(Clone, Debug, PartialEq)]
+/// struct Row {
+///     last_name: &'static str,
+///     first_name: &'static str,
+///     age: u32,
+/// }
+///
+/// let mut rows = vec![
+///     Row { last_name: "Smith", first_name: "Jane", age: 30 },
+///     Row { last_name: "Jones", first_name: "Bob", age: 40 },
+///     Row { last_name: "Smith", first_name: "Adam", age: 25 },
+///     Row { last_name: "Smith", first_name: "Jane", age: 45 },
+/// ];
+///
+/// Tilesort::by(|r: &Row| r.last_name)
+///     .then_by(|r| r.first_name)
+///     .then_by_desc(|r| r.age)
+///     .sort(&mut rows);
+///
+/// let ages: Vec<u32> = rows.iter().map(|r| r.age).collect();
+/// assert_eq!(ages, vec![40, 25, 45, 30]);
+/// ```
+pub struct Tilesort<T, E> {
+    extractor: E,
+    config: SortConfig,
+    _marker: PhantomData<fn(&T)>,
+}
+
+impl<T, E> Tilesort<T, E> {
+    /// Start a sort whose primary key is `key_fn`, in ascending order.
+    pub fn by<K>(key_fn: E) -> Self
+    where
+        E: Fn(&T) -> K,
+        K: Ord,
+    {
+        Self::from_extractor(key_fn)
+    }
+
+    fn from_extractor(extractor: E) -> Self {
+        Tilesort {
+            extractor,
+            config: SortConfig::default(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Use `config` for the sort.
+    ///
+    /// Choosing [`Order::Descending`](crate::Order::Descending) inverts every
+    /// level, including those added with `then_by_desc`.
+    pub fn with_config(mut self, config: SortConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Break ties with `key_fn`, in ascending order.
+    pub fn then_by<K, F>(self, key_fn: F) -> Tilesort<T, Then<E, F>>
+    where
+        F: Fn(&T) -> K,
+        K: Ord,
+    {
+        Tilesort {
+            extractor: Then::new(self.extractor, key_fn),
+            config: self.config,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Break ties with `key_fn`, in descending order.
+    pub fn then_by_desc<K, F>(self, key_fn: F) -> Tilesort<T, Then<E, Reversed<F>>>
+    where
+        F: Fn(&T) -> K,
+        K: Ord,
+    {
+        Tilesort {
+            extractor: Then::new(self.extractor, Reversed::new(key_fn)),
+            config: self.config,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Sort `data` in place by the configured keys.
+    pub fn sort<K>(&self, data: &mut [T])
+    where
+        T: Clone,
+        K: Ord,
+        E: KeyExtractor<T, K>,
+    {
+        sorter::tilesort_impl_with_key(data, &self.extractor, &self.config);
+    }
+
+    /// Return a sorted copy of `data`, leaving the original unchanged.
+    pub fn sorted<K>(&self, data: &[T]) -> Vec<T>
+    where
+        T: Clone,
+        K: Ord,
+        E: KeyExtractor<T, K>,
+    {
+        let mut result = data.to_vec();
+        self.sort(&mut result);
+        result
+    }
+}
+
+impl<T, F> Tilesort<T, Reversed<F>> {
+    /// Start a sort whose primary key is `key_fn`, in descending order.
+    pub fn by_desc<K>(key_fn: F) -> Self
+    where
+        F: Fn(&T) -> K,
+        K: Ord,
+    {
+        Self::from_extractor(Reversed::new(key_fn))
+    }
+}
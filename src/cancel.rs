@@ -0,0 +1,59 @@
+//! Cooperative cancellation for long-running sorts.
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Number of elements processed between cancellation checks.
+pub(crate) const CHECK_INTERVAL: usize = 4096;
+
+/// Shared flag used to request that a running sort stop early.
+///
+/// Clone the token and hand one copy to the sorting thread (via
+/// [`tilesort_cancellable`](crate::tilesort_cancellable) or
+/// [`tilesort_by_key_cancellable`](crate::tilesort_by_key_cancellable)); call
+/// [`cancel`](CancellationToken::cancel) on another copy to stop it. The sort
+/// polls the flag every few thousand elements.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    flag: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    /// Create a token that has not been cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request cancellation of every sort observing this token.
+    pub fn cancel(&self) {
+        self.flag.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns `true` once [`cancel`](CancellationToken::cancel) has been called.
+    pub fn is_cancelled(&self) -> bool {
+        self.flag.load(Ordering::Relaxed)
+    }
+}
+
+/// Error returned when a sort was stopped through its [`CancellationToken`].
+///
+/// The slice is left in its original order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("sort was cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// Fail with [`Cancelled`] if `token` is present and has been cancelled.
+pub(crate) fn check(token: Option<&CancellationToken>) -> Result<(), Cancelled> {
+    match token {
+        Some(token) if token.is_cancelled() => Err(Cancelled),
+        _ => Ok(()),
+    }
+}
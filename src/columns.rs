@@ -0,0 +1,114 @@
+//! Columns of struct-of-arrays data, sorted together by
+//! [`sort_columns`](crate::sort_columns).
+
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+
+use crate::opcount;
+
+/// A column that can be rearranged by swapping rows.
+///
+/// Implemented for slices and deques, and for mutable references to any
+/// column. Columns are passed to [`sort_columns`](crate::sort_columns) as
+/// `&mut dyn Column`, so one call can rearrange columns of different element
+/// types, which need not be `Clone`. A vector is passed through a reference
+/// to its slice, as `&mut v.as_mut_slice()`; it has no impl of its own, which
+/// would shadow [`Vec`]'s access to the slice's `swap`.
+pub trait Column {
+    /// Number of rows.
+    fn len(&self) -> usize;
+
+    /// Returns `true` if the column has no rows.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Exchange rows `a` and `b`.
+    fn swap(&mut self, a: usize, b: usize);
+}
+
+/// A column whose rows can be compared, for sorting a table by it.
+pub trait KeyColumn: Column {
+    /// Compare the keys of rows `a` and `b`.
+    fn compare(&self, a: usize, b: usize) -> Ordering;
+}
+
+impl<T> Column for [T] {
+    fn len(&self) -> usize {
+        <[T]>::len(self)
+    }
+
+    fn swap(&mut self, a: usize, b: usize) {
+        <[T]>::swap(self, a, b);
+    }
+}
+
+impl<T: Ord> KeyColumn for [T] {
+    fn compare(&self, a: usize, b: usize) -> Ordering {
+        opcount::comparisons(1);
+        self[a].cmp(&self[b])
+    }
+}
+
+impl<T> Column for VecDeque<T> {
+    fn len(&self) -> usize {
+        VecDeque::len(self)
+    }
+
+    fn swap(&mut self, a: usize, b: usize) {
+        VecDeque::swap(self, a, b);
+    }
+}
+
+impl<T: Ord> KeyColumn for VecDeque<T> {
+    fn compare(&self, a: usize, b: usize) -> Ordering {
+        opcount::comparisons(1);
+        self[a].cmp(&self[b])
+    }
+}
+
+impl<C: Column + ?Sized> Column for &mut C {
+    fn len(&self) -> usize {
+        (**self).len()
+    }
+
+    fn swap(&mut self, a: usize, b: usize) {
+        (**self).swap(a, b);
+    }
+}
+
+impl<C: KeyColumn + ?Sized> KeyColumn for &mut C {
+    fn compare(&self, a: usize, b: usize) -> Ordering {
+        (**self).compare(a, b)
+    }
+}
+
+/// A row of a table, ordered by the key columns in turn.
+pub(crate) struct Row<'a, 'b> {
+    pub(crate) row: usize,
+    pub(crate) keys: &'a [&'b mut dyn KeyColumn],
+}
+
+impl Ord for Row<'_, '_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.keys
+            .iter()
+            .map(|column| column.compare(self.row, other.row))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for Row<'_, '_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Row<'_, '_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Row<'_, '_> {}
@@ -0,0 +1,457 @@
+//! Sort configuration.
+
+#[cfg(feature = "rayon")]
+use std::sync::Arc;
+
+#[cfg(feature = "rayon")]
+use rayon::ThreadPool;
+
+use crate::small::INSERTION_SORT_THRESHOLD;
+use crate::tile_index::MAX_INLINE_TILES;
+
+/// Direction in which elements are ordered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Order {
+    /// Smallest key first.
+    #[default]
+    Ascending,
+    /// Largest key first.
+    Descending,
+}
+
+impl Order {
+    /// Returns `true` for [`Order::Descending`].
+    pub fn is_descending(self) -> bool {
+        self == Order::Descending
+    }
+
+    /// Returns the opposite direction.
+    pub fn reversed(self) -> Order {
+        match self {
+            Order::Ascending => Order::Descending,
+            Order::Descending => Order::Ascending,
+        }
+    }
+}
+
+/// How costly key extraction is, used to pick the restructuring strategy.
+///
+/// Every key is extracted once during the scan and kept next to its
+/// element's index, so an expensive key function is not called again for
+/// each comparison. The exception is [`KeyCost::Auto`], which extracts the
+/// first few keys an extra time to time them (fallible sorts time the scan's
+/// own extractions instead). What changes with the cost is the second phase:
+/// for [`KeyCost::Expensive`] keys the sort applies the resulting permutation
+/// in place, as with [`SortConfig::indirect`], on the assumption that
+/// elements with costly keys are themselves costly to clone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum KeyCost {
+    /// Keys are cheap; use the configured restructuring strategy.
+    #[default]
+    Cheap,
+    /// Keys are expensive; restructure indirectly.
+    Expensive,
+    /// Time the extraction of a few keys and treat them as expensive if they
+    /// take longer than about a microsecond each. The timed keys are
+    /// discarded, so those elements' keys are extracted twice.
+    Auto,
+}
+
+/// Expected number of tiles, set with [`SortConfig::assume_runs`] or
+/// [`SortConfig::assume_sorted_fraction`].
+///
+/// The tile index is allocated for this many tiles up front instead of
+/// growing as they are found. The key and restructuring buffers need no
+/// hint, as they are always allocated once for the whole input. A wrong hint
+/// only costs memory or reallocations, never correctness.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CapacityHint {
+    /// No expectation: the index grows as tiles are found.
+    #[default]
+    None,
+    /// About this many sorted runs.
+    Runs(usize),
+    /// About this many elements in every million continue the run of the
+    /// element before them; each of the rest starts a new run.
+    SortedPerMillion(u32),
+}
+
+impl CapacityHint {
+    /// Tiles expected in an input of `len` elements, or `0` for no hint.
+    pub(crate) fn expected_tiles(self, len: usize) -> usize {
+        match self {
+            CapacityHint::None => 0,
+            CapacityHint::Runs(runs) => runs.min(len),
+            CapacityHint::SortedPerMillion(sorted) => {
+                let unsorted = 1_000_000 - u128::from(sorted.min(1_000_000));
+                let breaks = len as u128 * unsorted / 1_000_000;
+                (breaks as usize).saturating_add(1).min(len)
+            }
+        }
+    }
+}
+
+/// How the second phase moves the elements into sorted order.
+///
+/// Chosen from [`SortConfig::indirect`] and [`SortConfig::max_aux_memory`],
+/// and returned by [`SortPlan::apply`](crate::SortPlan::apply).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Strategy {
+    /// Clone every element into a buffer in sorted order, then move them back.
+    /// Needs room for a copy of the data.
+    Buffered,
+    /// Collect the sorted order as source indices and swap each element into
+    /// place. Needs one `usize` per element.
+    Indirect,
+    /// Rotate tiles past each other into place, merge sorting them when
+    /// there are many. Needs no memory beyond a list of the tiles, but moves
+    /// each element several times: O(log² t) times for t tiles.
+    Rotate,
+}
+
+impl Strategy {
+    /// Auxiliary bytes this strategy needs to restructure `len` elements of
+    /// type `T`.
+    pub fn aux_memory<T>(self, len: usize) -> usize {
+        self.aux_bytes(len, std::mem::size_of::<T>())
+    }
+
+    /// [`aux_memory`](Strategy::aux_memory) for elements of `element_size`
+    /// bytes.
+    pub(crate) fn aux_bytes(self, len: usize, element_size: usize) -> usize {
+        match self {
+            Strategy::Buffered => len.saturating_mul(element_size),
+            Strategy::Indirect => len.saturating_mul(std::mem::size_of::<usize>()),
+            Strategy::Rotate => 0,
+        }
+    }
+}
+
+/// Threads used by the parallel sorts of
+/// [`ParallelTileSortExt`](crate::ParallelTileSortExt).
+#[cfg(feature = "rayon")]
+#[derive(Debug, Clone, Default)]
+pub enum Threads {
+    /// Run on rayon's global thread pool, or on the pool the caller is already
+    /// running in.
+    #[default]
+    Global,
+    /// Run on a pool of at most this many threads, built for each sort; `0`
+    /// lets rayon choose.
+    /// Prefer [`Threads::Pool`] when sorting repeatedly.
+    Max(usize),
+    /// Run on the given pool.
+    Pool(Arc<ThreadPool>),
+}
+
+#[cfg(feature = "rayon")]
+impl PartialEq for Threads {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Threads::Global, Threads::Global) => true,
+            (Threads::Max(a), Threads::Max(b)) => a == b,
+            (Threads::Pool(a), Threads::Pool(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl Eq for Threads {}
+
+/// Options controlling a tilesort run.
+///
+/// Construct with [`SortConfig::new`] (or `Default`) and adjust with the fluent
+/// setters, or use struct update syntax:
+///
+/// ```
+/// use tilesort::{Order, SortConfig};
+///
+/// let config = SortConfig::new().order(Order::Descending);
+/// assert_eq!(config, SortConfig { order: Order::Descending, ..Default::default() });
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SortConfig {
+    /// Direction of the sort. Defaults to [`Order::Ascending`].
+    pub order: Order,
+    /// Keep elements with equal keys in their original relative order.
+    /// Defaults to `true`; turning it off allows fewer tile splits on
+    /// duplicate-heavy data.
+    pub stable: bool,
+    /// Restructure by computing the sorted order as a vector of indices and
+    /// swapping each element straight into place, instead of cloning every
+    /// element into a buffer and moving it back. Worth enabling for large
+    /// elements or ones that are expensive to clone. Defaults to `false`.
+    pub indirect: bool,
+    /// Cost of the key function. Defaults to [`KeyCost::Cheap`]; see
+    /// [`KeyCost`] for how it affects the sort.
+    pub key_cost: KeyCost,
+    /// Widest key range (largest minus smallest key, plus one) for which the
+    /// integer sorts such as [`tilesort_integers`](crate::tilesort_integers)
+    /// use a counting sort instead of a tile index. Ranges wider than the
+    /// input itself never qualify. Defaults to 65536, covering every `u8` and
+    /// `u16` domain; `0` disables counting sort.
+    pub counting_sort_threshold: usize,
+    /// Most bytes the second phase may allocate besides the tile index, or
+    /// `None` (the default) for no limit. The cheapest [`Strategy`] that
+    /// fits is used: [`Strategy::Buffered`] (unless
+    /// [`indirect`](SortConfig::indirect) is set), then
+    /// [`Strategy::Indirect`], then [`Strategy::Rotate`]. Those three are the
+    /// only choices: nothing is spilled to disk, for which see
+    /// [`sort_file`](crate::sort_file). The keys buffered by the scan are not
+    /// counted, and the parallel sorts ignore the limit.
+    pub max_aux_memory: Option<usize>,
+    /// Number of tiles the index stores inline before allocating, at most
+    /// [`MAX_INLINE_TILES`](crate::MAX_INLINE_TILES). Sorting input with no
+    /// more tiles than this allocates nothing for the index. Defaults to
+    /// `MAX_INLINE_TILES`; `0` allocates from the first tile.
+    pub inline_tiles: usize,
+    /// Sample long inputs before sorting and take a shortcut when they look
+    /// sorted, reverse sorted or random (see [`InputPattern`](crate::InputPattern)).
+    /// Defaults to `true`.
+    pub detect_patterns: bool,
+    /// Mean run length, estimated by sampling, below which pattern detection
+    /// sorts by comparison instead of building a tile index. Defaults to 4;
+    /// `0` never falls back on run length alone.
+    pub min_run_len: usize,
+    /// Estimated number of tiles above which pattern detection sorts by
+    /// comparison instead of building a tile index, or `None` (the default)
+    /// for no limit.
+    pub max_tiles: Option<usize>,
+    /// Slices shorter than this are sorted by insertion sort, skipping the
+    /// tile index. Defaults to 32; `0` always uses the tile index.
+    pub insertion_sort_threshold: usize,
+    /// Expected number of tiles, to allocate the tile index once. Defaults
+    /// to [`CapacityHint::None`].
+    pub capacity_hint: CapacityHint,
+    /// Threads for the parallel sorts. Defaults to [`Threads::Global`]. Not
+    /// serialized, as a thread pool belongs to the running process.
+    #[cfg(feature = "rayon")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub threads: Threads,
+}
+
+impl Default for SortConfig {
+    fn default() -> Self {
+        SortConfig {
+            order: Order::default(),
+            stable: true,
+            indirect: false,
+            key_cost: KeyCost::default(),
+            counting_sort_threshold: 1 << 16,
+            max_aux_memory: None,
+            inline_tiles: MAX_INLINE_TILES,
+            detect_patterns: true,
+            min_run_len: 4,
+            max_tiles: None,
+            insertion_sort_threshold: INSERTION_SORT_THRESHOLD,
+            capacity_hint: CapacityHint::default(),
+            #[cfg(feature = "rayon")]
+            threads: Threads::default(),
+        }
+    }
+}
+
+impl SortConfig {
+    /// Create a configuration with default settings (ascending, stable).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the sort direction.
+    pub fn order(mut self, order: Order) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Sort in ascending order.
+    pub fn ascending(self) -> Self {
+        self.order(Order::Ascending)
+    }
+
+    /// Sort in descending order.
+    pub fn descending(self) -> Self {
+        self.order(Order::Descending)
+    }
+
+    /// Set whether elements with equal keys must keep their relative order.
+    pub fn stable(mut self, stable: bool) -> Self {
+        self.stable = stable;
+        self
+    }
+
+    /// Allow elements with equal keys to be reordered.
+    pub fn unstable(self) -> Self {
+        self.stable(false)
+    }
+
+    /// Set whether to restructure through an index permutation rather than a
+    /// cloned buffer.
+    ///
+    /// ```
+    /// use tilesort::SortConfig;
+    ///
+    /// let mut data = vec![[3u8; 256], [1u8; 256], [2u8; 256]];
+    /// tilesort::tilesort_with_config(&mut data, &SortConfig::new().indirect(true));
+    /// assert_eq!(data, vec![[1u8; 256], [2u8; 256], [3u8; 256]]);
+    /// ```
+    pub fn indirect(mut self, indirect: bool) -> Self {
+        self.indirect = indirect;
+        self
+    }
+
+    /// Declare how costly the key function is.
+    ///
+    /// ```
+    /// use tilesort::{KeyCost, SortConfig};
+    ///
+    /// let mut words = vec!["delta", "alpha", "charlie", "bravo"];
+    /// let config = SortConfig::new().key_cost(KeyCost::Auto);
+    /// tilesort::tilesort_by_key_with_config(&mut words, |w| w.to_uppercase(), &config);
+    /// assert_eq!(words, vec!["alpha", "bravo", "charlie", "delta"]);
+    /// ```
+    pub fn key_cost(mut self, key_cost: KeyCost) -> Self {
+        self.key_cost = key_cost;
+        self
+    }
+
+    /// Set the widest key range sorted by counting.
+    pub fn counting_sort_threshold(mut self, threshold: usize) -> Self {
+        self.counting_sort_threshold = threshold;
+        self
+    }
+
+    /// Limit the memory the second phase may allocate to `bytes`.
+    ///
+    /// ```
+    /// use tilesort::SortConfig;
+    ///
+    /// // No room for a copy or an index vector: tiles are rotated into place
+    /// let mut data = vec![7, 8, 9, 4, 5, 6, 1, 2, 3];
+    /// tilesort::tilesort_with_config(&mut data, &SortConfig::new().max_aux_memory(0));
+    /// assert_eq!(data, vec![1, 2, 3, 4, 5, 6, 7, 8, 9]);
+    /// ```
+    pub fn max_aux_memory(mut self, bytes: usize) -> Self {
+        self.max_aux_memory = Some(bytes);
+        self
+    }
+
+    /// Set the number of tiles stored inline; values above
+    /// [`MAX_INLINE_TILES`](crate::MAX_INLINE_TILES) are treated as that
+    /// maximum.
+    pub fn inline_tiles(mut self, tiles: usize) -> Self {
+        self.inline_tiles = tiles;
+        self
+    }
+
+    /// Enable or disable sampling the input for a cheaper sort.
+    pub fn detect_patterns(mut self, detect: bool) -> Self {
+        self.detect_patterns = detect;
+        self
+    }
+
+    /// Set the mean run length below which sampled input is sorted by
+    /// comparison.
+    ///
+    /// ```
+    /// use tilesort::SortConfig;
+    ///
+    /// // Runs of 20 are too short for this data: compare instead
+    /// let mut data: Vec<u32> = (0..10_000).map(|i| i % 20).collect();
+    /// tilesort::tilesort_with_config(&mut data, &SortConfig::new().min_run_len(32));
+    /// assert!(data.windows(2).all(|pair| pair[0] <= pair[1]));
+    /// ```
+    pub fn min_run_len(mut self, len: usize) -> Self {
+        self.min_run_len = len;
+        self
+    }
+
+    /// Sort sampled input by comparison when it is estimated to hold more
+    /// than `tiles` tiles.
+    pub fn max_tiles(mut self, tiles: usize) -> Self {
+        self.max_tiles = Some(tiles);
+        self
+    }
+
+    /// Set the length below which slices are sorted by insertion sort.
+    pub fn insertion_sort_threshold(mut self, len: usize) -> Self {
+        self.insertion_sort_threshold = len;
+        self
+    }
+
+    /// Expect about `runs` sorted runs, and allocate the tile index for them
+    /// up front.
+    ///
+    /// ```
+    /// use tilesort::SortConfig;
+    ///
+    /// // Logs merged from 64 sorted shards
+    /// let mut data: Vec<u32> = (0..6_400).map(|i| i % 100 * 64 + i / 100).collect();
+    /// tilesort::tilesort_with_config(&mut data, &SortConfig::new().assume_runs(64));
+    /// assert!(data.windows(2).all(|pair| pair[0] <= pair[1]));
+    /// ```
+    pub fn assume_runs(mut self, runs: usize) -> Self {
+        self.capacity_hint = CapacityHint::Runs(runs);
+        self
+    }
+
+    /// Expect about `fraction` of the elements, between `0.0` and `1.0`, to
+    /// continue the run of the element before them, and allocate the tile
+    /// index for the rest up front.
+    pub fn assume_sorted_fraction(mut self, fraction: f64) -> Self {
+        // `NaN` saturates to zero
+        let per_million = (fraction.clamp(0.0, 1.0) * 1_000_000.0).round() as u32;
+        self.capacity_hint = CapacityHint::SortedPerMillion(per_million);
+        self
+    }
+
+    /// The restructuring strategy for `len` elements of type `T`.
+    pub(crate) fn strategy<T>(&self, len: usize) -> Strategy {
+        self.strategy_for(len, std::mem::size_of::<T>())
+    }
+
+    /// The restructuring strategy for `len` elements of `element_size` bytes.
+    pub(crate) fn strategy_for(&self, len: usize, element_size: usize) -> Strategy {
+        let fits = |strategy: Strategy| match self.max_aux_memory {
+            Some(budget) => strategy.aux_bytes(len, element_size) <= budget,
+            None => true,
+        };
+        if !self.indirect && fits(Strategy::Buffered) {
+            Strategy::Buffered
+        } else if fits(Strategy::Indirect) {
+            Strategy::Indirect
+        } else {
+            Strategy::Rotate
+        }
+    }
+
+    /// Run the parallel sorts on `pool`, for applications that already divide
+    /// their cores between pools.
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    /// use tilesort::{ParallelTileSortExt, SortConfig};
+    ///
+    /// let pool = Arc::new(rayon::ThreadPoolBuilder::new().num_threads(2).build().unwrap());
+    /// let mut data = vec![4, 5, 6, 1, 2, 3];
+    /// data.par_tilesort_with_config(&SortConfig::new().thread_pool(pool));
+    /// assert_eq!(data, vec![1, 2, 3, 4, 5, 6]);
+    /// ```
+    #[cfg(feature = "rayon")]
+    pub fn thread_pool(mut self, pool: Arc<ThreadPool>) -> Self {
+        self.threads = Threads::Pool(pool);
+        self
+    }
+
+    /// Limit the parallel sorts to `threads` threads; `0` lets rayon choose.
+    #[cfg(feature = "rayon")]
+    pub fn max_threads(mut self, threads: usize) -> Self {
+        self.threads = Threads::Max(threads);
+        self
+    }
+}
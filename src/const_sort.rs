@@ -0,0 +1,85 @@
+//! Sorting small arrays at compile time.
+//!
+//! Trait methods such as [`Ord::cmp`] cannot be called from a `const fn`, so
+//! this module provides one function per primitive type. They are plain
+//! insertion sorts rather than tilesort: const evaluation has no heap to hold
+//! a tile index, and the arrays sorted this way (lookup tables, small static
+//! sets) are short enough that the simpler algorithm costs nothing at run
+//! time. For sorting at run time use [`tilesort_array`](crate::tilesort_array).
+//!
+//! ```
+//! use tilesort::const_sort;
+//!
+//! const PRIMES: [u16; 6] = const_sort::sort_u16([11, 13, 2, 3, 5, 7]);
+//! assert_eq!(PRIMES, [2, 3, 5, 7, 11, 13]);
+//!
+//! // Binary search needs sorted input; sort the table where it is defined
+//! static OFFSETS: [i32; 5] = const_sort::sort_i32([40, -8, 0, 16, -24]);
+//! assert_eq!(OFFSETS.binary_search(&16), Ok(3));
+//! ```
+
+macro_rules! const_sorts {
+    ($($name:ident: $ty:ty),* $(,)?) => {
+        $(
+            #[doc = concat!("Sort an array of `", stringify!($ty), "` in ascending order, in a const context.")]
+            ///
+            /// Runs of already ordered elements cost one comparison each, so
+            /// nearly sorted tables are cheap to evaluate.
+            pub const fn $name<const N: usize>(arr: [$ty; N]) -> [$ty; N] {
+                let mut arr = arr;
+                let mut i = 1;
+                while i < N {
+                    let value = arr[i];
+                    let mut j = i;
+                    while j > 0 && arr[j - 1] > value {
+                        arr[j] = arr[j - 1];
+                        j -= 1;
+                    }
+                    arr[j] = value;
+                    i += 1;
+                }
+                arr
+            }
+        )*
+    };
+}
+
+const_sorts! {
+    sort_u8: u8,
+    sort_u16: u16,
+    sort_u32: u32,
+    sort_u64: u64,
+    sort_u128: u128,
+    sort_usize: usize,
+    sort_i8: i8,
+    sort_i16: i16,
+    sort_i32: i32,
+    sort_i64: i64,
+    sort_i128: i128,
+    sort_isize: isize,
+    sort_char: char,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DESCENDING: [i64; 5] = sort_i64([5, 4, 3, 2, 1]);
+    const LETTERS: [char; 4] = sort_char(['d', 'a', 'c', 'b']);
+    const EMPTY: [u8; 0] = sort_u8([]);
+
+    #[test]
+    fn test_evaluated_at_compile_time() {
+        assert_eq!(DESCENDING, [1, 2, 3, 4, 5]);
+        assert_eq!(LETTERS, ['a', 'b', 'c', 'd']);
+        assert!(EMPTY.is_empty());
+    }
+
+    #[test]
+    fn test_matches_std_sort() {
+        let table = [9u32, 10, 11, 1, 2, 3, 6, 7, 1, 0, u32::MAX];
+        let mut expected = table;
+        expected.sort();
+        assert_eq!(sort_u32(table), expected);
+    }
+}
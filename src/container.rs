@@ -0,0 +1,176 @@
+//! Random-access containers that can be sorted in place.
+
+use std::collections::VecDeque;
+
+use crate::opcount;
+
+/// A container with indexed reads and swaps, sortable by
+/// [`tilesort_container`](crate::tilesort_container).
+///
+/// The sort reads every element once through [`get`](RandomAccess::get) to
+/// find the tiles, then moves elements into place with
+/// [`swap`](RandomAccess::swap), so elements need not be `Clone`.
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::RandomAccess;
+///
+/// /// A view presenting a slice back to front.
+/// struct BackToFront<'a, T>(&'a mut [T]);
+///
+/// impl<T> RandomAccess for BackToFront<'_, T> {
+///     type Item = T;
+///
+///     fn len(&self) -> usize {
+///         self.0.len()
+///     }
+///
+///     fn get(&self, index: usize) -> &T {
+///         &self.0[self.0.len() - 1 - index]
+///     }
+///
+///     fn swap(&mut self, a: usize, b: usize) {
+///         let last = self.0.len() - 1;
+///         self.0.swap(last - a, last - b);
+///     }
+/// }
+///
+/// // Sorting the reversed view sorts the slice in descending order
+/// let mut data = vec![1, 2, 3, 7, 8, 9];
+/// tilesort::tilesort_container(&mut BackToFront(&mut data));
+/// assert_eq!(data, vec![9, 8, 7, 3, 2, 1]);
+/// ```
+pub trait RandomAccess {
+    /// The element type.
+    type Item;
+
+    /// Number of elements.
+    fn len(&self) -> usize;
+
+    /// Returns `true` if the container holds no elements.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The element at `index`, which is always less than [`len`](RandomAccess::len).
+    fn get(&self, index: usize) -> &Self::Item;
+
+    /// Exchange the elements at `a` and `b`.
+    fn swap(&mut self, a: usize, b: usize);
+}
+
+impl<T> RandomAccess for [T] {
+    type Item = T;
+
+    fn len(&self) -> usize {
+        <[T]>::len(self)
+    }
+
+    fn get(&self, index: usize) -> &T {
+        &self[index]
+    }
+
+    fn swap(&mut self, a: usize, b: usize) {
+        <[T]>::swap(self, a, b);
+    }
+}
+
+impl<T> RandomAccess for VecDeque<T> {
+    type Item = T;
+
+    fn len(&self) -> usize {
+        VecDeque::len(self)
+    }
+
+    fn get(&self, index: usize) -> &T {
+        &self[index]
+    }
+
+    fn swap(&mut self, a: usize, b: usize) {
+        VecDeque::swap(self, a, b);
+    }
+}
+
+/// A keys slice and a values slice swapped in lockstep, read by key.
+pub(crate) struct Pairs<'a, K, V> {
+    pub(crate) keys: &'a mut [K],
+    pub(crate) values: &'a mut [V],
+}
+
+impl<K, V> RandomAccess for Pairs<'_, K, V> {
+    type Item = K;
+
+    fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    fn get(&self, index: usize) -> &K {
+        &self.keys[index]
+    }
+
+    fn swap(&mut self, a: usize, b: usize) {
+        self.keys.swap(a, b);
+        self.values.swap(a, b);
+    }
+}
+
+/// Rearrange `container` so that position `i` holds the element previously at
+/// `sources[i]`, following each cycle of the permutation with swaps.
+pub(crate) fn apply_permutation<C, S>(container: &mut C, sources: S)
+where
+    C: RandomAccess + ?Sized,
+    S: AsMut<[usize]>,
+{
+    permute_with(sources, |a, b| container.swap(a, b));
+}
+
+/// Apply the permutation `sources`, as [`apply_permutation`] does, through
+/// `swap`, for containers that can only swap.
+pub(crate) fn permute_with<S>(mut sources: S, swap: impl FnMut(usize, usize))
+where
+    S: AsMut<[usize]>,
+{
+    let sources = sources.as_mut();
+    #[cfg(feature = "verify")]
+    crate::verify::verify_moves(sources, |indices| {
+        let mut replay = crate::verify::copied(sources);
+        follow_cycles(replay.as_mut(), |a, b| indices.swap(a, b))
+    });
+    follow_cycles(sources, swap);
+}
+
+/// The swaps of [`permute_with`], leaving `sources` as the identity.
+fn follow_cycles(sources: &mut [usize], mut swap: impl FnMut(usize, usize)) {
+    for start in 0..sources.len() {
+        let mut position = start;
+        while sources[position] != start {
+            let source = sources[position];
+            swap(position, source);
+            opcount::element_moves(2);
+            // `position` now holds its final element
+            sources[position] = position;
+            position = source;
+        }
+        sources[position] = position;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_permutation() {
+        let mut data = vec!['a', 'b', 'c', 'd', 'e'];
+        apply_permutation(data.as_mut_slice(), vec![3, 0, 4, 1, 2]);
+        assert_eq!(data, vec!['d', 'a', 'e', 'b', 'c']);
+    }
+
+    #[test]
+    fn test_apply_identity_permutation() {
+        let mut data = vec![1, 2, 3];
+        apply_permutation(data.as_mut_slice(), vec![0, 1, 2]);
+        assert_eq!(data, vec![1, 2, 3]);
+    }
+}
@@ -0,0 +1,193 @@
+//! Type-erased key extraction for sort orders chosen at runtime.
+
+use std::any::{Any, TypeId};
+use std::cmp::Ordering;
+use std::fmt;
+use std::marker::PhantomData;
+
+use crate::key_extractor::KeyExtractor;
+
+/// Comparison between keys whose type is only known at runtime.
+trait ErasedOrd: Any {
+    fn as_any(&self) -> &dyn Any;
+    fn cmp_erased(&self, other: &dyn ErasedOrd) -> Ordering;
+}
+
+impl<K: Ord + 'static> ErasedOrd for K {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn cmp_erased(&self, other: &dyn ErasedOrd) -> Ordering {
+        let other = other.as_any();
+        match other.downcast_ref::<K>() {
+            Some(other) => self.cmp(other),
+            // Only keys of one extractor are ever compared, so this is rare;
+            // ordering by type keeps the comparison total
+            None => TypeId::of::<K>().cmp(&other.type_id()),
+        }
+    }
+}
+
+/// A sort key whose concrete type has been erased.
+///
+/// Produced by a [`KeyExtractorDyn`]. Keys of the same type compare with
+/// that type's `Ord`; keys of different types, which only a hand-written
+/// extractor returning several types could produce, are ordered by type in
+/// an arbitrary but consistent way. Each key is a separate allocation.
+pub struct DynKey(Box<dyn ErasedOrd>);
+
+impl DynKey {
+    /// Erase the type of `key`.
+    pub fn new<K: Ord + 'static>(key: K) -> Self {
+        DynKey(Box::new(key))
+    }
+
+    /// The key as its concrete type, if it is a `K`.
+    pub fn downcast_ref<K: 'static>(&self) -> Option<&K> {
+        self.0.as_any().downcast_ref()
+    }
+}
+
+impl fmt::Debug for DynKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("DynKey(..)")
+    }
+}
+
+impl PartialEq for DynKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for DynKey {}
+
+impl PartialOrd for DynKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for DynKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.cmp_erased(&*other.0)
+    }
+}
+
+/// Object-safe form of [`KeyExtractor`], producing [`DynKey`]s.
+///
+/// Turn any extractor into a `Box<dyn KeyExtractorDyn<T>>` with
+/// [`KeyExtractor::boxed`]. The boxed extractor is itself a `KeyExtractor`,
+/// and so is a `Vec` of them, comparing by the first and breaking ties with
+/// the rest, so a multi-key order assembled at runtime compiles to one
+/// instantiation of the sort.
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::{KeyExtractor, KeyExtractorDyn};
+///
+/// #[derive(Debug, Clone, PartialEq)]
+/// struct Item {
+///     name: &'static str,
+///     price: u32,
+/// }
+///
+/// // Criteria as they might arrive from configuration
+/// let criteria = ["-price", "name"];
+/// let order: Vec<Box<dyn KeyExtractorDyn<Item>>> = criteria
+///     .iter()
+///     .map(|&criterion| match criterion {
+///         "name" => (|item: &Item| item.name).boxed(),
+///         "-price" => (|item: &Item| item.price).reversed().boxed(),
+///         _ => unreachable!(),
+///     })
+///     .collect();
+///
+/// let item = |name, price| Item { name, price };
+/// let mut items = vec![item("pen", 2), item("ink", 5), item("cap", 2)];
+/// tilesort::tilesort_by_extractor(&mut items, order);
+/// assert_eq!(items, vec![item("ink", 5), item("cap", 2), item("pen", 2)]);
+/// ```
+pub trait KeyExtractorDyn<T> {
+    /// Extract the type-erased sort key from an element.
+    fn extract_dyn_key(&self, item: &T) -> DynKey;
+}
+
+/// Adapter erasing the key type of another extractor, made by
+/// [`KeyExtractor::boxed`].
+pub(crate) struct Erased<E, K> {
+    inner: E,
+    _marker: PhantomData<fn() -> K>,
+}
+
+impl<E, K> Erased<E, K> {
+    pub(crate) fn new(inner: E) -> Self {
+        Erased {
+            inner,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T, K, E> KeyExtractorDyn<T> for Erased<E, K>
+where
+    K: Ord + 'static,
+    E: KeyExtractor<T, K>,
+{
+    fn extract_dyn_key(&self, item: &T) -> DynKey {
+        DynKey::new(self.inner.extract_key(item))
+    }
+}
+
+impl<T> KeyExtractor<T, DynKey> for Box<dyn KeyExtractorDyn<T> + '_> {
+    fn extract_key(&self, item: &T) -> DynKey {
+        self.extract_dyn_key(item)
+    }
+}
+
+impl<T> KeyExtractor<T, Vec<DynKey>> for Vec<Box<dyn KeyExtractorDyn<T> + '_>> {
+    fn extract_key(&self, item: &T) -> Vec<DynKey> {
+        self.iter()
+            .map(|extractor| extractor.extract_dyn_key(item))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cmp::Reverse;
+
+    #[test]
+    fn test_dyn_keys_compare_by_type() {
+        assert!(DynKey::new(1) < DynKey::new(2));
+        assert!(DynKey::new("b") > DynKey::new("a"));
+        assert_eq!(DynKey::new(Reverse(3)), DynKey::new(Reverse(3)));
+        assert!(DynKey::new(Reverse(3)) < DynKey::new(Reverse(2)));
+
+        // Mismatched types still compare consistently
+        let (a, b) = (DynKey::new(1u8), DynKey::new("one"));
+        assert_ne!(a, b);
+        assert_eq!(a.cmp(&b), b.cmp(&a).reverse());
+    }
+
+    #[test]
+    fn test_downcast() {
+        let key = DynKey::new(42u64);
+        assert_eq!(key.downcast_ref::<u64>(), Some(&42));
+        assert_eq!(key.downcast_ref::<u32>(), None);
+    }
+
+    #[test]
+    fn test_boxed_extractors() {
+        let by_abs: Box<dyn KeyExtractorDyn<i32>> = (|x: &i32| x.abs()).boxed();
+        assert_eq!(by_abs.extract_key(&-4), DynKey::new(4));
+
+        let chain: Vec<Box<dyn KeyExtractorDyn<i32>>> =
+            vec![(|x: &i32| x.abs()).boxed(), (|x: &i32| *x).boxed()];
+        assert!(chain.extract_key(&-4) < chain.extract_key(&4));
+        assert!(chain.extract_key(&4) < chain.extract_key(&-5));
+    }
+}
@@ -0,0 +1,128 @@
+//! Predicting the memory a sort will allocate.
+
+use crate::config::{SortConfig, Strategy};
+use crate::tile_index::TileIndex;
+
+/// Heap memory a sort allocates, by phase, as predicted by
+/// [`estimate_memory`].
+///
+/// The key buffer lives only during the scan and the restructuring buffer
+/// only afterwards, while the tile index spans both, so the
+/// [`peak`](MemoryEstimate::peak) is less than the sum of the parts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryEstimate {
+    /// Bytes of the extracted keys, kept during the scan.
+    pub keys: usize,
+    /// Upper bound on the bytes of the tile index.
+    pub tile_index: usize,
+    /// Bytes allocated to restructure, depending on the [`strategy`](MemoryEstimate::strategy).
+    pub restructure: usize,
+    /// The strategy the sort will use.
+    pub strategy: Strategy,
+}
+
+impl MemoryEstimate {
+    /// Largest amount of heap memory allocated at any one time.
+    pub fn peak(&self) -> usize {
+        self.tile_index
+            .saturating_add(self.keys.max(self.restructure))
+    }
+}
+
+/// Estimate the heap memory of sorting `len` elements of `size_of_t` bytes
+/// with default options.
+///
+/// `size_of_k` is the size of one key, or `0` when the keys are compared in
+/// place, as with [`tilesort`](crate::tilesort) and
+/// [`IdentityKey`](crate::IdentityKey). `expected_tiles` is the number of tiles
+/// after splitting, at least the number of sorted runs in the input; a
+/// [`SortPlan`](crate::SortPlan) reports it exactly in its
+/// [`stats`](crate::SortPlan::stats).
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::{estimate_memory, Strategy};
+///
+/// let estimate = estimate_memory(1_000_000, 64, 8, 100);
+/// assert_eq!(estimate.keys, 8_000_000);
+/// assert_eq!(estimate.restructure, 64_000_000);
+/// assert_eq!(estimate.strategy, Strategy::Buffered);
+/// assert!(estimate.peak() < 64_000_000 + 10_000);
+/// ```
+pub fn estimate_memory(
+    len: usize,
+    size_of_t: usize,
+    size_of_k: usize,
+    expected_tiles: usize,
+) -> MemoryEstimate {
+    estimate_memory_with_config(
+        len,
+        size_of_t,
+        size_of_k,
+        expected_tiles,
+        &SortConfig::new(),
+    )
+}
+
+/// Like [`estimate_memory`], for a sort with the given options.
+///
+/// The [`indirect`](SortConfig::indirect) and
+/// [`max_aux_memory`](SortConfig::max_aux_memory) settings decide the
+/// restructuring strategy, and a [`capacity_hint`](SortConfig::capacity_hint)
+/// expecting more than `expected_tiles` tiles sizes the tile index.
+pub fn estimate_memory_with_config(
+    len: usize,
+    size_of_t: usize,
+    size_of_k: usize,
+    expected_tiles: usize,
+    config: &SortConfig,
+) -> MemoryEstimate {
+    let strategy = config.strategy_for(len, size_of_t);
+    MemoryEstimate {
+        keys: len.saturating_mul(size_of_k),
+        tile_index: TileIndex::estimated_bytes(
+            expected_tiles.max(config.capacity_hint.expected_tiles(len)),
+            config.inline_tiles,
+        ),
+        restructure: strategy.aux_bytes(len, size_of_t),
+        strategy,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_peak_is_larger_phase() {
+        let estimate = MemoryEstimate {
+            keys: 100,
+            tile_index: 10,
+            restructure: 40,
+            strategy: Strategy::Buffered,
+        };
+        assert_eq!(estimate.peak(), 110);
+    }
+
+    #[test]
+    fn test_budget_changes_strategy() {
+        let config = SortConfig::new().max_aux_memory(0);
+        let estimate = estimate_memory_with_config(1_000, 32, 0, 10, &config);
+        assert_eq!(estimate.strategy, Strategy::Rotate);
+        assert_eq!(estimate.restructure, 0);
+        assert_eq!(estimate.peak(), estimate.tile_index);
+    }
+
+    #[test]
+    fn test_capacity_hint_raises_tile_estimate() {
+        let unhinted = estimate_memory_with_config(100_000, 8, 0, 10, &SortConfig::new());
+        let config = SortConfig::new().assume_runs(5_000);
+        let hinted = estimate_memory_with_config(100_000, 8, 0, 10, &config);
+        assert_eq!(
+            hinted.tile_index,
+            TileIndex::estimated_bytes(5_000, config.inline_tiles)
+        );
+        assert!(hinted.tile_index > unhinted.tile_index);
+    }
+}
@@ -0,0 +1,1297 @@
+//! Sorting and merging files larger than memory.
+
+use std::cmp::Ordering;
+use std::collections::binary_heap::{BinaryHeap, PeekMut};
+use std::collections::BTreeMap;
+#[cfg(feature = "compression")]
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::mem;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{self, AtomicUsize};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::{process, thread};
+
+use crate::cancel::CHECK_INTERVAL;
+use crate::config::{Order, SortConfig};
+use crate::progress::Progress;
+
+/// Default size of the read buffer for each input and of the output buffer.
+pub const DEFAULT_MERGE_BUFFER: usize = 64 * 1024;
+
+/// Default memory for the records of one run formed by [`sort_file`].
+pub const DEFAULT_RUN_SIZE: usize = 64 * 1024 * 1024;
+
+/// Default number of runs [`sort_file`] merges at once.
+pub const DEFAULT_BATCH_SIZE: usize = 64;
+
+/// How records are delimited in the files passed to [`merge_files`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum RecordFormat {
+    /// One record per line, terminated by `\n`. The terminator is not part
+    /// of the record; a final line without one is still a record, and is
+    /// written with a terminator.
+    #[default]
+    Lines,
+    /// Each record is preceded by its length in bytes as a little-endian
+    /// `u32`.
+    LengthPrefixed,
+}
+
+/// Configuration for [`merge_files`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeConfig {
+    /// How records are delimited, in the inputs and the output.
+    pub format: RecordFormat,
+    /// Direction the inputs are sorted in, and the output will be.
+    pub order: Order,
+    /// Bytes buffered for each input and for the output.
+    pub buffer_size: usize,
+}
+
+impl Default for MergeConfig {
+    fn default() -> Self {
+        Self {
+            format: RecordFormat::default(),
+            order: Order::default(),
+            buffer_size: DEFAULT_MERGE_BUFFER,
+        }
+    }
+}
+
+impl MergeConfig {
+    /// Create a configuration for ascending, newline-delimited records.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set how records are delimited.
+    pub fn format(mut self, format: RecordFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Set the direction the inputs are sorted in.
+    pub fn order(mut self, order: Order) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Merge inputs sorted in descending order.
+    pub fn descending(self) -> Self {
+        self.order(Order::Descending)
+    }
+
+    /// Set the bytes buffered for each input and for the output.
+    pub fn buffer_size(mut self, buffer_size: usize) -> Self {
+        self.buffer_size = buffer_size;
+        self
+    }
+}
+
+/// Compare two records, by their keys if the merge has a key function and
+/// otherwise as byte strings.
+fn compare_records<K: Ord>(a: &[u8], a_key: &Option<K>, b: &[u8], b_key: &Option<K>) -> Ordering {
+    match (a_key, b_key) {
+        (Some(a_key), Some(b_key)) => a_key.cmp(b_key),
+        _ => a.cmp(b),
+    }
+}
+
+/// The next record of one input, with its key if the merge has a key
+/// function.
+struct Head<K> {
+    record: Vec<u8>,
+    key: Option<K>,
+    source: usize,
+    descending: bool,
+}
+
+impl<K: Ord> Ord for Head<K> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // `BinaryHeap` is a max-heap, so invert the order. Ties go to the
+        // earlier input, keeping equal records in input order.
+        let ordering = compare_records(&other.record, &other.key, &self.record, &self.key);
+        let ordering = if self.descending {
+            ordering.reverse()
+        } else {
+            ordering
+        };
+        ordering.then_with(|| other.source.cmp(&self.source))
+    }
+}
+
+impl<K: Ord> PartialOrd for Head<K> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K: Ord> PartialEq for Head<K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<K: Ord> Eq for Head<K> {}
+
+/// Read the next record from `reader` into `record`, replacing its contents.
+/// Returns `false` at the end of the input.
+fn read_record<R: BufRead>(
+    reader: &mut R,
+    format: RecordFormat,
+    record: &mut Vec<u8>,
+) -> io::Result<bool> {
+    record.clear();
+    match format {
+        RecordFormat::Lines => {
+            if reader.read_until(b'\n', record)? == 0 {
+                return Ok(false);
+            }
+            if record.last() == Some(&b'\n') {
+                record.pop();
+            }
+        }
+        RecordFormat::LengthPrefixed => {
+            let mut len = [0; 4];
+            // Tell a clean end of input apart from a truncated prefix
+            let read = reader.read(&mut len)?;
+            if read == 0 {
+                return Ok(false);
+            }
+            reader.read_exact(&mut len[read..])?;
+            let len = u32::from_le_bytes(len) as u64;
+            reader.take(len).read_to_end(record)?;
+            if (record.len() as u64) < len {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "record shorter than its length prefix",
+                ));
+            }
+        }
+    }
+    Ok(true)
+}
+
+/// Write `record` to `writer` in `format`.
+fn write_record<W: Write>(writer: &mut W, format: RecordFormat, record: &[u8]) -> io::Result<()> {
+    match format {
+        RecordFormat::Lines => {
+            writer.write_all(record)?;
+            writer.write_all(b"\n")
+        }
+        RecordFormat::LengthPrefixed => {
+            let len = u32::try_from(record.len()).map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "record longer than u32::MAX bytes",
+                )
+            })?;
+            writer.write_all(&len.to_le_bytes())?;
+            writer.write_all(record)
+        }
+    }
+}
+
+/// Merge files that are each already sorted into one sorted `output` file,
+/// returning the number of records written.
+///
+/// Records are compared as byte strings, in the direction of
+/// [`MergeConfig::order`]. Only one record per input is held in memory at a
+/// time, plus a buffer of [`MergeConfig::buffer_size`] bytes for each file,
+/// so the inputs may be far larger than memory. Equal records are written in
+/// input order: first by input, then by position within it.
+///
+/// # Errors
+///
+/// Any I/O error from the files is returned as is. An input that turns out
+/// not to be sorted, found when the merged output would go out of order,
+/// fails with [`io::ErrorKind::InvalidData`]; `output` is then left
+/// incomplete.
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::{merge_files, MergeConfig};
+///
+/// let dir = std::env::temp_dir().join(format!("tilesort-doc-{}", std::process::id()));
+/// std::fs::create_dir_all(&dir)?;
+/// std::fs::write(dir.join("a.txt"), "apple\ncherry\n")?;
+/// std::fs::write(dir.join("b.txt"), "banana\ndate\n")?;
+///
+/// let inputs = [dir.join("a.txt"), dir.join("b.txt")];
+/// let written = merge_files(&inputs, dir.join("merged.txt"), &MergeConfig::new())?;
+/// assert_eq!(written, 4);
+/// let merged = std::fs::read_to_string(dir.join("merged.txt"))?;
+/// assert_eq!(merged, "apple\nbanana\ncherry\ndate\n");
+/// # std::fs::remove_dir_all(&dir)?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn merge_files<P, Q>(inputs: &[P], output: Q, config: &MergeConfig) -> io::Result<u64>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    merge_impl::<P, Q, (), fn(&[u8])>(inputs, output, config, None)
+}
+
+/// Merge files that are each already sorted by `key_fn` into one `output`
+/// file sorted by it, returning the number of records written.
+///
+/// Like [`merge_files`], but comparing each record by the key `key_fn`
+/// reads from it, extracted once per record. Records with equal keys are
+/// written in input order, first by input, then by position within it.
+///
+/// # Errors
+///
+/// As for [`merge_files`], an input not sorted by `key_fn` fails with
+/// [`io::ErrorKind::InvalidData`] once it would put the output out of
+/// order.
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::keys::FieldKey;
+/// use tilesort::{merge_files_by_key, KeyExtractor, MergeConfig};
+///
+/// let dir = std::env::temp_dir().join(format!("tilesort-doc-key-{}", std::process::id()));
+/// std::fs::create_dir_all(&dir)?;
+/// std::fs::write(dir.join("a.txt"), "ann 5\nbob 20\n")?;
+/// std::fs::write(dir.join("b.txt"), "cat 9\ndan 100\n")?;
+///
+/// // Both inputs are sorted by their second field, numerically
+/// let by_score = FieldKey::parse("2,2n").unwrap();
+/// let inputs = [dir.join("a.txt"), dir.join("b.txt")];
+/// merge_files_by_key(&inputs, dir.join("merged.txt"), &MergeConfig::new(), |record| {
+///     by_score.extract_key(&String::from_utf8_lossy(record))
+/// })?;
+/// let merged = std::fs::read_to_string(dir.join("merged.txt"))?;
+/// assert_eq!(merged, "ann 5\ncat 9\nbob 20\ndan 100\n");
+/// # std::fs::remove_dir_all(&dir)?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn merge_files_by_key<P, Q, K, F>(
+    inputs: &[P],
+    output: Q,
+    config: &MergeConfig,
+    key_fn: F,
+) -> io::Result<u64>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+    K: Ord,
+    F: Fn(&[u8]) -> K,
+{
+    merge_impl(inputs, output, config, Some(key_fn))
+}
+
+/// Merge sorted `inputs` into `output`, by the keys of `key_fn` if given,
+/// otherwise as byte strings.
+fn merge_impl<P, Q, K, F>(
+    inputs: &[P],
+    output: Q,
+    config: &MergeConfig,
+    key_fn: Option<F>,
+) -> io::Result<u64>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+    K: Ord,
+    F: Fn(&[u8]) -> K,
+{
+    let readers = inputs
+        .iter()
+        .map(|path| {
+            Ok(BufReader::with_capacity(
+                config.buffer_size,
+                File::open(path)?,
+            ))
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+    let mut writer = BufWriter::with_capacity(config.buffer_size, File::create(output)?);
+    let written = merge_readers(readers, &mut writer, config, key_fn, |_| {})?;
+    writer.flush()?;
+    Ok(written)
+}
+
+/// Merge the sorted records of `readers` into `writer`, by the keys of
+/// `key_fn` if given, otherwise as byte strings, passing each record written
+/// to `on_record`.
+fn merge_readers<R, W, K, F, G>(
+    mut readers: Vec<R>,
+    writer: &mut W,
+    config: &MergeConfig,
+    key_fn: Option<F>,
+    mut on_record: G,
+) -> io::Result<u64>
+where
+    R: BufRead,
+    W: Write,
+    K: Ord,
+    F: Fn(&[u8]) -> K,
+    G: FnMut(&[u8]),
+{
+    let key_of = |record: &[u8]| key_fn.as_ref().map(|key_fn| key_fn(record));
+
+    let descending = config.order.is_descending();
+    let mut heads = BinaryHeap::with_capacity(readers.len());
+    for (source, reader) in readers.iter_mut().enumerate() {
+        let mut record = Vec::new();
+        if read_record(reader, config.format, &mut record)? {
+            heads.push(Head {
+                key: key_of(&record),
+                record,
+                source,
+                descending,
+            });
+        }
+    }
+
+    // The last record written and its key, to catch inputs that are not
+    // sorted
+    let mut previous: Option<(Vec<u8>, Option<K>)> = None;
+    let mut written = 0;
+    while let Some(mut head) = heads.peek_mut() {
+        if let Some((record, key)) = &previous {
+            let ordering = compare_records(&head.record, &head.key, record, key);
+            let ordering = if descending {
+                ordering.reverse()
+            } else {
+                ordering
+            };
+            if ordering == Ordering::Less {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("input {} is not sorted", head.source),
+                ));
+            }
+        }
+        write_record(writer, config.format, &head.record)?;
+        on_record(&head.record);
+        written += 1;
+
+        // Read the input's next record into the buffer of the one before
+        let mut next = previous
+            .take()
+            .map(|(record, _)| record)
+            .unwrap_or_default();
+        if read_record(&mut readers[head.source], config.format, &mut next)? {
+            let key = mem::replace(&mut head.key, key_of(&next));
+            previous = Some((mem::replace(&mut head.record, next), key));
+        } else {
+            let head = PeekMut::pop(head);
+            previous = Some((head.record, head.key));
+        }
+    }
+    Ok(written)
+}
+
+/// Configuration for [`sort_file`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExternalSortConfig {
+    /// How records are delimited, in the input and the output.
+    pub format: RecordFormat,
+    /// Direction to sort in.
+    pub order: Order,
+    /// Bytes buffered for each file read or written.
+    pub buffer_size: usize,
+    /// Memory for the records of one run, counting each record's bytes and
+    /// the `Vec` holding them. Runs are sorted in memory and spilled to a
+    /// temporary file each.
+    pub run_size: usize,
+    /// Number of threads sorting runs. `0` is treated as `1`.
+    pub workers: usize,
+    /// Most runs merged at once, and so most files open for reading, as
+    /// `sort --batch-size` sets. More runs are merged in passes through
+    /// intermediate files. Values below `2` are treated as `2`.
+    pub batch_size: usize,
+    /// Directory for the temporary run files, or `None` for
+    /// [`std::env::temp_dir`].
+    pub temp_dir: Option<PathBuf>,
+    /// Directory to keep the run files and their manifest in, so an
+    /// interrupted sort can be resumed, instead of a temporary directory.
+    pub checkpoint_dir: Option<PathBuf>,
+    /// Whether to reuse the runs already listed in the checkpoint
+    /// directory's manifest rather than starting afresh.
+    pub resume: bool,
+    /// How the run files and intermediate merges are compressed on disk, or
+    /// `None` to write them as records.
+    #[cfg(feature = "compression")]
+    pub compression: Option<RunCompression>,
+}
+
+impl Default for ExternalSortConfig {
+    fn default() -> Self {
+        Self {
+            format: RecordFormat::default(),
+            order: Order::default(),
+            buffer_size: DEFAULT_MERGE_BUFFER,
+            run_size: DEFAULT_RUN_SIZE,
+            workers: thread::available_parallelism().map_or(1, |n| n.get()),
+            batch_size: DEFAULT_BATCH_SIZE,
+            temp_dir: None,
+            checkpoint_dir: None,
+            resume: false,
+            #[cfg(feature = "compression")]
+            compression: None,
+        }
+    }
+}
+
+impl ExternalSortConfig {
+    /// Create a configuration for an ascending sort of newline-delimited
+    /// records, with one worker per available core.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set how records are delimited.
+    pub fn format(mut self, format: RecordFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Set the direction to sort in.
+    pub fn order(mut self, order: Order) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Sort in descending order.
+    pub fn descending(self) -> Self {
+        self.order(Order::Descending)
+    }
+
+    /// Set the bytes buffered for each file read or written.
+    pub fn buffer_size(mut self, buffer_size: usize) -> Self {
+        self.buffer_size = buffer_size;
+        self
+    }
+
+    /// Set the memory for the records of one run.
+    pub fn run_size(mut self, run_size: usize) -> Self {
+        self.run_size = run_size;
+        self
+    }
+
+    /// Set the number of threads sorting runs.
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.workers = workers;
+        self
+    }
+
+    /// Set the most runs merged at once.
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size;
+        self
+    }
+
+    /// Set the directory for the temporary run files.
+    pub fn temp_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.temp_dir = Some(dir.into());
+        self
+    }
+
+    /// Keep the run files and their manifest in `dir`, a directory of the
+    /// sort's own, so the sort can be resumed if interrupted.
+    pub fn checkpoint_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.checkpoint_dir = Some(dir.into());
+        self
+    }
+
+    /// Set whether to resume from the runs already in the checkpoint
+    /// directory.
+    pub fn resume(mut self, resume: bool) -> Self {
+        self.resume = resume;
+        self
+    }
+
+    /// Compress the run files and intermediate merges with `codec` at
+    /// `level`, trading CPU for disk bandwidth.
+    #[cfg(feature = "compression")]
+    pub fn compression(mut self, codec: impl RunCodec + 'static, level: i32) -> Self {
+        self.compression = Some(RunCompression {
+            codec: Arc::new(codec),
+            level,
+        });
+        self
+    }
+}
+
+/// A compression format for the files [`sort_file`] spills, such as lz4 or
+/// zstd from a crate of the caller's choice.
+///
+/// The codec wraps each run file as it is written and read; the input and
+/// the output are never compressed. An lz4 or zstd stream encoder that must
+/// be finished to write its last frame implements [`RunEncoder`] by doing
+/// so, and its decoder is returned from [`decoder`](Self::decoder) as is.
+#[cfg(feature = "compression")]
+pub trait RunCodec: fmt::Debug + Send + Sync {
+    /// Name of the format, recorded in a checkpoint's manifest so that runs
+    /// are only resumed with the codec that wrote them.
+    fn name(&self) -> &str;
+
+    /// Wrap `writer` in an encoder compressing at `level`, whose meaning is
+    /// the codec's own.
+    fn encoder<'a>(
+        &self,
+        writer: &'a mut dyn Write,
+        level: i32,
+    ) -> io::Result<Box<dyn RunEncoder + 'a>>;
+
+    /// Wrap `reader` in a decoder of what [`encoder`](Self::encoder) wrote.
+    fn decoder<'a>(&self, reader: Box<dyn BufRead + 'a>) -> io::Result<Box<dyn Read + 'a>>;
+}
+
+/// The writer returned by [`RunCodec::encoder`].
+#[cfg(feature = "compression")]
+pub trait RunEncoder: Write {
+    /// Write out everything buffered, ending the compressed stream.
+    fn finish(self: Box<Self>) -> io::Result<()>;
+}
+
+/// The codec and level [`sort_file`] compresses its runs with, set by
+/// [`ExternalSortConfig::compression`].
+///
+/// Two settings are equal when their codecs have the same name and their
+/// levels match.
+#[cfg(feature = "compression")]
+#[derive(Debug, Clone)]
+pub struct RunCompression {
+    /// Format the runs are written in.
+    pub codec: Arc<dyn RunCodec>,
+    /// Level passed to [`RunCodec::encoder`].
+    pub level: i32,
+}
+
+#[cfg(feature = "compression")]
+impl PartialEq for RunCompression {
+    fn eq(&self, other: &Self) -> bool {
+        self.codec.name() == other.codec.name() && self.level == other.level
+    }
+}
+
+#[cfg(feature = "compression")]
+impl Eq for RunCompression {}
+
+/// Name of the manifest a checkpoint directory keeps its completed runs in.
+const MANIFEST: &str = "manifest";
+
+/// A run of records, numbered by its position in the input.
+struct Run {
+    index: usize,
+    /// Byte range of the run in the input.
+    input: Range<u64>,
+    records: Vec<Vec<u8>>,
+}
+
+/// Path of the file run `index` is spilled to in `dir`.
+fn run_path(dir: &Path, index: usize) -> PathBuf {
+    dir.join(format!("run-{:08}", index))
+}
+
+/// A reader counting the bytes consumed from it.
+struct Tracked<R> {
+    inner: R,
+    position: u64,
+}
+
+impl<R: Read> Read for Tracked<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl<R: BufRead> BufRead for Tracked<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.inner.consume(amt);
+        self.position += amt as u64;
+    }
+}
+
+/// The directory run files are spilled to.
+struct RunDir {
+    path: PathBuf,
+    /// Whether this is a checkpoint directory, kept if the sort fails.
+    checkpoint: bool,
+    succeeded: bool,
+}
+
+impl RunDir {
+    /// Create a fresh temporary directory in `parent`.
+    fn temporary(parent: Option<&Path>) -> io::Result<Self> {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let parent = parent.map_or_else(std::env::temp_dir, Path::to_path_buf);
+        let unique = NEXT.fetch_add(1, atomic::Ordering::Relaxed);
+        let path = parent.join(format!("tilesort-runs-{}-{}", process::id(), unique));
+        fs::create_dir_all(&parent)?;
+        fs::create_dir(&path)?;
+        Ok(Self {
+            path,
+            checkpoint: false,
+            succeeded: false,
+        })
+    }
+
+    /// Use `path` as a checkpoint directory, creating it if needed.
+    fn checkpoint(path: &Path) -> io::Result<Self> {
+        fs::create_dir_all(path)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            checkpoint: true,
+            succeeded: false,
+        })
+    }
+}
+
+impl Drop for RunDir {
+    fn drop(&mut self) {
+        if !self.checkpoint {
+            let _ = fs::remove_dir_all(&self.path);
+        } else if self.succeeded {
+            // Remove only the sort's own files, and the directory if that
+            // leaves it empty
+            if let Ok(entries) = fs::read_dir(&self.path) {
+                for entry in entries.flatten() {
+                    let name = entry.file_name();
+                    let name = name.to_string_lossy();
+                    let own = ["run-", "merge-", MANIFEST]
+                        .iter()
+                        .any(|prefix| name.starts_with(prefix));
+                    if own {
+                        let _ = fs::remove_file(entry.path());
+                    }
+                }
+            }
+            let _ = fs::remove_dir(&self.path);
+        }
+    }
+}
+
+/// The manifest of a checkpoint directory, listing the runs spilled so far.
+///
+/// A header describing the settings and input the runs were made with is
+/// followed by one `run <index> <start> <end>` line per spilled run, each
+/// appended and synced once the run's file is synced, so a sort killed at
+/// any point leaves a manifest naming only complete runs.
+struct Manifest {
+    file: File,
+}
+
+impl Manifest {
+    /// Open the manifest in `dir`, returning the byte range of each run it
+    /// lists. The runs are only kept when resuming with the same `header`;
+    /// otherwise the manifest starts afresh.
+    fn open(
+        dir: &Path,
+        header: &str,
+        resume: bool,
+    ) -> io::Result<(Self, BTreeMap<usize, Range<u64>>)> {
+        let path = dir.join(MANIFEST);
+        let mut runs = BTreeMap::new();
+        if resume && path.exists() {
+            let contents = fs::read_to_string(&path)?;
+            let Some(listed) = contents.strip_prefix(header) else {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "checkpoint was made with other settings or another input",
+                ));
+            };
+            // A line cut short by the sort being killed names no run
+            for line in listed.split_inclusive('\n') {
+                let Some(line) = line.strip_suffix('\n') else {
+                    break;
+                };
+                let (index, input) = parse_run_line(line)?;
+                runs.insert(index, input);
+            }
+        }
+
+        // Rewrite the manifest without any partial line, then append to it
+        let temp = dir.join(format!("{}.tmp", MANIFEST));
+        let mut file = File::create(&temp)?;
+        file.write_all(header.as_bytes())?;
+        for (index, input) in &runs {
+            writeln!(file, "run {} {} {}", index, input.start, input.end)?;
+        }
+        file.sync_all()?;
+        fs::rename(&temp, &path)?;
+        let file = fs::OpenOptions::new().append(true).open(&path)?;
+        Ok((Self { file }, runs))
+    }
+
+    /// Record that `run` is spilled, once its file is synced.
+    fn record(&mut self, run: &Run) -> io::Result<()> {
+        let line = format!("run {} {} {}\n", run.index, run.input.start, run.input.end);
+        self.file.write_all(line.as_bytes())?;
+        self.file.sync_data()
+    }
+}
+
+/// Parse a `run <index> <start> <end>` line of a manifest.
+fn parse_run_line(line: &str) -> io::Result<(usize, Range<u64>)> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "malformed checkpoint manifest");
+    let mut fields = line.split(' ');
+    if fields.next() != Some("run") {
+        return Err(invalid());
+    }
+    let mut number = || -> io::Result<u64> {
+        fields
+            .next()
+            .and_then(|field| field.parse().ok())
+            .ok_or_else(invalid)
+    };
+    let index = usize::try_from(number()?).map_err(|_| invalid())?;
+    let (start, end) = (number()?, number()?);
+    if fields.next().is_some() || start > end {
+        return Err(invalid());
+    }
+    Ok((index, start..end))
+}
+
+/// Header of the manifest for sorting an input of `input_len` bytes with
+/// `config`: everything that decides where runs start and how they are
+/// sorted.
+fn manifest_header(config: &ExternalSortConfig, input_len: u64) -> String {
+    let mut header = format!(
+        "tilesort checkpoint 1\nformat {:?}\norder {:?}\nrun-size {}\n",
+        config.format, config.order, config.run_size
+    );
+    #[cfg(feature = "compression")]
+    if let Some(compression) = &config.compression {
+        header.push_str(&format!("codec {}\n", compression.codec.name()));
+    }
+    header.push_str(&format!("input-len {}\n", input_len));
+    header
+}
+
+/// Sort the records of the `input` file into `output`, returning the number
+/// of records written.
+///
+/// Records are compared as byte strings, in the direction of
+/// [`ExternalSortConfig::order`], and the input may be far larger than
+/// memory. It is read in runs of about [`ExternalSortConfig::run_size`]
+/// bytes, which are sorted in memory and spilled to temporary files, then
+/// merged into `output` as by [`merge_files`]. The sort is stable.
+///
+/// The stages overlap: while one thread reads the next run,
+/// [`ExternalSortConfig::workers`] threads sort the runs already read and
+/// another writes the sorted ones to disk. The stages are joined by bounded
+/// channels, so at most about `workers + 4` runs are in memory at once,
+/// and a slow disk holds back reading rather than filling memory. The merge
+/// starts once every run is on disk, and reads at most
+/// [`ExternalSortConfig::batch_size`] files at once: with more runs than
+/// that, each batch of runs is first merged into an intermediate file, in as
+/// many passes as needed. These passes do not overlap the earlier stages.
+/// The temporary files are removed before returning, whether or not the sort
+/// succeeds.
+///
+/// With a [`checkpoint_dir`](ExternalSortConfig::checkpoint_dir), the runs
+/// are spilled there and listed in a manifest as each is synced to disk, and
+/// are kept if the sort fails or is killed. Running the same sort again with
+/// [`resume`](ExternalSortConfig::resume) set reuses the listed runs: the
+/// input already covered by them is skipped, and only the runs that were
+/// in flight are sorted again before the final merge. The checkpoint's
+/// files are removed once the sort succeeds.
+///
+/// With the `compression` feature, runs and intermediate merges can be
+/// written through a [`RunCodec`] set by
+/// [`compression`](ExternalSortConfig::compression), for sorts bound by disk
+/// bandwidth rather than CPU. Each file merged then has a decoder's buffer
+/// as well as its own.
+///
+/// # Errors
+///
+/// Any I/O error from the files is returned as is, leaving `output`
+/// incomplete if it was already created. Resuming from a checkpoint made
+/// with a different format, order or run size, or from an input of another
+/// length, fails with [`io::ErrorKind::InvalidInput`]; an input found to
+/// have changed otherwise fails with [`io::ErrorKind::InvalidData`].
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::{sort_file, ExternalSortConfig};
+///
+/// let dir = std::env::temp_dir().join(format!("tilesort-doc-sort-{}", std::process::id()));
+/// std::fs::create_dir_all(&dir)?;
+/// std::fs::write(dir.join("in.txt"), "pear\napple\nfig\nbanana\n")?;
+///
+/// // Tiny runs, so the records are spilled and merged
+/// let config = ExternalSortConfig::new().run_size(64).temp_dir(&dir);
+/// let written = sort_file(dir.join("in.txt"), dir.join("out.txt"), &config)?;
+/// assert_eq!(written, 4);
+/// let sorted = std::fs::read_to_string(dir.join("out.txt"))?;
+/// assert_eq!(sorted, "apple\nbanana\nfig\npear\n");
+/// # std::fs::remove_dir_all(&dir)?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn sort_file<P, Q>(input: P, output: Q, config: &ExternalSortConfig) -> io::Result<u64>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    sort_file_with_progress(input, output, config, |_| {})
+}
+
+/// Sort the records of the `input` file into `output` as [`sort_file`]
+/// does, reporting progress to `on_progress`.
+///
+/// The callback receives a [`Progress::Spilling`] update as each run is
+/// read, then a [`Progress::Merging`] update every few thousand records
+/// merged, in every merge pass, so that an
+/// [`EtaEstimator`](crate::EtaEstimator) can tell how long each phase has
+/// left. It is called on the calling thread.
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::{sort_file_with_progress, EtaEstimator, ExternalSortConfig, Progress};
+///
+/// let dir = std::env::temp_dir().join(format!("tilesort-doc-progress-{}", std::process::id()));
+/// std::fs::create_dir_all(&dir)?;
+/// std::fs::write(dir.join("in.txt"), "pear\napple\nfig\nbanana\n")?;
+///
+/// let config = ExternalSortConfig::new().run_size(64).temp_dir(&dir);
+/// let mut eta = EtaEstimator::new();
+/// let mut last = None;
+/// sort_file_with_progress(dir.join("in.txt"), dir.join("out.txt"), &config, |p| {
+///     let _remaining = eta.update(&p);
+///     last = Some(p);
+/// })?;
+/// assert_eq!(last, Some(Progress::Merging { bytes_merged: 22, total_bytes: 22 }));
+/// # std::fs::remove_dir_all(&dir)?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn sort_file_with_progress<P, Q, F>(
+    input: P,
+    output: Q,
+    config: &ExternalSortConfig,
+    mut on_progress: F,
+) -> io::Result<u64>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+    F: FnMut(Progress),
+{
+    let mut file = File::open(input)?;
+    let input_len = file.metadata()?.len();
+    let mut dir = match &config.checkpoint_dir {
+        Some(path) => RunDir::checkpoint(path)?,
+        None => RunDir::temporary(config.temp_dir.as_deref())?,
+    };
+    let (manifest, spilled) = if dir.checkpoint {
+        let header = manifest_header(config, input_len);
+        let (manifest, spilled) = Manifest::open(&dir.path, &header, config.resume)?;
+        (Some(manifest), spilled)
+    } else {
+        (None, BTreeMap::new())
+    };
+
+    // Skip the input covered by the first runs already spilled
+    let mut first = 0;
+    while spilled.contains_key(&first) {
+        first += 1;
+    }
+    let start = first.checked_sub(1).map_or(0, |last| spilled[&last].end);
+    file.seek(SeekFrom::Start(start))?;
+    let reader = Tracked {
+        inner: BufReader::with_capacity(config.buffer_size, file),
+        position: start,
+    };
+
+    let spilling = |bytes_read| Progress::Spilling {
+        bytes_read,
+        total_bytes: input_len,
+    };
+    on_progress(spilling(start));
+    let runs = spill_runs(
+        reader,
+        first,
+        &spilled,
+        manifest,
+        &dir.path,
+        config,
+        &mut |bytes_read| on_progress(spilling(bytes_read)),
+    )?;
+    let mut runs: Vec<PathBuf> = (0..runs).map(|index| run_path(&dir.path, index)).collect();
+    let merge = MergeConfig {
+        format: config.format,
+        order: config.order,
+        buffer_size: config.buffer_size,
+    };
+    let batch_size = config.batch_size.max(2);
+    let mut progress = MergeProgress::new(
+        &mut on_progress,
+        config.format,
+        input_len * (merge_passes(runs.len(), batch_size) + 1),
+    );
+    let mut pass = 0;
+    while runs.len() > batch_size {
+        runs = merge_pass(&runs, pass, &dir, &merge, config, &mut progress)?;
+        pass += 1;
+    }
+    let written = merge_runs(&runs, output.as_ref(), &merge, config, false, &mut progress)?;
+    progress.finish();
+    dir.succeeded = true;
+    Ok(written)
+}
+
+/// Number of passes merging `batch_size` files at a time takes to leave at
+/// most `batch_size` of `runs`.
+fn merge_passes(mut runs: usize, batch_size: usize) -> u64 {
+    let mut passes = 0;
+    while runs > batch_size {
+        runs = (runs + batch_size - 1) / batch_size;
+        passes += 1;
+    }
+    passes
+}
+
+/// Reports the progress of the merge passes and final merge of
+/// [`sort_file_with_progress`], in bytes of the input merged.
+struct MergeProgress<'a> {
+    on_progress: &'a mut dyn FnMut(Progress),
+    format: RecordFormat,
+    merged: u64,
+    records: usize,
+    total: u64,
+}
+
+impl<'a> MergeProgress<'a> {
+    fn new(on_progress: &'a mut dyn FnMut(Progress), format: RecordFormat, total: u64) -> Self {
+        let mut progress = Self {
+            on_progress,
+            format,
+            merged: 0,
+            records: 0,
+            total,
+        };
+        progress.report();
+        progress
+    }
+
+    /// Count `record` as merged, with the delimiter it had in the input.
+    fn record(&mut self, record: &[u8]) {
+        let delimiter = match self.format {
+            RecordFormat::Lines => 1,
+            RecordFormat::LengthPrefixed => mem::size_of::<u32>(),
+        };
+        self.merged += (record.len() + delimiter) as u64;
+        self.records += 1;
+        if self.records % CHECK_INTERVAL == 0 {
+            self.report();
+        }
+    }
+
+    fn report(&mut self) {
+        (self.on_progress)(Progress::Merging {
+            // A final line without a terminator is counted with one
+            bytes_merged: self.merged.min(self.total),
+            total_bytes: self.total,
+        });
+    }
+
+    /// Report the merge complete.
+    fn finish(&mut self) {
+        self.merged = self.total;
+        self.report();
+    }
+}
+
+/// Merge each `config.batch_size` consecutive `runs` into a file of its own
+/// in `dir`, returning the merged files in order, so that ties still keep
+/// input order.
+///
+/// Each batch is removed once merged, to bound the disk used, except that a
+/// checkpoint keeps its spilled runs to resume from.
+fn merge_pass(
+    runs: &[PathBuf],
+    pass: usize,
+    dir: &RunDir,
+    merge: &MergeConfig,
+    config: &ExternalSortConfig,
+    progress: &mut MergeProgress<'_>,
+) -> io::Result<Vec<PathBuf>> {
+    runs.chunks(config.batch_size.max(2))
+        .enumerate()
+        .map(|(index, batch)| {
+            let merged = dir.path.join(format!("merge-{}-{:08}", pass, index));
+            merge_runs(batch, &merged, merge, config, true, progress)?;
+            if pass > 0 || !dir.checkpoint {
+                for file in batch {
+                    fs::remove_file(file)?;
+                }
+            }
+            Ok(merged)
+        })
+        .collect()
+}
+
+/// Merge the files `runs` spilled into `output`, compressed as an
+/// `intermediate` file of the sort's own, or uncompressed as its output,
+/// counting the records merged in `progress`.
+#[cfg_attr(not(feature = "compression"), allow(unused_variables))]
+fn merge_runs(
+    runs: &[PathBuf],
+    output: &Path,
+    merge: &MergeConfig,
+    config: &ExternalSortConfig,
+    intermediate: bool,
+    progress: &mut MergeProgress<'_>,
+) -> io::Result<u64> {
+    let on_record = |record: &[u8]| progress.record(record);
+    #[cfg(feature = "compression")]
+    if let Some(compression) = &config.compression {
+        let codec = &compression.codec;
+        let readers = runs
+            .iter()
+            .map(|path| {
+                let file = BufReader::with_capacity(merge.buffer_size, File::open(path)?);
+                let decoder = codec.decoder(Box::new(file))?;
+                Ok(BufReader::with_capacity(merge.buffer_size, decoder))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        let mut writer = BufWriter::with_capacity(merge.buffer_size, File::create(output)?);
+        let written = if intermediate {
+            let mut encoder = codec.encoder(&mut writer, compression.level)?;
+            let written =
+                merge_readers(readers, &mut encoder, merge, None::<fn(&[u8])>, on_record)?;
+            encoder.finish()?;
+            written
+        } else {
+            merge_readers(readers, &mut writer, merge, None::<fn(&[u8])>, on_record)?
+        };
+        writer.flush()?;
+        return Ok(written);
+    }
+    let readers = runs
+        .iter()
+        .map(|path| {
+            Ok(BufReader::with_capacity(
+                merge.buffer_size,
+                File::open(path)?,
+            ))
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+    let mut writer = BufWriter::with_capacity(merge.buffer_size, File::create(output)?);
+    let written = merge_readers(readers, &mut writer, merge, None::<fn(&[u8])>, on_record)?;
+    writer.flush()?;
+    Ok(written)
+}
+
+/// Run the reader, sorter and writer stages, starting with run `first`,
+/// returning the total number of runs. Runs in `spilled` are read but not
+/// sorted or written again. `on_read` is passed the input's position after
+/// each run is read.
+fn spill_runs<R: BufRead>(
+    reader: Tracked<R>,
+    first: usize,
+    spilled: &BTreeMap<usize, Range<u64>>,
+    manifest: Option<Manifest>,
+    dir: &Path,
+    config: &ExternalSortConfig,
+    on_read: &mut dyn FnMut(u64),
+) -> io::Result<usize> {
+    let workers = config.workers.max(1);
+    let (read_tx, read_rx) = mpsc::sync_channel::<Run>(1);
+    let (sorted_tx, sorted_rx) = mpsc::sync_channel::<Run>(1);
+    // Shared by the workers, so the reader's sends fail once they all stop
+    let read_rx = Arc::new(Mutex::new(read_rx));
+    let sort_config = SortConfig::new().order(config.order);
+
+    thread::scope(|scope| {
+        let writer = scope.spawn(|| write_runs(sorted_rx, manifest, dir, config));
+        for _ in 0..workers {
+            let read_rx = Arc::clone(&read_rx);
+            let sorted_tx = sorted_tx.clone();
+            let sort_config = &sort_config;
+            scope.spawn(move || loop {
+                let next = read_rx.lock().unwrap_or_else(|e| e.into_inner()).recv();
+                let Ok(mut run) = next else {
+                    break;
+                };
+                crate::tilesort_with_config(&mut run.records, sort_config);
+                // The writer has failed, and will report why
+                if sorted_tx.send(run).is_err() {
+                    break;
+                }
+            });
+        }
+        drop((read_rx, sorted_tx));
+
+        let read = read_runs(reader, first, spilled, config, read_tx, on_read);
+        writer
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic))?;
+        read
+    })
+}
+
+/// Read records from `reader` in runs of about `config.run_size` bytes,
+/// sending each run not already `spilled`, and returning the total number of
+/// runs. `on_read` is passed the position of `reader` after each run.
+fn read_runs<R: BufRead>(
+    mut reader: Tracked<R>,
+    first: usize,
+    spilled: &BTreeMap<usize, Range<u64>>,
+    config: &ExternalSortConfig,
+    runs: SyncSender<Run>,
+    on_read: &mut dyn FnMut(u64),
+) -> io::Result<usize> {
+    let mut index = first;
+    let mut records = Vec::new();
+    let mut bytes = 0;
+    let mut start = reader.position;
+    let mut record = Vec::new();
+    loop {
+        let more = read_record(&mut reader, config.format, &mut record)?;
+        if more {
+            bytes += record.len() + mem::size_of::<Vec<u8>>();
+            records.push(mem::take(&mut record));
+            if bytes < config.run_size {
+                continue;
+            }
+        } else if records.is_empty() {
+            return Ok(index);
+        }
+
+        let run = Run {
+            index,
+            input: start..reader.position,
+            records: mem::take(&mut records),
+        };
+        match spilled.get(&index) {
+            Some(input) if *input != run.input => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "input has changed since the checkpoint",
+                ));
+            }
+            Some(_) => {}
+            // Every worker has stopped because the writer failed
+            None if runs.send(run).is_err() => return Ok(index + 1),
+            None => {}
+        }
+        on_read(reader.position);
+        index += 1;
+        bytes = 0;
+        start = reader.position;
+        if !more {
+            return Ok(index);
+        }
+    }
+}
+
+/// Write each sorted run to its own file in `dir`, recording it in the
+/// checkpoint's `manifest` if there is one.
+fn write_runs(
+    runs: Receiver<Run>,
+    mut manifest: Option<Manifest>,
+    dir: &Path,
+    config: &ExternalSortConfig,
+) -> io::Result<()> {
+    // Runs arrive in the order the workers finish them
+    for run in runs {
+        let file = File::create(run_path(dir, run.index))?;
+        let mut writer = BufWriter::with_capacity(config.buffer_size, file);
+        encode_run(&mut writer, &run.records, config)?;
+        if let Some(manifest) = &mut manifest {
+            writer
+                .into_inner()
+                .map_err(|e| e.into_error())?
+                .sync_all()?;
+            manifest.record(&run)?;
+        } else {
+            writer.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// Write the sorted `records` of a run to `writer`, compressed if the
+/// sort compresses its runs.
+fn encode_run<W: Write>(
+    writer: &mut W,
+    records: &[Vec<u8>],
+    config: &ExternalSortConfig,
+) -> io::Result<()> {
+    #[cfg(feature = "compression")]
+    if let Some(compression) = &config.compression {
+        let mut encoder = compression.codec.encoder(writer, compression.level)?;
+        for record in records {
+            write_record(&mut encoder, config.format, record)?;
+        }
+        return encoder.finish();
+    }
+    for record in records {
+        write_record(writer, config.format, record)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn records(bytes: &[u8], format: RecordFormat) -> io::Result<Vec<Vec<u8>>> {
+        let mut reader = bytes;
+        let mut records = Vec::new();
+        let mut record = Vec::new();
+        while read_record(&mut reader, format, &mut record)? {
+            records.push(record.clone());
+        }
+        Ok(records)
+    }
+
+    #[test]
+    fn test_read_lines() {
+        let lines = records(b"b\n\na", RecordFormat::Lines).unwrap();
+        assert_eq!(lines, vec![b"b".to_vec(), vec![], b"a".to_vec()]);
+    }
+
+    #[test]
+    fn test_length_prefixed_round_trip() {
+        let mut bytes = Vec::new();
+        for record in [&b"one"[..], b"", b"with\nnewline"] {
+            write_record(&mut bytes, RecordFormat::LengthPrefixed, record).unwrap();
+        }
+        let read = records(&bytes, RecordFormat::LengthPrefixed).unwrap();
+        assert_eq!(
+            read,
+            vec![b"one".to_vec(), vec![], b"with\nnewline".to_vec()]
+        );
+    }
+
+    #[test]
+    fn test_truncated_length_prefixed() {
+        let err = records(&[5, 0, 0, 0, b'a'], RecordFormat::LengthPrefixed).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        let err = records(&[5, 0], RecordFormat::LengthPrefixed).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_parse_run_line() {
+        assert_eq!(parse_run_line("run 3 10 25").unwrap(), (3, 10..25));
+        for line in [
+            "run 3 10",
+            "run 3 25 10",
+            "run 3 10 25 1",
+            "walk 3 10 25",
+            "run x 1 2",
+        ] {
+            let err = parse_run_line(line).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+}
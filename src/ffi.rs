@@ -0,0 +1,181 @@
+//! C interface for sorting primitive arrays and opaque records.
+//!
+//! Every function takes a raw pointer and an element count. A null pointer
+//! is accepted when the count is zero. The matching declarations are in
+//! `include/tilesort.h`, which can be regenerated with `cbindgen`.
+
+use std::cmp::Ordering;
+use std::ffi::{c_int, c_void};
+use std::slice;
+
+use crate::config::SortConfig;
+use crate::float::f64_total_order_key;
+use crate::key_extractor::IdentityKey;
+use crate::sorter;
+
+/// Comparison callback: negative, zero or positive as `a` sorts before,
+/// with, or after `b`. `context` is passed through unchanged.
+pub type TilesortCompare =
+    extern "C" fn(a: *const c_void, b: *const c_void, context: *mut c_void) -> c_int;
+
+/// Borrow `len` elements at `data`, treating a zero count as empty.
+unsafe fn as_slice_mut<'a, T>(data: *mut T, len: usize) -> &'a mut [T] {
+    if len == 0 {
+        &mut []
+    } else {
+        slice::from_raw_parts_mut(data, len)
+    }
+}
+
+/// Sort `len` 64-bit integers at `data` in ascending order.
+///
+/// # Safety
+///
+/// Unless `len` is zero, `data` must point to `len` initialized, writable
+/// `int64_t` values that nothing else accesses during the call.
+#[no_mangle]
+pub unsafe extern "C" fn tilesort_i64(data: *mut i64, len: usize) {
+    crate::tilesort(as_slice_mut(data, len));
+}
+
+/// Sort `len` doubles at `data` in ascending IEEE 754 total order: negative
+/// NaNs, negative infinity, negative numbers, `-0.0`, `0.0`, positive
+/// numbers, positive infinity, positive NaNs.
+///
+/// # Safety
+///
+/// Unless `len` is zero, `data` must point to `len` initialized, writable
+/// `double` values that nothing else accesses during the call.
+#[no_mangle]
+pub unsafe extern "C" fn tilesort_f64_total(data: *mut f64, len: usize) {
+    crate::tilesort_by_key(as_slice_mut(data, len), |&x| f64_total_order_key(x));
+}
+
+/// Sort `len` records of `size` bytes each at `base` with `compare`, like
+/// C's `qsort` but stable and fast on nearly sorted input.
+///
+/// # Safety
+///
+/// Unless `len` or `size` is zero, `base` must point to `len * size`
+/// initialized, writable bytes that nothing else accesses during the call.
+/// `compare` must be safe to call with pointers to any two records and
+/// `context`, must not unwind, and must define a total order.
+#[no_mangle]
+pub unsafe extern "C" fn tilesort_by_comparator(
+    base: *mut c_void,
+    len: usize,
+    size: usize,
+    compare: TilesortCompare,
+    context: *mut c_void,
+) {
+    if len <= 1 || size == 0 {
+        return;
+    }
+    let bytes = slice::from_raw_parts_mut(base.cast::<u8>(), len * size);
+
+    let records: Vec<Record> = bytes
+        .chunks_exact(size)
+        .map(|record| Record {
+            ptr: record.as_ptr().cast(),
+            compare,
+            context,
+        })
+        .collect();
+    let tile_index = sorter::scan_plan(&records, &IdentityKey, &SortConfig::new());
+
+    let mut sorted = Vec::with_capacity(bytes.len());
+    for idx in tile_index.sorted_indices() {
+        sorted.extend_from_slice(&bytes[idx * size..(idx + 1) * size]);
+    }
+    drop(records);
+    bytes.copy_from_slice(&sorted);
+}
+
+/// A record compared through the caller's callback.
+#[derive(Clone, Copy)]
+struct Record {
+    ptr: *const c_void,
+    compare: TilesortCompare,
+    context: *mut c_void,
+}
+
+impl Ord for Record {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.compare)(self.ptr, other.ptr, self.context).cmp(&0)
+    }
+}
+
+impl PartialOrd for Record {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Record {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Record {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_i64() {
+        let mut data = vec![4i64, 5, -3, 1, 2];
+        unsafe { tilesort_i64(data.as_mut_ptr(), data.len()) };
+        assert_eq!(data, vec![-3, 1, 2, 4, 5]);
+        unsafe { tilesort_i64(std::ptr::null_mut(), 0) };
+    }
+
+    #[test]
+    fn test_f64_total_order() {
+        let mut data = vec![1.5, f64::NAN, -0.0, f64::NEG_INFINITY, 0.0, -2.0];
+        unsafe { tilesort_f64_total(data.as_mut_ptr(), data.len()) };
+        let mut expected = data.clone();
+        expected.sort_by(f64::total_cmp);
+        assert_eq!(
+            data.iter().map(|x| x.to_bits()).collect::<Vec<_>>(),
+            expected.iter().map(|x| x.to_bits()).collect::<Vec<_>>()
+        );
+    }
+
+    #[repr(C)]
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct Sample {
+        timestamp: u32,
+        value: u16,
+    }
+
+    extern "C" fn by_timestamp(a: *const c_void, b: *const c_void, calls: *mut c_void) -> c_int {
+        unsafe {
+            *calls.cast::<usize>() += 1;
+            let (a, b) = (&*a.cast::<Sample>(), &*b.cast::<Sample>());
+            a.timestamp.cmp(&b.timestamp) as c_int
+        }
+    }
+
+    #[test]
+    fn test_comparator_is_stable() {
+        let mut data: Vec<Sample> = [(3, 0), (4, 1), (1, 2), (3, 3), (2, 4)]
+            .into_iter()
+            .map(|(timestamp, value)| Sample { timestamp, value })
+            .collect();
+        let mut calls = 0usize;
+        unsafe {
+            tilesort_by_comparator(
+                data.as_mut_ptr().cast(),
+                data.len(),
+                std::mem::size_of::<Sample>(),
+                by_timestamp,
+                (&mut calls as *mut usize).cast(),
+            )
+        };
+        let order: Vec<(u32, u16)> = data.iter().map(|s| (s.timestamp, s.value)).collect();
+        assert_eq!(order, vec![(1, 2), (2, 4), (3, 0), (3, 3), (4, 1)]);
+        assert!(calls > 0);
+    }
+}
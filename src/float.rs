@@ -0,0 +1,42 @@
+//! Integer keys for floats, shared by the language bindings.
+
+/// Map a double to an integer with the same order as `f64::total_cmp`.
+pub(crate) fn f64_total_order_key(x: f64) -> i64 {
+    let bits = x.to_bits() as i64;
+    bits ^ ((((bits >> 63) as u64) >> 1) as i64)
+}
+
+/// Map a float to an integer with the same order as `f32::total_cmp`.
+#[cfg(feature = "python")]
+pub(crate) fn f32_total_order_key(x: f32) -> i32 {
+    let bits = x.to_bits() as i32;
+    bits ^ ((((bits >> 31) as u32) >> 1) as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_f64_key_matches_total_cmp() {
+        let values = [
+            f64::NAN,
+            -f64::NAN,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            -0.0,
+            0.0,
+            1.5,
+            -1.5,
+            f64::MIN_POSITIVE,
+        ];
+        for a in values {
+            for b in values {
+                assert_eq!(
+                    f64_total_order_key(a).cmp(&f64_total_order_key(b)),
+                    a.total_cmp(&b)
+                );
+            }
+        }
+    }
+}
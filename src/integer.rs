@@ -0,0 +1,247 @@
+//! Integer-specific strategies for inputs that would fragment the tile index.
+//!
+//! With few distinct keys, every run in the input overlaps every other one and
+//! the tile index fragments into roughly one tile per key per run. Counting
+//! the keys instead places every element in one pass over the keys plus one
+//! over the domain.
+//!
+//! Stretches of essentially random keys fragment the index too, into tiles of
+//! one or two elements. Those are radix sorted in place before the scan, so
+//! each becomes a single tile.
+
+use crate::config::SortConfig;
+use crate::container::{apply_permutation, Pairs};
+
+/// Runs shorter than this count as random.
+const SHORT_RUN: usize = 8;
+
+/// Stretches of short runs at least this long are radix sorted.
+const MIN_RANDOM_SEGMENT: usize = 256;
+
+mod sealed {
+    pub trait Sealed: Copy {
+        /// Order-preserving mapping onto `u64`.
+        fn ordinal(self) -> u64;
+    }
+}
+
+/// Integer keys accepted by [`tilesort_integers`](crate::tilesort_integers)
+/// and [`tilesort_by_integer_key`](crate::tilesort_by_integer_key).
+///
+/// Implemented for the fixed-width integer types.
+pub trait IntegerKey: sealed::Sealed + Ord {}
+
+macro_rules! impl_integer_key {
+    (unsigned: $($u:ty),*; signed: $($s:ty),*) => {
+        $(
+            impl sealed::Sealed for $u {
+                fn ordinal(self) -> u64 {
+                    self as u64
+                }
+            }
+            impl IntegerKey for $u {}
+        )*
+        $(
+            impl sealed::Sealed for $s {
+                fn ordinal(self) -> u64 {
+                    // Flip the sign bit so negative values order first
+                    (self as i64 as u64) ^ (1 << 63)
+                }
+            }
+            impl IntegerKey for $s {}
+        )*
+    };
+}
+
+impl_integer_key!(unsigned: u8, u16, u32, u64; signed: i8, i16, i32, i64);
+
+/// Input indices of `keys` in stable sorted order, computed by counting, or
+/// `None` if the keys are already in order or their range is too wide.
+///
+/// The range must fit within [`SortConfig::counting_sort_threshold`] and must
+/// not exceed the number of keys, so the counting table is never larger than
+/// the input.
+pub(crate) fn counting_sort_order<K: IntegerKey>(
+    keys: &[K],
+    config: &SortConfig,
+) -> Option<Vec<usize>> {
+    let reverse = config.order.is_descending();
+    let first = keys.first()?.ordinal();
+    let (mut min, mut max) = (first, first);
+    let mut in_order = true;
+    for pair in keys.windows(2) {
+        let (prev, next) = (pair[0].ordinal(), pair[1].ordinal());
+        in_order &= if reverse { next <= prev } else { next >= prev };
+        min = min.min(next);
+        max = max.max(next);
+    }
+
+    let span = max - min;
+    let limit = config.counting_sort_threshold.min(keys.len()) as u64;
+    if in_order || span >= limit {
+        return None;
+    }
+
+    let bucket = |key: &K| {
+        let offset = key.ordinal() - min;
+        (if reverse { span - offset } else { offset }) as usize
+    };
+
+    // Count each key, then turn the counts into starting positions
+    let mut starts = vec![0usize; span as usize + 1];
+    for key in keys {
+        starts[bucket(key)] += 1;
+    }
+    let mut position = 0;
+    for slot in starts.iter_mut() {
+        let count = *slot;
+        *slot = position;
+        position += count;
+    }
+
+    let mut sources = vec![0; keys.len()];
+    for (idx, key) in keys.iter().enumerate() {
+        let slot = &mut starts[bucket(key)];
+        sources[*slot] = idx;
+        *slot += 1;
+    }
+    Some(sources)
+}
+
+/// Radix sort every stretch of at least [`MIN_RANDOM_SEGMENT`] keys made of
+/// runs shorter than [`SHORT_RUN`], moving `data` along with `keys`.
+///
+/// Each segment stays in its input position and is sorted stably, so the
+/// tile index still sees its elements in input order relative to the rest.
+pub(crate) fn sort_random_segments<T, K: IntegerKey>(
+    keys: &mut [K],
+    data: &mut [T],
+    reverse: bool,
+) -> usize {
+    let breaks = |prev: &K, next: &K| if reverse { next > prev } else { next < prev };
+    let mut sorted = 0;
+    let mut segment_start = 0;
+    let mut run_start = 0;
+
+    for idx in 1..=keys.len() {
+        if idx < keys.len() && !breaks(&keys[idx - 1], &keys[idx]) {
+            continue;
+        }
+        // A run ended at `idx`; long runs close the current segment
+        if idx - run_start >= SHORT_RUN || idx == keys.len() {
+            let segment_end = if idx - run_start >= SHORT_RUN {
+                run_start
+            } else {
+                idx
+            };
+            if segment_end - segment_start >= MIN_RANDOM_SEGMENT {
+                let segment = segment_start..segment_end;
+                let sources = radix_sort_order(&keys[segment.clone()], reverse);
+                apply_permutation(
+                    &mut Pairs {
+                        keys: &mut keys[segment.clone()],
+                        values: &mut data[segment],
+                    },
+                    sources,
+                );
+                sorted += 1;
+            }
+            segment_start = idx;
+        }
+        run_start = idx;
+    }
+
+    sorted
+}
+
+/// Indices of `keys` in stable sorted order, by least-significant-digit radix
+/// sort over the bytes of their ordinals.
+fn radix_sort_order<K: IntegerKey>(keys: &[K], reverse: bool) -> Vec<usize> {
+    let ordinals: Vec<u64> = keys.iter().map(|key| key.ordinal()).collect();
+    // Bytes above the highest bit where the extremes differ are the same in
+    // every key and need no pass
+    let (min, max) = ordinals
+        .iter()
+        .fold((u64::MAX, 0), |(lo, hi), &o| (lo.min(o), hi.max(o)));
+    let passes = (64 - (min ^ max).leading_zeros() as usize + 7) / 8;
+
+    let mut order: Vec<usize> = (0..keys.len()).collect();
+    let mut scratch = vec![0; keys.len()];
+    for pass in 0..passes {
+        let digit = |idx: usize| {
+            let byte = (ordinals[idx] >> (pass * 8)) as u8;
+            (if reverse { u8::MAX - byte } else { byte }) as usize
+        };
+
+        let mut starts = [0usize; 256];
+        for &idx in &order {
+            starts[digit(idx)] += 1;
+        }
+        let mut position = 0;
+        for slot in starts.iter_mut() {
+            let count = *slot;
+            *slot = position;
+            position += count;
+        }
+        for &idx in &order {
+            let slot = &mut starts[digit(idx)];
+            scratch[*slot] = idx;
+            *slot += 1;
+        }
+        std::mem::swap(&mut order, &mut scratch);
+    }
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sealed::Sealed;
+    use super::*;
+
+    #[test]
+    fn test_ordinal_preserves_order() {
+        let signed = [i64::MIN, -2, -1, 0, 1, i64::MAX];
+        assert!(signed.windows(2).all(|w| w[0].ordinal() < w[1].ordinal()));
+        let small = [i8::MIN, -1, 0, i8::MAX];
+        assert!(small.windows(2).all(|w| w[0].ordinal() < w[1].ordinal()));
+    }
+
+    #[test]
+    fn test_counting_order_is_stable() {
+        let keys = [2u8, 0, 2, 1, 0];
+        let sources = counting_sort_order(&keys, &SortConfig::new()).unwrap();
+        assert_eq!(sources, vec![1, 4, 3, 0, 2]);
+
+        let descending = counting_sort_order(&keys, &SortConfig::new().descending()).unwrap();
+        assert_eq!(descending, vec![0, 2, 3, 1, 4]);
+    }
+
+    #[test]
+    fn test_radix_order_is_stable() {
+        let keys = [300i32, -7, 300, 5, -7, 70_000];
+        assert_eq!(radix_sort_order(&keys, false), vec![1, 4, 3, 0, 2, 5]);
+        assert_eq!(radix_sort_order(&keys, true), vec![5, 0, 2, 3, 1, 4]);
+    }
+
+    #[test]
+    fn test_random_segments_become_runs() {
+        // Sorted head, scrambled middle, sorted tail
+        let mut keys: Vec<u32> = (5_000..5_100).collect();
+        keys.extend((0..1_000).map(|i| (i * 7919) % 1_000));
+        keys.extend(0..100);
+        let mut data: Vec<u32> = keys.clone();
+
+        assert_eq!(sort_random_segments(&mut keys, &mut data, false), 1);
+        assert_eq!(keys, data);
+        assert_eq!(keys[100..1_100], (0..1_000).collect::<Vec<u32>>()[..]);
+        assert_eq!(keys[..100], (5_000..5_100).collect::<Vec<u32>>()[..]);
+    }
+
+    #[test]
+    fn test_counting_declines_wide_or_sorted_keys() {
+        assert!(counting_sort_order(&[1u32, 2, 3], &SortConfig::new()).is_none());
+        assert!(counting_sort_order(&[1000u32, 1, 2], &SortConfig::new()).is_none());
+        let disabled = SortConfig::new().counting_sort_threshold(0);
+        assert!(counting_sort_order(&[1u8, 0, 1, 0], &disabled).is_none());
+    }
+}
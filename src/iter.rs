@@ -0,0 +1,92 @@
+//! Iterator adapter for sorting collected items.
+
+use std::vec;
+
+use crate::config::SortConfig;
+use crate::sorter;
+
+/// Extension trait adding tilesort adapters to every iterator.
+///
+/// Each adapter collects the iterator, tilesorts the items and returns an
+/// iterator over them in order.
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::TilesortIterExt;
+///
+/// let batches = vec![vec![4, 5, 6], vec![1, 2, 3]];
+/// let sorted: Vec<i32> = batches.into_iter().flatten().tilesorted().collect();
+/// assert_eq!(sorted, vec![1, 2, 3, 4, 5, 6]);
+/// ```
+pub trait TilesortIterExt: Iterator + Sized {
+    /// Collect and sort the items in ascending order.
+    fn tilesorted(self) -> vec::IntoIter<Self::Item>
+    where
+        Self::Item: Ord + Clone,
+    {
+        self.tilesorted_with_config(&SortConfig::new())
+    }
+
+    /// Collect and sort the items in descending order.
+    fn tilesorted_reverse(self) -> vec::IntoIter<Self::Item>
+    where
+        Self::Item: Ord + Clone,
+    {
+        self.tilesorted_with_config(&SortConfig::new().descending())
+    }
+
+    /// Collect and sort the items with explicit options.
+    fn tilesorted_with_config(self, config: &SortConfig) -> vec::IntoIter<Self::Item>
+    where
+        Self::Item: Ord + Clone,
+    {
+        let mut items: Vec<Self::Item> = self.collect();
+        sorter::tilesort_impl(&mut items, config);
+        items.into_iter()
+    }
+
+    /// Collect and sort the items by a key.
+    ///
+    /// ```
+    /// use tilesort::TilesortIterExt;
+    ///
+    /// let words = ["ccc", "a", "bb"].into_iter().tilesorted_by_key(|w| w.len());
+    /// assert_eq!(words.collect::<Vec<_>>(), vec!["a", "bb", "ccc"]);
+    /// ```
+    fn tilesorted_by_key<K, F>(self, key_fn: F) -> vec::IntoIter<Self::Item>
+    where
+        Self::Item: Clone,
+        K: Ord,
+        F: Fn(&Self::Item) -> K,
+    {
+        let mut items: Vec<Self::Item> = self.collect();
+        sorter::tilesort_impl_with_key(&mut items, &key_fn, &SortConfig::new());
+        items.into_iter()
+    }
+}
+
+impl<I: Iterator> TilesortIterExt for I {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tilesorted_reverse() {
+        let sorted: Vec<u8> = vec![1, 2, 3, 0].into_iter().tilesorted_reverse().collect();
+        assert_eq!(sorted, vec![3, 2, 1, 0]);
+    }
+
+    #[test]
+    fn test_tilesorted_empty() {
+        assert_eq!(std::iter::empty::<i32>().tilesorted().len(), 0);
+    }
+
+    #[test]
+    fn test_tilesorted_with_config() {
+        let config = SortConfig::new().descending();
+        let sorted: Vec<char> = "abzy".chars().tilesorted_with_config(&config).collect();
+        assert_eq!(sorted, vec!['z', 'y', 'b', 'a']);
+    }
+}
@@ -0,0 +1,197 @@
+//! Lazy iteration over the smallest elements of a slice.
+
+use std::cmp::Ordering;
+use std::collections::binary_heap::{BinaryHeap, PeekMut};
+use std::iter::FusedIterator;
+use std::mem;
+
+use crate::key_extractor::KeyExtractor;
+use crate::opcount;
+
+/// Iterator over the `k` smallest elements of a slice, in sorted order.
+///
+/// Created by [`k_smallest`]. The slice is scanned for tiles once, up front;
+/// after that each element costs one key extraction and a heap operation over
+/// the tile heads.
+pub struct KSmallest<'a, T, K, E> {
+    data: &'a [T],
+    extractor: E,
+    heads: BinaryHeap<Head<K>>,
+    remaining: usize,
+}
+
+/// The next unvisited element of a tile.
+struct Head<K> {
+    key: K,
+    position: usize,
+    end: usize,
+}
+
+impl<K: Ord> Ord for Head<K> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // `BinaryHeap` is a max-heap, so invert the order. Ties go to the
+        // earlier position, keeping equal keys in input order.
+        other
+            .key
+            .cmp(&self.key)
+            .then_with(|| other.position.cmp(&self.position))
+    }
+}
+
+impl<K: Ord> PartialOrd for Head<K> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K: Ord> PartialEq for Head<K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<K: Ord> Eq for Head<K> {}
+
+/// Iterate over the `k` elements of `data` with the smallest keys, in order.
+///
+/// Unlike [`tilesort_partial`](crate::tilesort_partial), `data` is not
+/// modified: the tiles are merged lazily with a heap holding one entry per
+/// tile. Elements with equal keys are yielded in input
+/// order. Use [`IdentityKey`](crate::IdentityKey) to compare the elements
+/// themselves.
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::{k_smallest, IdentityKey};
+///
+/// let data = vec![40, 41, 42, 1, 2, 3, 20, 21];
+/// let smallest: Vec<&i32> = k_smallest(&data, 4, IdentityKey).collect();
+/// assert_eq!(smallest, vec![&1, &2, &3, &20]);
+///
+/// let words = ["pear", "fig", "banana", "kiwi"];
+/// let shortest: Vec<&&str> = k_smallest(&words, 2, |w: &&str| w.len()).collect();
+/// assert_eq!(shortest, vec![&"fig", &"pear"]);
+/// ```
+pub fn k_smallest<T, K, E>(data: &[T], k: usize, extractor: E) -> KSmallest<'_, T, K, E>
+where
+    K: Ord,
+    E: KeyExtractor<T, K>,
+{
+    let mut heads = BinaryHeap::new();
+    if k > 0 && !data.is_empty() {
+        // Find the tiles, keeping the first key of each
+        let mut tile_start = 0;
+        let mut first_key = opcount::extract_key(&extractor, &data[0]);
+        // Last key of the current tile, once it has more than one element
+        let mut last_key: Option<K> = None;
+        for (idx, element) in data.iter().enumerate().skip(1) {
+            let key = opcount::extract_key(&extractor, element);
+            if key < *last_key.as_ref().unwrap_or(&first_key) {
+                heads.push(Head {
+                    key: mem::replace(&mut first_key, key),
+                    position: tile_start,
+                    end: idx,
+                });
+                tile_start = idx;
+                last_key = None;
+            } else {
+                last_key = Some(key);
+            }
+        }
+        heads.push(Head {
+            key: first_key,
+            position: tile_start,
+            end: data.len(),
+        });
+    }
+
+    KSmallest {
+        data,
+        extractor,
+        heads,
+        remaining: k.min(data.len()),
+    }
+}
+
+impl<'a, T, K, E> Iterator for KSmallest<'a, T, K, E>
+where
+    K: Ord,
+    E: KeyExtractor<T, K>,
+{
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let mut head = self.heads.peek_mut()?;
+        let element = &self.data[head.position];
+
+        // Advance the tile, or drop it once exhausted
+        head.position += 1;
+        if head.position < head.end {
+            head.key = opcount::extract_key(&self.extractor, &self.data[head.position]);
+        } else {
+            PeekMut::pop(head);
+        }
+
+        self.remaining -= 1;
+        Some(element)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<T, K, E> ExactSizeIterator for KSmallest<'_, T, K, E>
+where
+    K: Ord,
+    E: KeyExtractor<T, K>,
+{
+}
+
+impl<T, K, E> FusedIterator for KSmallest<'_, T, K, E>
+where
+    K: Ord,
+    E: KeyExtractor<T, K>,
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IdentityKey;
+
+    #[test]
+    fn test_k_zero_and_empty() {
+        assert_eq!(k_smallest(&[3, 1, 2], 0, IdentityKey).count(), 0);
+        assert_eq!(k_smallest::<i32, _, _>(&[], 3, IdentityKey).count(), 0);
+    }
+
+    #[test]
+    fn test_k_larger_than_len() {
+        let data = [5, 6, 1, 2];
+        let all: Vec<i32> = k_smallest(&data, 10, IdentityKey).copied().collect();
+        assert_eq!(all, vec![1, 2, 5, 6]);
+    }
+
+    #[test]
+    fn test_exact_size() {
+        let data = [3, 4, 1, 2];
+        let mut iter = k_smallest(&data, 3, IdentityKey);
+        assert_eq!(iter.len(), 3);
+        iter.next();
+        assert_eq!(iter.len(), 2);
+    }
+
+    #[test]
+    fn test_equal_keys_in_input_order() {
+        let data = [(1, 'a'), (2, 'b'), (1, 'c'), (2, 'd')];
+        let tags: Vec<char> = k_smallest(&data, 4, |&(k, _): &(i32, char)| k)
+            .map(|&(_, tag)| tag)
+            .collect();
+        assert_eq!(tags, vec!['a', 'c', 'b', 'd']);
+    }
+}
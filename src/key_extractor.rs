@@ -1,12 +1,86 @@
 //! Key extraction trait for sorting with custom key functions.
 
+use std::cmp::Reverse;
+use std::marker::PhantomData;
+
+use crate::erased::{Erased, KeyExtractorDyn};
+use crate::opcount;
+
 /// Trait for extracting sort keys from elements.
 ///
 /// This trait allows tilesort to work with both elements that are directly
 /// comparable (identity extraction) and elements that need a custom key function.
+///
+/// Extractors compose like iterators:
+///
+/// ```
+/// use tilesort::KeyExtractor;
+///
+/// let mut words = vec!["bb", "a", "ccc", "dd"];
+/// let by_len_then_desc = (|s: &&str| s.len()).then((|s: &&str| s.to_string()).reversed());
+/// tilesort::tilesort_by_extractor(&mut words, by_len_then_desc);
+/// assert_eq!(words, vec!["a", "dd", "bb", "ccc"]);
+/// ```
+///
+/// A tuple of up to six extractors is itself an extractor, comparing by the
+/// first key and breaking ties with the next, like chained
+/// [`then`](KeyExtractor::then) calls:
+///
+/// ```
+/// let mut tags = vec![(31, 'b'), (25, 'c'), (25, 'a')];
+/// let by_number_then_letter = (|t: &(u32, char)| t.0, |t: &(u32, char)| t.1);
+/// tilesort::tilesort_by_extractor(&mut tags, by_number_then_letter);
+/// assert_eq!(tags, vec![(25, 'a'), (25, 'c'), (31, 'b')]);
+/// ```
 pub trait KeyExtractor<T, K> {
     /// Extract the sort key from an element.
     fn extract_key(&self, item: &T) -> K;
+
+    /// The keys of all of `data`, if they can be borrowed without extraction.
+    ///
+    /// Returning `Some` lets the sort compare keys in place rather than
+    /// collecting them into a buffer as large as the input. The default
+    /// returns `None`; [`IdentityKey`] returns `data` itself.
+    fn borrowed_keys<'a>(&self, data: &'a [T]) -> Option<&'a [K]> {
+        let _ = data;
+        None
+    }
+
+    /// Invert the ordering of this extractor's keys.
+    fn reversed(self) -> Reversed<Self>
+    where
+        Self: Sized,
+    {
+        Reversed::new(self)
+    }
+
+    /// Break ties between equal keys with the key from `next`.
+    fn then<K2, E>(self, next: E) -> Then<Self, E>
+    where
+        Self: Sized,
+        E: KeyExtractor<T, K2>,
+    {
+        Then::new(self, next)
+    }
+
+    /// Post-process each extracted key with `f`.
+    fn map<K2, F>(self, f: F) -> Map<Self, F, K>
+    where
+        Self: Sized,
+        F: Fn(K) -> K2,
+    {
+        Map::new(self, f)
+    }
+
+    /// Box this extractor behind the object-safe [`KeyExtractorDyn`], for
+    /// sort orders assembled at runtime.
+    fn boxed<'a>(self) -> Box<dyn KeyExtractorDyn<T> + 'a>
+    where
+        Self: Sized + 'a,
+        K: Ord + 'static,
+    {
+        Box::new(Erased::new(self))
+    }
 }
 
 /// Identity key extractor - the element is its own key.
@@ -16,8 +90,58 @@ pub struct IdentityKey;
 
 impl<T: Clone> KeyExtractor<T, T> for IdentityKey {
     fn extract_key(&self, item: &T) -> T {
+        opcount::key_clones(1);
         item.clone()
     }
+
+    fn borrowed_keys<'a>(&self, data: &'a [T]) -> Option<&'a [T]> {
+        Some(data)
+    }
+}
+
+/// Types that can produce their own sort key.
+///
+/// Implement this by hand, or with `#[derive(TileKey)]` when the `derive`
+/// feature is enabled, annotating key fields with
+/// `#[tile_key(order = N, desc)]`. Sort with [`ByTileKey`]:
+///
+/// ```
+/// use std::cmp::Reverse;
+/// use tilesort::{ByTileKey, TileKey};
+///
+/// #[derive(Clone)]
+/// struct Row {
+///     name: &'static str,
+///     age: u32,
+/// }
+///
+/// impl TileKey for Row {
+///     type Key = (&'static str, Reverse<u32>);
+///
+///     fn tile_key(&self) -> Self::Key {
+///         (self.name, Reverse(self.age))
+///     }
+/// }
+///
+/// let mut rows = vec![Row { name: "b", age: 1 }, Row { name: "a", age: 1 }, Row { name: "a", age: 2 }];
+/// tilesort::tilesort_by_extractor(&mut rows, ByTileKey);
+/// assert_eq!(rows.iter().map(|r| r.age).collect::<Vec<_>>(), vec![2, 1, 1]);
+/// ```
+pub trait TileKey {
+    /// The composite key type.
+    type Key: Ord;
+
+    /// Build the sort key for this value.
+    fn tile_key(&self) -> Self::Key;
+}
+
+/// Key extractor for types implementing [`TileKey`].
+pub struct ByTileKey;
+
+impl<T: TileKey> KeyExtractor<T, T::Key> for ByTileKey {
+    fn extract_key(&self, item: &T) -> T::Key {
+        item.tile_key()
+    }
 }
 
 /// Blanket implementation for function-based key extraction.
@@ -33,6 +157,168 @@ where
     }
 }
 
+/// Trait for extracting sort keys that may fail.
+///
+/// Used by [`try_tilesort_by_key`](crate::try_tilesort_by_key) when keys are
+/// parsed out of the elements and a malformed element should abort the sort
+/// rather than panic.
+pub trait TryKeyExtractor<T, K, Error> {
+    /// Extract the sort key from an element, or report why it could not be extracted.
+    fn try_extract_key(&self, item: &T) -> Result<K, Error>;
+}
+
+/// Blanket implementation for fallible closures and function pointers.
+impl<T, K, Error, F> TryKeyExtractor<T, K, Error> for F
+where
+    F: Fn(&T) -> Result<K, Error>,
+{
+    fn try_extract_key(&self, item: &T) -> Result<K, Error> {
+        self(item)
+    }
+}
+
+/// Key extractor that inverts the ordering of another extractor's keys.
+///
+/// The wrapped key is returned inside [`std::cmp::Reverse`], so a level built
+/// with this adapter sorts in descending order regardless of the overall sort
+/// direction.
+pub struct Reversed<E> {
+    inner: E,
+}
+
+impl<E> Reversed<E> {
+    pub(crate) fn new(inner: E) -> Self {
+        Reversed { inner }
+    }
+}
+
+impl<T, K, E> KeyExtractor<T, Reverse<K>> for Reversed<E>
+where
+    E: KeyExtractor<T, K>,
+{
+    fn extract_key(&self, item: &T) -> Reverse<K> {
+        Reverse(self.inner.extract_key(item))
+    }
+}
+
+/// Key extractor that chains two extractors into a tuple key.
+///
+/// Elements are compared by the key of `first`; ties are broken by the key of
+/// `second`.
+pub struct Then<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<A, B> Then<A, B> {
+    pub(crate) fn new(first: A, second: B) -> Self {
+        Then { first, second }
+    }
+}
+
+impl<T, K1, K2, A, B> KeyExtractor<T, (K1, K2)> for Then<A, B>
+where
+    A: KeyExtractor<T, K1>,
+    B: KeyExtractor<T, K2>,
+{
+    fn extract_key(&self, item: &T) -> (K1, K2) {
+        (self.first.extract_key(item), self.second.extract_key(item))
+    }
+}
+
+/// Key extractor that transforms the key produced by another extractor.
+///
+/// `K` is the key type produced by the inner extractor.
+pub struct Map<E, F, K> {
+    inner: E,
+    f: F,
+    _marker: PhantomData<fn() -> K>,
+}
+
+impl<E, F, K> Map<E, F, K> {
+    pub(crate) fn new(inner: E, f: F) -> Self {
+        Map {
+            inner,
+            f,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T, K, K2, E, F> KeyExtractor<T, K2> for Map<E, F, K>
+where
+    E: KeyExtractor<T, K>,
+    F: Fn(K) -> K2,
+{
+    fn extract_key(&self, item: &T) -> K2 {
+        (self.f)(self.inner.extract_key(item))
+    }
+}
+
+// Tuples of extractors produce tuples of their keys, compared in order
+macro_rules! tuple_extractors {
+    ($(($($extractor:ident $key:ident $index:tt),+)),+ $(,)?) => {
+        $(
+            impl<T, $($key,)+ $($extractor,)+> KeyExtractor<T, ($($key,)+)> for ($($extractor,)+)
+            where
+                $($extractor: KeyExtractor<T, $key>,)+
+            {
+                fn extract_key(&self, item: &T) -> ($($key,)+) {
+                    ($(self.$index.extract_key(item),)+)
+                }
+            }
+        )+
+    };
+}
+
+tuple_extractors!(
+    (A KA 0, B KB 1),
+    (A KA 0, B KB 1, C KC 2),
+    (A KA 0, B KB 1, C KC 2, D KD 3),
+    (A KA 0, B KB 1, C KC 2, D KD 3, E KE 4),
+    (A KA 0, B KB 1, C KC 2, D KD 3, E KE 4, F KF 5),
+);
+
+/// Key function that clones one field of a struct, written as
+/// `field!(Type::field)`.
+///
+/// Expands to `|item: &Type| item.field.clone()`, so the field must be
+/// `Clone`. The type may be given by path (`field!(records::Row::id)`), and
+/// tuple struct fields by index (`field!(Pair::0)`). Combine projections with
+/// tuples or [`then`](KeyExtractor::then) to sort by several fields.
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::field;
+///
+/// #[derive(Clone, Debug, PartialEq)]
+/// struct Event {
+///     host: String,
+///     time: u64,
+/// }
+///
+/// let event = |host: &str, time| Event { host: host.to_string(), time };
+/// let mut events = vec![event("b", 2), event("a", 2), event("a", 1)];
+/// tilesort::tilesort_by_extractor(&mut events, (field!(Event::time), field!(Event::host)));
+/// assert_eq!(events, vec![event("a", 1), event("a", 2), event("b", 2)]);
+///
+/// tilesort::tilesort_by_key(&mut events, field!(Event::host));
+/// assert_eq!(events, vec![event("a", 1), event("a", 2), event("b", 2)]);
+/// ```
+#[macro_export]
+macro_rules! field {
+    (@path [$($path:tt)*] :: $field:tt) => {
+        (|item: &$($path)*| ::core::clone::Clone::clone(&item.$field))
+    };
+    (@path [$($path:tt)*] :: $segment:ident $($rest:tt)*) => {
+        $crate::field!(@path [$($path)* :: $segment] $($rest)*)
+    };
+    ($first:ident $($rest:tt)*) => {
+        $crate::field!(@path [$first] $($rest)*)
+    };
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -44,6 +330,13 @@ mod tests {
         assert_eq!(extractor.extract_key(&"hello"), "hello");
     }
 
+    #[test]
+    fn test_borrowed_keys() {
+        let data = [3, 1, 2];
+        assert_eq!(IdentityKey.borrowed_keys(&data), Some(&data[..]));
+        assert_eq!((|x: &i32| *x).borrowed_keys(&data), None);
+    }
+
     #[test]
     fn test_function_key() {
         let extractor = |x: &i32| x.abs();
@@ -70,4 +363,94 @@ mod tests {
         let by_name_len = |p: &Person| p.name.len();
         assert_eq!(by_name_len.extract_key(&person), 5);
     }
+
+    #[test]
+    fn test_reversed_key() {
+        let extractor = Reversed::new(|x: &i32| x.abs());
+        assert_eq!(extractor.extract_key(&-42), Reverse(42));
+        assert!(extractor.extract_key(&1) > extractor.extract_key(&2));
+    }
+
+    #[test]
+    fn test_then_key() {
+        let extractor = Then::new(|s: &&str| s.len(), Reversed::new(|s: &&str| s.to_string()));
+        assert_eq!(extractor.extract_key(&"ab"), (2, Reverse("ab".to_string())));
+        assert!(extractor.extract_key(&"ba") < extractor.extract_key(&"ab"));
+        assert!(extractor.extract_key(&"zz") < extractor.extract_key(&"abc"));
+    }
+
+    #[test]
+    fn test_combinator_methods() {
+        let extractor = (|x: &i32| *x).reversed();
+        assert_eq!(extractor.extract_key(&3), Reverse(3));
+
+        let extractor = (|x: &i32| x.abs()).then(|x: &i32| x.signum());
+        assert_eq!(extractor.extract_key(&-7), (7, -1));
+
+        let extractor = (|s: &&str| s.len()).map(|len| len % 2);
+        assert_eq!(extractor.extract_key(&"abc"), 1);
+        assert_eq!(extractor.extract_key(&"ab"), 0);
+    }
+
+    #[test]
+    fn test_tuple_keys() {
+        let extractor = (|x: &i32| x.abs(), Reversed::new(|x: &i32| *x));
+        assert_eq!(extractor.extract_key(&-3), (3, Reverse(-3)));
+        assert!(extractor.extract_key(&3) < extractor.extract_key(&-3));
+
+        let extractor = (IdentityKey, |x: &i32| x % 2, |x: &i32| x % 3);
+        assert_eq!(extractor.extract_key(&5), (5, 1, 2));
+    }
+
+    #[test]
+    fn test_field_projection() {
+        mod shapes {
+            pub struct Rect {
+                pub width: u32,
+                pub label: String,
+            }
+        }
+        struct Pair(i32, &'static str);
+
+        let rect = shapes::Rect {
+            width: 4,
+            label: "door".to_string(),
+        };
+        assert_eq!(field!(shapes::Rect::width).extract_key(&rect), 4);
+        assert_eq!(field!(shapes::Rect::label).extract_key(&rect), "door");
+        assert_eq!(field!(Pair::0).extract_key(&Pair(1, "one")), 1);
+        assert_eq!(field!(Pair::1).extract_key(&Pair(1, "one")), "one");
+    }
+
+    #[test]
+    fn test_by_tile_key() {
+        struct Point {
+            x: i32,
+            y: i32,
+        }
+
+        impl TileKey for Point {
+            type Key = (i32, Reverse<i32>);
+
+            fn tile_key(&self) -> Self::Key {
+                (self.x, Reverse(self.y))
+            }
+        }
+
+        let point = Point { x: 1, y: 2 };
+        assert_eq!(ByTileKey.extract_key(&point), (1, Reverse(2)));
+    }
+
+    #[test]
+    fn test_try_key() {
+        let extractor = |s: &&str| s.parse::<i32>();
+        assert_eq!(extractor.try_extract_key(&"42"), Ok(42));
+        assert!(extractor.try_extract_key(&"forty-two").is_err());
+    }
+
+    #[test]
+    fn test_combinators_on_identity() {
+        let extractor = IdentityKey.map(|x: i32| -x).then(IdentityKey);
+        assert_eq!(extractor.extract_key(&5), (-5, 5));
+    }
 }
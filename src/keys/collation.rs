@@ -0,0 +1,150 @@
+//! Locale-aware collation keys backed by ICU4X.
+
+use std::fmt;
+
+use icu_collator::options::CollatorOptions;
+use icu_collator::{Collator, CollatorBorrowed};
+use icu_locale_core::Locale;
+
+use crate::key_extractor::KeyExtractor;
+
+/// Error returned when a collator cannot be created.
+#[derive(Debug)]
+pub enum CollationError {
+    /// The locale identifier could not be parsed.
+    InvalidLocale(String),
+    /// No collation data is available for the locale and options.
+    Data(icu_provider::DataError),
+}
+
+impl fmt::Display for CollationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CollationError::InvalidLocale(locale) => write!(f, "invalid locale: {}", locale),
+            CollationError::Data(err) => write!(f, "collation data unavailable: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for CollationError {}
+
+/// Opaque collation sort key.
+///
+/// Keys compare by plain byte order, which matches the collator's ordering, so
+/// each comparison during the sort is a cheap `memcmp` instead of a full
+/// collation pass.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CollationKey(Vec<u8>);
+
+impl CollationKey {
+    /// The raw sort key bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// Key extractor producing locale-aware [`CollationKey`]s.
+///
+/// The collator is built once when the extractor is created and reused for
+/// every element.
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::keys::Collation;
+///
+/// let swedish = Collation::for_locale("sv").unwrap();
+/// let mut words = vec!["ö", "z", "å", "a", "ä"];
+/// tilesort::tilesort_by_extractor(&mut words, &swedish);
+/// assert_eq!(words, vec!["a", "z", "å", "ä", "ö"]);
+/// ```
+pub struct Collation {
+    collator: CollatorBorrowed<'static>,
+}
+
+impl Collation {
+    /// Create an extractor for `locale` (a BCP-47 identifier such as `"de"` or `"sv-SE"`)
+    /// with default collator options.
+    pub fn for_locale(locale: &str) -> Result<Self, CollationError> {
+        Self::with_options(locale, CollatorOptions::default())
+    }
+
+    /// Create an extractor for `locale` with explicit collator options (strength, etc.).
+    pub fn with_options(locale: &str, options: CollatorOptions) -> Result<Self, CollationError> {
+        let locale = Locale::try_from_str(locale)
+            .map_err(|_| CollationError::InvalidLocale(locale.to_string()))?;
+        let collator =
+            Collator::try_new((&locale).into(), options).map_err(CollationError::Data)?;
+        Ok(Collation { collator })
+    }
+
+    /// Compute the collation key for `s`.
+    pub fn collation_key(&self, s: &str) -> CollationKey {
+        let mut bytes = Vec::new();
+        let Ok(()) = self.collator.write_sort_key_to(s, &mut bytes);
+        CollationKey(bytes)
+    }
+}
+
+impl<T: AsRef<str>> KeyExtractor<T, CollationKey> for Collation {
+    fn extract_key(&self, item: &T) -> CollationKey {
+        self.collation_key(item.as_ref())
+    }
+}
+
+// Lets one extractor be shared across many sorts without rebuilding the collator.
+impl<T: AsRef<str>> KeyExtractor<T, CollationKey> for &Collation {
+    fn extract_key(&self, item: &T) -> CollationKey {
+        self.collation_key(item.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use icu_collator::options::Strength;
+
+    fn sorted(collation: &Collation, words: &[&str]) -> Vec<String> {
+        let mut words: Vec<String> = words.iter().map(|w| w.to_string()).collect();
+        words.sort_by_key(|w| collation.collation_key(w));
+        words
+    }
+
+    #[test]
+    fn test_swedish_letters_after_z() {
+        let collation = Collation::for_locale("sv").unwrap();
+        assert_eq!(
+            sorted(&collation, &["ö", "å", "z", "ä"]),
+            vec!["z", "å", "ä", "ö"]
+        );
+    }
+
+    #[test]
+    fn test_german_letters_sort_with_base_letter() {
+        let collation = Collation::for_locale("de").unwrap();
+        assert_eq!(
+            sorted(&collation, &["Zebra", "Äpfel", "Bär", "Apfel"]),
+            vec!["Apfel", "Äpfel", "Bär", "Zebra"]
+        );
+        assert!(collation.collation_key("Straße") < collation.collation_key("Strasze"));
+    }
+
+    #[test]
+    fn test_primary_strength_ignores_case_and_accents() {
+        let mut options = CollatorOptions::default();
+        options.strength = Some(Strength::Primary);
+        let collation = Collation::with_options("en", options).unwrap();
+        assert_eq!(
+            collation.collation_key("Héllo"),
+            collation.collation_key("hello")
+        );
+    }
+
+    #[test]
+    fn test_invalid_locale() {
+        assert!(matches!(
+            Collation::for_locale("not a locale!"),
+            Err(CollationError::InvalidLocale(_))
+        ));
+    }
+}
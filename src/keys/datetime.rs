@@ -0,0 +1,366 @@
+//! Timestamp keys parsed from log lines and other strings.
+
+use std::fmt;
+
+use crate::key_extractor::KeyExtractor;
+
+/// A point in time, as seconds and nanoseconds since the Unix epoch in UTC.
+///
+/// Ordering is chronological. Parsed with [`parse_rfc3339`] or
+/// [`parse_epoch_millis`], or extracted from string elements by [`Rfc3339`]
+/// and [`EpochMillis`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp {
+    seconds: i64,
+    nanos: u32,
+}
+
+impl Timestamp {
+    /// The timestamp `millis` milliseconds after the Unix epoch.
+    pub fn from_unix_millis(millis: i64) -> Self {
+        Timestamp {
+            seconds: millis.div_euclid(1000),
+            nanos: millis.rem_euclid(1000) as u32 * 1_000_000,
+        }
+    }
+
+    /// Whole seconds since the Unix epoch, rounded towards the past.
+    pub fn unix_seconds(&self) -> i64 {
+        self.seconds
+    }
+
+    /// Nanoseconds past [`unix_seconds`](Timestamp::unix_seconds).
+    pub fn subsec_nanos(&self) -> u32 {
+        self.nanos
+    }
+
+    /// Milliseconds since the Unix epoch, rounded towards the past.
+    pub fn unix_millis(&self) -> i64 {
+        self.seconds * 1000 + i64::from(self.nanos / 1_000_000)
+    }
+}
+
+/// Error returned when a string does not hold a valid timestamp.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimestampError {
+    reason: &'static str,
+}
+
+impl fmt::Display for TimestampError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid timestamp: {}", self.reason)
+    }
+}
+
+impl std::error::Error for TimestampError {}
+
+fn invalid(reason: &'static str) -> TimestampError {
+    TimestampError { reason }
+}
+
+/// Reads fixed-width fields from the front of a string.
+struct Cursor<'a> {
+    rest: &'a [u8],
+}
+
+impl Cursor<'_> {
+    /// Parse exactly `width` ASCII digits.
+    fn digits(&mut self, width: usize, field: &'static str) -> Result<u32, TimestampError> {
+        let digits = self.rest.get(..width).ok_or(invalid(field))?;
+        if !digits.iter().all(u8::is_ascii_digit) {
+            return Err(invalid(field));
+        }
+        self.rest = &self.rest[width..];
+        Ok(digits
+            .iter()
+            .fold(0, |value, digit| value * 10 + u32::from(digit - b'0')))
+    }
+
+    /// Consume one byte if it is among `expected`.
+    fn byte(&mut self, expected: &[u8], field: &'static str) -> Result<u8, TimestampError> {
+        match self.rest.split_first() {
+            Some((&byte, rest)) if expected.contains(&byte) => {
+                self.rest = rest;
+                Ok(byte)
+            }
+            _ => Err(invalid(field)),
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.rest.first().copied()
+    }
+}
+
+/// Days from 1970-01-01 to the given date of the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    // Count years from March, so the leap day ends the year
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month_from_march = i64::from((month + 9) % 12);
+    let day_of_year = (153 * month_from_march + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn days_in_month(year: u32, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Parse an RFC 3339 timestamp from the start of `s`, returning it and the
+/// unparsed remainder.
+fn parse_rfc3339_prefix(s: &str) -> Result<(Timestamp, &str), TimestampError> {
+    let mut cursor = Cursor { rest: s.as_bytes() };
+    let year = cursor.digits(4, "year")?;
+    cursor.byte(b"-", "date separator")?;
+    let month = cursor.digits(2, "month")?;
+    cursor.byte(b"-", "date separator")?;
+    let day = cursor.digits(2, "day")?;
+    cursor.byte(b"Tt ", "date and time separator")?;
+    let hour = cursor.digits(2, "hour")?;
+    cursor.byte(b":", "time separator")?;
+    let minute = cursor.digits(2, "minute")?;
+    cursor.byte(b":", "time separator")?;
+    let second = cursor.digits(2, "second")?;
+
+    if !(1..=12).contains(&month) {
+        return Err(invalid("month"));
+    }
+    if day == 0 || day > days_in_month(year, month) {
+        return Err(invalid("day"));
+    }
+    // A leap second (60) sorts as the first instant of the next minute
+    if hour > 23 || minute > 59 || second > 60 {
+        return Err(invalid("time of day"));
+    }
+
+    // Fractional seconds, keeping nanosecond precision
+    let mut nanos = 0;
+    if cursor.peek() == Some(b'.') {
+        cursor.byte(b".", "fraction")?;
+        let mut scale = 100_000_000;
+        let mut any = false;
+        while let Some(digit) = cursor.peek().filter(u8::is_ascii_digit) {
+            cursor.rest = &cursor.rest[1..];
+            nanos += u32::from(digit - b'0') * scale;
+            scale /= 10;
+            any = true;
+        }
+        if !any {
+            return Err(invalid("fraction"));
+        }
+    }
+
+    let offset_seconds = match cursor.byte(b"Zz+-", "UTC offset")? {
+        b'Z' | b'z' => 0,
+        sign => {
+            let hours = cursor.digits(2, "UTC offset")?;
+            cursor.byte(b":", "UTC offset")?;
+            let minutes = cursor.digits(2, "UTC offset")?;
+            if hours > 23 || minutes > 59 {
+                return Err(invalid("UTC offset"));
+            }
+            let offset = i64::from(hours * 3600 + minutes * 60);
+            if sign == b'-' {
+                -offset
+            } else {
+                offset
+            }
+        }
+    };
+
+    let seconds = days_from_civil(i64::from(year), month, day) * 86_400
+        + i64::from(hour * 3600 + minute * 60 + second)
+        - offset_seconds;
+    let rest = &s[s.len() - cursor.rest.len()..];
+    Ok((Timestamp { seconds, nanos }, rest))
+}
+
+/// Parse a number of milliseconds since the Unix epoch, possibly negative,
+/// from the start of `s`, returning it and the unparsed remainder.
+fn parse_epoch_millis_prefix(s: &str) -> Result<(Timestamp, &str), TimestampError> {
+    let body = s.strip_prefix('-').unwrap_or(s);
+    let digits = body
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(body.len());
+    if digits == 0 {
+        return Err(invalid("epoch milliseconds"));
+    }
+    let end = s.len() - body.len() + digits;
+    let millis = s[..end]
+        .parse()
+        .map_err(|_| invalid("epoch milliseconds out of range"))?;
+    Ok((Timestamp::from_unix_millis(millis), &s[end..]))
+}
+
+fn complete(
+    parsed: Result<(Timestamp, &str), TimestampError>,
+) -> Result<Timestamp, TimestampError> {
+    match parsed? {
+        (timestamp, "") => Ok(timestamp),
+        _ => Err(invalid("trailing characters")),
+    }
+}
+
+/// Parse an RFC 3339 timestamp such as `2024-03-01T12:30:00.250+01:00`.
+///
+/// The whole string must be the timestamp. The date and time may also be
+/// separated by a space, and fractional seconds beyond nanoseconds are
+/// truncated.
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::keys::parse_rfc3339;
+///
+/// let utc = parse_rfc3339("2024-03-01T11:30:00Z").unwrap();
+/// let paris = parse_rfc3339("2024-03-01T12:30:00+01:00").unwrap();
+/// assert_eq!(utc, paris);
+///
+/// // Fallible keys sort with `try_tilesort_by_key`
+/// let mut stamps = vec!["2024-03-01T12:00:00Z", "2024-02-29T23:59:59.5Z"];
+/// tilesort::try_tilesort_by_key(&mut stamps, |s| parse_rfc3339(s)).unwrap();
+/// assert_eq!(stamps, vec!["2024-02-29T23:59:59.5Z", "2024-03-01T12:00:00Z"]);
+/// ```
+pub fn parse_rfc3339(s: &str) -> Result<Timestamp, TimestampError> {
+    complete(parse_rfc3339_prefix(s))
+}
+
+/// Parse a number of milliseconds since the Unix epoch, such as
+/// `1709292600000`.
+pub fn parse_epoch_millis(s: &str) -> Result<Timestamp, TimestampError> {
+    complete(parse_epoch_millis_prefix(s))
+}
+
+/// Key extractor for strings that start with an RFC 3339 timestamp, such as
+/// log lines.
+///
+/// Anything after the timestamp is ignored. Elements that do not start with
+/// a valid timestamp get the key `None` and sort before all others, keeping
+/// their input order; use [`parse_rfc3339`] with
+/// [`try_tilesort_by_key`](crate::try_tilesort_by_key) to reject them
+/// instead.
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::keys::Rfc3339;
+///
+/// let mut log = vec![
+///     "2024-03-01T12:00:02Z INFO b",
+///     "2024-03-01T12:00:01Z INFO a",
+///     "continuation line",
+/// ];
+/// tilesort::tilesort_by_extractor(&mut log, Rfc3339);
+/// assert_eq!(
+///     log,
+///     vec!["continuation line", "2024-03-01T12:00:01Z INFO a", "2024-03-01T12:00:02Z INFO b"]
+/// );
+/// ```
+pub struct Rfc3339;
+
+impl<T: AsRef<str>> KeyExtractor<T, Option<Timestamp>> for Rfc3339 {
+    fn extract_key(&self, item: &T) -> Option<Timestamp> {
+        parse_rfc3339_prefix(item.as_ref())
+            .ok()
+            .map(|(timestamp, _)| timestamp)
+    }
+}
+
+/// Key extractor for strings that start with milliseconds since the Unix
+/// epoch.
+///
+/// Like [`Rfc3339`], anything after the number is ignored, and elements that
+/// do not start with one sort first.
+pub struct EpochMillis;
+
+impl<T: AsRef<str>> KeyExtractor<T, Option<Timestamp>> for EpochMillis {
+    fn extract_key(&self, item: &T) -> Option<Timestamp> {
+        parse_epoch_millis_prefix(item.as_ref())
+            .ok()
+            .map(|(timestamp, _)| timestamp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seconds(s: &str) -> i64 {
+        parse_rfc3339(s).unwrap().unix_seconds()
+    }
+
+    #[test]
+    fn test_known_instants() {
+        assert_eq!(seconds("1970-01-01T00:00:00Z"), 0);
+        assert_eq!(seconds("2000-03-01T00:00:00Z"), 951_868_800);
+        assert_eq!(seconds("2024-02-29T12:00:00Z"), 1_709_208_000);
+        assert_eq!(seconds("1969-12-31T23:59:59Z"), -1);
+        assert_eq!(seconds("0000-01-01T00:00:00Z"), -62_167_219_200);
+        assert_eq!(seconds("1970-01-01 01:00:00+01:00"), 0);
+        assert_eq!(seconds("1970-01-01t00:00:00-00:30"), 1800);
+    }
+
+    #[test]
+    fn test_fraction() {
+        let stamp = parse_rfc3339("2024-01-01T00:00:00.123456789123Z").unwrap();
+        assert_eq!(stamp.subsec_nanos(), 123_456_789);
+        let stamp = parse_rfc3339("2024-01-01T00:00:00.5Z").unwrap();
+        assert_eq!(stamp.subsec_nanos(), 500_000_000);
+        assert!(parse_rfc3339("2024-01-01T00:00:00.Z").is_err());
+    }
+
+    #[test]
+    fn test_rejects_invalid_fields() {
+        for bad in [
+            "",
+            "2024-13-01T00:00:00Z",
+            "2023-02-29T00:00:00Z",
+            "2024-04-31T00:00:00Z",
+            "2024-01-01T24:00:00Z",
+            "2024-01-01T00:00:00",
+            "2024-01-01T00:00:00+1:00",
+            "2024-01-01X00:00:00Z",
+            "2024-01-01T00:00:00Z trailing",
+        ] {
+            assert!(parse_rfc3339(bad).is_err(), "{:?}", bad);
+        }
+        assert_eq!(
+            parse_rfc3339("2024-13-01T00:00:00Z")
+                .unwrap_err()
+                .to_string(),
+            "invalid timestamp: month"
+        );
+    }
+
+    #[test]
+    fn test_epoch_millis() {
+        let stamp = parse_epoch_millis("1709208000250").unwrap();
+        assert_eq!(stamp, parse_rfc3339("2024-02-29T12:00:00.25Z").unwrap());
+        assert_eq!(stamp.unix_millis(), 1_709_208_000_250);
+        let before = parse_epoch_millis("-1").unwrap();
+        assert_eq!(
+            (before.unix_seconds(), before.subsec_nanos()),
+            (-1, 999_000_000)
+        );
+        assert_eq!(before.unix_millis(), -1);
+        for bad in ["", "-", "12a", "99999999999999999999"] {
+            assert!(parse_epoch_millis(bad).is_err(), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn test_extractors_ignore_trailing_text() {
+        let key = Rfc3339.extract_key(&"2024-02-29T12:00:00Z GET /");
+        assert_eq!(key, Some(parse_rfc3339("2024-02-29T12:00:00Z").unwrap()));
+        assert_eq!(Rfc3339.extract_key(&"GET /"), None);
+        let key = EpochMillis.extract_key(&String::from("1000,event"));
+        assert_eq!(key, Some(Timestamp::from_unix_millis(1000)));
+    }
+}
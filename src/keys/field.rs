@@ -0,0 +1,421 @@
+//! Keys over fields of text lines, specified as `sort -k` specifies them.
+
+use std::cmp::Ordering;
+use std::fmt;
+
+use crate::erased::KeyExtractorDyn;
+use crate::key_extractor::KeyExtractor;
+
+/// Error returned by [`FieldKey::parse`] for a malformed key specification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldKeyError {
+    reason: &'static str,
+}
+
+impl fmt::Display for FieldKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid key specification: {}", self.reason)
+    }
+}
+
+impl std::error::Error for FieldKeyError {}
+
+fn invalid(reason: &'static str) -> FieldKeyError {
+    FieldKeyError { reason }
+}
+
+/// Whether `c` separates fields when no separator is given.
+fn is_blank(c: char) -> bool {
+    c == ' ' || c == '\t'
+}
+
+/// Key extractor comparing text lines by a span of their fields, specified
+/// as GNU `sort -k POS1[,POS2]` specifies it.
+///
+/// Each position is `F[.C]`, field `F` and character `C` within it, both
+/// counted from 1. The key runs from the start of `POS1` to the end of
+/// `POS2`, or to the end of the line without one; a `POS2` character of 0,
+/// or none, means the end of its field. Either position may end with
+/// modifiers, which apply to the whole key:
+///
+/// - `n` compares a leading decimal number, such as `-12.5`, numerically,
+///   after skipping blanks; text that is not a number counts as zero.
+/// - `f` folds ASCII lowercase letters to uppercase before comparing.
+/// - `r` reverses the order.
+///
+/// Without a [`separator`](Self::separator), fields are separated by runs
+/// of spaces and tabs, and belong to the field after them, so `-k2` of
+/// `"a  b"` is `"  b"`; with one, as with `sort -t`, every occurrence of it
+/// ends a field. Text keys compare by bytes, as `sort` does in the C locale.
+/// Lines whose keys are equal keep their input order, as with `sort -s`.
+///
+/// Several keys combine with [`boxed`](KeyExtractor::boxed) into a vector
+/// extractor, earlier keys taking precedence, or with [`FieldKey::parse_all`].
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::keys::FieldKey;
+///
+/// let mut users = vec!["root:x:0:0", "daemon:x:1:1", "zed:x:1000:100", "amy:x:1000:10"];
+/// // -t: -k3,3nr -k1,1
+/// let keys = FieldKey::parse_all(["3,3nr", "1,1"], Some(':'))?;
+/// tilesort::tilesort_by_extractor(&mut users, keys);
+/// assert_eq!(users, vec!["amy:x:1000:10", "zed:x:1000:100", "daemon:x:1:1", "root:x:0:0"]);
+/// # Ok::<(), tilesort::keys::FieldKeyError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldKey {
+    start_field: usize,
+    start_char: usize,
+    /// Field and character of `POS2`; a character of 0 is the field's end.
+    end: Option<(usize, usize)>,
+    numeric: bool,
+    fold: bool,
+    reverse: bool,
+    separator: Option<char>,
+}
+
+impl FieldKey {
+    /// Parse a key specification such as `2`, `2,2n` or `1.3,1.5r`.
+    pub fn parse(spec: &str) -> Result<Self, FieldKeyError> {
+        let mut key = FieldKey {
+            start_field: 0,
+            start_char: 0,
+            end: None,
+            numeric: false,
+            fold: false,
+            reverse: false,
+            separator: None,
+        };
+        let (start, end) = match spec.split_once(',') {
+            Some((start, end)) => (start, Some(end)),
+            None => (spec, None),
+        };
+        let (field, char, modifiers) = parse_position(start)?;
+        key.start_field = field;
+        key.start_char = match char {
+            Some(0) => return Err(invalid("character position is zero")),
+            Some(char) => char,
+            None => 1,
+        };
+        key.modifiers(modifiers)?;
+        if let Some(end) = end {
+            let (field, char, modifiers) = parse_position(end)?;
+            key.end = Some((field, char.unwrap_or(0)));
+            key.modifiers(modifiers)?;
+        }
+        Ok(key)
+    }
+
+    /// Parse several key specifications, applying `separator` to each, into
+    /// one extractor comparing by each key in turn.
+    pub fn parse_all<'a, T, I>(
+        specs: I,
+        separator: Option<char>,
+    ) -> Result<Vec<Box<dyn KeyExtractorDyn<T> + 'a>>, FieldKeyError>
+    where
+        T: AsRef<str>,
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        specs
+            .into_iter()
+            .map(|spec| {
+                let mut key = FieldKey::parse(spec.as_ref())?;
+                key.separator = separator;
+                Ok(key.boxed())
+            })
+            .collect()
+    }
+
+    /// Separate fields at every `separator`, as `sort -t` does, rather than
+    /// at runs of blanks.
+    pub fn separator(mut self, separator: char) -> Self {
+        self.separator = Some(separator);
+        self
+    }
+
+    fn modifiers(&mut self, modifiers: &str) -> Result<(), FieldKeyError> {
+        for modifier in modifiers.chars() {
+            match modifier {
+                'n' => self.numeric = true,
+                'f' => self.fold = true,
+                'r' => self.reverse = true,
+                _ => return Err(invalid("unsupported modifier")),
+            }
+        }
+        Ok(())
+    }
+
+    /// The part of `line` this key covers.
+    pub fn text<'l>(&self, line: &'l str) -> &'l str {
+        let start = self.skip_fields(line, self.start_field - 1);
+        let start = advance(line, start, self.start_char - 1);
+        let end = match self.end {
+            None => line.len(),
+            Some((field, 0)) => self.end_of_field(line, field),
+            Some((field, char)) => {
+                let field_start = self.skip_fields(line, field - 1);
+                advance(line, field_start, char)
+            }
+        };
+        &line[start..end.max(start)]
+    }
+
+    /// Byte offset of the start of the field after the first `skip`.
+    fn skip_fields(&self, line: &str, skip: usize) -> usize {
+        let mut at = 0;
+        for _ in 0..skip {
+            at = match self.separator {
+                Some(separator) => match line[at..].find(separator) {
+                    Some(offset) => at + offset + separator.len_utf8(),
+                    None => return line.len(),
+                },
+                None => end_of_blank_field(line, at),
+            };
+        }
+        at
+    }
+
+    /// Byte offset of the end of field `field`, counted from 1.
+    fn end_of_field(&self, line: &str, field: usize) -> usize {
+        let start = self.skip_fields(line, field - 1);
+        match self.separator {
+            Some(separator) => line[start..]
+                .find(separator)
+                .map_or(line.len(), |offset| start + offset),
+            None => end_of_blank_field(line, start),
+        }
+    }
+}
+
+/// Byte offset past the blanks and then the non-blanks at `at`.
+fn end_of_blank_field(line: &str, at: usize) -> usize {
+    let rest = &line[at..];
+    let blanks = rest.len() - rest.trim_start_matches(is_blank).len();
+    let word = rest[blanks..].find(is_blank).unwrap_or(rest.len() - blanks);
+    at + blanks + word
+}
+
+/// Split `F[.C]` and its trailing modifiers.
+fn parse_position(position: &str) -> Result<(usize, Option<usize>, &str), FieldKeyError> {
+    let digits = |s: &str| s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let field_len = digits(position);
+    if field_len == 0 {
+        return Err(invalid("missing field number"));
+    }
+    let field: usize = position[..field_len]
+        .parse()
+        .map_err(|_| invalid("field number out of range"))?;
+    if field == 0 {
+        return Err(invalid("field number is zero"));
+    }
+    let rest = &position[field_len..];
+    match rest.strip_prefix('.') {
+        Some(rest) => {
+            let char_len = digits(rest);
+            if char_len == 0 {
+                return Err(invalid("missing character position"));
+            }
+            let char = rest[..char_len]
+                .parse()
+                .map_err(|_| invalid("character position out of range"))?;
+            Ok((field, Some(char), &rest[char_len..]))
+        }
+        None => Ok((field, None, rest)),
+    }
+}
+
+/// Byte offset `chars` characters after `at`, stopping at the end of `line`.
+fn advance(line: &str, at: usize, chars: usize) -> usize {
+    line[at..]
+        .char_indices()
+        .nth(chars)
+        .map_or(line.len(), |(offset, _)| at + offset)
+}
+
+/// The sort key a [`FieldKey`] extracts from a line.
+///
+/// Keys from the same `FieldKey` compare as its specification says; keys
+/// from different ones are not meaningfully ordered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldValue {
+    value: Value,
+    reverse: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Value {
+    Text(String),
+    Number(Decimal),
+}
+
+/// A decimal number with no leading or trailing zeros, compared exactly.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Decimal {
+    negative: bool,
+    integer: String,
+    fraction: String,
+}
+
+impl Decimal {
+    /// Parse the number at the start of `s` after any blanks, or zero.
+    fn parse(s: &str) -> Self {
+        let s = s.trim_start_matches(is_blank);
+        let (negative, s) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s),
+        };
+        let integer_len = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        let integer = s[..integer_len].trim_start_matches('0');
+        let fraction = match s[integer_len..].strip_prefix('.') {
+            Some(rest) => {
+                let len = rest
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(rest.len());
+                rest[..len].trim_end_matches('0')
+            }
+            None => "",
+        };
+        Decimal {
+            negative: negative && !(integer.is_empty() && fraction.is_empty()),
+            integer: integer.to_string(),
+            fraction: fraction.to_string(),
+        }
+    }
+
+    fn cmp_magnitude(&self, other: &Self) -> Ordering {
+        self.integer
+            .len()
+            .cmp(&other.integer.len())
+            .then_with(|| self.integer.cmp(&other.integer))
+            .then_with(|| self.fraction.cmp(&other.fraction))
+    }
+}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, false) => self.cmp_magnitude(other),
+            (true, true) => other.cmp_magnitude(self),
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+        }
+    }
+}
+
+impl PartialOrd for FieldValue {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for FieldValue {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let ordering = self.value.cmp(&other.value);
+        if self.reverse {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+}
+
+impl<T: AsRef<str>> KeyExtractor<T, FieldValue> for FieldKey {
+    fn extract_key(&self, item: &T) -> FieldValue {
+        let text = self.text(item.as_ref());
+        let value = if self.numeric {
+            Value::Number(Decimal::parse(text))
+        } else if self.fold {
+            Value::Text(text.to_ascii_uppercase())
+        } else {
+            Value::Text(text.to_string())
+        };
+        FieldValue {
+            value,
+            reverse: self.reverse,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text<'l>(spec: &str, separator: Option<char>, line: &'l str) -> &'l str {
+        let mut key = FieldKey::parse(spec).unwrap();
+        key.separator = separator;
+        key.text(line)
+    }
+
+    #[test]
+    fn test_blank_separated_fields() {
+        let line = "  alpha beta\t\tgamma";
+        assert_eq!(text("1", None, line), line);
+        assert_eq!(text("1,1", None, line), "  alpha");
+        assert_eq!(text("2,2", None, line), " beta");
+        assert_eq!(text("3", None, line), "\t\tgamma");
+        assert_eq!(text("2.3,2.4", None, line), "et");
+        assert_eq!(text("2,3.1", None, line), " beta\t");
+        assert_eq!(text("4", None, line), "");
+        assert_eq!(text("3,2", None, line), "");
+    }
+
+    #[test]
+    fn test_separated_fields() {
+        let line = "a:b::d";
+        assert_eq!(text("2,2", Some(':'), line), "b");
+        assert_eq!(text("3,3", Some(':'), line), "");
+        assert_eq!(text("3", Some(':'), line), ":d");
+        assert_eq!(text("1.2,2.0", Some(':'), line), ":b");
+        assert_eq!(text("5", Some(':'), line), "");
+        assert_eq!(text("2,2", Some('é'), "aébéc"), "b");
+    }
+
+    #[test]
+    fn test_numeric_order() {
+        let key = FieldKey::parse("1n").unwrap();
+        let ordered = [
+            "-100", "-9.5", "-9", "-0.5", "zero", "0", "-0", ".25", "1", "9.", "10", "10.01",
+        ];
+        for pair in ordered.windows(2) {
+            assert!(
+                key.extract_key(&pair[0]) <= key.extract_key(&pair[1]),
+                "{:?}",
+                pair
+            );
+        }
+        assert_eq!(key.extract_key(&"007.50x"), key.extract_key(&" 7.5"));
+        assert_ne!(key.extract_key(&"7.5"), key.extract_key(&"7.05"));
+    }
+
+    #[test]
+    fn test_modifiers() {
+        let mut lines = vec!["b x", "A y", "a z", "B w"];
+        crate::tilesort_by_extractor(&mut lines, FieldKey::parse("1,1f").unwrap());
+        assert_eq!(lines, vec!["A y", "a z", "b x", "B w"]);
+
+        let keys = FieldKey::parse_all(["1,1fr", "2,2"], None).unwrap();
+        crate::tilesort_by_extractor(&mut lines, keys);
+        assert_eq!(lines, vec!["B w", "b x", "A y", "a z"]);
+    }
+
+    #[test]
+    fn test_rejects_invalid() {
+        for bad in ["", "0", "1.0", "a", "1.", "1x", "1,", "1,0", "1.1.1", ",2"] {
+            assert!(FieldKey::parse(bad).is_err(), "{:?}", bad);
+        }
+        assert_eq!(
+            FieldKey::parse("2b").unwrap_err().to_string(),
+            "invalid key specification: unsupported modifier"
+        );
+        assert!(FieldKey::parse("2,3.0n").is_ok());
+    }
+}
@@ -0,0 +1,167 @@
+//! Strings compared by grapheme cluster, as readers perceive characters.
+
+use std::cmp::Ordering;
+
+use unicode_normalization::UnicodeNormalization;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::key_extractor::KeyExtractor;
+
+/// Sort key comparing strings by their extended grapheme clusters, the
+/// characters a reader sees, rather than by code points or bytes.
+///
+/// Code point order lets a combining accent decide a comparison against the
+/// next letter of another string, so a decomposed `"José"` sorts after
+/// `"Josef"`, and a precomposed `é` sorts after every ASCII letter. This key
+/// compares the canonical decomposition (NFD) of each string one cluster at a
+/// time: first by each cluster's base character only, so accents and other
+/// marks break ties rather than reorder words, then by the whole clusters,
+/// and finally by the original text, so the ordering is total and
+/// consistent with string equality. Emoji sequences and other clusters of
+/// several code points compare as one unit.
+///
+/// This is not a locale's collation (see [`Collation`](super::Collation) with
+/// the `icu` feature); base characters still compare by code point.
+#[derive(Debug, Clone)]
+pub struct GraphemeKey {
+    decomposed: String,
+    /// Byte offset in `decomposed` of each cluster's start, then of its end.
+    bounds: Vec<usize>,
+    original: String,
+}
+
+impl GraphemeKey {
+    /// Build the grapheme sort key for `s`.
+    pub fn new(s: &str) -> Self {
+        let decomposed: String = s.nfd().collect();
+        let bounds = decomposed
+            .grapheme_indices(true)
+            .map(|(start, _)| start)
+            .chain([decomposed.len()])
+            .collect();
+        GraphemeKey {
+            decomposed,
+            bounds,
+            original: s.to_string(),
+        }
+    }
+
+    /// The string this key was built from.
+    pub fn as_str(&self) -> &str {
+        &self.original
+    }
+
+    /// Number of grapheme clusters in the string.
+    pub fn grapheme_count(&self) -> usize {
+        self.bounds.len() - 1
+    }
+
+    fn clusters(&self) -> impl Iterator<Item = &str> + '_ {
+        self.bounds
+            .windows(2)
+            .map(|bounds| &self.decomposed[bounds[0]..bounds[1]])
+    }
+
+    fn base_chars(&self) -> impl Iterator<Item = char> + '_ {
+        self.clusters()
+            .map(|cluster| cluster.chars().next().expect("clusters are not empty"))
+    }
+}
+
+impl PartialEq for GraphemeKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.original == other.original
+    }
+}
+
+impl Eq for GraphemeKey {}
+
+impl PartialOrd for GraphemeKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for GraphemeKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.base_chars()
+            .cmp(other.base_chars())
+            .then_with(|| self.clusters().cmp(other.clusters()))
+            .then_with(|| self.original.cmp(&other.original))
+    }
+}
+
+/// Build the grapheme sort key for `s`; convenient inside key closures.
+///
+/// # Examples
+///
+/// ```
+/// // The last name's accent is a separate combining character
+/// let mut names = vec!["Josef", "Jose\u{301}", "Josa"];
+/// tilesort::tilesort_by_key(&mut names, |s| tilesort::keys::grapheme_key(s));
+/// assert_eq!(names, vec!["Josa", "Jose\u{301}", "Josef"]);
+/// ```
+pub fn grapheme_key(s: &str) -> GraphemeKey {
+    GraphemeKey::new(s)
+}
+
+/// Key extractor producing a [`GraphemeKey`] for any string-like element.
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::keys::Graphemes;
+///
+/// // Byte order would put "élan" after "zebra"
+/// let mut words = vec!["zebra", "élan", "eagle", "echo"];
+/// tilesort::tilesort_by_extractor(&mut words, Graphemes);
+/// assert_eq!(words, vec!["eagle", "echo", "élan", "zebra"]);
+/// ```
+pub struct Graphemes;
+
+impl<T: AsRef<str>> KeyExtractor<T, GraphemeKey> for Graphemes {
+    fn extract_key(&self, item: &T) -> GraphemeKey {
+        GraphemeKey::new(item.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cmp(a: &str, b: &str) -> Ordering {
+        grapheme_key(a).cmp(&grapheme_key(b))
+    }
+
+    #[test]
+    fn test_marks_break_ties_only() {
+        // Code point order puts the combining accent after 'f'
+        assert!("Jose\u{301}" > "Josef");
+        assert_eq!(cmp("Jose\u{301}", "Josef"), Ordering::Less);
+        assert_eq!(cmp("resume", "re\u{301}sume\u{301}"), Ordering::Less);
+        assert_eq!(cmp("re\u{301}sume\u{301}", "resumes"), Ordering::Less);
+    }
+
+    #[test]
+    fn test_precomposed_and_decomposed() {
+        // Canonically equivalent, ordered only by their raw text
+        let (precomposed, decomposed) = ("caf\u{e9}", "cafe\u{301}");
+        assert_eq!(cmp(precomposed, decomposed), precomposed.cmp(decomposed));
+        assert_eq!(cmp(precomposed, "cafes"), Ordering::Less);
+        assert_eq!(cmp(decomposed, decomposed), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_clusters_compare_as_units() {
+        // A family emoji is one cluster of five code points
+        let family = "\u{1f469}\u{200d}\u{1f469}\u{200d}\u{1f467}";
+        assert_eq!(grapheme_key(family).grapheme_count(), 1);
+        assert_eq!(grapheme_key("e\u{301}x").grapheme_count(), 2);
+        // Its joined code points do not compare against the next letter
+        let (family_a, woman_b) = (format!("{}a", family), "\u{1f469}b");
+        assert!(family_a.as_str() > woman_b);
+        assert_eq!(cmp(&family_a, woman_b), Ordering::Less);
+        assert_eq!(cmp("", "a"), Ordering::Less);
+        assert_eq!(grapheme_key(family).as_str(), family);
+    }
+}
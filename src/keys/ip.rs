@@ -0,0 +1,116 @@
+//! IP address keys for access logs and flow records.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::key_extractor::KeyExtractor;
+
+/// Parse the IPv4 or IPv6 address at the start of `s`.
+///
+/// The address ends at the first whitespace or comma, and may carry a port,
+/// as in `192.0.2.7:443` or `[2001:db8::1]:443`.
+fn parse_ip_prefix(s: &str) -> Option<IpAddr> {
+    let token = s
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .next()
+        .unwrap_or("");
+    if let Some(bracketed) = token.strip_prefix('[') {
+        let end = bracketed.find(']')?;
+        return bracketed[..end].parse::<Ipv6Addr>().ok().map(IpAddr::V6);
+    }
+    if let Ok(ip) = token.parse() {
+        return Some(ip);
+    }
+    let (host, port) = token.rsplit_once(':')?;
+    if port.is_empty() || !port.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    host.parse::<Ipv4Addr>().ok().map(IpAddr::V4)
+}
+
+/// Key extractor for strings that start with an IP address, such as access
+/// log lines, ordering them numerically.
+///
+/// Comparing addresses as text puts `10.0.0.10` before `10.0.0.9`; this key
+/// compares them as [`IpAddr`]s instead, every IPv4 address before every
+/// IPv6 one. Anything after the address is ignored. Elements that do not
+/// start with an address get the key `None` and sort before all others,
+/// keeping their input order; parse with [`str::parse`] and
+/// [`try_tilesort_by_key`](crate::try_tilesort_by_key) to reject them
+/// instead.
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::keys::Ip;
+///
+/// let mut log = vec![
+///     "10.0.0.10 GET /b",
+///     "[2001:db8::1]:443 GET /c",
+///     "10.0.0.9:8080 GET /a",
+///     "- GET /d",
+/// ];
+/// tilesort::tilesort_by_extractor(&mut log, Ip);
+/// assert_eq!(
+///     log,
+///     vec!["- GET /d", "10.0.0.9:8080 GET /a", "10.0.0.10 GET /b", "[2001:db8::1]:443 GET /c"]
+/// );
+/// ```
+pub struct Ip;
+
+impl<T: AsRef<str>> KeyExtractor<T, Option<IpAddr>> for Ip {
+    fn extract_key(&self, item: &T) -> Option<IpAddr> {
+        parse_ip_prefix(item.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_address_forms() {
+        assert_eq!(parse_ip_prefix("192.0.2.7"), Some(ip("192.0.2.7")));
+        assert_eq!(parse_ip_prefix("192.0.2.7:443 x"), Some(ip("192.0.2.7")));
+        assert_eq!(parse_ip_prefix("192.0.2.7,80,tcp"), Some(ip("192.0.2.7")));
+        assert_eq!(parse_ip_prefix("2001:db8::1\tx"), Some(ip("2001:db8::1")));
+        assert_eq!(
+            parse_ip_prefix("[2001:db8::1]:443"),
+            Some(ip("2001:db8::1"))
+        );
+        assert_eq!(
+            parse_ip_prefix("::ffff:192.0.2.7"),
+            Some(ip("::ffff:192.0.2.7"))
+        );
+    }
+
+    #[test]
+    fn test_rejects_non_addresses() {
+        for bad in [
+            "",
+            " 192.0.2.7",
+            "192.0.2",
+            "192.0.2.256",
+            "192.0.2.7:",
+            "192.0.2.7:http",
+            "[192.0.2.7]",
+            "[2001:db8::1",
+            "example.com",
+        ] {
+            assert_eq!(parse_ip_prefix(bad), None, "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn test_numeric_order() {
+        let mut addresses = vec!["10.0.0.10", "::1", "9.255.255.255", "10.0.0.9", "fe80::"];
+        crate::tilesort_by_extractor(&mut addresses, Ip);
+        assert_eq!(
+            addresses,
+            vec!["9.255.255.255", "10.0.0.9", "10.0.0.10", "::1", "fe80::"]
+        );
+    }
+}
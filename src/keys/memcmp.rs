@@ -0,0 +1,111 @@
+//! Keys ordered by their bytes alone.
+
+use std::borrow::Cow;
+
+/// Marker for types ordered exactly as their bytes compare
+/// lexicographically, as `memcmp` compares them.
+///
+/// Strings, byte strings and byte arrays qualify, and so do encodings built
+/// for it: big-endian unsigned integers, big-endian signed integers with the
+/// sign bit flipped, and normalized strings. Sorting by such a key with
+/// [`tilesort_by_memcmp_key`](crate::tilesort_by_memcmp_key) compares the
+/// borrowed bytes directly, with no call to the type's own `Ord`.
+///
+/// Implementing this for a type whose order differs from its bytes' is not
+/// unsafe, but sorts by it will follow the bytes.
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::keys::MemcmpOrd;
+///
+/// /// A `u32` stored big-endian, so its bytes sort as its value does.
+/// #[derive(Debug, Clone, PartialEq)]
+/// struct BigEndian([u8; 4]);
+///
+/// impl MemcmpOrd for BigEndian {
+///     fn ordered_bytes(&self) -> &[u8] {
+///         &self.0
+///     }
+/// }
+///
+/// let mut ids: Vec<BigEndian> = [300u32, 7, 65_536]
+///     .iter()
+///     .map(|id| BigEndian(id.to_be_bytes()))
+///     .collect();
+/// tilesort::tilesort_by_memcmp_key(&mut ids, |id| id);
+/// assert_eq!(ids[0], BigEndian(7u32.to_be_bytes()));
+/// assert_eq!(ids[2], BigEndian(65_536u32.to_be_bytes()));
+/// ```
+pub trait MemcmpOrd {
+    /// The bytes whose lexicographic order is the order of `self`.
+    fn ordered_bytes(&self) -> &[u8];
+}
+
+impl MemcmpOrd for [u8] {
+    fn ordered_bytes(&self) -> &[u8] {
+        self
+    }
+}
+
+impl<const N: usize> MemcmpOrd for [u8; N] {
+    fn ordered_bytes(&self) -> &[u8] {
+        self
+    }
+}
+
+impl MemcmpOrd for Vec<u8> {
+    fn ordered_bytes(&self) -> &[u8] {
+        self
+    }
+}
+
+impl MemcmpOrd for str {
+    fn ordered_bytes(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl MemcmpOrd for String {
+    fn ordered_bytes(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl<T: MemcmpOrd + ?Sized> MemcmpOrd for &T {
+    fn ordered_bytes(&self) -> &[u8] {
+        (**self).ordered_bytes()
+    }
+}
+
+impl<T: MemcmpOrd + ?Sized> MemcmpOrd for Box<T> {
+    fn ordered_bytes(&self) -> &[u8] {
+        (**self).ordered_bytes()
+    }
+}
+
+impl<T> MemcmpOrd for Cow<'_, T>
+where
+    T: MemcmpOrd + ToOwned + ?Sized,
+{
+    fn ordered_bytes(&self) -> &[u8] {
+        (**self).ordered_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_own_order() {
+        let words = ["", "a", "a\0", "ab", "b", "\u{e9}", "z"];
+        for a in words {
+            for b in words {
+                assert_eq!(a.ordered_bytes().cmp(b.ordered_bytes()), a.cmp(b));
+                let (a, b) = (String::from(a), Cow::<str>::Borrowed(b));
+                assert_eq!(a.ordered_bytes().cmp(b.ordered_bytes()), a.as_str().cmp(&b));
+            }
+        }
+    }
+}
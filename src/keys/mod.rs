@@ -0,0 +1,46 @@
+//! Ready-made sort keys for common element types.
+//!
+//! Each key type implements `Ord` and comes with a unit-struct
+//! [`KeyExtractor`](crate::KeyExtractor) for use with
+//! [`tilesort_by_extractor`](crate::tilesort_by_extractor).
+
+#[cfg(feature = "icu")]
+mod collation;
+mod datetime;
+mod field;
+#[cfg(feature = "graphemes")]
+mod grapheme;
+mod ip;
+mod memcmp;
+mod natural;
+mod path;
+mod prefix;
+mod ranked;
+mod semver;
+mod size;
+#[cfg(feature = "unicode")]
+mod unicode;
+
+#[cfg(feature = "icu")]
+pub use collation::{Collation, CollationError, CollationKey};
+pub use datetime::{
+    parse_epoch_millis, parse_rfc3339, EpochMillis, Rfc3339, Timestamp, TimestampError,
+};
+pub use field::{FieldKey, FieldKeyError, FieldValue};
+#[cfg(feature = "graphemes")]
+pub use grapheme::{grapheme_key, GraphemeKey, Graphemes};
+pub use ip::Ip;
+pub use memcmp::MemcmpOrd;
+pub use natural::{natural_key, Natural, NaturalKey};
+pub use path::{os_str_key, path_key, DirectoryTree, NativeOs, OsStrKey, PathKey};
+pub use prefix::PrefixKey;
+pub use ranked::RankedKey;
+pub use semver::{parse_version, Semver, Version, VersionError};
+pub use size::{
+    parse_human_size, ByteSize, HumanNumber, HumanNumeric, HumanSize, SizeError, SizeUnits,
+};
+#[cfg(feature = "unicode")]
+pub use unicode::{
+    case_folded_key, caseless_key, compatibility_caseless_key, normalized_key, CaseFolded,
+    Caseless, CompatibilityCaseless, NormalizationForm, Normalized,
+};
@@ -0,0 +1,206 @@
+//! Natural ("numeric-aware") string ordering.
+
+use std::cmp::Ordering;
+
+use crate::key_extractor::KeyExtractor;
+
+/// One run of a string: either ASCII digits or everything else.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Chunk {
+    /// Digit run with leading zeros stripped, plus the number of zeros removed.
+    Number {
+        digits: String,
+        leading_zeros: usize,
+    },
+    Text(String),
+}
+
+impl Chunk {
+    fn cmp_value(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Chunk::Number { digits: a, .. }, Chunk::Number { digits: b, .. }) => {
+                // Longer digit strings (without leading zeros) are larger numbers
+                a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+            }
+            (Chunk::Text(a), Chunk::Text(b)) => a.cmp(b),
+            (Chunk::Number { .. }, Chunk::Text(_)) => Ordering::Less,
+            (Chunk::Text(_), Chunk::Number { .. }) => Ordering::Greater,
+        }
+    }
+}
+
+/// Sort key that orders strings "naturally", so `file2 < file10`.
+///
+/// The string is split into runs of ASCII digits and runs of other
+/// characters. Digit runs compare by numeric value (of any length, without
+/// overflow); other runs compare as ordinary strings; a digit run sorts before
+/// a text run. Strings that compare equal this way (such as `a01` and `a1`)
+/// are ordered by fewer leading zeros first and finally by their raw text, so
+/// the ordering is total and consistent with string equality.
+#[derive(Debug, Clone)]
+pub struct NaturalKey {
+    chunks: Vec<Chunk>,
+    original: String,
+}
+
+impl NaturalKey {
+    /// Build the natural sort key for `s`.
+    pub fn new(s: &str) -> Self {
+        let mut chunks = Vec::new();
+        let mut rest = s;
+
+        while let Some(first) = rest.chars().next() {
+            let is_digit = first.is_ascii_digit();
+            let end = rest
+                .find(|c: char| c.is_ascii_digit() != is_digit)
+                .unwrap_or(rest.len());
+            let (run, tail) = rest.split_at(end);
+
+            if is_digit {
+                let digits = run.trim_start_matches('0');
+                chunks.push(Chunk::Number {
+                    digits: digits.to_string(),
+                    leading_zeros: run.len() - digits.len(),
+                });
+            } else {
+                chunks.push(Chunk::Text(run.to_string()));
+            }
+            rest = tail;
+        }
+
+        NaturalKey {
+            chunks,
+            original: s.to_string(),
+        }
+    }
+
+    /// The string this key was built from.
+    pub fn as_str(&self) -> &str {
+        &self.original
+    }
+
+    fn leading_zeros(&self) -> impl Iterator<Item = usize> + '_ {
+        self.chunks.iter().map(|chunk| match chunk {
+            Chunk::Number { leading_zeros, .. } => *leading_zeros,
+            Chunk::Text(_) => 0,
+        })
+    }
+}
+
+impl PartialEq for NaturalKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.original == other.original
+    }
+}
+
+impl Eq for NaturalKey {}
+
+impl PartialOrd for NaturalKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for NaturalKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        for (a, b) in self.chunks.iter().zip(&other.chunks) {
+            match a.cmp_value(b) {
+                Ordering::Equal => continue,
+                unequal => return unequal,
+            }
+        }
+
+        self.chunks
+            .len()
+            .cmp(&other.chunks.len())
+            .then_with(|| self.leading_zeros().cmp(other.leading_zeros()))
+            .then_with(|| self.original.cmp(&other.original))
+    }
+}
+
+/// Build the natural sort key for `s`; convenient inside key closures.
+///
+/// # Examples
+///
+/// ```
+/// let mut files = vec!["file10.txt", "file2.txt", "file1.txt"];
+/// tilesort::tilesort_by_key(&mut files, |s| tilesort::keys::natural_key(s));
+/// assert_eq!(files, vec!["file1.txt", "file2.txt", "file10.txt"]);
+/// ```
+pub fn natural_key(s: &str) -> NaturalKey {
+    NaturalKey::new(s)
+}
+
+/// Key extractor producing a [`NaturalKey`] for any string-like element.
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::keys::Natural;
+///
+/// let mut lines = vec!["log-9", "log-10", "log-11", "log-1", "log-2"];
+/// tilesort::tilesort_by_extractor(&mut lines, Natural);
+/// assert_eq!(lines, vec!["log-1", "log-2", "log-9", "log-10", "log-11"]);
+/// ```
+pub struct Natural;
+
+impl<T: AsRef<str>> KeyExtractor<T, NaturalKey> for Natural {
+    fn extract_key(&self, item: &T) -> NaturalKey {
+        NaturalKey::new(item.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cmp(a: &str, b: &str) -> Ordering {
+        natural_key(a).cmp(&natural_key(b))
+    }
+
+    #[test]
+    fn test_numeric_runs_compare_by_value() {
+        assert_eq!(cmp("file2", "file10"), Ordering::Less);
+        assert_eq!(cmp("file10", "file9"), Ordering::Greater);
+        assert_eq!(cmp("v1.10.0", "v1.9.3"), Ordering::Greater);
+        assert_eq!(
+            cmp("id99999999999999999999999", "id100000000000000000000000"),
+            Ordering::Less
+        );
+    }
+
+    #[test]
+    fn test_text_runs_compare_lexically() {
+        assert_eq!(cmp("apple", "banana"), Ordering::Less);
+        assert_eq!(cmp("a1b", "a1c"), Ordering::Less);
+        assert_eq!(cmp("abc", "abc1"), Ordering::Less);
+    }
+
+    #[test]
+    fn test_numbers_before_text() {
+        assert_eq!(cmp("1abc", "abc"), Ordering::Less);
+        assert_eq!(cmp("x1", "xa"), Ordering::Less);
+    }
+
+    #[test]
+    fn test_leading_zeros_tiebreak() {
+        assert_eq!(cmp("a1", "a01"), Ordering::Less);
+        assert_eq!(cmp("a01", "a001"), Ordering::Less);
+        assert_eq!(cmp("a01", "a2"), Ordering::Less);
+        assert_eq!(cmp("a01", "a01"), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_empty_and_unicode() {
+        assert_eq!(cmp("", "a"), Ordering::Less);
+        assert_eq!(cmp("", ""), Ordering::Equal);
+        assert_eq!(cmp("café2", "café10"), Ordering::Less);
+        assert_eq!(natural_key("café2").as_str(), "café2");
+    }
+
+    #[test]
+    fn test_extractor() {
+        let key = Natural.extract_key(&String::from("img12"));
+        assert_eq!(key, natural_key("img12"));
+    }
+}
@@ -0,0 +1,190 @@
+//! `OsStr` and `Path` keys ordered as the platform stores them.
+
+use std::ffi::OsStr;
+use std::path::Path;
+
+use crate::key_extractor::KeyExtractor;
+
+/// The units an `OsStr` is stored as: UTF-16 code units on Windows, bytes
+/// elsewhere.
+#[cfg(windows)]
+type Unit = u16;
+#[cfg(not(windows))]
+type Unit = u8;
+
+/// Sort key for an `OsStr`, ordered by the platform's native encoding.
+///
+/// On Unix an `OsStr` is arbitrary bytes and compares as those bytes; on
+/// Windows it is (possibly ill-formed) UTF-16, WTF-16, and compares by code
+/// unit, as the system's ordinal comparisons do. Either way no name is lost
+/// or merged with another, as they would be converting to `String` lossily.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct OsStrKey(Vec<Unit>);
+
+impl OsStrKey {
+    /// Build the native sort key for `s`.
+    pub fn new(s: &OsStr) -> Self {
+        OsStrKey(native_units(s))
+    }
+}
+
+#[cfg(unix)]
+fn native_units(s: &OsStr) -> Vec<Unit> {
+    use std::os::unix::ffi::OsStrExt;
+    s.as_bytes().to_vec()
+}
+
+#[cfg(windows)]
+fn native_units(s: &OsStr) -> Vec<Unit> {
+    use std::os::windows::ffi::OsStrExt;
+    s.encode_wide().collect()
+}
+
+// Elsewhere, such as on WebAssembly, an `OsStr` holds UTF-8
+#[cfg(not(any(unix, windows)))]
+fn native_units(s: &OsStr) -> Vec<Unit> {
+    s.to_string_lossy().into_owned().into_bytes()
+}
+
+/// Build the native sort key for `s`; convenient inside key closures.
+pub fn os_str_key(s: &OsStr) -> OsStrKey {
+    OsStrKey::new(s)
+}
+
+/// Sort key for a path in directory-tree order: component by component,
+/// each compared as an [`OsStrKey`].
+///
+/// Every directory sorts directly before its contents, and its contents
+/// before its later siblings: `a`, `a/b`, `a/b/c`, `a/d`, `a-b`, where
+/// comparing whole strings would put `a-b` before `a/b` because `-` encodes
+/// below `/`. Redundant separators and `.` components are ignored, as
+/// [`Path::components`] ignores them.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PathKey(Vec<OsStrKey>);
+
+impl PathKey {
+    /// Build the directory-tree sort key for `path`.
+    pub fn new(path: &Path) -> Self {
+        PathKey(
+            path.components()
+                .map(|component| OsStrKey::new(component.as_os_str()))
+                .collect(),
+        )
+    }
+
+    /// Number of components in the path.
+    pub fn depth(&self) -> usize {
+        self.0.len()
+    }
+}
+
+/// Build the directory-tree sort key for `path`; convenient inside key
+/// closures.
+pub fn path_key(path: &Path) -> PathKey {
+    PathKey::new(path)
+}
+
+/// Key extractor producing an [`OsStrKey`] for `OsString`s, `PathBuf`s and
+/// anything else viewable as an `OsStr`, comparing whole strings in the
+/// platform's native encoding.
+///
+/// # Examples
+///
+/// ```
+/// use std::ffi::OsString;
+///
+/// use tilesort::keys::NativeOs;
+///
+/// let mut names: Vec<OsString> = ["b.txt", "a.txt", "C.txt"].iter().map(OsString::from).collect();
+/// tilesort::tilesort_by_extractor(&mut names, NativeOs);
+/// assert_eq!(names, ["C.txt", "a.txt", "b.txt"]);
+/// ```
+pub struct NativeOs;
+
+impl<T: AsRef<OsStr>> KeyExtractor<T, OsStrKey> for NativeOs {
+    fn extract_key(&self, item: &T) -> OsStrKey {
+        OsStrKey::new(item.as_ref())
+    }
+}
+
+/// Key extractor producing a [`PathKey`] for paths, sorting them in
+/// directory-tree order with parents before their children.
+///
+/// File listings, such as the output of a recursive directory walk, arrive
+/// close to this order already, so they form long tiles.
+///
+/// # Examples
+///
+/// ```
+/// use std::path::PathBuf;
+///
+/// use tilesort::keys::DirectoryTree;
+///
+/// let mut paths: Vec<PathBuf> = ["src-old", "src/main.rs", "src", "src/bin/tool.rs"]
+///     .iter()
+///     .map(PathBuf::from)
+///     .collect();
+/// tilesort::tilesort_by_extractor(&mut paths, DirectoryTree);
+/// assert_eq!(paths, ["src", "src/bin/tool.rs", "src/main.rs", "src-old"].map(PathBuf::from));
+/// ```
+pub struct DirectoryTree;
+
+impl<T: AsRef<Path>> KeyExtractor<T, PathKey> for DirectoryTree {
+    fn extract_key(&self, item: &T) -> PathKey {
+        PathKey::new(item.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tree_sorted(paths: &[&str]) -> Vec<String> {
+        let mut paths: Vec<&str> = paths.to_vec();
+        paths.sort_by_key(|p| path_key(Path::new(p)));
+        paths.iter().map(|p| p.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parents_before_children() {
+        assert_eq!(
+            tree_sorted(&["a-b", "a/d", "a/b/c", "a", "a/b"]),
+            vec!["a", "a/b", "a/b/c", "a/d", "a-b"]
+        );
+        // Whole strings compare `-` below `/`
+        assert!(os_str_key("a-b".as_ref()) < os_str_key("a/b".as_ref()));
+    }
+
+    #[test]
+    fn test_redundant_components_ignored() {
+        assert_eq!(
+            path_key(Path::new("a//b/./c")),
+            path_key(Path::new("a/b/c"))
+        );
+        assert_eq!(path_key(Path::new("a/b/")).depth(), 2);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unix_compares_raw_bytes() {
+        use std::os::unix::ffi::OsStrExt;
+        // Not UTF-8, so a lossy conversion would make these equal
+        let (a, b) = (
+            OsStr::from_bytes(b"\xff\xfe"),
+            OsStr::from_bytes(b"\xff\xff"),
+        );
+        assert_eq!(a.to_string_lossy(), b.to_string_lossy());
+        assert!(os_str_key(a) < os_str_key(b));
+        assert!(os_str_key("z".as_ref()) < os_str_key(a));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_windows_compares_code_units() {
+        // U+FF5E is above the surrogate pair for U+1F600 in UTF-16, though
+        // below it as a code point
+        let (high, astral) = ("\u{ff5e}", "\u{1f600}");
+        assert!(high < astral);
+        assert!(os_str_key(high.as_ref()) > os_str_key(astral.as_ref()));
+    }
+}
@@ -0,0 +1,77 @@
+//! Prefix-first comparison keys for long strings.
+
+/// Sort key for a borrowed string that compares an 8-byte prefix first.
+///
+/// The first eight bytes of the string are packed big-endian into an integer,
+/// zero-padded for shorter strings. Two keys compare by that integer and fall
+/// back to the full string only when the prefixes are equal, so strings that
+/// differ early are ordered with one integer comparison. The ordering is the
+/// same as `str`'s byte-wise ordering.
+///
+/// The key borrows its string rather than copying it. Because of that it
+/// cannot be produced by a [`KeyExtractor`](crate::KeyExtractor); use
+/// [`tilesort_by_str_prefix`](crate::tilesort_by_str_prefix), which accepts a
+/// key function returning a `&str` borrowed from the element.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PrefixKey<'a> {
+    prefix: u64,
+    full: &'a str,
+}
+
+impl<'a> PrefixKey<'a> {
+    /// Build the prefix key for `s`.
+    pub fn new(s: &'a str) -> Self {
+        let mut bytes = [0u8; 8];
+        let len = s.len().min(8);
+        bytes[..len].copy_from_slice(&s.as_bytes()[..len]);
+        PrefixKey {
+            prefix: u64::from_be_bytes(bytes),
+            full: s,
+        }
+    }
+
+    /// The string this key was built from.
+    pub fn as_str(&self) -> &'a str {
+        self.full
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_str_order() {
+        let words = [
+            "",
+            "a",
+            "a\0",
+            "ab",
+            "abcdefgh",
+            "abcdefgh\0",
+            "abcdefghi",
+            "abcdefgz",
+            "b",
+            "\u{e9}",
+        ];
+        for a in words {
+            for b in words {
+                assert_eq!(
+                    PrefixKey::new(a).cmp(&PrefixKey::new(b)),
+                    a.cmp(b),
+                    "{:?} vs {:?}",
+                    a,
+                    b
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_as_str() {
+        assert_eq!(
+            PrefixKey::new("long string key").as_str(),
+            "long string key"
+        );
+    }
+}
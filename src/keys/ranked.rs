@@ -0,0 +1,166 @@
+//! Categorical values ordered by a table the caller supplies.
+
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::key_extractor::KeyExtractor;
+
+/// Key extractor ranking categorical values, such as enum variants or status
+/// strings, by their position in a caller-provided table.
+///
+/// Each value's key is its index in the table, so `["pending", "active",
+/// "done"]` sorts pending work first without an `Ord` wrapper type. A value
+/// listed twice keeps its first position. Values missing from the table sort
+/// after every listed one, in no particular order among themselves, or
+/// before them with [`unknown_first`](Self::unknown_first).
+///
+/// As an extractor it ranks elements that are the values themselves; to sort
+/// records by a categorical field, call [`rank`](Self::rank) from a key
+/// closure.
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::keys::RankedKey;
+///
+/// let mut statuses = vec!["done", "active", "pending", "active", "done"];
+/// let workflow = RankedKey::new(["pending", "active", "done"]);
+/// tilesort::tilesort_by_extractor(&mut statuses, &workflow);
+/// assert_eq!(statuses, vec!["pending", "active", "active", "done", "done"]);
+///
+/// #[derive(Clone)]
+/// struct Ticket {
+///     id: u32,
+///     status: String,
+/// }
+/// let mut tickets = vec![
+///     Ticket { id: 1, status: "done".into() },
+///     Ticket { id: 2, status: "blocked".into() },
+///     Ticket { id: 3, status: "pending".into() },
+/// ];
+/// let workflow = RankedKey::new(["pending", "active", "done"].map(String::from));
+/// tilesort::tilesort_by_key(&mut tickets, |t| workflow.rank(t.status.as_str()));
+/// let ids: Vec<u32> = tickets.iter().map(|t| t.id).collect();
+/// assert_eq!(ids, vec![3, 1, 2]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct RankedKey<V> {
+    ranks: HashMap<V, usize>,
+    unknown_first: bool,
+}
+
+impl<V: Hash + Eq> RankedKey<V> {
+    /// Create an extractor ranking values in the order `table` lists them.
+    pub fn new(table: impl IntoIterator<Item = V>) -> Self {
+        let mut ranks = HashMap::new();
+        for value in table {
+            let next = ranks.len();
+            ranks.entry(value).or_insert(next);
+        }
+        RankedKey {
+            ranks,
+            unknown_first: false,
+        }
+    }
+
+    /// Sort values missing from the table before every listed value, rather
+    /// than after.
+    pub fn unknown_first(mut self) -> Self {
+        self.unknown_first = true;
+        self
+    }
+
+    /// The rank of `value`: its position in the table, shifted past the
+    /// rank of unknown values if those sort first.
+    pub fn rank<Q>(&self, value: &Q) -> usize
+    where
+        V: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        match (self.ranks.get(value), self.unknown_first) {
+            (Some(&rank), false) => rank,
+            (Some(&rank), true) => rank + 1,
+            (None, false) => self.ranks.len(),
+            (None, true) => 0,
+        }
+    }
+
+    /// Whether `value` is listed in the table.
+    pub fn contains<Q>(&self, value: &Q) -> bool
+    where
+        V: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.ranks.contains_key(value)
+    }
+}
+
+impl<T, V> KeyExtractor<T, usize> for RankedKey<V>
+where
+    T: Hash + Eq,
+    V: Hash + Eq + Borrow<T>,
+{
+    fn extract_key(&self, item: &T) -> usize {
+        self.rank(item)
+    }
+}
+
+// Lets one table be shared across many sorts without rebuilding it.
+impl<T, V> KeyExtractor<T, usize> for &RankedKey<V>
+where
+    T: Hash + Eq,
+    V: Hash + Eq + Borrow<T>,
+{
+    fn extract_key(&self, item: &T) -> usize {
+        self.rank(item)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    enum Severity {
+        Info,
+        Warning,
+        Error,
+        Fatal,
+    }
+
+    #[test]
+    fn test_enum_ranks_follow_table() {
+        use Severity::*;
+        // Most severe first, the opposite of declaration order
+        let triage = RankedKey::new([Fatal, Error, Warning, Info]);
+        let mut events = vec![Info, Error, Info, Fatal, Warning, Error];
+        crate::tilesort_by_extractor(&mut events, &triage);
+        assert_eq!(events, vec![Fatal, Error, Error, Warning, Info, Info]);
+    }
+
+    #[test]
+    fn test_unknown_values() {
+        let table = RankedKey::new(["low", "high", "low"]);
+        assert_eq!(table.rank("low"), 0);
+        assert_eq!(table.rank("high"), 1);
+        assert_eq!(table.rank("other"), 2);
+        assert!(!table.contains("other"));
+
+        let table = table.unknown_first();
+        assert_eq!(table.rank("other"), 0);
+        assert_eq!(table.rank("low"), 1);
+        assert_eq!(table.rank("high"), 2);
+    }
+
+    #[test]
+    fn test_sort_is_stable_within_rank() {
+        let table = RankedKey::new(["b", "a"]);
+        let mut pairs = vec![("a", 1), ("x", 2), ("b", 3), ("a", 4), ("b", 5)];
+        crate::tilesort_by_key(&mut pairs, |&(status, _)| table.rank(status));
+        assert_eq!(
+            pairs,
+            vec![("b", 3), ("b", 5), ("a", 1), ("a", 4), ("x", 2)]
+        );
+    }
+}
@@ -0,0 +1,304 @@
+//! Semantic version keys for release and tag lists.
+
+use std::cmp::Ordering;
+use std::fmt;
+
+use crate::key_extractor::KeyExtractor;
+
+/// A semantic version such as `1.2.10-rc.1+build.5`, ordered by
+/// [semver precedence](https://semver.org/#spec-item-11).
+///
+/// Major, minor and patch compare numerically, so `1.2.10` follows `1.2.9`.
+/// A pre-release sorts before its release, and pre-release identifiers
+/// compare one at a time: numbers numerically, below any alphanumeric
+/// identifier, which compare in ASCII order, with a shorter list first when
+/// one is a prefix of the other. Build metadata is ignored, so versions
+/// differing only in their builds are equal and keep their input order.
+#[derive(Debug, Clone)]
+pub struct Version {
+    major: u64,
+    minor: u64,
+    patch: u64,
+    pre: Vec<Identifier>,
+    build: Vec<String>,
+}
+
+/// One dot-separated pre-release identifier; numbers sort first.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Identifier {
+    Numeric(u64),
+    Alphanumeric(String),
+}
+
+impl Version {
+    /// The major version number.
+    pub fn major(&self) -> u64 {
+        self.major
+    }
+
+    /// The minor version number.
+    pub fn minor(&self) -> u64 {
+        self.minor
+    }
+
+    /// The patch version number.
+    pub fn patch(&self) -> u64 {
+        self.patch
+    }
+
+    /// Whether this is a pre-release, such as `1.0.0-rc.1`.
+    pub fn is_prerelease(&self) -> bool {
+        !self.pre.is_empty()
+    }
+}
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Version {}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.major, self.minor, self.patch)
+            .cmp(&(other.major, other.minor, other.patch))
+            .then_with(|| match (self.pre.is_empty(), other.pre.is_empty()) {
+                (true, true) => Ordering::Equal,
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                (false, false) => self.pre.cmp(&other.pre),
+            })
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+        for (i, identifier) in self.pre.iter().enumerate() {
+            f.write_str(if i == 0 { "-" } else { "." })?;
+            match identifier {
+                Identifier::Numeric(n) => write!(f, "{}", n)?,
+                Identifier::Alphanumeric(s) => f.write_str(s)?,
+            }
+        }
+        if !self.build.is_empty() {
+            write!(f, "+{}", self.build.join("."))?;
+        }
+        Ok(())
+    }
+}
+
+/// Error returned when a string does not hold a valid semantic version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VersionError {
+    reason: &'static str,
+}
+
+impl fmt::Display for VersionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid version: {}", self.reason)
+    }
+}
+
+impl std::error::Error for VersionError {}
+
+fn invalid(reason: &'static str) -> VersionError {
+    VersionError { reason }
+}
+
+/// Parse a number without leading zeros, as semver requires of version
+/// numbers and numeric pre-release identifiers.
+fn number(s: &str, field: &'static str) -> Result<u64, VersionError> {
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(invalid(field));
+    }
+    if s.len() > 1 && s.starts_with('0') {
+        return Err(invalid("leading zero"));
+    }
+    s.parse().map_err(|_| invalid("number out of range"))
+}
+
+/// Split dot-separated identifiers, each non-empty and of ASCII letters,
+/// digits and hyphens.
+fn identifiers<'a>(
+    s: &'a str,
+    field: &'static str,
+) -> impl Iterator<Item = Result<&'a str, VersionError>> {
+    s.split('.').map(move |identifier| {
+        let valid = !identifier.is_empty()
+            && identifier
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'-');
+        if valid {
+            Ok(identifier)
+        } else {
+            Err(invalid(field))
+        }
+    })
+}
+
+/// Parse a semantic version such as `1.2.10-rc.1`.
+///
+/// The whole string must be the version, optionally after a `v` or `V` as
+/// release tags often have. Version numbers must not have leading zeros.
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::keys::parse_version;
+///
+/// let rc = parse_version("v1.2.10-rc.1").unwrap();
+/// assert!(rc.is_prerelease());
+/// assert!(rc > parse_version("1.2.9").unwrap());
+/// assert!(rc < parse_version("1.2.10").unwrap());
+/// assert_eq!(rc.to_string(), "1.2.10-rc.1");
+///
+/// // Fallible keys sort with `try_tilesort_by_key`
+/// let mut tags = vec!["1.10.0", "1.9.0", "1.10.0-beta"];
+/// tilesort::try_tilesort_by_key(&mut tags, |s| parse_version(s)).unwrap();
+/// assert_eq!(tags, vec!["1.9.0", "1.10.0-beta", "1.10.0"]);
+/// ```
+pub fn parse_version(s: &str) -> Result<Version, VersionError> {
+    let s = s.strip_prefix(['v', 'V']).unwrap_or(s);
+    let (s, build) = match s.split_once('+') {
+        Some((s, build)) => (
+            s,
+            identifiers(build, "build metadata")
+                .map(|identifier| identifier.map(str::to_string))
+                .collect::<Result<_, _>>()?,
+        ),
+        None => (s, Vec::new()),
+    };
+    let (core, pre) = match s.split_once('-') {
+        Some((core, pre)) => (
+            core,
+            identifiers(pre, "pre-release")
+                .map(|identifier| {
+                    let identifier = identifier?;
+                    if identifier.bytes().all(|b| b.is_ascii_digit()) {
+                        number(identifier, "pre-release").map(Identifier::Numeric)
+                    } else {
+                        Ok(Identifier::Alphanumeric(identifier.to_string()))
+                    }
+                })
+                .collect::<Result<_, _>>()?,
+        ),
+        None => (s, Vec::new()),
+    };
+
+    let mut numbers = core.split('.');
+    let mut next = |field| number(numbers.next().unwrap_or(""), field);
+    let (major, minor, patch) = (next("major")?, next("minor")?, next("patch")?);
+    if numbers.next().is_some() {
+        return Err(invalid("too many version numbers"));
+    }
+    Ok(Version {
+        major,
+        minor,
+        patch,
+        pre,
+        build,
+    })
+}
+
+/// Key extractor for strings holding a semantic version, such as release
+/// tags.
+///
+/// Elements that are not valid versions get the key `None` and sort before
+/// all others, keeping their input order; use [`parse_version`] with
+/// [`try_tilesort_by_key`](crate::try_tilesort_by_key) to reject them
+/// instead.
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::keys::Semver;
+///
+/// let mut tags = vec!["v0.10.0", "v0.9.1", "v0.10.0-rc.2", "v0.10.0-rc.10", "nightly"];
+/// tilesort::tilesort_by_extractor(&mut tags, Semver);
+/// assert_eq!(
+///     tags,
+///     vec!["nightly", "v0.9.1", "v0.10.0-rc.2", "v0.10.0-rc.10", "v0.10.0"]
+/// );
+/// ```
+pub struct Semver;
+
+impl<T: AsRef<str>> KeyExtractor<T, Option<Version>> for Semver {
+    fn extract_key(&self, item: &T) -> Option<Version> {
+        parse_version(item.as_ref()).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(s: &str) -> Version {
+        parse_version(s).unwrap()
+    }
+
+    #[test]
+    fn test_precedence() {
+        // The ordering given by the semver specification
+        let ordered = [
+            "1.0.0-alpha",
+            "1.0.0-alpha.1",
+            "1.0.0-alpha.beta",
+            "1.0.0-beta",
+            "1.0.0-beta.2",
+            "1.0.0-beta.11",
+            "1.0.0-rc.1",
+            "1.0.0",
+            "1.0.1",
+            "1.2.9",
+            "1.2.10",
+            "2.0.0",
+        ];
+        for pair in ordered.windows(2) {
+            assert!(version(pair[0]) < version(pair[1]), "{:?}", pair);
+        }
+        assert_eq!(version("1.0.0+build.1"), version("1.0.0+build.2"));
+        assert_eq!(version("v1.0.0"), version("1.0.0"));
+    }
+
+    #[test]
+    fn test_parts_and_display() {
+        let v = version("V12.0.3-rc.01a.7+exp.sha.5114f85");
+        assert_eq!((v.major(), v.minor(), v.patch()), (12, 0, 3));
+        assert!(v.is_prerelease());
+        assert_eq!(v.to_string(), "12.0.3-rc.01a.7+exp.sha.5114f85");
+        assert!(!version("1.0.0+001").is_prerelease());
+    }
+
+    #[test]
+    fn test_rejects_invalid() {
+        for bad in [
+            "",
+            "1",
+            "1.2",
+            "1.2.3.4",
+            "1.02.3",
+            "1.2.3-",
+            "1.2.3-rc..1",
+            "1.2.3-01",
+            "1.2.3+",
+            "1.2.3-rc_1",
+            "a.b.c",
+            "1.2.99999999999999999999",
+        ] {
+            assert!(parse_version(bad).is_err(), "{:?}", bad);
+        }
+        assert_eq!(
+            parse_version("1.02.3").unwrap_err().to_string(),
+            "invalid version: leading zero"
+        );
+    }
+}
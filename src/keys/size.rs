@@ -0,0 +1,369 @@
+//! Human-readable size keys such as `2.3K` or `1.2G`, as `du -h` prints.
+
+use std::cmp::Ordering;
+use std::fmt;
+
+use crate::key_extractor::KeyExtractor;
+
+/// What a bare suffix such as `K` or `M` multiplies by.
+///
+/// Suffixes with an `i`, as in `KiB` or `Mi`, are always binary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SizeUnits {
+    /// Powers of 1024, as `du -h` and `ls -h` print.
+    Binary,
+    /// Powers of 1000, as `du --si` prints.
+    Decimal,
+}
+
+/// A size in bytes parsed from a human-readable string, ordered
+/// numerically.
+///
+/// The value is held as an `f64`, so sizes beyond 2^53 bytes are
+/// approximate.
+#[derive(Debug, Clone, Copy)]
+pub struct ByteSize {
+    bytes: f64,
+}
+
+impl ByteSize {
+    /// The size in bytes.
+    pub fn bytes(&self) -> f64 {
+        self.bytes
+    }
+}
+
+impl PartialEq for ByteSize {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for ByteSize {}
+
+impl PartialOrd for ByteSize {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ByteSize {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Parsed sizes are finite and never negative zero
+        self.bytes.total_cmp(&other.bytes)
+    }
+}
+
+/// Error returned when a string does not hold a valid size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SizeError {
+    reason: &'static str,
+}
+
+impl fmt::Display for SizeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid size: {}", self.reason)
+    }
+}
+
+impl std::error::Error for SizeError {}
+
+fn invalid(reason: &'static str) -> SizeError {
+    SizeError { reason }
+}
+
+/// The suffixes `sort -h` accepts, in increasing order of magnitude.
+const SUFFIXES: &[u8] = b"KMGTPEZYRQ";
+
+/// Parse a size from the start of `s`, returning it and the unparsed
+/// remainder.
+fn parse_size_prefix(s: &str, units: SizeUnits) -> Result<(ByteSize, &str), SizeError> {
+    let bytes = s.as_bytes();
+    let mut end = usize::from(bytes.first() == Some(&b'-'));
+    let mut digits = 0;
+    let mut point = false;
+    while let Some(&byte) = bytes.get(end) {
+        if byte.is_ascii_digit() {
+            digits += 1;
+        } else if byte == b'.' && !point {
+            point = true;
+        } else {
+            break;
+        }
+        end += 1;
+    }
+    if digits == 0 {
+        return Err(invalid("number"));
+    }
+    let number: f64 = s[..end].parse().map_err(|_| invalid("number"))?;
+
+    let mut rest = &bytes[end..];
+    let mut exponent = 0;
+    if let Some((&letter, after)) = rest.split_first() {
+        let letter = if letter == b'k' { b'K' } else { letter };
+        if let Some(position) = SUFFIXES.iter().position(|&suffix| suffix == letter) {
+            exponent = position as i32 + 1;
+            rest = after;
+        }
+    }
+    let mut base = match units {
+        SizeUnits::Binary => 1024.0,
+        SizeUnits::Decimal => 1000.0,
+    };
+    if exponent > 0 {
+        if let Some(after) = rest.strip_prefix(b"i") {
+            base = 1024.0;
+            rest = after;
+        }
+    }
+    rest = rest.strip_prefix(b"B").unwrap_or(rest);
+
+    // Adding zero turns "-0" into 0, so it equals "0"
+    let bytes = number * f64::powi(base, exponent) + 0.0;
+    if !bytes.is_finite() {
+        return Err(invalid("size out of range"));
+    }
+    Ok((ByteSize { bytes }, &s[s.len() - rest.len()..]))
+}
+
+/// Parse a human-readable size such as `1.5K`, `150MB`, `2GiB` or `512`.
+///
+/// The whole string must be the size: a number, possibly negative and
+/// fractional, then optionally one of the suffixes `K` (or `k`), `M`, `G`,
+/// `T`, `P`, `E`, `Z`, `Y`, `R` and `Q`, an `i` marking a binary suffix, and
+/// a `B`. `units` decides what a suffix without an `i` multiplies by.
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::keys::{parse_human_size, SizeUnits};
+///
+/// let size = parse_human_size("1.5K", SizeUnits::Binary).unwrap();
+/// assert_eq!(size.bytes(), 1536.0);
+/// let size = parse_human_size("1.5kB", SizeUnits::Decimal).unwrap();
+/// assert_eq!(size.bytes(), 1500.0);
+/// let size = parse_human_size("1.5KiB", SizeUnits::Decimal).unwrap();
+/// assert_eq!(size.bytes(), 1536.0);
+/// ```
+pub fn parse_human_size(s: &str, units: SizeUnits) -> Result<ByteSize, SizeError> {
+    match parse_size_prefix(s, units)? {
+        (size, "") => Ok(size),
+        _ => Err(invalid("trailing characters")),
+    }
+}
+
+/// Key extractor for strings that start with a human-readable size, such as
+/// the lines `du -h` prints, ordering them by size in bytes.
+///
+/// Like `sort -h`, leading blanks are skipped and anything after the size
+/// is ignored. Unlike `sort -h`, which ranks sizes by suffix before number
+/// and so relies on every size being printed with the largest suffix that
+/// fits, this key compares the sizes themselves, so `1536` and `1.5K` are
+/// equal; [`HumanNumeric`] ranks them as `sort -h` does. Elements that do
+/// not start with a size get the key `None` and sort before all others,
+/// keeping their input order.
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::keys::{HumanSize, SizeUnits};
+///
+/// let mut du = vec!["1.2G\t./video", "150M\t./photos", "2.3K\t./notes", "4.0K\t./src"];
+/// tilesort::tilesort_by_extractor(&mut du, HumanSize(SizeUnits::Binary));
+/// assert_eq!(
+///     du,
+///     vec!["2.3K\t./notes", "4.0K\t./src", "150M\t./photos", "1.2G\t./video"]
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HumanSize(pub SizeUnits);
+
+impl<T: AsRef<str>> KeyExtractor<T, Option<ByteSize>> for HumanSize {
+    fn extract_key(&self, item: &T) -> Option<ByteSize> {
+        let s = item.as_ref().trim_start_matches([' ', '\t']);
+        parse_size_prefix(s, self.0).ok().map(|(size, _)| size)
+    }
+}
+
+/// A number with an optional size suffix, as `sort -h` ranks it: by sign
+/// and suffix first, then by number.
+///
+/// Zero and numbers without a suffix rank below `K`, then come `K` (or
+/// `k`), `M`, `G` and so on up to `Q`, with negative numbers below zero in
+/// the reverse order, so `2000K` ranks below `1M` and `-1M` below `-2000K`.
+/// What a suffix multiplies by does not matter.
+#[derive(Debug, Clone, Copy)]
+pub struct HumanNumber {
+    /// The suffix's position in [`SUFFIXES`], from 1, negated for negative
+    /// numbers; 0 without a suffix or for zero.
+    order: i32,
+    number: f64,
+}
+
+impl HumanNumber {
+    /// The number before the suffix.
+    pub fn number(&self) -> f64 {
+        self.number
+    }
+}
+
+impl PartialEq for HumanNumber {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for HumanNumber {}
+
+impl PartialOrd for HumanNumber {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for HumanNumber {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.order
+            .cmp(&other.order)
+            .then_with(|| self.number.total_cmp(&other.number))
+    }
+}
+
+/// Key extractor ordering strings that start with a number and an optional
+/// size suffix as GNU `sort -h` does, such as the lines `du -h` prints.
+///
+/// Leading blanks are skipped and anything after the suffix is ignored.
+/// Sizes are ranked by suffix before number, as [`HumanNumber`] describes,
+/// which orders sizes by value as long as each is printed with the largest
+/// suffix that fits, as `du -h` prints them; [`HumanSize`] compares their
+/// values instead. As for `sort -h`, elements that do not start with a
+/// number rank as zero.
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::keys::HumanNumeric;
+///
+/// let mut sizes = vec!["1M", "2000K", "0.5G", "3", "-1K"];
+/// tilesort::tilesort_by_extractor(&mut sizes, HumanNumeric);
+/// assert_eq!(sizes, vec!["-1K", "3", "2000K", "1M", "0.5G"]);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HumanNumeric;
+
+impl<T: AsRef<str>> KeyExtractor<T, HumanNumber> for HumanNumeric {
+    fn extract_key(&self, item: &T) -> HumanNumber {
+        let s = item.as_ref().trim_start_matches([' ', '\t']);
+        let bytes = s.as_bytes();
+        let negative = bytes.first() == Some(&b'-');
+        let mut end = usize::from(negative);
+        let mut point = false;
+        while let Some(&byte) = bytes.get(end) {
+            if byte == b'.' && !point {
+                point = true;
+            } else if !byte.is_ascii_digit() {
+                break;
+            }
+            end += 1;
+        }
+        // Adding zero turns "-0" into 0, so it equals "0"
+        let number = match s[..end].parse::<f64>() {
+            Ok(number) => number + 0.0,
+            Err(_) => {
+                return HumanNumber {
+                    order: 0,
+                    number: 0.0,
+                }
+            }
+        };
+        let order = match bytes.get(end) {
+            _ if number == 0.0 => 0,
+            Some(b'k') => 1,
+            Some(letter) => SUFFIXES
+                .iter()
+                .position(|suffix| suffix == letter)
+                .map_or(0, |position| position as i32 + 1),
+            None => 0,
+        };
+        HumanNumber {
+            order: if negative { -order } else { order },
+            number,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bytes(s: &str, units: SizeUnits) -> f64 {
+        parse_human_size(s, units).unwrap().bytes()
+    }
+
+    #[test]
+    fn test_suffixes() {
+        use SizeUnits::*;
+        assert_eq!(bytes("512", Binary), 512.0);
+        assert_eq!(bytes("512B", Decimal), 512.0);
+        assert_eq!(bytes("2k", Binary), 2048.0);
+        assert_eq!(bytes("150M", Binary), 150.0 * 1024.0 * 1024.0);
+        assert_eq!(bytes("150M", Decimal), 150e6);
+        assert_eq!(bytes("1.2G", Decimal), 1.2e9);
+        assert_eq!(bytes("1GiB", Decimal), 1024.0 * 1024.0 * 1024.0);
+        assert_eq!(bytes("1Q", Decimal), 1e30);
+        assert_eq!(bytes(".5K", Binary), 512.0);
+        assert_eq!(bytes("-1K", Binary), -1024.0);
+        assert_eq!(
+            parse_human_size("-0", Binary),
+            parse_human_size("0K", Binary)
+        );
+    }
+
+    #[test]
+    fn test_rejects_invalid() {
+        for bad in ["", "K", ".", "-", "1.2.3", "1X", "1 K", "1KiBs", "1iB"] {
+            assert!(
+                parse_human_size(bad, SizeUnits::Binary).is_err(),
+                "{:?}",
+                bad
+            );
+        }
+        assert_eq!(
+            parse_human_size("1X", SizeUnits::Binary)
+                .unwrap_err()
+                .to_string(),
+            "invalid size: trailing characters"
+        );
+    }
+
+    #[test]
+    fn test_extractor_ignores_surrounding_text() {
+        let key = HumanSize(SizeUnits::Binary);
+        assert_eq!(
+            key.extract_key(&"  4.0K\t./src").map(|size| size.bytes()),
+            Some(4096.0)
+        );
+        assert_eq!(key.extract_key(&"total"), None);
+        // Sizes compare by value, not by suffix first
+        assert_eq!(key.extract_key(&"1536"), key.extract_key(&"1.5K"));
+        assert!(key.extract_key(&"999") > key.extract_key(&"0.5K"));
+    }
+
+    #[test]
+    fn test_human_numeric_ranks_suffix_first() {
+        let ranked = [
+            "-1G", "-2000M", "-1M", "-0.5K", "-3", "total", "0", "-0", "0.0K", "2.5", "1023", "1k",
+            "1.5K", "2000K", "1M", "1MiB", "0.5G", "1Q",
+        ];
+        let keys: Vec<HumanNumber> = ranked.iter().map(|s| HumanNumeric.extract_key(s)).collect();
+        for (pair, keys) in ranked.windows(2).zip(keys.windows(2)) {
+            assert!(keys[0] <= keys[1], "{:?}", pair);
+        }
+        // Zero however written, and lines without a number, rank alike
+        assert_eq!(keys[5], keys[6]);
+        assert_eq!(keys[6], keys[7]);
+        assert_eq!(keys[7], keys[8]);
+        assert_eq!(keys[14], keys[15]);
+        assert_eq!(HumanNumeric.extract_key(&"  4.0K\t./src").number(), 4.0);
+    }
+}
@@ -0,0 +1,166 @@
+//! Case-insensitive and Unicode-normalized string keys.
+//!
+//! These follow the default case algorithms of the Unicode Standard (section
+//! 3.13): full case folding rather than `to_lowercase`, and normalization so
+//! that canonically equivalent strings (precomposed `é` versus `e` plus a
+//! combining accent) produce the same key.
+
+use caseless::Caseless as _;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::key_extractor::KeyExtractor;
+
+/// Unicode normalization form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NormalizationForm {
+    /// Canonical decomposition followed by canonical composition.
+    Nfc,
+    /// Canonical decomposition.
+    Nfd,
+    /// Compatibility decomposition followed by canonical composition.
+    Nfkc,
+    /// Compatibility decomposition.
+    Nfkd,
+}
+
+/// Normalize `s` to `form`.
+pub fn normalized_key(s: &str, form: NormalizationForm) -> String {
+    match form {
+        NormalizationForm::Nfc => s.nfc().collect(),
+        NormalizationForm::Nfd => s.nfd().collect(),
+        NormalizationForm::Nfkc => s.nfkc().collect(),
+        NormalizationForm::Nfkd => s.nfkd().collect(),
+    }
+}
+
+/// Apply Unicode default case folding to `s` (so `"Straße"` and `"STRASSE"` match).
+///
+/// This does not normalize; use [`caseless_key`] when inputs may mix
+/// precomposed and decomposed characters.
+pub fn case_folded_key(s: &str) -> String {
+    caseless::default_case_fold_str(s)
+}
+
+/// Canonical caseless key: `NFD(fold(NFD(s)))`.
+///
+/// Two strings produce the same key exactly when they are canonical caseless
+/// matches.
+pub fn caseless_key(s: &str) -> String {
+    s.nfd().default_case_fold().nfd().collect()
+}
+
+/// Compatibility caseless key: `NFKD(fold(NFKD(fold(NFD(s)))))`.
+///
+/// Also folds compatibility variants such as full-width forms
+/// (`"Ａ"` matches `"a"`).
+pub fn compatibility_caseless_key(s: &str) -> String {
+    s.nfd()
+        .default_case_fold()
+        .nfkd()
+        .default_case_fold()
+        .nfkd()
+        .collect()
+}
+
+/// Key extractor applying [`case_folded_key`].
+pub struct CaseFolded;
+
+impl<T: AsRef<str>> KeyExtractor<T, String> for CaseFolded {
+    fn extract_key(&self, item: &T) -> String {
+        case_folded_key(item.as_ref())
+    }
+}
+
+/// Key extractor applying [`caseless_key`].
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::keys::Caseless;
+///
+/// let mut names = vec!["beta", "Alpha", "alpha", "BETA", "Gamma"];
+/// tilesort::tilesort_by_extractor(&mut names, Caseless);
+/// assert_eq!(names, vec!["Alpha", "alpha", "beta", "BETA", "Gamma"]);
+/// ```
+pub struct Caseless;
+
+impl<T: AsRef<str>> KeyExtractor<T, String> for Caseless {
+    fn extract_key(&self, item: &T) -> String {
+        caseless_key(item.as_ref())
+    }
+}
+
+/// Key extractor applying [`compatibility_caseless_key`].
+pub struct CompatibilityCaseless;
+
+impl<T: AsRef<str>> KeyExtractor<T, String> for CompatibilityCaseless {
+    fn extract_key(&self, item: &T) -> String {
+        compatibility_caseless_key(item.as_ref())
+    }
+}
+
+/// Key extractor applying [`normalized_key`] with the given form.
+pub struct Normalized(pub NormalizationForm);
+
+impl<T: AsRef<str>> KeyExtractor<T, String> for Normalized {
+    fn extract_key(&self, item: &T) -> String {
+        normalized_key(item.as_ref(), self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const E_ACUTE_COMPOSED: &str = "caf\u{e9}";
+    const E_ACUTE_DECOMPOSED: &str = "cafe\u{301}";
+
+    #[test]
+    fn test_case_fold_is_full_folding() {
+        assert_eq!(case_folded_key("Straße"), case_folded_key("STRASSE"));
+        assert_eq!(case_folded_key("ΣΑΣ"), case_folded_key("σας"));
+        assert_ne!(case_folded_key("a"), case_folded_key("b"));
+    }
+
+    #[test]
+    fn test_normalization_forms() {
+        assert_eq!(
+            normalized_key(E_ACUTE_DECOMPOSED, NormalizationForm::Nfc),
+            E_ACUTE_COMPOSED
+        );
+        assert_eq!(
+            normalized_key(E_ACUTE_COMPOSED, NormalizationForm::Nfd),
+            E_ACUTE_DECOMPOSED
+        );
+        assert_eq!(normalized_key("\u{fb01}", NormalizationForm::Nfkc), "fi");
+        assert_eq!(
+            normalized_key("\u{fb01}", NormalizationForm::Nfc),
+            "\u{fb01}"
+        );
+    }
+
+    #[test]
+    fn test_caseless_matches_canonical_equivalents() {
+        assert_eq!(caseless_key("CAF\u{c9}"), caseless_key(E_ACUTE_DECOMPOSED));
+        assert_eq!(caseless_key("\u{fb01}"), caseless_key("FI"));
+        assert_ne!(caseless_key("\u{ff21}"), caseless_key("a"));
+        assert_eq!(
+            compatibility_caseless_key("\u{ff21}"),
+            compatibility_caseless_key("a")
+        );
+    }
+
+    #[test]
+    fn test_extractors() {
+        assert_eq!(CaseFolded.extract_key(&"ABC"), "abc");
+        assert_eq!(
+            Caseless.extract_key(&String::from(E_ACUTE_COMPOSED)),
+            E_ACUTE_DECOMPOSED
+        );
+        assert_eq!(CompatibilityCaseless.extract_key(&"\u{fb01}"), "fi");
+        assert_eq!(
+            Normalized(NormalizationForm::Nfc).extract_key(&E_ACUTE_DECOMPOSED),
+            E_ACUTE_COMPOSED
+        );
+    }
+}
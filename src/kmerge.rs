@@ -0,0 +1,308 @@
+//! Lazy merging of several already sorted inputs.
+
+use std::cmp::Ordering;
+use std::collections::binary_heap::{BinaryHeap, PeekMut};
+use std::iter::FusedIterator;
+
+use crate::config::Order;
+use crate::key_extractor::KeyExtractor;
+use crate::opcount;
+
+/// Iterator over the elements of several sorted slices, in merged order.
+///
+/// Created by [`kmerge`]. Each slice is treated as one tile: the heap holds
+/// the head of every slice not yet exhausted, so each element costs one key
+/// extraction and a heap operation.
+pub struct KMerge<'a, T, K, E> {
+    slices: Vec<&'a [T]>,
+    extractor: E,
+    heads: BinaryHeap<Head<K, usize>>,
+    remaining: usize,
+}
+
+/// Iterator over the elements of several sorted iterators, in merged order.
+///
+/// Created by [`kmerge_owned`]. Each input is advanced only when its next
+/// element is needed, so at most one element per input is held at a time.
+pub struct KMergeOwned<I: Iterator, K, E> {
+    sources: Vec<I>,
+    extractor: E,
+    heads: BinaryHeap<Head<K, I::Item>>,
+}
+
+/// The next element of one input.
+struct Head<K, V> {
+    key: K,
+    source: usize,
+    descending: bool,
+    /// Position in the slice, or the element itself for owned inputs
+    value: V,
+}
+
+impl<K: Ord, V> Ord for Head<K, V> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // `BinaryHeap` is a max-heap, so invert the order. Ties go to the
+        // earlier input, keeping equal keys in input order.
+        let ordering = other.key.cmp(&self.key);
+        let ordering = if self.descending {
+            ordering.reverse()
+        } else {
+            ordering
+        };
+        ordering.then_with(|| other.source.cmp(&self.source))
+    }
+}
+
+impl<K: Ord, V> PartialOrd for Head<K, V> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K: Ord, V> PartialEq for Head<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<K: Ord, V> Eq for Head<K, V> {}
+
+/// Merge slices that are each sorted in `order` into one sorted sequence.
+///
+/// No element is moved or cloned. Elements with equal keys are yielded in
+/// input order: first by slice, then by position within it. A slice that is
+/// not actually sorted does not cause a panic, but the output is then not
+/// sorted either.
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::{kmerge, IdentityKey, Order};
+///
+/// let a = [1, 4, 7];
+/// let b = [2, 5, 8];
+/// let c = [3, 6];
+/// let merged: Vec<i32> = kmerge(&[&a, &b, &c], IdentityKey, Order::Ascending)
+///     .copied()
+///     .collect();
+/// assert_eq!(merged, vec![1, 2, 3, 4, 5, 6, 7, 8]);
+///
+/// let words = ["kiwi", "fig"];
+/// let more = ["banana", "pear", "a"];
+/// let by_len = |w: &&str| w.len();
+/// let merged: Vec<&&str> = kmerge(&[&words, &more], by_len, Order::Descending).collect();
+/// assert_eq!(merged, vec![&"banana", &"kiwi", &"pear", &"fig", &"a"]);
+/// ```
+pub fn kmerge<'a, T, K, E>(slices: &[&'a [T]], extractor: E, order: Order) -> KMerge<'a, T, K, E>
+where
+    K: Ord,
+    E: KeyExtractor<T, K>,
+{
+    let heads = slices
+        .iter()
+        .enumerate()
+        .filter(|(_, slice)| !slice.is_empty())
+        .map(|(source, slice)| Head {
+            key: opcount::extract_key(&extractor, &slice[0]),
+            source,
+            descending: order.is_descending(),
+            value: 0,
+        })
+        .collect();
+
+    KMerge {
+        slices: slices.to_vec(),
+        extractor,
+        heads,
+        remaining: slices.iter().map(|slice| slice.len()).sum(),
+    }
+}
+
+/// Merge iterators that each yield elements sorted in `order`, taking
+/// ownership of the elements.
+///
+/// Like [`kmerge`], but for inputs that are consumed rather than borrowed,
+/// such as `Vec`s or readers producing sorted records. Elements with equal
+/// keys are yielded in input order.
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::{kmerge_owned, IdentityKey, Order};
+///
+/// let shards = vec![
+///     vec![String::from("apple"), String::from("cherry")],
+///     vec![String::from("banana")],
+/// ];
+/// let merged: Vec<String> = kmerge_owned(shards, IdentityKey, Order::Ascending).collect();
+/// assert_eq!(merged, ["apple", "banana", "cherry"]);
+/// ```
+pub fn kmerge_owned<S, T, K, E>(
+    sources: S,
+    extractor: E,
+    order: Order,
+) -> KMergeOwned<<S::Item as IntoIterator>::IntoIter, K, E>
+where
+    S: IntoIterator,
+    S::Item: IntoIterator<Item = T>,
+    K: Ord,
+    E: KeyExtractor<T, K>,
+{
+    let mut sources: Vec<_> = sources.into_iter().map(IntoIterator::into_iter).collect();
+    let heads = sources
+        .iter_mut()
+        .enumerate()
+        .filter_map(|(source, iter)| {
+            let value = iter.next()?;
+            Some(Head {
+                key: opcount::extract_key(&extractor, &value),
+                source,
+                descending: order.is_descending(),
+                value,
+            })
+        })
+        .collect();
+
+    KMergeOwned {
+        sources,
+        extractor,
+        heads,
+    }
+}
+
+impl<'a, T, K, E> Iterator for KMerge<'a, T, K, E>
+where
+    K: Ord,
+    E: KeyExtractor<T, K>,
+{
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        let mut head = self.heads.peek_mut()?;
+        let slice = self.slices[head.source];
+        let element = &slice[head.value];
+
+        // Advance the slice, or drop it once exhausted
+        head.value += 1;
+        if head.value < slice.len() {
+            head.key = opcount::extract_key(&self.extractor, &slice[head.value]);
+        } else {
+            PeekMut::pop(head);
+        }
+
+        self.remaining -= 1;
+        Some(element)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<T, K, E> ExactSizeIterator for KMerge<'_, T, K, E>
+where
+    K: Ord,
+    E: KeyExtractor<T, K>,
+{
+}
+
+impl<T, K, E> FusedIterator for KMerge<'_, T, K, E>
+where
+    K: Ord,
+    E: KeyExtractor<T, K>,
+{
+}
+
+impl<I, T, K, E> Iterator for KMergeOwned<I, K, E>
+where
+    I: Iterator<Item = T>,
+    K: Ord,
+    E: KeyExtractor<T, K>,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let mut head = self.heads.peek_mut()?;
+
+        // Replace the head with the next element of its input, or drop it
+        // once the input is exhausted
+        match self.sources[head.source].next() {
+            Some(next) => {
+                head.key = opcount::extract_key(&self.extractor, &next);
+                Some(std::mem::replace(&mut head.value, next))
+            }
+            None => Some(PeekMut::pop(head).value),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.sources.iter().map(Iterator::size_hint).fold(
+            (self.heads.len(), Some(self.heads.len())),
+            |(lo, hi), (source_lo, source_hi)| {
+                (
+                    lo.saturating_add(source_lo),
+                    hi.zip(source_hi).and_then(|(a, b)| a.checked_add(b)),
+                )
+            },
+        )
+    }
+}
+
+// Exhausted inputs are dropped from the heap and never polled again
+impl<I, T, K, E> FusedIterator for KMergeOwned<I, K, E>
+where
+    I: Iterator<Item = T>,
+    K: Ord,
+    E: KeyExtractor<T, K>,
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IdentityKey;
+
+    #[test]
+    fn test_empty_inputs() {
+        assert_eq!(
+            kmerge::<i32, _, _>(&[], IdentityKey, Order::Ascending).count(),
+            0
+        );
+        let merged: Vec<i32> = kmerge(&[&[], &[2, 3], &[]], IdentityKey, Order::Ascending)
+            .copied()
+            .collect();
+        assert_eq!(merged, vec![2, 3]);
+        let owned: Vec<Vec<i32>> = vec![vec![], vec![]];
+        assert_eq!(
+            kmerge_owned(owned, IdentityKey, Order::Ascending).count(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_exact_size() {
+        let (a, b) = ([1, 3], [2]);
+        let mut iter = kmerge(&[&a, &b], IdentityKey, Order::Ascending);
+        assert_eq!(iter.len(), 3);
+        iter.next();
+        assert_eq!(iter.len(), 2);
+
+        let owned = kmerge_owned(vec![vec![1, 3], vec![2]], IdentityKey, Order::Ascending);
+        assert_eq!(owned.size_hint(), (3, Some(3)));
+    }
+
+    #[test]
+    fn test_equal_keys_in_input_order() {
+        let a = [(1, 'a'), (2, 'b')];
+        let b = [(1, 'c'), (2, 'd')];
+        let key = |&(k, _): &(i32, char)| k;
+        let tags: Vec<char> = kmerge(&[&a, &b], key, Order::Ascending)
+            .map(|&(_, tag)| tag)
+            .collect();
+        assert_eq!(tags, vec!['a', 'c', 'b', 'd']);
+        let tags: Vec<char> = kmerge_owned([a, b], key, Order::Ascending)
+            .map(|(_, tag)| tag)
+            .collect();
+        assert_eq!(tags, vec!['a', 'c', 'b', 'd']);
+    }
+}
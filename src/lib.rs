@@ -3,165 +3,1671 @@
 //! This library provides efficient sorting for data consisting of non-overlapping,
 //! pre-sorted contiguous blocks called "tiles".
 
+mod analysis;
+mod array;
+#[cfg(feature = "ndarray")]
+mod axis;
+mod builder;
+mod cancel;
+mod columns;
+mod config;
+pub mod const_sort;
+mod container;
+mod erased;
+mod estimate;
+mod external;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(any(feature = "ffi", feature = "python", feature = "wasm"))]
+mod float;
+mod integer;
+mod iter;
+mod k_smallest;
 mod key_extractor;
+pub mod keys;
+mod kmerge;
+mod logging;
+#[cfg(feature = "async")]
+pub mod nonblocking;
+mod nulls;
+mod observer;
+mod opcount;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod pattern;
+mod plan;
+mod progress;
+#[cfg(feature = "simd")]
+mod simd;
+pub mod slice;
+mod small;
 mod sorter;
+mod spec;
+mod stream;
 mod tile_index;
+#[cfg(feature = "verify")]
+mod verify;
+mod view;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
-pub use key_extractor::{IdentityKey, KeyExtractor};
+pub use analysis::{
+    count_inversions, run_length_histogram, run_length_histogram_with_config, Histogram,
+};
+#[cfg(feature = "ndarray")]
+pub use axis::{sort_axis_by_key, sort_rows_by_column, sorted_axis_indices};
+pub use builder::Tilesort;
+pub use cancel::{CancellationToken, Cancelled};
+pub use columns::{Column, KeyColumn};
+#[cfg(feature = "rayon")]
+pub use config::Threads;
+pub use config::{CapacityHint, KeyCost, Order, SortConfig, Strategy};
+pub use container::RandomAccess;
+pub use erased::{DynKey, KeyExtractorDyn};
+pub use estimate::{estimate_memory, estimate_memory_with_config, MemoryEstimate};
+pub use external::{
+    merge_files, merge_files_by_key, sort_file, sort_file_with_progress, ExternalSortConfig,
+    MergeConfig, RecordFormat, DEFAULT_BATCH_SIZE, DEFAULT_MERGE_BUFFER, DEFAULT_RUN_SIZE,
+};
+#[cfg(feature = "compression")]
+pub use external::{RunCodec, RunCompression, RunEncoder};
+pub use integer::IntegerKey;
+pub use iter::TilesortIterExt;
+pub use k_smallest::{k_smallest, KSmallest};
+pub use key_extractor::{
+    ByTileKey, IdentityKey, KeyExtractor, Map, Reversed, Then, TileKey, TryKeyExtractor,
+};
+pub use kmerge::{kmerge, kmerge_owned, KMerge, KMergeOwned};
+pub use nulls::{sort_to_indices, sort_to_indices_with_config, NullOrder};
+pub use observer::Observer;
+#[cfg(feature = "opcount")]
+pub use opcount::SortStats;
+#[cfg(feature = "rayon")]
+pub use parallel::ParallelTileSortExt;
+pub use pattern::InputPattern;
+pub use plan::{
+    find, find_by_key, partition_point, scan, scan_with_config, InvalidRuns, PlanStats, SortPlan,
+    StalePlan,
+};
+pub use progress::{Eta, EtaEstimator, Progress, DEFAULT_ETA_WINDOW};
+#[cfg(feature = "simd")]
+pub use simd::SimdKey;
+pub use spec::{SortSpec, SortSpecError};
+pub use stream::StreamSorter;
+pub use tile_index::{Tile, TileIndex, MAX_INLINE_TILES};
+pub use view::SortedView;
+
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::ops::Range;
+
+use sorter::Hooks;
+
+/// Derive [`TileKey`] from fields annotated with `#[tile_key(order = N, desc)]`.
+#[cfg(feature = "derive")]
+pub use tilesort_derive::TileKey;
 
 // Rust sorting implementation (always available)
 
-/// Sort a slice using the tilesort algorithm.
+/// Sort a slice using the tilesort algorithm.
+///
+/// This function sorts elements in ascending order, optimized for data that consists
+/// of pre-sorted contiguous blocks (tiles).
+///
+/// # Examples
+///
+/// ```
+/// let mut data = vec![3, 4, 5, 1, 2, 6, 7, 8];
+/// tilesort::tilesort(&mut data);
+/// assert_eq!(data, vec![1, 2, 3, 4, 5, 6, 7, 8]);
+/// ```
+pub fn tilesort<T: Ord + Clone>(data: &mut [T]) {
+    tilesort_with_config(data, &SortConfig::new());
+}
+
+/// Sort a slice in descending order using the tilesort algorithm.
+///
+/// # Examples
+///
+/// ```
+/// let mut data = vec![3, 4, 5, 1, 2, 6, 7, 8];
+/// tilesort::tilesort_reverse(&mut data);
+/// assert_eq!(data, vec![8, 7, 6, 5, 4, 3, 2, 1]);
+/// ```
+pub fn tilesort_reverse<T: Ord + Clone>(data: &mut [T]) {
+    tilesort_with_config(data, &SortConfig::new().descending());
+}
+
+/// Sort a slice using the tilesort algorithm with explicit options.
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::{Order, SortConfig};
+///
+/// let mut data = vec![3, 4, 5, 1, 2, 6, 7, 8];
+/// tilesort::tilesort_with_config(&mut data, &SortConfig::new().order(Order::Descending));
+/// assert_eq!(data, vec![8, 7, 6, 5, 4, 3, 2, 1]);
+/// ```
+pub fn tilesort_with_config<T: Ord + Clone>(data: &mut [T], config: &SortConfig) {
+    sorter::tilesort_impl(data, config);
+}
+
+/// Return a sorted copy of a slice using the tilesort algorithm.
+///
+/// This function does not modify the original slice and returns a new sorted vector.
+/// The tiles are found in the original and each element is cloned once, straight
+/// into its sorted position, so there is no need to clone the slice first.
+///
+/// # Examples
+///
+/// ```
+/// let data = vec![3, 4, 5, 1, 2, 6, 7, 8];
+/// let sorted = tilesort::tilesorted(&data);
+/// assert_eq!(sorted, vec![1, 2, 3, 4, 5, 6, 7, 8]);
+/// assert_eq!(data, vec![3, 4, 5, 1, 2, 6, 7, 8]); // Original unchanged
+/// ```
+pub fn tilesorted<T: Ord + Clone>(data: &[T]) -> Vec<T> {
+    tilesorted_with_config(data, &SortConfig::new())
+}
+
+/// Return a sorted copy of a slice in descending order using the tilesort algorithm.
+///
+/// This function does not modify the original slice and returns a new sorted vector.
+///
+/// # Examples
+///
+/// ```
+/// let data = vec![3, 4, 5, 1, 2, 6, 7, 8];
+/// let sorted = tilesort::tilesorted_reverse(&data);
+/// assert_eq!(sorted, vec![8, 7, 6, 5, 4, 3, 2, 1]);
+/// assert_eq!(data, vec![3, 4, 5, 1, 2, 6, 7, 8]); // Original unchanged
+/// ```
+pub fn tilesorted_reverse<T: Ord + Clone>(data: &[T]) -> Vec<T> {
+    tilesorted_with_config(data, &SortConfig::new().descending())
+}
+
+/// Return a sorted copy of a slice using the tilesort algorithm with explicit options.
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::SortConfig;
+///
+/// let data = vec![3, 4, 5, 1, 2];
+/// let sorted = tilesort::tilesorted_with_config(&data, &SortConfig::new().descending());
+/// assert_eq!(sorted, vec![5, 4, 3, 2, 1]);
+/// assert_eq!(data, vec![3, 4, 5, 1, 2]); // Original unchanged
+/// ```
+pub fn tilesorted_with_config<T: Ord + Clone>(data: &[T], config: &SortConfig) -> Vec<T> {
+    sorter::tilesorted_impl_with_key(data, &IdentityKey, config)
+}
+
+/// Sort a slice using a custom key extraction function.
+///
+/// # Examples
+///
+/// ```
+/// let mut data = vec![-5i32, -3, -1, 2, 4];
+/// tilesort::tilesort_by_key(&mut data, |&x| x.abs());
+/// assert_eq!(data, vec![-1, 2, -3, 4, -5]);
+/// ```
+pub fn tilesort_by_key<T, K, F>(data: &mut [T], key_fn: F)
+where
+    T: Clone,
+    K: Ord,
+    F: Fn(&T) -> K,
+{
+    tilesort_by_key_with_config(data, key_fn, &SortConfig::new());
+}
+
+/// Sort a slice in descending order using a custom key extraction function.
+///
+/// # Examples
+///
+/// ```
+/// let mut data = vec![-5i32, -3, -1, 2, 4];
+/// tilesort::tilesort_by_key_reverse(&mut data, |&x| x.abs());
+/// assert_eq!(data, vec![-5, 4, -3, 2, -1]);
+/// ```
+pub fn tilesort_by_key_reverse<T, K, F>(data: &mut [T], key_fn: F)
+where
+    T: Clone,
+    K: Ord,
+    F: Fn(&T) -> K,
+{
+    tilesort_by_key_with_config(data, key_fn, &SortConfig::new().descending());
+}
+
+/// Sort a slice using a custom key extraction function with explicit options.
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::SortConfig;
+///
+/// let mut data = vec![-5i32, -3, -1, 2, 4];
+/// tilesort::tilesort_by_key_with_config(&mut data, |&x| x.abs(), &SortConfig::new().descending());
+/// assert_eq!(data, vec![-5, 4, -3, 2, -1]);
+/// ```
+pub fn tilesort_by_key_with_config<T, K, F>(data: &mut [T], key_fn: F, config: &SortConfig)
+where
+    T: Clone,
+    K: Ord,
+    F: Fn(&T) -> K,
+{
+    sorter::tilesort_impl_with_key(data, &key_fn, config);
+}
+
+/// Return a sorted copy using a custom key extraction function.
+///
+/// # Examples
+///
+/// ```
+/// let data = vec![-5i32, -3, -1, 2, 4];
+/// let sorted = tilesort::tilesorted_by_key(&data, |&x| x.abs());
+/// assert_eq!(sorted, vec![-1, 2, -3, 4, -5]);
+/// assert_eq!(data, vec![-5, -3, -1, 2, 4]); // Original unchanged
+/// ```
+pub fn tilesorted_by_key<T, K, F>(data: &[T], key_fn: F) -> Vec<T>
+where
+    T: Clone,
+    K: Ord,
+    F: Fn(&T) -> K,
+{
+    tilesorted_by_key_with_config(data, key_fn, &SortConfig::new())
+}
+
+/// Return a sorted copy in descending order using a custom key extraction function.
+///
+/// # Examples
+///
+/// ```
+/// let data = vec![-5i32, -3, -1, 2, 4];
+/// let sorted = tilesort::tilesorted_by_key_reverse(&data, |&x| x.abs());
+/// assert_eq!(sorted, vec![-5, 4, -3, 2, -1]);
+/// assert_eq!(data, vec![-5, -3, -1, 2, 4]); // Original unchanged
+/// ```
+pub fn tilesorted_by_key_reverse<T, K, F>(data: &[T], key_fn: F) -> Vec<T>
+where
+    T: Clone,
+    K: Ord,
+    F: Fn(&T) -> K,
+{
+    tilesorted_by_key_with_config(data, key_fn, &SortConfig::new().descending())
+}
+
+/// Return a sorted copy using a custom key extraction function with explicit options.
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::SortConfig;
+///
+/// let data = vec![-5i32, -3, -1, 2, 4];
+/// let sorted = tilesort::tilesorted_by_key_with_config(&data, |&x| x.abs(), &SortConfig::new());
+/// assert_eq!(sorted, vec![-1, 2, -3, 4, -5]);
+/// assert_eq!(data, vec![-5, -3, -1, 2, 4]); // Original unchanged
+/// ```
+pub fn tilesorted_by_key_with_config<T, K, F>(data: &[T], key_fn: F, config: &SortConfig) -> Vec<T>
+where
+    T: Clone,
+    K: Ord,
+    F: Fn(&T) -> K,
+{
+    sorter::tilesorted_impl_with_key(data, &key_fn, config)
+}
+
+/// Sort a slice using any [`KeyExtractor`], such as one built from combinators.
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::KeyExtractor;
+///
+/// let mut data = vec![(1, 'b'), (2, 'a'), (1, 'a'), (3, 'z')];
+/// let extractor = (|p: &(i32, char)| p.0).reversed().then(|p: &(i32, char)| p.1);
+/// tilesort::tilesort_by_extractor(&mut data, extractor);
+/// assert_eq!(data, vec![(3, 'z'), (2, 'a'), (1, 'a'), (1, 'b')]);
+/// ```
+pub fn tilesort_by_extractor<T, K, E>(data: &mut [T], extractor: E)
+where
+    T: Clone,
+    K: Ord,
+    E: KeyExtractor<T, K>,
+{
+    sorter::tilesort_impl_with_key(data, &extractor, &SortConfig::new());
+}
+
+/// Return a sorted copy using any [`KeyExtractor`].
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::KeyExtractor;
+///
+/// let data = vec![-5i32, -3, -1, 2, 4];
+/// let sorted = tilesort::tilesorted_by_extractor(&data, (|x: &i32| x.abs()).reversed());
+/// assert_eq!(sorted, vec![-5, 4, -3, 2, -1]);
+/// assert_eq!(data, vec![-5, -3, -1, 2, 4]); // Original unchanged
+/// ```
+pub fn tilesorted_by_extractor<T, K, E>(data: &[T], extractor: E) -> Vec<T>
+where
+    T: Clone,
+    K: Ord,
+    E: KeyExtractor<T, K>,
+{
+    sorter::tilesorted_impl_with_key(data, &extractor, &SortConfig::new())
+}
+
+/// Return a slice in sorted order, borrowing it if it is already sorted.
+///
+/// If the scan finds a single tile the input is returned as
+/// `Cow::Borrowed`, allocating and cloning nothing; otherwise a sorted copy
+/// is returned as `Cow::Owned`, as from [`tilesorted`].
+///
+/// # Examples
+///
+/// ```
+/// use std::borrow::Cow;
+///
+/// let sorted = vec![1, 2, 3];
+/// assert!(matches!(tilesort::tilesorted_cow(&sorted), Cow::Borrowed(_)));
+///
+/// let unsorted = vec![3, 1, 2];
+/// let result = tilesort::tilesorted_cow(&unsorted);
+/// assert!(matches!(result, Cow::Owned(_)));
+/// assert_eq!(*result, [1, 2, 3]);
+/// ```
+pub fn tilesorted_cow<T: Ord + Clone>(data: &[T]) -> Cow<'_, [T]> {
+    tilesorted_cow_with_config(data, &SortConfig::new())
+}
+
+/// Return a slice in sorted order with custom options, borrowing it if it
+/// is already sorted.
+pub fn tilesorted_cow_with_config<'a, T: Ord + Clone>(
+    data: &'a [T],
+    config: &SortConfig,
+) -> Cow<'a, [T]> {
+    sorter::tilesorted_cow_impl(data, &IdentityKey, config)
+}
+
+/// Return a slice sorted by key, borrowing it if it is already sorted.
+///
+/// # Examples
+///
+/// ```
+/// let words = ["fig", "pear", "banana"];
+/// let by_len = tilesort::tilesorted_cow_by_key(&words, |w| w.len());
+/// assert!(matches!(by_len, std::borrow::Cow::Borrowed(_)));
+/// ```
+pub fn tilesorted_cow_by_key<T, K, F>(data: &[T], key_fn: F) -> Cow<'_, [T]>
+where
+    T: Clone,
+    K: Ord,
+    F: Fn(&T) -> K,
+{
+    tilesorted_cow_by_key_with_config(data, key_fn, &SortConfig::new())
+}
+
+/// Return a slice sorted by key with custom options, borrowing it if it is
+/// already sorted.
+pub fn tilesorted_cow_by_key_with_config<'a, T, K, F>(
+    data: &'a [T],
+    key_fn: F,
+    config: &SortConfig,
+) -> Cow<'a, [T]>
+where
+    T: Clone,
+    K: Ord,
+    F: Fn(&T) -> K,
+{
+    sorter::tilesorted_cow_impl(data, &key_fn, config)
+}
+
+/// Return references to the elements of a slice in sorted order.
+///
+/// The tiles are found as for [`tilesorted`], but no element is cloned or
+/// moved, which suits large immutable datasets and element types that are
+/// not `Clone`. For input positions rather than references, see
+/// [`SortPlan::sorted_indices`].
+///
+/// # Examples
+///
+/// ```
+/// let data = vec![String::from("pear"), String::from("fig"), String::from("kiwi")];
+/// let sorted: Vec<&String> = tilesort::tilesort_refs(&data);
+/// assert_eq!(sorted, ["fig", "kiwi", "pear"]);
+/// ```
+pub fn tilesort_refs<T: Ord>(data: &[T]) -> Vec<&T> {
+    tilesort_refs_with_config(data, &SortConfig::new())
+}
+
+/// Return references to the elements of a slice in sorted order, with
+/// custom options.
+pub fn tilesort_refs_with_config<'a, T: Ord>(data: &'a [T], config: &SortConfig) -> Vec<&'a T> {
+    sorter::sorted_refs_impl(data, &sorter::InPlaceKey, config)
+}
+
+/// Return references to the elements of a slice, sorted by key.
+///
+/// # Examples
+///
+/// ```
+/// let data = vec![(3, "c"), (1, "a"), (2, "b")];
+/// let sorted = tilesort::tilesort_refs_by_key(&data, |&(n, _)| n);
+/// assert_eq!(sorted, vec![&(1, "a"), &(2, "b"), &(3, "c")]);
+/// ```
+pub fn tilesort_refs_by_key<T, K, F>(data: &[T], key_fn: F) -> Vec<&T>
+where
+    K: Ord,
+    F: Fn(&T) -> K,
+{
+    tilesort_refs_by_key_with_config(data, key_fn, &SortConfig::new())
+}
+
+/// Return references to the elements of a slice, sorted by key with custom
+/// options.
+pub fn tilesort_refs_by_key_with_config<'a, T, K, F>(
+    data: &'a [T],
+    key_fn: F,
+    config: &SortConfig,
+) -> Vec<&'a T>
+where
+    K: Ord,
+    F: Fn(&T) -> K,
+{
+    sorter::sorted_refs_impl(data, &key_fn, config)
+}
+
+/// Sort a slice in place and return where each element went.
+///
+/// Entry `i` of the returned vector is the sorted position of the element
+/// that was at position `i`, so IDs, row handles or selections that pointed
+/// into the original order can be updated with `permutation[old]`. The sort
+/// is stable and moves elements only by swapping, so `T` need not be `Clone`.
+///
+/// # Examples
+///
+/// ```
+/// let mut data = vec!['c', 'a', 'b'];
+/// let permutation = tilesort::tilesort_with_permutation(&mut data);
+/// assert_eq!(data, vec!['a', 'b', 'c']);
+/// assert_eq!(permutation, vec![2, 0, 1]);
+///
+/// // A selection of 'c' at its old position follows it to its new one
+/// let selected = 0;
+/// assert_eq!(data[permutation[selected]], 'c');
+/// ```
+pub fn tilesort_with_permutation<T: Ord>(data: &mut [T]) -> Vec<usize> {
+    sorter::sort_with_permutation_impl(data, &sorter::InPlaceKey, &SortConfig::new())
+}
+
+/// Sort a slice in place by key and return where each element went, as
+/// [`tilesort_with_permutation`] does.
+///
+/// # Examples
+///
+/// ```
+/// let mut rows = vec![(3, "c"), (1, "a"), (3, "b"), (2, "d")];
+/// let permutation = tilesort::tilesort_by_key_with_permutation(&mut rows, |&(n, _)| n);
+/// assert_eq!(rows, vec![(1, "a"), (2, "d"), (3, "c"), (3, "b")]);
+/// assert_eq!(permutation, vec![2, 0, 3, 1]);
+/// ```
+pub fn tilesort_by_key_with_permutation<T, K, F>(data: &mut [T], key_fn: F) -> Vec<usize>
+where
+    K: Ord,
+    F: Fn(&T) -> K,
+{
+    sorter::sort_with_permutation_impl(data, &key_fn, &SortConfig::new())
+}
+
+/// Sort a slice in ascending order without preserving the order of equal elements.
+///
+/// This may split fewer tiles than [`tilesort`] on data with many duplicate
+/// keys. Equivalent to [`tilesort_with_config`] with [`SortConfig::unstable`].
+///
+/// # Examples
+///
+/// ```
+/// let mut data = vec![2, 2, 3, 1, 2, 3];
+/// tilesort::tilesort_unstable(&mut data);
+/// assert_eq!(data, vec![1, 2, 2, 2, 3, 3]);
+/// ```
+pub fn tilesort_unstable<T: Ord + Clone>(data: &mut [T]) {
+    tilesort_with_config(data, &SortConfig::new().unstable());
+}
+
+/// Sort a slice by key without preserving the order of elements with equal keys.
+///
+/// # Examples
+///
+/// ```
+/// let mut data = vec![(1, 'a'), (3, 'b'), (2, 'c'), (3, 'd')];
+/// tilesort::tilesort_unstable_by_key(&mut data, |&(k, _)| k);
+/// let keys: Vec<i32> = data.iter().map(|&(k, _)| k).collect();
+/// assert_eq!(keys, vec![1, 2, 3, 3]);
+/// ```
+pub fn tilesort_unstable_by_key<T, K, F>(data: &mut [T], key_fn: F)
+where
+    T: Clone,
+    K: Ord,
+    F: Fn(&T) -> K,
+{
+    tilesort_by_key_with_config(data, key_fn, &SortConfig::new().unstable());
+}
+
+/// Sort a slice and remove duplicates in the same pass.
+///
+/// Returns the number of unique elements, which are moved, sorted, to the
+/// front of `data`; the elements after them are left in an unspecified order.
+/// Of several equal elements the first one in the input is kept. Unlike
+/// sorting followed by `Vec::dedup`, duplicates are never cloned, and the
+/// scan that finds the tiles also finds the duplicates within each, so
+/// elements are compared again only where tiles meet.
+///
+/// # Examples
+///
+/// ```
+/// let mut data = vec![2, 3, 3, 1, 2, 2, 4];
+/// let len = tilesort::tilesort_dedup(&mut data);
+/// data.truncate(len);
+/// assert_eq!(data, vec![1, 2, 3, 4]);
+/// ```
+pub fn tilesort_dedup<T: Ord + Clone>(data: &mut [T]) -> usize {
+    sorter::tilesort_dedup_impl(data, &SortConfig::new())
+}
+
+/// Sort a slice by key and keep only the first element for each distinct key.
+///
+/// Returns the number of retained elements; see [`tilesort_dedup`] for the
+/// layout of `data` afterwards.
+///
+/// # Examples
+///
+/// ```
+/// let mut data = vec![("b", 1), ("c", 2), ("a", 3), ("b", 4), ("c", 5)];
+/// let len = tilesort::tilesort_dedup_by_key(&mut data, |&(name, _)| name);
+/// assert_eq!(&data[..len], &[("a", 3), ("b", 1), ("c", 2)]);
+/// ```
+pub fn tilesort_dedup_by_key<T, K, F>(data: &mut [T], key_fn: F) -> usize
+where
+    T: Clone,
+    K: Ord,
+    F: Fn(&T) -> K,
+{
+    sorter::tilesort_dedup_impl_with_key(data, &key_fn, &SortConfig::new())
+}
+
+/// Sort a slice and return the index ranges of its groups of equal elements.
+///
+/// The ranges are in order, non-empty and together cover the whole slice.
+///
+/// # Examples
+///
+/// ```
+/// let mut data = vec![2, 3, 1, 2, 3, 3];
+/// let groups = tilesort::tilesort_grouped(&mut data);
+/// assert_eq!(data, vec![1, 2, 2, 3, 3, 3]);
+/// assert_eq!(groups, vec![0..1, 1..3, 3..6]);
+/// ```
+pub fn tilesort_grouped<T: Ord + Clone>(data: &mut [T]) -> Vec<Range<usize>> {
+    sorter::tilesort_grouped_impl(data, &SortConfig::new())
+}
+
+/// Sort a slice by key and return the index ranges of its equal-key groups.
+///
+/// # Examples
+///
+/// ```
+/// let mut words = vec!["bb", "a", "cc", "ddd", "e"];
+/// let groups = tilesort::tilesort_grouped_by_key(&mut words, |w| w.len());
+/// assert_eq!(words, vec!["a", "e", "bb", "cc", "ddd"]);
+///
+/// let by_len: Vec<&[&str]> = groups.into_iter().map(|g| &words[g]).collect();
+/// assert_eq!(by_len, vec![&["a", "e"][..], &["bb", "cc"], &["ddd"]]);
+/// ```
+pub fn tilesort_grouped_by_key<T, K, F>(data: &mut [T], key_fn: F) -> Vec<Range<usize>>
+where
+    T: Clone,
+    K: Ord,
+    F: Fn(&T) -> K,
+{
+    sorter::tilesort_grouped_impl_with_key(data, &key_fn, &SortConfig::new())
+}
+
+/// Sort a slice using any [`KeyExtractor`] and return the index ranges of
+/// its equal-key groups.
+///
+/// The length of each range counts the elements sharing its key, so this
+/// sorts and counts distinct lines in one pass, as `sort | uniq -c` does.
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::keys::FieldKey;
+///
+/// // Count requests per client address, the first field of each line
+/// let mut log = vec!["10.0.0.2 GET /", "10.0.0.1 GET /a", "10.0.0.2 GET /b"];
+/// let groups = tilesort::tilesort_grouped_by_extractor(&mut log, FieldKey::parse("1,1")?);
+/// let counts: Vec<(usize, &str)> = groups.into_iter().map(|g| (g.len(), log[g.start])).collect();
+/// assert_eq!(counts, vec![(1, "10.0.0.1 GET /a"), (2, "10.0.0.2 GET /")]);
+/// # Ok::<(), tilesort::keys::FieldKeyError>(())
+/// ```
+pub fn tilesort_grouped_by_extractor<T, K, E>(data: &mut [T], extractor: E) -> Vec<Range<usize>>
+where
+    T: Clone,
+    K: Ord,
+    E: KeyExtractor<T, K>,
+{
+    sorter::tilesort_grouped_impl_with_key(data, &extractor, &SortConfig::new())
+}
+
+/// Move the elements of a slice satisfying `pred` before those that do not,
+/// keeping the relative order within each side, and return how many satisfy
+/// it.
+///
+/// `pred` is called once per element, in order. Each run of elements on the
+/// same side becomes a tile, so input that is already nearly partitioned is
+/// rearranged with little work. Elements are moved only by swapping.
+///
+/// # Examples
+///
+/// ```
+/// let mut data = vec![1, 2, 3, 4, 5, 6, 7];
+/// let evens = tilesort::stable_partition(&mut data, |&x| x % 2 == 0);
+/// assert_eq!(evens, 3);
+/// assert_eq!(data, vec![2, 4, 6, 1, 3, 5, 7]);
+/// ```
+pub fn stable_partition<T, F>(data: &mut [T], pred: F) -> usize
+where
+    F: FnMut(&T) -> bool,
+{
+    sorter::stable_partition_impl(data, pred, &SortConfig::new())
+}
+
+/// Move the `k` smallest elements, in sorted order, to the front of a slice.
+///
+/// Only the tiles contributing to the first `k` positions are placed, by
+/// swapping elements, so this is much cheaper than a full sort when `k` is
+/// small. The elements after position `k` are left in an unspecified order.
+/// If `k >= data.len()` the whole slice is sorted.
+///
+/// # Examples
+///
+/// ```
+/// let mut data = vec![50, 51, 52, 1, 2, 3, 20, 21];
+/// tilesort::tilesort_partial(&mut data, 3);
+/// assert_eq!(&data[..3], &[1, 2, 3]);
+/// ```
+pub fn tilesort_partial<T: Ord>(data: &mut [T], k: usize) {
+    sorter::partial_sort_impl(data, k, &SortConfig::new());
+}
+
+/// Move the `k` elements with the smallest keys, in sorted order, to the front of a slice.
+///
+/// See [`tilesort_partial`] for details.
+///
+/// # Examples
+///
+/// ```
+/// let mut records = vec![("z", 26), ("y", 25), ("a", 1), ("b", 2)];
+/// tilesort::tilesort_partial_by_key(&mut records, 2, |&(_, rank)| rank);
+/// assert_eq!(&records[..2], &[("a", 1), ("b", 2)]);
+/// ```
+pub fn tilesort_partial_by_key<T, K, F>(data: &mut [T], k: usize, key_fn: F)
+where
+    K: Ord,
+    F: Fn(&T) -> K,
+{
+    sorter::partial_sort_impl_with_key(data, k, &key_fn, &SortConfig::new());
+}
+
+/// Return the element that would be at index `n` if the slice were sorted.
+///
+/// Only the tile scan runs; the slice is not modified. Returns `None` if
+/// `n >= data.len()`.
+///
+/// # Examples
+///
+/// ```
+/// let data = vec![4, 5, 6, 1, 2, 3];
+/// assert_eq!(tilesort::tilesort_select_nth(&data, 0), Some(&1));
+/// assert_eq!(tilesort::tilesort_select_nth(&data, 4), Some(&5));
+/// assert_eq!(tilesort::tilesort_select_nth(&data, 6), None);
+/// ```
+pub fn tilesort_select_nth<T: Ord>(data: &[T], n: usize) -> Option<&T> {
+    sorter::select_nth_impl(data, n, &SortConfig::new()).map(|idx| &data[idx])
+}
+
+/// Return the element that would be at index `n` if the slice were sorted by key.
+///
+/// # Examples
+///
+/// ```
+/// let words = vec!["ccc", "dddd", "a", "bb"];
+/// assert_eq!(tilesort::tilesort_select_nth_by_key(&words, 1, |w| w.len()), Some(&"bb"));
+/// ```
+pub fn tilesort_select_nth_by_key<T, K, F>(data: &[T], n: usize, key_fn: F) -> Option<&T>
+where
+    K: Ord,
+    F: Fn(&T) -> K,
+{
+    sorter::select_nth_impl_with_key(data, n, &key_fn, &SortConfig::new()).map(|idx| &data[idx])
+}
+
+/// Returns `true` if `data` is sorted in ascending order.
+///
+/// Stops at the first element out of order, so callers can skip a sort on
+/// input that is usually already sorted.
+///
+/// # Examples
+///
+/// ```
+/// assert!(tilesort::is_sorted(&[1, 2, 2, 3]));
+/// assert!(!tilesort::is_sorted(&[1, 3, 2]));
+/// ```
+pub fn is_sorted<T: Ord>(data: &[T]) -> bool {
+    is_sorted_with_config(data, &SortConfig::new())
+}
+
+/// Returns `true` if `data` is sorted in the direction given by `config`.
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::SortConfig;
+///
+/// assert!(tilesort::is_sorted_with_config(&[3, 2, 2, 1], &SortConfig::new().descending()));
+/// ```
+pub fn is_sorted_with_config<T: Ord>(data: &[T], config: &SortConfig) -> bool {
+    sorter::is_sorted_impl(data, config)
+}
+
+/// Returns `true` if `data` is sorted by `key_fn` in ascending order.
+///
+/// Each key is extracted at most once.
+///
+/// # Examples
+///
+/// ```
+/// let words = ["fig", "pear", "kiwi", "banana"];
+/// assert!(tilesort::is_sorted_by_key(&words, |w| w.len()));
+/// ```
+pub fn is_sorted_by_key<T, K, F>(data: &[T], key_fn: F) -> bool
+where
+    K: Ord,
+    F: Fn(&T) -> K,
+{
+    is_sorted_by_key_with_config(data, key_fn, &SortConfig::new())
+}
+
+/// Returns `true` if `data` is sorted by `key_fn` in the direction given by
+/// `config`.
+pub fn is_sorted_by_key_with_config<T, K, F>(data: &[T], key_fn: F, config: &SortConfig) -> bool
+where
+    K: Ord,
+    F: Fn(&T) -> K,
+{
+    sorter::is_sorted_impl_with_key(data, &key_fn, config)
+}
+
+/// Returns `true` if `data` is sorted by the keys of `extractor`, in the
+/// direction given by `config`.
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::{KeyExtractor, SortConfig};
+///
+/// let pairs = [(1, 'c'), (1, 'b'), (2, 'a')];
+/// let by_number_then_letter = (|p: &(i32, char)| p.0).then(|p: &(i32, char)| p.1);
+/// assert!(!tilesort::is_sorted_by_extractor(&pairs, by_number_then_letter, &SortConfig::new()));
+/// ```
+pub fn is_sorted_by_extractor<T, K, E>(data: &[T], extractor: E, config: &SortConfig) -> bool
+where
+    K: Ord,
+    E: KeyExtractor<T, K>,
+{
+    sorter::is_sorted_impl_with_key(data, &extractor, config)
+}
+
+/// Returns the length of the longest sorted prefix of `data`, so that
+/// `data[n]`, if `n < data.len()`, is the first element out of order.
+///
+/// This is the check of `sort -c`, which reports the first line out of
+/// order; like [`is_sorted`], it stops there.
+///
+/// # Examples
+///
+/// ```
+/// let data = [1, 2, 2, 5, 3, 4];
+/// assert_eq!(tilesort::sorted_until(&data), 4);
+/// assert_eq!(tilesort::sorted_until(&[1, 2, 3]), 3);
+/// ```
+pub fn sorted_until<T: Ord>(data: &[T]) -> usize {
+    sorter::sorted_until_impl(data, &SortConfig::new())
+}
+
+/// Returns the length of the longest prefix of `data` sorted by the keys of
+/// `extractor`, in the direction given by `config`.
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::keys::FieldKey;
+/// use tilesort::SortConfig;
+///
+/// let lines = ["a 1", "b 3", "c 20", "d 10"];
+/// let by_number = FieldKey::parse("2,2n")?;
+/// let n = tilesort::sorted_until_by_extractor(&lines, by_number, &SortConfig::new());
+/// assert_eq!(lines.get(n), Some(&"d 10"));
+/// # Ok::<(), tilesort::keys::FieldKeyError>(())
+/// ```
+pub fn sorted_until_by_extractor<T, K, E>(data: &[T], extractor: E, config: &SortConfig) -> usize
+where
+    K: Ord,
+    E: KeyExtractor<T, K>,
+{
+    sorter::sorted_until_impl_with_key(data, &extractor, config)
+}
+
+/// Sort a fixed-size array by value, returning it in ascending order.
+///
+/// Equal elements keep their order. Arrays of up to 64 elements are sorted
+/// with scratch space on the stack, so no heap allocation takes place; longer
+/// arrays fall back to the regular tile index. Elements are moved with swaps
+/// and need not be `Clone`.
+///
+/// # Examples
+///
+/// ```
+/// let sorted = tilesort::tilesort_array([4, 5, 6, 1, 2, 3]);
+/// assert_eq!(sorted, [1, 2, 3, 4, 5, 6]);
+/// ```
+pub fn tilesort_array<T: Ord, const N: usize>(mut arr: [T; N]) -> [T; N] {
+    array::sort_array(&mut arr);
+    arr
+}
+
+/// Sort a fixed-size array in place, in ascending order.
+///
+/// Like [`tilesort_array`], this allocates nothing for arrays of up to 64
+/// elements.
+///
+/// # Examples
+///
+/// ```
+/// let mut arr = [30, 31, 10, 11, 20, 21];
+/// tilesort::tilesort_array_mut(&mut arr);
+/// assert_eq!(arr, [10, 11, 20, 21, 30, 31]);
+/// ```
+pub fn tilesort_array_mut<T: Ord, const N: usize>(arr: &mut [T; N]) {
+    array::sort_array(arr);
+}
+
+/// Sort a `VecDeque` in ascending order without making it contiguous.
+///
+/// Calling [`tilesort`] on `deque.make_contiguous()` first rotates the ring
+/// buffer, moving every element. This instead scans the deque's two slices as
+/// one sequence and writes the result straight back.
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::VecDeque;
+///
+/// let mut deque: VecDeque<i32> = VecDeque::with_capacity(6);
+/// deque.extend([4, 5, 6]);
+/// deque.push_front(3);
+/// deque.push_front(2);
+/// deque.push_front(1);
+/// deque.rotate_left(2);
+///
+/// tilesort::tilesort_deque(&mut deque);
+/// assert_eq!(deque, [1, 2, 3, 4, 5, 6]);
+/// ```
+pub fn tilesort_deque<T: Ord + Clone>(deque: &mut VecDeque<T>) {
+    sorter::tilesort_deque_impl(deque, &SortConfig::new());
+}
+
+/// Sort a `VecDeque` by key without making it contiguous.
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::VecDeque;
+///
+/// let mut deque: VecDeque<i32> = VecDeque::from(vec![-1, 2, -3]);
+/// deque.push_front(-4);
+/// tilesort::tilesort_deque_by_key(&mut deque, |x| x.abs());
+/// assert_eq!(deque, [-1, 2, -3, -4]);
+/// ```
+pub fn tilesort_deque_by_key<T, K, F>(deque: &mut VecDeque<T>, key_fn: F)
+where
+    T: Clone,
+    K: Ord,
+    F: Fn(&T) -> K,
+{
+    sorter::tilesort_deque_impl_with_key(deque, &key_fn, &SortConfig::new());
+}
+
+/// Sort any [`RandomAccess`] container in ascending order, in place.
+///
+/// Elements are read through [`RandomAccess::get`] and moved only with
+/// [`RandomAccess::swap`], so custom containers can be sorted without copying
+/// into a slice and elements need not be `Clone`.
+///
+/// # Examples
+///
+/// ```
+/// let mut data = vec![String::from("b"), String::from("c"), String::from("a")];
+/// tilesort::tilesort_container(data.as_mut_slice());
+/// assert_eq!(data, vec!["a", "b", "c"]);
+/// ```
+pub fn tilesort_container<C>(container: &mut C)
+where
+    C: RandomAccess + ?Sized,
+    C::Item: Ord,
+{
+    sorter::tilesort_container_impl(container, &SortConfig::new());
+}
+
+/// Sort any [`RandomAccess`] container by key, in place.
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::VecDeque;
+///
+/// let mut deque = VecDeque::from(vec![-3i32, 1, -2]);
+/// tilesort::tilesort_container_by_key(&mut deque, |x: &i32| x.abs());
+/// assert_eq!(deque, [1, -2, -3]);
+/// ```
+pub fn tilesort_container_by_key<C, K, F>(container: &mut C, key_fn: F)
+where
+    C: RandomAccess + ?Sized,
+    K: Ord,
+    F: Fn(&C::Item) -> K,
+{
+    sorter::tilesort_container_impl_with_key(container, &key_fn, &SortConfig::new());
+}
+
+/// Sort `keys` and apply the same rearrangement to `values`.
+///
+/// This avoids zipping the two into a `Vec<(K, V)>` first. Elements are moved
+/// with swaps, so neither type needs to be `Clone`. The sort is stable.
+///
+/// # Panics
+///
+/// Panics if the two slices have different lengths.
+///
+/// # Examples
+///
+/// ```
+/// let mut keys = vec![3, 4, 1, 2];
+/// let mut values = vec!["c", "d", "a", "b"];
+/// tilesort::tilesort_pairs(&mut keys, &mut values);
+/// assert_eq!(keys, vec![1, 2, 3, 4]);
+/// assert_eq!(values, vec!["a", "b", "c", "d"]);
+/// ```
+pub fn tilesort_pairs<K: Ord, V>(keys: &mut [K], values: &mut [V]) {
+    assert_eq!(
+        keys.len(),
+        values.len(),
+        "keys and values must have the same length"
+    );
+    sorter::tilesort_container_impl(&mut container::Pairs { keys, values }, &SortConfig::new());
+}
+
+/// Sort the rows of a struct-of-arrays table by one or more key columns,
+/// applying the same rearrangement to every column.
+///
+/// Rows are compared by the first key column, with ties broken by the next.
+/// The sorted order is found once and then applied to each key and value
+/// column with one pass of swaps, so columns of any element type can be
+/// sorted together without being `Clone` or zipped into rows. The sort is
+/// stable.
+///
+/// # Panics
+///
+/// Panics if the columns have different lengths.
+///
+/// # Examples
+///
+/// ```
+/// let mut city = vec!["Oslo", "Lima", "Oslo", "Lima"];
+/// let mut year = vec![2021, 2020, 2019, 2022];
+/// let mut temperature = vec![5.1, 19.2, 4.8, 18.9];
+///
+/// // Vectors are passed as mutable references to their slices
+/// tilesort::sort_columns(
+///     &mut [&mut city.as_mut_slice(), &mut year.as_mut_slice()],
+///     &mut [&mut temperature.as_mut_slice()],
+/// );
+/// assert_eq!(city, vec!["Lima", "Lima", "Oslo", "Oslo"]);
+/// assert_eq!(year, vec![2020, 2022, 2019, 2021]);
+/// assert_eq!(temperature, vec![19.2, 18.9, 4.8, 5.1]);
+/// ```
+pub fn sort_columns(keys: &mut [&mut dyn KeyColumn], values: &mut [&mut dyn Column]) {
+    sort_columns_with_config(keys, values, &SortConfig::new());
+}
+
+/// Sort the rows of a struct-of-arrays table with custom options.
 ///
-/// This function sorts elements in ascending order, optimized for data that consists
-/// of pre-sorted contiguous blocks (tiles).
+/// See [`sort_columns`]; a descending order reverses the combined order of
+/// the key columns.
 ///
 /// # Examples
 ///
 /// ```
-/// let mut data = vec![3, 4, 5, 1, 2, 6, 7, 8];
-/// tilesort::tilesort(&mut data);
-/// assert_eq!(data, vec![1, 2, 3, 4, 5, 6, 7, 8]);
+/// use tilesort::SortConfig;
+///
+/// let mut id = vec![2, 3, 1];
+/// let mut name = vec![String::from("b"), String::from("c"), String::from("a")];
+/// let config = SortConfig::new().descending();
+/// tilesort::sort_columns_with_config(
+///     &mut [&mut id.as_mut_slice()],
+///     &mut [&mut name.as_mut_slice()],
+///     &config,
+/// );
+/// assert_eq!(id, vec![3, 2, 1]);
+/// assert_eq!(name, vec!["c", "b", "a"]);
 /// ```
-pub fn tilesort<T: Ord + Clone>(data: &mut [T]) {
-    sorter::tilesort_impl(data, false);
+pub fn sort_columns_with_config(
+    keys: &mut [&mut dyn KeyColumn],
+    values: &mut [&mut dyn Column],
+    config: &SortConfig,
+) {
+    sorter::sort_columns_impl(keys, values, config);
 }
 
-/// Sort a slice in descending order using the tilesort algorithm.
+/// Sort a slice by keys that have already been computed.
+///
+/// `keys[i]` is the key of `data[i]`. No key extraction takes place and no key
+/// buffer is allocated; `keys` itself is left untouched. Use
+/// [`tilesort_pairs`] instead to reorder the keys along with the data.
+///
+/// # Panics
+///
+/// Panics if `keys` and `data` have different lengths.
 ///
 /// # Examples
 ///
 /// ```
-/// let mut data = vec![3, 4, 5, 1, 2, 6, 7, 8];
-/// tilesort::tilesort_reverse(&mut data);
-/// assert_eq!(data, vec![8, 7, 6, 5, 4, 3, 2, 1]);
+/// let mut data = vec!["banana", "fig", "apple"];
+/// let keys = vec![6, 3, 5];
+/// tilesort::tilesort_by_keys(&mut data, &keys);
+/// assert_eq!(data, vec!["fig", "apple", "banana"]);
 /// ```
-pub fn tilesort_reverse<T: Ord + Clone>(data: &mut [T]) {
-    sorter::tilesort_impl(data, true);
+pub fn tilesort_by_keys<T: Clone, K: Ord>(data: &mut [T], keys: &[K]) {
+    sorter::tilesort_impl_with_keys(data, keys, &SortConfig::new());
 }
 
-/// Return a sorted copy of a slice using the tilesort algorithm.
+/// Sort a slice by precomputed keys with the given options.
 ///
-/// This function does not modify the original slice and returns a new sorted vector.
+/// See [`tilesort_by_keys`].
 ///
 /// # Examples
 ///
 /// ```
-/// let data = vec![3, 4, 5, 1, 2, 6, 7, 8];
-/// let sorted = tilesort::tilesorted(&data);
-/// assert_eq!(sorted, vec![1, 2, 3, 4, 5, 6, 7, 8]);
-/// assert_eq!(data, vec![3, 4, 5, 1, 2, 6, 7, 8]); // Original unchanged
+/// use tilesort::SortConfig;
+///
+/// let mut data = vec!['a', 'b', 'c'];
+/// tilesort::tilesort_by_keys_with_config(&mut data, &[1, 3, 2], &SortConfig::new().descending());
+/// assert_eq!(data, vec!['b', 'c', 'a']);
 /// ```
-pub fn tilesorted<T: Ord + Clone>(data: &[T]) -> Vec<T> {
-    let mut result = data.to_vec();
-    sorter::tilesort_impl(&mut result, false);
-    result
+pub fn tilesort_by_keys_with_config<T: Clone, K: Ord>(
+    data: &mut [T],
+    keys: &[K],
+    config: &SortConfig,
+) {
+    sorter::tilesort_impl_with_keys(data, keys, config);
 }
 
-/// Return a sorted copy of a slice in descending order using the tilesort algorithm.
+/// Sort the fixed-width records packed in a byte buffer by a key within each
+/// record.
 ///
-/// This function does not modify the original slice and returns a new sorted vector.
+/// `buf` holds consecutive records of `record_len` bytes each, and the key of
+/// a record is its bytes at `key_range`, compared lexicographically as
+/// `memcmp` does. This suits big-endian integers and other byte-comparable
+/// encodings in file formats and storage pages. Whole records are moved, one
+/// copy per tile, through a buffer as large as `buf`. The sort is stable.
+///
+/// # Panics
+///
+/// Panics if `record_len` is zero, if `buf.len()` is not a multiple of it, or
+/// if `key_range` does not lie within a record.
 ///
 /// # Examples
 ///
 /// ```
-/// let data = vec![3, 4, 5, 1, 2, 6, 7, 8];
-/// let sorted = tilesort::tilesorted_reverse(&data);
-/// assert_eq!(sorted, vec![8, 7, 6, 5, 4, 3, 2, 1]);
-/// assert_eq!(data, vec![3, 4, 5, 1, 2, 6, 7, 8]); // Original unchanged
+/// use tilesort::Order;
+///
+/// // Records of a big-endian u16 id followed by a one-byte payload
+/// let mut buf = vec![0, 3, b'c', 0, 1, b'a', 1, 0, b'z', 0, 2, b'b'];
+/// tilesort::sort_records(&mut buf, 3, 0..2, Order::Ascending);
+/// assert_eq!(buf, vec![0, 1, b'a', 0, 2, b'b', 0, 3, b'c', 1, 0, b'z']);
 /// ```
-pub fn tilesorted_reverse<T: Ord + Clone>(data: &[T]) -> Vec<T> {
-    let mut result = data.to_vec();
-    sorter::tilesort_impl(&mut result, true);
-    result
+pub fn sort_records(buf: &mut [u8], record_len: usize, key_range: Range<usize>, order: Order) {
+    sorter::sort_records_impl(buf, record_len, key_range, &SortConfig::new().order(order));
 }
 
-/// Sort a slice using a custom key extraction function.
+/// Sort a slice by a string borrowed from each element, comparing an 8-byte
+/// prefix first and the full string only on ties.
+///
+/// The keys are [`keys::PrefixKey`]s that borrow from `data`, so no string is
+/// copied; the result is the same as sorting by the `&str` itself. This pays
+/// off for long strings that mostly differ within their first few bytes.
 ///
 /// # Examples
 ///
 /// ```
-/// let mut data = vec![-5i32, -3, -1, 2, 4];
-/// tilesort::tilesort_by_key(&mut data, |&x| x.abs());
-/// assert_eq!(data, vec![-1, 2, -3, 4, -5]);
+/// let mut paths = vec![
+///     String::from("/var/log/syslog"),
+///     String::from("/etc/hosts"),
+///     String::from("/usr/share/dict/words"),
+/// ];
+/// tilesort::tilesort_by_str_prefix(&mut paths, |path| path.as_str());
+/// assert_eq!(paths, ["/etc/hosts", "/usr/share/dict/words", "/var/log/syslog"]);
 /// ```
-pub fn tilesort_by_key<T, K, F>(data: &mut [T], key_fn: F)
+pub fn tilesort_by_str_prefix<T, F>(data: &mut [T], key_fn: F)
+where
+    T: Clone,
+    F: for<'a> Fn(&'a T) -> &'a str,
+{
+    sorter::tilesort_str_prefix_impl(data, &key_fn, &SortConfig::new());
+}
+
+/// Sort a slice by borrowed string prefix keys with the given options.
+///
+/// See [`tilesort_by_str_prefix`].
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::SortConfig;
+///
+/// let mut names = vec![("carol", 3), ("alice", 1), ("bob", 2)];
+/// let config = SortConfig::new().descending();
+/// tilesort::tilesort_by_str_prefix_with_config(&mut names, |entry| entry.0, &config);
+/// assert_eq!(names, vec![("carol", 3), ("bob", 2), ("alice", 1)]);
+/// ```
+pub fn tilesort_by_str_prefix_with_config<T, F>(data: &mut [T], key_fn: F, config: &SortConfig)
+where
+    T: Clone,
+    F: for<'a> Fn(&'a T) -> &'a str,
+{
+    sorter::tilesort_str_prefix_impl(data, &key_fn, config);
+}
+
+/// Sort a slice by bytes borrowed from each element, comparing them as
+/// `memcmp` does.
+///
+/// The key is any [`keys::MemcmpOrd`] type, such as a string, byte string or
+/// big-endian encoded integer, whose order is the order of its bytes. Keys
+/// are borrowed from `data` as byte slices and compared directly, so no key
+/// is copied and the type's own `Ord` is never called.
+///
+/// # Examples
+///
+/// ```
+/// let mut rows = vec![(b"bob".to_vec(), 2), (b"alice".to_vec(), 1), (b"carol".to_vec(), 3)];
+/// tilesort::tilesort_by_memcmp_key(&mut rows, |row| &row.0);
+/// assert_eq!(rows.iter().map(|row| row.1).collect::<Vec<_>>(), vec![1, 2, 3]);
+/// ```
+pub fn tilesort_by_memcmp_key<T, K, F>(data: &mut [T], key_fn: F)
+where
+    T: Clone,
+    K: keys::MemcmpOrd + ?Sized,
+    F: for<'a> Fn(&'a T) -> &'a K,
+{
+    sorter::tilesort_memcmp_impl(data, &key_fn, &SortConfig::new());
+}
+
+/// Sort a slice by borrowed byte keys with the given options.
+///
+/// See [`tilesort_by_memcmp_key`].
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::SortConfig;
+///
+/// let mut words = vec!["fig", "pear", "apple"];
+/// let config = SortConfig::new().descending();
+/// tilesort::tilesort_by_memcmp_key_with_config(&mut words, |word| *word, &config);
+/// assert_eq!(words, vec!["pear", "fig", "apple"]);
+/// ```
+pub fn tilesort_by_memcmp_key_with_config<T, K, F>(data: &mut [T], key_fn: F, config: &SortConfig)
+where
+    T: Clone,
+    K: keys::MemcmpOrd + ?Sized,
+    F: for<'a> Fn(&'a T) -> &'a K,
+{
+    sorter::tilesort_memcmp_impl(data, &key_fn, config);
+}
+
+/// Sort a slice of integers, switching to a counting sort when the values
+/// span a small range.
+///
+/// See [`SortConfig::counting_sort_threshold`]; otherwise this behaves like
+/// [`tilesort`].
+///
+/// # Examples
+///
+/// ```
+/// let mut status_codes: Vec<u16> = vec![200, 404, 200, 500, 301, 200, 404];
+/// tilesort::tilesort_integers(&mut status_codes);
+/// assert_eq!(status_codes, vec![200, 200, 200, 301, 404, 404, 500]);
+/// ```
+pub fn tilesort_integers<T: IntegerKey>(data: &mut [T]) {
+    sorter::tilesort_integer_impl_with_key(data, &IdentityKey, &SortConfig::new());
+}
+
+/// Sort a slice of integers with the given options.
+///
+/// See [`tilesort_integers`].
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::SortConfig;
+///
+/// let mut data = vec![-1i8, 3, -1, 2];
+/// tilesort::tilesort_integers_with_config(&mut data, &SortConfig::new().descending());
+/// assert_eq!(data, vec![3, 2, -1, -1]);
+/// ```
+pub fn tilesort_integers_with_config<T: IntegerKey>(data: &mut [T], config: &SortConfig) {
+    sorter::tilesort_integer_impl_with_key(data, &IdentityKey, config);
+}
+
+/// Sort a slice by an integer key, switching to a counting sort when the keys
+/// span a small range.
+///
+/// The counting sort is stable and moves elements with swaps.
+///
+/// # Examples
+///
+/// ```
+/// let mut requests = vec![("/a", 404u16), ("/b", 200), ("/c", 404), ("/d", 200)];
+/// tilesort::tilesort_by_integer_key(&mut requests, |&(_, status)| status);
+/// assert_eq!(requests, vec![("/b", 200), ("/d", 200), ("/a", 404), ("/c", 404)]);
+/// ```
+pub fn tilesort_by_integer_key<T, K, F>(data: &mut [T], key_fn: F)
 where
     T: Clone,
-    K: Ord + Clone,
+    K: IntegerKey,
     F: Fn(&T) -> K,
 {
-    sorter::tilesort_impl_with_key(data, key_fn, false);
+    sorter::tilesort_integer_impl_with_key(data, &key_fn, &SortConfig::new());
 }
 
-/// Sort a slice in descending order using a custom key extraction function.
+/// Sort a slice by an integer key with the given options.
+///
+/// See [`tilesort_by_integer_key`].
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::SortConfig;
+///
+/// let mut data = vec!["bb", "a", "ccc", "dd"];
+/// let config = SortConfig::new().descending();
+/// tilesort::tilesort_by_integer_key_with_config(&mut data, |s| s.len() as u8, &config);
+/// assert_eq!(data, vec!["ccc", "bb", "dd", "a"]);
+/// ```
+pub fn tilesort_by_integer_key_with_config<T, K, F>(data: &mut [T], key_fn: F, config: &SortConfig)
+where
+    T: Clone,
+    K: IntegerKey,
+    F: Fn(&T) -> K,
+{
+    sorter::tilesort_integer_impl_with_key(data, &key_fn, config);
+}
+
+/// Sort a slice of primitive integers, finding the tiles with vectorized
+/// comparisons.
+///
+/// Produces the same result as [`tilesort`]; only the scan differs, comparing
+/// blocks of adjacent elements at once. This is the dominant cost on nearly
+/// sorted numeric data, where tiles are long. Requires the `simd` feature.
 ///
 /// # Examples
 ///
 /// ```
+/// let mut data: Vec<u32> = (500..1000).chain(0..500).collect();
+/// tilesort::tilesort_simd(&mut data);
+/// assert_eq!(data, (0..1000).collect::<Vec<u32>>());
+/// ```
+#[cfg(feature = "simd")]
+pub fn tilesort_simd<T: SimdKey>(data: &mut [T]) {
+    sorter::tilesort_simd_impl(data, &SortConfig::new());
+}
+
+/// Sort a slice of primitive integers with vectorized tile detection and the
+/// given options.
+///
+/// See [`tilesort_simd`]. Requires the `simd` feature.
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::SortConfig;
+///
+/// let mut data = vec![1i64, 2, 3, -5, -4];
+/// tilesort::tilesort_simd_with_config(&mut data, &SortConfig::new().descending());
+/// assert_eq!(data, vec![3, 2, 1, -4, -5]);
+/// ```
+#[cfg(feature = "simd")]
+pub fn tilesort_simd_with_config<T: SimdKey>(data: &mut [T], config: &SortConfig) {
+    sorter::tilesort_simd_impl(data, config);
+}
+
+/// Sort a slice using a fallible key extraction function.
+///
+/// Keys are extracted for every element before anything is moved. If any
+/// extraction fails, the first error is returned and `data` is left in its
+/// original order.
+///
+/// # Examples
+///
+/// ```
+/// let mut data = vec!["10", "20", "3", "4"];
+/// tilesort::try_tilesort_by_key(&mut data, |s| s.parse::<u32>()).unwrap();
+/// assert_eq!(data, vec!["3", "4", "10", "20"]);
+///
+/// let mut bad = vec!["10", "x", "3"];
+/// assert!(tilesort::try_tilesort_by_key(&mut bad, |s| s.parse::<u32>()).is_err());
+/// assert_eq!(bad, vec!["10", "x", "3"]); // Original order kept
+/// ```
+pub fn try_tilesort_by_key<T, K, Error, F>(data: &mut [T], key_fn: F) -> Result<(), Error>
+where
+    T: Clone,
+    K: Ord,
+    F: Fn(&T) -> Result<K, Error>,
+{
+    try_tilesort_by_key_with_config(data, key_fn, &SortConfig::new())
+}
+
+/// Sort a slice using a fallible key extraction function with explicit options.
+///
+/// See [`try_tilesort_by_key`] for the error behavior.
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::SortConfig;
+///
+/// let mut data = vec!["10", "20", "3", "4"];
+/// tilesort::try_tilesort_by_key_with_config(&mut data, |s| s.parse::<u32>(), &SortConfig::new().descending())
+///     .unwrap();
+/// assert_eq!(data, vec!["20", "10", "4", "3"]);
+/// ```
+pub fn try_tilesort_by_key_with_config<T, K, Error, F>(
+    data: &mut [T],
+    key_fn: F,
+    config: &SortConfig,
+) -> Result<(), Error>
+where
+    T: Clone,
+    K: Ord,
+    F: Fn(&T) -> Result<K, Error>,
+{
+    sorter::try_tilesort_impl_with_key(data, &key_fn, config)
+}
+
+/// Return a sorted copy using a fallible key extraction function.
+///
+/// # Examples
+///
+/// ```
+/// let data = vec!["10", "20", "3", "4"];
+/// let sorted = tilesort::try_tilesorted_by_key(&data, |s| s.parse::<u32>()).unwrap();
+/// assert_eq!(sorted, vec!["3", "4", "10", "20"]);
+/// ```
+pub fn try_tilesorted_by_key<T, K, Error, F>(data: &[T], key_fn: F) -> Result<Vec<T>, Error>
+where
+    T: Clone,
+    K: Ord,
+    F: Fn(&T) -> Result<K, Error>,
+{
+    let mut result = data.to_vec();
+    sorter::try_tilesort_impl_with_key(&mut result, &key_fn, &SortConfig::new())?;
+    Ok(result)
+}
+
+/// Sort a slice with explicit options, stopping early if `token` is cancelled.
+///
+/// The token is checked periodically during both phases. On cancellation,
+/// [`Cancelled`] is returned and `data` is left in its original order.
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::{CancellationToken, Cancelled, SortConfig};
+///
+/// let token = CancellationToken::new();
+/// let mut data = vec![3, 4, 5, 1, 2];
+/// assert_eq!(tilesort::tilesort_cancellable(&mut data, &SortConfig::new(), &token), Ok(()));
+/// assert_eq!(data, vec![1, 2, 3, 4, 5]);
+///
+/// token.cancel();
+/// let mut data = vec![3, 4, 5, 1, 2];
+/// assert_eq!(tilesort::tilesort_cancellable(&mut data, &SortConfig::new(), &token), Err(Cancelled));
+/// assert_eq!(data, vec![3, 4, 5, 1, 2]); // Original order kept
+/// ```
+pub fn tilesort_cancellable<T: Ord + Clone>(
+    data: &mut [T],
+    config: &SortConfig,
+    token: &CancellationToken,
+) -> Result<(), Cancelled> {
+    let mut hooks = Hooks {
+        cancel: Some(token),
+        ..Hooks::default()
+    };
+    sorter::tilesort_impl_hooks(data, config, &mut hooks)
+}
+
+/// Sort a slice by key with explicit options, stopping early if `token` is cancelled.
+///
+/// See [`tilesort_cancellable`] for the cancellation behavior.
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::{CancellationToken, SortConfig};
+///
+/// let token = CancellationToken::new();
 /// let mut data = vec![-5i32, -3, -1, 2, 4];
-/// tilesort::tilesort_by_key_reverse(&mut data, |&x| x.abs());
-/// assert_eq!(data, vec![-5, 4, -3, 2, -1]);
+/// tilesort::tilesort_by_key_cancellable(&mut data, |&x| x.abs(), &SortConfig::new(), &token).unwrap();
+/// assert_eq!(data, vec![-1, 2, -3, 4, -5]);
 /// ```
-pub fn tilesort_by_key_reverse<T, K, F>(data: &mut [T], key_fn: F)
+pub fn tilesort_by_key_cancellable<T, K, F>(
+    data: &mut [T],
+    key_fn: F,
+    config: &SortConfig,
+    token: &CancellationToken,
+) -> Result<(), Cancelled>
 where
     T: Clone,
-    K: Ord + Clone,
+    K: Ord,
     F: Fn(&T) -> K,
 {
-    sorter::tilesort_impl_with_key(data, key_fn, true);
+    let mut hooks = Hooks {
+        cancel: Some(token),
+        ..Hooks::default()
+    };
+    sorter::tilesort_impl_with_key_hooks(data, &key_fn, config, &mut hooks)
 }
 
-/// Return a sorted copy using a custom key extraction function.
+/// Sort a slice with explicit options, reporting progress to `on_progress`.
+///
+/// The callback receives a [`Progress`] update every few thousand elements, so
+/// it can drive a progress bar for very large sorts.
 ///
 /// # Examples
 ///
 /// ```
-/// let data = vec![-5i32, -3, -1, 2, 4];
-/// let sorted = tilesort::tilesorted_by_key(&data, |&x| x.abs());
-/// assert_eq!(sorted, vec![-1, 2, -3, 4, -5]);
-/// assert_eq!(data, vec![-5, -3, -1, 2, 4]); // Original unchanged
+/// use tilesort::{Progress, SortConfig};
+///
+/// let mut data: Vec<u32> = (5_000..10_000).chain(0..5_000).collect();
+/// let mut last = None;
+/// tilesort::tilesort_with_progress(&mut data, &SortConfig::new(), |p| last = Some(p));
+///
+/// assert!(data.windows(2).all(|w| w[0] <= w[1]));
+/// assert_eq!(last.map(|p| p.percent()), Some(100.0));
+/// assert!(matches!(last, Some(Progress::Restructuring { .. })));
 /// ```
-pub fn tilesorted_by_key<T, K, F>(data: &[T], key_fn: F) -> Vec<T>
+pub fn tilesort_with_progress<T, P>(data: &mut [T], config: &SortConfig, mut on_progress: P)
 where
+    T: Ord + Clone,
+    P: FnMut(Progress),
+{
+    let mut hooks = Hooks {
+        progress: Some(&mut on_progress),
+        ..Hooks::default()
+    };
+    sorter::never_cancelled(sorter::tilesort_impl_hooks(data, config, &mut hooks));
+}
+
+/// Sort a slice by key with explicit options, reporting progress to `on_progress`.
+///
+/// See [`tilesort_with_progress`] for when updates are delivered.
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::{Progress, SortConfig};
+///
+/// let mut words = vec!["ccc", "dddd", "a", "bb"];
+/// let mut updates = Vec::new();
+/// tilesort::tilesort_by_key_with_progress(&mut words, |w| w.len(), &SortConfig::new(), |p| {
+///     updates.push(p)
+/// });
+///
+/// assert_eq!(words, vec!["a", "bb", "ccc", "dddd"]);
+/// assert_eq!(updates.first(), Some(&Progress::Scanning { processed: 0, total: 4 }));
+/// ```
+pub fn tilesort_by_key_with_progress<T, K, F, P>(
+    data: &mut [T],
+    key_fn: F,
+    config: &SortConfig,
+    mut on_progress: P,
+) where
     T: Clone,
-    K: Ord + Clone,
+    K: Ord,
     F: Fn(&T) -> K,
+    P: FnMut(Progress),
 {
-    let mut result = data.to_vec();
-    sorter::tilesort_impl_with_key(&mut result, key_fn, false);
-    result
+    let mut hooks = Hooks {
+        progress: Some(&mut on_progress),
+        ..Hooks::default()
+    };
+    sorter::never_cancelled(sorter::tilesort_impl_with_key_hooks(
+        data, &key_fn, config, &mut hooks,
+    ));
 }
 
-/// Return a sorted copy in descending order using a custom key extraction function.
+/// Sort a slice with explicit options, telling `observer` of each phase,
+/// tile and split.
+///
+/// See [`Observer`] for the events and their order.
 ///
 /// # Examples
 ///
 /// ```
-/// let data = vec![-5i32, -3, -1, 2, 4];
-/// let sorted = tilesort::tilesorted_by_key_reverse(&data, |&x| x.abs());
-/// assert_eq!(sorted, vec![-5, 4, -3, 2, -1]);
-/// assert_eq!(data, vec![-5, -3, -1, 2, 4]); // Original unchanged
+/// use tilesort::{Observer, SortConfig};
+///
+/// struct Phases(Vec<&'static str>);
+///
+/// impl Observer for Phases {
+///     fn on_scan_start(&mut self, _elements: usize) {
+///         self.0.push("scan");
+///     }
+///
+///     fn on_restructure_start(&mut self) {
+///         self.0.push("restructure");
+///     }
+/// }
+///
+/// let mut data: Vec<u32> = (5_000..10_000).chain(0..5_000).collect();
+/// let mut phases = Phases(Vec::new());
+/// tilesort::tilesort_with_observer(&mut data, &SortConfig::new(), &mut phases);
+/// assert!(data.windows(2).all(|w| w[0] <= w[1]));
+/// assert_eq!(phases.0, vec!["scan", "restructure"]);
 /// ```
-pub fn tilesorted_by_key_reverse<T, K, F>(data: &[T], key_fn: F) -> Vec<T>
+pub fn tilesort_with_observer<T, O>(data: &mut [T], config: &SortConfig, observer: &mut O)
 where
+    T: Ord + Clone,
+    O: Observer,
+{
+    let mut hooks = Hooks {
+        observer: Some(observer),
+        ..Hooks::default()
+    };
+    sorter::never_cancelled(sorter::tilesort_impl_hooks(data, config, &mut hooks));
+}
+
+/// Sort a slice by key with explicit options, telling `observer` of each
+/// phase, tile and split.
+///
+/// See [`Observer`] for the events and their order.
+///
+/// # Examples
+///
+/// ```
+/// use std::ops::Range;
+///
+/// use tilesort::{Observer, SortConfig};
+///
+/// struct Tiles(Vec<Range<usize>>);
+///
+/// impl Observer for Tiles {
+///     fn on_tile_detected(&mut self, tile: Range<usize>) {
+///         self.0.push(tile);
+///     }
+/// }
+///
+/// let mut words: Vec<String> = ["ccc", "dddd", "a", "bb"].iter().map(|w| w.to_string()).collect();
+/// let mut tiles = Tiles(Vec::new());
+/// let config = SortConfig::new().insertion_sort_threshold(0);
+/// tilesort::tilesort_by_key_with_observer(&mut words, |w| w.len(), &config, &mut tiles);
+/// assert_eq!(words, vec!["a", "bb", "ccc", "dddd"]);
+/// assert_eq!(tiles.0, vec![0..2, 2..4]);
+/// ```
+pub fn tilesort_by_key_with_observer<T, K, F, O>(
+    data: &mut [T],
+    key_fn: F,
+    config: &SortConfig,
+    observer: &mut O,
+) where
     T: Clone,
-    K: Ord + Clone,
+    K: Ord,
     F: Fn(&T) -> K,
+    O: Observer,
 {
-    let mut result = data.to_vec();
-    sorter::tilesort_impl_with_key(&mut result, key_fn, true);
-    result
+    let mut hooks = Hooks {
+        observer: Some(observer),
+        ..Hooks::default()
+    };
+    sorter::never_cancelled(sorter::tilesort_impl_with_key_hooks(
+        data, &key_fn, config, &mut hooks,
+    ));
 }
 
 // Python bindings (only when 'python' feature is enabled)
 #[cfg(feature = "python")]
 mod python_bindings {
+    use pyo3::buffer::{Element, PyBuffer};
+    use pyo3::exceptions::{PyTypeError, PyValueError};
     use pyo3::prelude::*;
     use pyo3::types::{PyAny, PyList};
 
-    use crate::key_extractor::KeyExtractor;
-    use crate::sorter::{tilesort_impl, tilesort_impl_with_key};
+    use crate::config::{Order, SortConfig};
+    use crate::float::{f32_total_order_key, f64_total_order_key};
+    use crate::key_extractor::TryKeyExtractor;
+    use crate::sorter::{tilesort_impl, tilesort_impl_with_key, try_tilesort_impl_with_key};
     use std::cmp::Ordering;
 
     /// Wrapper around PyObject that implements Ord using Python's comparison protocol
@@ -229,39 +1735,119 @@ mod python_bindings {
         key_fn: Py<PyAny>,
     }
 
-    impl KeyExtractor<PyOrd, PyOrd> for PyKeyExtractor {
-        fn extract_key(&self, item: &PyOrd) -> PyOrd {
+    impl TryKeyExtractor<PyOrd, PyOrd, PyErr> for PyKeyExtractor {
+        fn try_extract_key(&self, item: &PyOrd) -> PyResult<PyOrd> {
             Python::with_gil(|py| {
-                // Call the Python key function with the item
-                let result = self
-                    .key_fn
-                    .call1(py, (&item.obj,))
-                    .expect("Key function call failed");
-                PyOrd::new(result)
+                // Call the Python key function with the item; an exception
+                // aborts the sort and is re-raised to the caller
+                let result = self.key_fn.call1(py, (&item.obj,))?;
+                Ok(PyOrd::new(result))
             })
         }
     }
 
-    /// Sort a Python list in place (like list.sort())
+    /// Translate Python's `reverse` flag into a sort configuration
+    fn sort_config(reverse: bool) -> SortConfig {
+        let order = if reverse {
+            Order::Descending
+        } else {
+            Order::Ascending
+        };
+        SortConfig::new().order(order)
+    }
+
+    /// Borrow the memory behind a writable, C-contiguous, one-dimensional
+    /// buffer as a mutable slice, without copying
+    fn buffer_slice<'a, T: Element>(
+        py: Python<'a>,
+        buffer: &'a PyBuffer<T>,
+    ) -> PyResult<&'a mut [T]> {
+        if buffer.dimensions() > 1 {
+            return Err(PyValueError::new_err(
+                "only one-dimensional arrays can be sorted",
+            ));
+        }
+        let cells = buffer
+            .as_mut_slice(py)
+            .ok_or_else(|| PyTypeError::new_err("array must be writable and C-contiguous"))?;
+        // SAFETY: `Cell<T>` has the same layout as `T`. The buffer stays
+        // exported for `'a`, and no Python code runs while the slice is in
+        // use, since sorting primitives never calls back into the
+        // interpreter.
+        Ok(unsafe { std::slice::from_raw_parts_mut(cells.as_ptr() as *mut T, cells.len()) })
+    }
+
+    /// Sort an object exposing the buffer protocol (a NumPy array,
+    /// `array.array`, ...) in place. Returns `Ok(false)` if the object is not
+    /// a buffer of a supported primitive type.
+    fn sort_buffer(py: Python<'_>, obj: &Bound<'_, PyAny>, config: &SortConfig) -> PyResult<bool> {
+        macro_rules! sort_as {
+            ($($ty:ty),*) => {
+                $(
+                    if let Ok(buffer) = PyBuffer::<$ty>::get(obj) {
+                        tilesort_impl(buffer_slice(py, &buffer)?, config);
+                        return Ok(true);
+                    }
+                )*
+            };
+        }
+        sort_as!(i64, i32, i16, i8, u64, u32, u16, u8);
+
+        if let Ok(buffer) = PyBuffer::<f64>::get(obj) {
+            let extractor = |&x: &f64| f64_total_order_key(x);
+            tilesort_impl_with_key(buffer_slice(py, &buffer)?, &extractor, config);
+            return Ok(true);
+        }
+        if let Ok(buffer) = PyBuffer::<f32>::get(obj) {
+            let extractor = |&x: &f32| f32_total_order_key(x);
+            tilesort_impl_with_key(buffer_slice(py, &buffer)?, &extractor, config);
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    /// Sort a Python list or a one-dimensional NumPy array in place (like
+    /// list.sort())
+    ///
+    /// Writable, C-contiguous arrays of integers or floats are sorted
+    /// directly in their own memory. Floats follow IEEE 754 total order.
     ///
     /// # Arguments
-    /// * `list` - The Python list to sort in place
-    /// * `key` - Optional Python callable for key extraction
+    /// * `list` - The Python list or array to sort in place
+    /// * `key` - Optional Python callable for key extraction (lists only)
     /// * `reverse` - If true, sort in descending order
     #[pyfunction]
     #[pyo3(signature = (list, key=None, reverse=false))]
-    fn sort(list: &Bound<'_, PyList>, key: Option<Py<PyAny>>, reverse: bool) -> PyResult<()> {
+    fn sort(list: &Bound<'_, PyAny>, key: Option<Py<PyAny>>, reverse: bool) -> PyResult<()> {
         Python::with_gil(|py| {
+            let list = match list.downcast::<PyList>() {
+                Ok(list) => list,
+                Err(_) => {
+                    if key.is_some() {
+                        return Err(PyTypeError::new_err(
+                            "key functions are only supported when sorting a list",
+                        ));
+                    }
+                    if sort_buffer(py, list, &sort_config(reverse))? {
+                        return Ok(());
+                    }
+                    return Err(PyTypeError::new_err(
+                        "expected a list or an array of integers or floats",
+                    ));
+                }
+            };
+
             // Extract Python objects from the list and wrap in PyOrd
             let mut items: Vec<PyOrd> = list.iter().map(|item| PyOrd::new(item.into())).collect();
+            let config = sort_config(reverse);
 
             // Sort based on whether we have a key function
             if let Some(key_fn) = key {
                 let extractor = PyKeyExtractor { key_fn };
-                tilesort_impl_with_key(&mut items, extractor, reverse);
+                try_tilesort_impl_with_key(&mut items, &extractor, &config)?;
             } else {
                 // Use Python's natural ordering via __lt__
-                tilesort_impl(&mut items, reverse);
+                tilesort_impl(&mut items, &config);
             }
 
             // Clear the original list and repopulate it
@@ -291,14 +1877,15 @@ mod python_bindings {
         Python::with_gil(|py| {
             // Extract Python objects from the list and wrap in PyOrd
             let mut items: Vec<PyOrd> = list.iter().map(|item| PyOrd::new(item.into())).collect();
+            let config = sort_config(reverse);
 
             // Sort based on whether we have a key function
             if let Some(key_fn) = key {
                 let extractor = PyKeyExtractor { key_fn };
-                tilesort_impl_with_key(&mut items, extractor, reverse);
+                try_tilesort_impl_with_key(&mut items, &extractor, &config)?;
             } else {
                 // Use Python's natural ordering via __lt__
-                tilesort_impl(&mut items, reverse);
+                tilesort_impl(&mut items, &config);
             }
 
             // Create a new Python list with sorted items
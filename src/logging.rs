@@ -0,0 +1,35 @@
+//! Internal logging macros.
+//!
+//! With the `log` feature these forward to the `log` crate. Without it they
+//! still type-check their arguments but expand to dead code, so the calls in
+//! the scan and insertion loops cost nothing.
+
+#[cfg(feature = "log")]
+macro_rules! debug {
+    ($($arg:tt)*) => { ::log::debug!($($arg)*) };
+}
+
+#[cfg(not(feature = "log"))]
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        if false {
+            let _ = format_args!($($arg)*);
+        }
+    };
+}
+
+#[cfg(feature = "log")]
+macro_rules! info {
+    ($($arg:tt)*) => { ::log::info!($($arg)*) };
+}
+
+#[cfg(not(feature = "log"))]
+macro_rules! info {
+    ($($arg:tt)*) => {
+        if false {
+            let _ = format_args!($($arg)*);
+        }
+    };
+}
+
+pub(crate) use {debug, info};
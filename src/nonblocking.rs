@@ -0,0 +1,127 @@
+//! Async entry points to the external sort, for services whose tasks must
+//! not block on disk.
+//!
+//! No async runtime is required: the blocking [`sort_file`](crate::sort_file)
+//! pipeline runs on a thread of its own, and the returned future is woken
+//! when it finishes, so it can be awaited from tokio, async-std or a plain
+//! executor alike.
+
+use std::future::Future;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+
+use crate::external::ExternalSortConfig;
+
+/// Sort `input` into `output` as [`sort_file`](crate::sort_file) does,
+/// without blocking the task awaiting it.
+///
+/// The sort starts when the future is first polled, on a new thread that
+/// runs the reading, sorting, spilling and merging stages as the blocking
+/// function does. Dropping the future does not stop that thread: the sort
+/// runs to completion, and its temporary files are removed as usual.
+///
+/// # Errors
+///
+/// As for [`sort_file`](crate::sort_file); a failure to start the thread is
+/// returned as is, and a panic in the sort fails with
+/// [`io::ErrorKind::Other`].
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::ExternalSortConfig;
+///
+/// # fn block_on<F: std::future::Future>(future: F) -> F::Output {
+/// #     struct Unpark(std::thread::Thread);
+/// #     impl std::task::Wake for Unpark {
+/// #         fn wake(self: std::sync::Arc<Self>) { self.0.unpark() }
+/// #     }
+/// #     let waker = std::sync::Arc::new(Unpark(std::thread::current())).into();
+/// #     let mut cx = std::task::Context::from_waker(&waker);
+/// #     let mut future = std::pin::pin!(future);
+/// #     loop {
+/// #         match future.as_mut().poll(&mut cx) {
+/// #             std::task::Poll::Ready(output) => return output,
+/// #             std::task::Poll::Pending => std::thread::park(),
+/// #         }
+/// #     }
+/// # }
+/// let dir = std::env::temp_dir().join(format!("tilesort-doc-async-{}", std::process::id()));
+/// std::fs::create_dir_all(&dir)?;
+/// std::fs::write(dir.join("in.txt"), "pear\napple\nfig\n")?;
+///
+/// let config = ExternalSortConfig::new().temp_dir(&dir);
+/// // e.g. inside a tokio task
+/// let future = tilesort::nonblocking::sort_file(dir.join("in.txt"), dir.join("out.txt"), &config);
+/// let written = block_on(future)?;
+/// assert_eq!(written, 3);
+/// assert_eq!(std::fs::read_to_string(dir.join("out.txt"))?, "apple\nfig\npear\n");
+/// # std::fs::remove_dir_all(&dir)?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub async fn sort_file<P, Q>(input: P, output: Q, config: &ExternalSortConfig) -> io::Result<u64>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    let input = input.as_ref().to_path_buf();
+    let output = output.as_ref().to_path_buf();
+    let config = config.clone();
+    spawn_blocking(move || crate::sort_file(&input, &output, &config))?.await
+}
+
+/// What the sort thread hands back to the future.
+struct Shared<R> {
+    result: Option<io::Result<R>>,
+    waker: Option<Waker>,
+}
+
+/// Future resolved with the result of `work` once its thread finishes.
+struct Blocking<R> {
+    shared: Arc<Mutex<Shared<R>>>,
+}
+
+fn spawn_blocking<R, F>(work: F) -> io::Result<Blocking<R>>
+where
+    R: Send + 'static,
+    F: FnOnce() -> io::Result<R> + Send + 'static,
+{
+    let shared = Arc::new(Mutex::new(Shared {
+        result: None,
+        waker: None,
+    }));
+    let done = Arc::clone(&shared);
+    thread::Builder::new()
+        .name("tilesort-sort-file".into())
+        .spawn(move || {
+            let result = panic::catch_unwind(AssertUnwindSafe(work)).unwrap_or_else(|_| {
+                Err(io::Error::new(io::ErrorKind::Other, "sort thread panicked"))
+            });
+            let mut done = done.lock().unwrap_or_else(|e| e.into_inner());
+            done.result = Some(result);
+            if let Some(waker) = done.waker.take() {
+                waker.wake();
+            }
+        })?;
+    Ok(Blocking { shared })
+}
+
+impl<R> Future for Blocking<R> {
+    type Output = io::Result<R>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut shared = self.shared.lock().unwrap_or_else(|e| e.into_inner());
+        match shared.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                shared.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
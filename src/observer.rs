@@ -0,0 +1,101 @@
+//! Callbacks for the events of a sort, for custom metrics and UI.
+
+use std::ops::Range;
+
+/// Receiver of the events of a sort run by
+/// [`tilesort_with_observer`](crate::tilesort_with_observer) or
+/// [`tilesort_by_key_with_observer`](crate::tilesort_by_key_with_observer).
+///
+/// Every method does nothing by default, so an observer implements only the
+/// events it cares about, and can feed them to whatever metrics backend or
+/// display the caller uses. Events arrive in order: the scan starts, each
+/// tile is detected and any splits it causes follow it, the scan ends, then
+/// restructuring starts and ends. Sorts finished by a shortcut, such as short
+/// slices sorted by insertion or reversed inputs, report the start and end of
+/// both phases with no tiles in between. A slice with fewer than two elements
+/// reports nothing.
+///
+/// # Examples
+///
+/// ```
+/// use std::ops::Range;
+///
+/// use tilesort::{Observer, SortConfig};
+///
+/// #[derive(Default)]
+/// struct Metrics {
+///     tiles: usize,
+///     longest_tile: usize,
+///     splits: usize,
+/// }
+///
+/// impl Observer for Metrics {
+///     fn on_tile_detected(&mut self, tile: Range<usize>) {
+///         self.tiles += 1;
+///         self.longest_tile = self.longest_tile.max(tile.len());
+///     }
+///
+///     fn on_split(&mut self, _at: usize) {
+///         self.splits += 1;
+///     }
+/// }
+///
+/// let mut data: Vec<u32> = (0..50).chain(100..150).chain(50..100).collect();
+/// let mut metrics = Metrics::default();
+/// tilesort::tilesort_with_observer(&mut data, &SortConfig::new(), &mut metrics);
+/// assert!(data.windows(2).all(|w| w[0] <= w[1]));
+/// assert_eq!((metrics.tiles, metrics.longest_tile, metrics.splits), (2, 100, 1));
+/// ```
+pub trait Observer {
+    /// Phase 1 is starting, to scan `elements` elements for tiles.
+    fn on_scan_start(&mut self, elements: usize) {
+        let _ = elements;
+    }
+
+    /// The scan found a run of elements already in order, at input
+    /// positions `tile`, and is about to insert it into the tile index.
+    fn on_tile_detected(&mut self, tile: Range<usize>) {
+        let _ = tile;
+    }
+
+    /// A tile overlapped the key range of another and was split in two, the
+    /// second piece starting with the element at input position `at`.
+    fn on_split(&mut self, at: usize) {
+        let _ = at;
+    }
+
+    /// Phase 1 is complete.
+    fn on_scan_end(&mut self) {}
+
+    /// Phase 2 is starting, to move the tiles into sorted order.
+    fn on_restructure_start(&mut self) {}
+
+    /// Phase 2 is complete and the slice is sorted.
+    fn on_restructure_end(&mut self) {}
+}
+
+impl<O: Observer + ?Sized> Observer for &mut O {
+    fn on_scan_start(&mut self, elements: usize) {
+        (**self).on_scan_start(elements);
+    }
+
+    fn on_tile_detected(&mut self, tile: Range<usize>) {
+        (**self).on_tile_detected(tile);
+    }
+
+    fn on_split(&mut self, at: usize) {
+        (**self).on_split(at);
+    }
+
+    fn on_scan_end(&mut self) {
+        (**self).on_scan_end();
+    }
+
+    fn on_restructure_start(&mut self) {
+        (**self).on_restructure_start();
+    }
+
+    fn on_restructure_end(&mut self) {
+        (**self).on_restructure_end();
+    }
+}
@@ -0,0 +1,195 @@
+//! Operation counters for comparing sorts by work done rather than time.
+//!
+//! With the `opcount` feature the sort records its comparisons, key
+//! extractions, key clones, element moves and tile splits, and the pattern it
+//! detected in its input, in thread-local counters read with
+//! [`SortStats::measure`]. Without it the recording functions are empty and
+//! compile away.
+
+#[cfg(feature = "opcount")]
+use std::cell::Cell;
+
+use crate::key_extractor::{KeyExtractor, TryKeyExtractor};
+use crate::pattern::InputPattern;
+
+/// Operations performed by the sorts run inside [`SortStats::measure`].
+///
+/// Counts cover tilesort's own work on the calling thread: the parallel sorts
+/// of `ParallelTileSortExt` and standard library sorts used internally (such
+/// as for short random segments of `tilesort_integers`) are not counted.
+#[cfg(feature = "opcount")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SortStats {
+    /// Key comparisons made finding runs and inserting tiles.
+    pub comparisons: u64,
+    /// Calls to the key function or extractor.
+    pub key_extractions: u64,
+    /// Keys obtained by cloning an element, as [`IdentityKey`](crate::IdentityKey)
+    /// does where it cannot compare elements in place.
+    pub key_clones: u64,
+    /// Elements cloned into a restructuring buffer.
+    pub element_clones: u64,
+    /// Elements written into a new position of the sorted container, counting
+    /// a swap as two.
+    pub element_moves: u64,
+    /// Tiles split in two to keep the index in order.
+    pub tile_splits: u64,
+    /// Input pattern detected by the most recent sort that sampled its input
+    /// (see [`SortConfig::detect_patterns`](crate::SortConfig::detect_patterns)).
+    pub pattern: Option<InputPattern>,
+}
+
+#[cfg(feature = "opcount")]
+impl SortStats {
+    const ZERO: SortStats = SortStats {
+        comparisons: 0,
+        key_extractions: 0,
+        key_clones: 0,
+        element_clones: 0,
+        element_moves: 0,
+        tile_splits: 0,
+        pattern: None,
+    };
+
+    /// Run `f`, returning its result and the operations of every sort it ran
+    /// on this thread.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tilesort::SortStats;
+    ///
+    /// let mut data = vec![4, 5, 6, 1, 2, 3];
+    /// let ((), stats) = SortStats::measure(|| tilesort::tilesort(&mut data));
+    /// assert_eq!(stats.tile_splits, 0);
+    /// assert_eq!(stats.element_moves, 6);
+    /// assert!(stats.comparisons >= 5);
+    /// ```
+    pub fn measure<R>(f: impl FnOnce() -> R) -> (R, SortStats) {
+        // Keep counts from an enclosing measurement, which includes this one
+        let outer = STATS.with(|stats| stats.replace(SortStats::ZERO));
+        let result = f();
+        let inner = STATS.with(|stats| stats.replace(SortStats::ZERO));
+        STATS.with(|stats| stats.set(outer.plus(&inner)));
+        (result, inner)
+    }
+
+    fn plus(&self, other: &SortStats) -> SortStats {
+        SortStats {
+            comparisons: self.comparisons + other.comparisons,
+            key_extractions: self.key_extractions + other.key_extractions,
+            key_clones: self.key_clones + other.key_clones,
+            element_clones: self.element_clones + other.element_clones,
+            element_moves: self.element_moves + other.element_moves,
+            tile_splits: self.tile_splits + other.tile_splits,
+            pattern: other.pattern.or(self.pattern),
+        }
+    }
+}
+
+#[cfg(feature = "opcount")]
+thread_local! {
+    static STATS: Cell<SortStats> = const { Cell::new(SortStats::ZERO) };
+}
+
+macro_rules! counter {
+    ($(#[$doc:meta])* $name:ident => $field:ident) => {
+        $(#[$doc])*
+        #[cfg(feature = "opcount")]
+        pub(crate) fn $name(count: usize) {
+            STATS.with(|stats| {
+                let mut current = stats.get();
+                current.$field += count as u64;
+                stats.set(current);
+            });
+        }
+
+        $(#[$doc])*
+        #[cfg(not(feature = "opcount"))]
+        #[inline(always)]
+        pub(crate) fn $name(_count: usize) {}
+    };
+}
+
+counter!(
+    /// Record `count` key comparisons.
+    comparisons => comparisons
+);
+counter!(
+    /// Record `count` key extractions.
+    key_extractions => key_extractions
+);
+counter!(
+    /// Record `count` keys cloned from elements.
+    key_clones => key_clones
+);
+counter!(
+    /// Record `count` elements cloned into a buffer.
+    element_clones => element_clones
+);
+counter!(
+    /// Record `count` element writes.
+    element_moves => element_moves
+);
+counter!(
+    /// Record `count` tile splits.
+    tile_splits => tile_splits
+);
+
+/// Record the pattern detected in a sort's input.
+#[cfg(feature = "opcount")]
+pub(crate) fn pattern(pattern: InputPattern) {
+    STATS.with(|stats| {
+        let mut current = stats.get();
+        current.pattern = Some(pattern);
+        stats.set(current);
+    });
+}
+
+/// Record the pattern detected in a sort's input.
+#[cfg(not(feature = "opcount"))]
+#[inline(always)]
+pub(crate) fn pattern(_pattern: InputPattern) {}
+
+/// Run `f` without recording its operations, for the `verify` feature's
+/// replays of a sort's moves.
+#[cfg(feature = "verify")]
+pub(crate) fn unrecorded<R>(f: impl FnOnce() -> R) -> R {
+    #[cfg(feature = "opcount")]
+    let saved = STATS.with(|stats| stats.get());
+    let result = f();
+    #[cfg(feature = "opcount")]
+    STATS.with(|stats| stats.set(saved));
+    result
+}
+
+/// Extract the key of `item`, counting the extraction.
+pub(crate) fn extract_key<T, K, E: KeyExtractor<T, K>>(extractor: &E, item: &T) -> K {
+    key_extractions(1);
+    extractor.extract_key(item)
+}
+
+/// Extract the key of `item` fallibly, counting the attempt.
+pub(crate) fn try_extract_key<T, K, Error, E>(extractor: &E, item: &T) -> Result<K, Error>
+where
+    E: TryKeyExtractor<T, K, Error>,
+{
+    key_extractions(1);
+    extractor.try_extract_key(item)
+}
+
+#[cfg(all(test, feature = "opcount"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nested_measurements() {
+        let ((inner, ()), outer) = SortStats::measure(|| {
+            tile_splits(1);
+            let (_, inner) = SortStats::measure(|| tile_splits(2));
+            (inner, tile_splits(3))
+        });
+        assert_eq!(inner.tile_splits, 2);
+        assert_eq!(outer.tile_splits, 6);
+    }
+}
@@ -0,0 +1,262 @@
+//! Parallel sorting on the rayon thread pool.
+
+use std::iter;
+use std::time::Instant;
+
+use rayon::prelude::*;
+use rayon::ThreadPoolBuilder;
+
+use crate::config::{SortConfig, Threads};
+use crate::container::apply_permutation;
+use crate::sorter;
+use crate::tile_index::{Tile, TileIndex};
+
+/// Extension trait adding parallel tilesorts to slices, in the style of
+/// rayon's `ParallelSliceMut::par_sort`.
+///
+/// Keys are extracted and compared against their neighbours in parallel to
+/// find the run boundaries; the tiles are then inserted into the index in
+/// input order, exactly as the sequential sort does, and the elements are
+/// cloned into place in parallel.
+///
+/// The result is always the same as the sequential sort with the same
+/// [`SortConfig`], whatever the number of threads or their scheduling. That
+/// includes the order of equal elements in an
+/// [unstable](SortConfig::unstable) sort, so output can be compared byte for
+/// byte across runs.
+///
+/// The work runs on rayon's global thread pool unless the configuration
+/// names another with [`SortConfig::thread_pool`] or
+/// [`SortConfig::max_threads`].
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::ParallelTileSortExt;
+///
+/// let mut data: Vec<u32> = (5_000..10_000).chain(0..5_000).collect();
+/// data.par_tilesort();
+/// assert!(data.windows(2).all(|w| w[0] <= w[1]));
+///
+/// let mut words = vec!["ccc", "a", "bb"];
+/// words.par_tilesort_by_key(|w| w.len());
+/// assert_eq!(words, vec!["a", "bb", "ccc"]);
+/// ```
+pub trait ParallelTileSortExt<T: Send> {
+    /// Sort the slice in ascending order in parallel.
+    fn par_tilesort(&mut self)
+    where
+        T: Ord + Clone + Sync;
+
+    /// Sort the slice in parallel with explicit options.
+    fn par_tilesort_with_config(&mut self, config: &SortConfig)
+    where
+        T: Ord + Clone + Sync;
+
+    /// Sort the slice in ascending order of `key_fn` in parallel.
+    fn par_tilesort_by_key<K, F>(&mut self, key_fn: F)
+    where
+        T: Clone + Sync,
+        K: Ord + Send + Sync,
+        F: Fn(&T) -> K + Sync;
+
+    /// Sort the slice by `key_fn` in parallel with explicit options.
+    fn par_tilesort_by_key_with_config<K, F>(&mut self, key_fn: F, config: &SortConfig)
+    where
+        T: Clone + Sync,
+        K: Ord + Send + Sync,
+        F: Fn(&T) -> K + Sync;
+
+    /// Extract the keys in parallel, then sort by them sequentially.
+    ///
+    /// Only the key function runs on the thread pool; the run detection and
+    /// restructuring that follow are those of
+    /// [`tilesort_by_key`](crate::tilesort_by_key), so the keys need not be
+    /// `Sync`. This suits expensive key functions, such as parsing or
+    /// collation, whose cost dominates the sort.
+    fn par_extract_tilesort_by_key<K, F>(&mut self, key_fn: F)
+    where
+        T: Clone + Sync,
+        K: Ord + Send,
+        F: Fn(&T) -> K + Sync;
+
+    /// Extract the keys in parallel, then sort by them sequentially with
+    /// explicit options.
+    ///
+    /// Unlike the fully parallel sorts, this honours
+    /// [`max_aux_memory`](SortConfig::max_aux_memory). With
+    /// [`KeyCost::Auto`](crate::KeyCost::Auto) the keys are judged by the
+    /// time the parallel extraction took across the pool.
+    fn par_extract_tilesort_by_key_with_config<K, F>(&mut self, key_fn: F, config: &SortConfig)
+    where
+        T: Clone + Sync,
+        K: Ord + Send,
+        F: Fn(&T) -> K + Sync;
+}
+
+impl<T: Send> ParallelTileSortExt<T> for [T] {
+    fn par_tilesort(&mut self)
+    where
+        T: Ord + Clone + Sync,
+    {
+        self.par_tilesort_with_config(&SortConfig::new());
+    }
+
+    fn par_tilesort_with_config(&mut self, config: &SortConfig)
+    where
+        T: Ord + Clone + Sync,
+    {
+        run_on(&config.threads, || {
+            let tile_index = par_scan(self, config);
+            par_restructure(self, &tile_index, config);
+        });
+    }
+
+    fn par_tilesort_by_key<K, F>(&mut self, key_fn: F)
+    where
+        T: Clone + Sync,
+        K: Ord + Send + Sync,
+        F: Fn(&T) -> K + Sync,
+    {
+        self.par_tilesort_by_key_with_config(key_fn, &SortConfig::new());
+    }
+
+    fn par_tilesort_by_key_with_config<K, F>(&mut self, key_fn: F, config: &SortConfig)
+    where
+        T: Clone + Sync,
+        K: Ord + Send + Sync,
+        F: Fn(&T) -> K + Sync,
+    {
+        run_on(&config.threads, || {
+            let keys: Vec<K> = self.par_iter().map(&key_fn).collect();
+            let tile_index = par_scan(&keys, config);
+            par_restructure(self, &tile_index, config);
+        });
+    }
+
+    fn par_extract_tilesort_by_key<K, F>(&mut self, key_fn: F)
+    where
+        T: Clone + Sync,
+        K: Ord + Send,
+        F: Fn(&T) -> K + Sync,
+    {
+        self.par_extract_tilesort_by_key_with_config(key_fn, &SortConfig::new());
+    }
+
+    fn par_extract_tilesort_by_key_with_config<K, F>(&mut self, key_fn: F, config: &SortConfig)
+    where
+        T: Clone + Sync,
+        K: Ord + Send,
+        F: Fn(&T) -> K + Sync,
+    {
+        let started = Instant::now();
+        let (keys, threads) = run_on(&config.threads, || {
+            let keys: Vec<K> = self.par_iter().map(&key_fn).collect();
+            (keys, rayon::current_num_threads())
+        });
+        // Wall time understates the cost of each key by the number of threads
+        let elapsed = started.elapsed() * threads as u32;
+        let config = sorter::resolve_key_cost(config, || (elapsed, keys.len()));
+        sorter::tilesort_impl_with_keys(self, &keys, &config);
+    }
+}
+
+/// Run `op` on the thread pool selected by `threads`.
+fn run_on<R: Send>(threads: &Threads, op: impl FnOnce() -> R + Send) -> R {
+    match threads {
+        Threads::Global => op(),
+        Threads::Pool(pool) => pool.install(op),
+        Threads::Max(count) => match ThreadPoolBuilder::new().num_threads(*count).build() {
+            Ok(pool) => pool.install(op),
+            // Without a pool of our own, fall back to the current one
+            Err(_) => op(),
+        },
+    }
+}
+
+/// Phase 1: find the run boundaries in parallel, then insert the runs in
+/// input order.
+fn par_scan<K: Ord + Sync>(keys: &[K], config: &SortConfig) -> TileIndex {
+    let reverse = config.order.is_descending();
+    let mut tile_index = sorter::new_tile_index(keys.len(), config);
+    if keys.is_empty() {
+        return tile_index;
+    }
+
+    // Whether a run ends before `idx` depends only on its neighbour, so the
+    // boundaries can be found independently; `collect` keeps them in order
+    let run_starts: Vec<usize> = (1..keys.len())
+        .into_par_iter()
+        .filter(|&idx| {
+            if reverse {
+                keys[idx] > keys[idx - 1]
+            } else {
+                keys[idx] < keys[idx - 1]
+            }
+        })
+        .collect();
+
+    let mut start = 0;
+    for end in run_starts.into_iter().chain(iter::once(keys.len())) {
+        tile_index.insert_tile(Tile::new(start, end - start), keys, reverse);
+        start = end;
+    }
+
+    #[cfg(feature = "verify")]
+    crate::verify::verify_tile_index(&tile_index, keys, reverse);
+
+    tile_index
+}
+
+/// Phase 2: clone the elements into a buffer in sorted order in parallel, then
+/// move them back. As in the sequential sort, `data` is untouched if a clone
+/// panics. With `config.indirect` the permutation is applied with swaps
+/// instead, which is sequential.
+fn par_restructure<T>(data: &mut [T], tile_index: &TileIndex, config: &SortConfig)
+where
+    T: Clone + Send + Sync,
+{
+    let sources: Vec<usize> = tile_index.sorted_indices().collect();
+    if config.indirect {
+        apply_permutation(data, sources);
+        return;
+    }
+
+    let sorted: Vec<T> = sources.par_iter().map(|&idx| data[idx].clone()).collect();
+    data.par_iter_mut()
+        .zip(sorted)
+        .for_each(|(slot, element)| *slot = element);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_and_single() {
+        let mut empty: Vec<i32> = Vec::new();
+        empty.par_tilesort();
+        assert!(empty.is_empty());
+
+        let mut single = vec![7];
+        single.par_tilesort();
+        assert_eq!(single, vec![7]);
+    }
+
+    #[test]
+    fn test_matches_sequential_with_config() {
+        let data: Vec<(u8, usize)> = (0..10_000).map(|i| ((i * 7 % 13) as u8, i)).collect();
+        for config in [
+            SortConfig::new(),
+            SortConfig::new().descending(),
+            SortConfig::new().unstable(),
+            SortConfig::new().indirect(true),
+        ] {
+            let mut parallel = data.clone();
+            parallel.par_tilesort_by_key_with_config(|&(k, _)| k, &config);
+            let mut sequential = data.clone();
+            crate::tilesort_by_key_with_config(&mut sequential, |&(k, _)| k, &config);
+            assert_eq!(parallel, sequential);
+        }
+    }
+}
@@ -0,0 +1,313 @@
+//! Sampling the input to pick a cheaper sort for common patterns.
+//!
+//! Before building a tile index the sort inspects a few short windows spread
+//! across the input. Input that looks sorted or reverse sorted is checked in
+//! full and finished without an index; input whose runs are too short for
+//! tiles to pay off is sorted by a comparison sort over an index permutation
+//! instead. Everything else goes through the tile index as usual.
+
+use crate::config::SortConfig;
+use crate::container::apply_permutation;
+use crate::logging::debug;
+use crate::opcount;
+use crate::small::Sources;
+use crate::tile_index::precedes;
+
+/// Shortest input the detector samples; below this the tile index is cheap
+/// enough that sampling would not pay for itself.
+pub(crate) const DETECT_MIN_LEN: usize = 1024;
+
+/// Number of windows sampled, spread evenly across the input.
+const SAMPLE_WINDOWS: usize = 8;
+
+/// Adjacent pairs compared in each window.
+const WINDOW_PAIRS: usize = 8;
+
+/// Estimated tiles at or below which the input counts as a few long runs.
+const FEW_RUNS: usize = 64;
+
+/// Shape of the input, as estimated by sampling it.
+///
+/// With the `opcount` feature the detected pattern is reported in
+/// [`SortStats::pattern`](crate::SortStats::pattern).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InputPattern {
+    /// Already in order; the sort checks this and returns.
+    Sorted,
+    /// In the opposite order. The sort reverses the slice if no two
+    /// neighbours are equal, and otherwise sorts it like [`Random`](InputPattern::Random).
+    Reversed,
+    /// Many runs of moderate length, sorted with the tile index.
+    Sawtooth,
+    /// A handful of long runs, sorted with the tile index.
+    FewRuns,
+    /// Runs too short for tiles to help, or too many of them (see
+    /// [`SortConfig::min_run_len`] and [`SortConfig::max_tiles`]); sorted
+    /// with a comparison sort of the element indices instead.
+    Random,
+}
+
+/// Cheaper way to sort an input than building a tile index.
+// Large only when holding the inline sources of a short input
+#[allow(clippy::large_enum_variant)]
+pub(crate) enum Shortcut {
+    /// The input is already in order.
+    Sorted,
+    /// Reversing the input sorts it.
+    Reverse,
+    /// Position `i` takes the element at `sources[i]`.
+    Permute(Sources),
+}
+
+impl Shortcut {
+    /// Sort `data`, for which this shortcut was found.
+    pub(crate) fn apply<T>(self, data: &mut [T]) {
+        match self {
+            Shortcut::Sorted => {}
+            Shortcut::Reverse => {
+                data.reverse();
+                opcount::element_moves(data.len() / 2 * 2);
+            }
+            Shortcut::Permute(sources) => apply_permutation(data, sources),
+        }
+    }
+
+    /// Returns `true` if the shortcut leaves every element where it is.
+    pub(crate) fn is_identity(&self) -> bool {
+        match self {
+            Shortcut::Sorted => true,
+            Shortcut::Reverse => false,
+            Shortcut::Permute(sources) => sources
+                .as_ref()
+                .iter()
+                .enumerate()
+                .all(|(position, &source)| position == source),
+        }
+    }
+
+    /// References to the elements of `data`, for which this shortcut was
+    /// found, in sorted order.
+    pub(crate) fn sorted_refs<T>(self, data: &[T]) -> Vec<&T> {
+        match self {
+            Shortcut::Sorted => data.iter().collect(),
+            Shortcut::Reverse => data.iter().rev().collect(),
+            Shortcut::Permute(mut sources) => sources
+                .as_mut()
+                .iter()
+                .map(|&source| &data[source])
+                .collect(),
+        }
+    }
+
+    /// Input position of the element at each sorted position, for the
+    /// `len` elements this shortcut was found for.
+    pub(crate) fn sources(self, len: usize) -> Vec<usize> {
+        match self {
+            Shortcut::Sorted => (0..len).collect(),
+            Shortcut::Reverse => (0..len).rev().collect(),
+            Shortcut::Permute(sources) => sources.as_ref().to_vec(),
+        }
+    }
+}
+
+/// Estimate the shape of `keys` from a few windows of adjacent pairs, or
+/// `None` for inputs shorter than [`DETECT_MIN_LEN`]. `config` gives the
+/// direction and the thresholds for treating the input as random.
+pub(crate) fn sample<K: Ord>(keys: &[K], config: &SortConfig) -> Option<InputPattern> {
+    if keys.len() < DETECT_MIN_LEN {
+        return None;
+    }
+    let reverse = config.order.is_descending();
+
+    let pairs = SAMPLE_WINDOWS * WINDOW_PAIRS;
+    let stride = (keys.len() - WINDOW_PAIRS - 1) / (SAMPLE_WINDOWS - 1);
+    let descents = (0..SAMPLE_WINDOWS)
+        .flat_map(|window| keys[window * stride..=window * stride + WINDOW_PAIRS].windows(2))
+        .filter(|pair| precedes(&pair[1], &pair[0], reverse))
+        .count();
+    let estimated_tiles = keys.len() * descents / pairs + 1;
+
+    Some(if descents == 0 {
+        InputPattern::Sorted
+    } else if descents == pairs {
+        InputPattern::Reversed
+    } else if pairs < descents.saturating_mul(config.min_run_len)
+        || config.max_tiles.is_some_and(|max| estimated_tiles > max)
+    {
+        InputPattern::Random
+    } else if estimated_tiles <= FEW_RUNS {
+        InputPattern::FewRuns
+    } else {
+        InputPattern::Sawtooth
+    })
+}
+
+/// A cheaper sort for `keys` than the tile index, if sampling suggests one
+/// and it checks out, recording the detected pattern. The comparison sort
+/// is only used when `allow_permutation` is set, as its index vector counts
+/// against the memory budget.
+pub(crate) fn shortcut<K: Ord>(
+    keys: &[K],
+    config: &SortConfig,
+    allow_permutation: bool,
+) -> Option<Shortcut> {
+    if !config.detect_patterns {
+        return None;
+    }
+    let (pattern, shortcut) = plan(keys, config, allow_permutation)?;
+    debug!("Detected input pattern {:?}", pattern);
+    opcount::pattern(pattern);
+    #[cfg(feature = "verify")]
+    if let Some(shortcut) = &shortcut {
+        crate::verify::verify_shortcut(shortcut, keys, config.order.is_descending());
+    }
+    shortcut
+}
+
+/// The pattern of `keys` and the shortcut for it, if any, checking in full
+/// what the sample suggests.
+fn plan<K: Ord>(
+    keys: &[K],
+    config: &SortConfig,
+    allow_permutation: bool,
+) -> Option<(InputPattern, Option<Shortcut>)> {
+    let reverse = config.order.is_descending();
+    let permute = || {
+        allow_permutation
+            .then(|| Shortcut::Permute(Sources::Heap(sorted_sources(keys, config.stable, reverse))))
+    };
+
+    Some(match sample(keys, config)? {
+        InputPattern::Sorted => {
+            if is_ordered(keys, |a, b| !precedes(b, a, reverse)) {
+                (InputPattern::Sorted, Some(Shortcut::Sorted))
+            } else {
+                // Descents too rare to show up in the sample: long runs
+                (InputPattern::FewRuns, None)
+            }
+        }
+        InputPattern::Reversed => {
+            // Strictly, so that reversing cannot reorder equal keys. Equal
+            // neighbours in descending input would make single-element
+            // tiles, so sort those by comparison.
+            if is_ordered(keys, |a, b| precedes(b, a, reverse)) {
+                (InputPattern::Reversed, Some(Shortcut::Reverse))
+            } else {
+                (InputPattern::Reversed, permute())
+            }
+        }
+        InputPattern::Random => (InputPattern::Random, permute()),
+        pattern => (pattern, None),
+    })
+}
+
+/// Returns `true` if `in_order` holds for every adjacent pair of `keys`.
+fn is_ordered<K>(keys: &[K], in_order: impl Fn(&K, &K) -> bool) -> bool {
+    keys.windows(2).all(|pair| in_order(&pair[0], &pair[1]))
+}
+
+/// Input positions of `keys` in sorted order.
+fn sorted_sources<K: Ord>(keys: &[K], stable: bool, reverse: bool) -> Vec<usize> {
+    let compare = |&a: &usize, &b: &usize| {
+        opcount::comparisons(1);
+        let ordering = keys[a].cmp(&keys[b]);
+        if reverse {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    };
+    let mut sources: Vec<usize> = (0..keys.len()).collect();
+    if stable {
+        sources.sort_by(compare);
+    } else {
+        sources.sort_unstable_by(compare);
+    }
+    sources
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pattern_of(keys: &[u32]) -> Option<InputPattern> {
+        sample(keys, &SortConfig::new())
+    }
+
+    #[test]
+    fn test_classifies_common_shapes() {
+        let n = 4 * DETECT_MIN_LEN as u32;
+        let sorted: Vec<u32> = (0..n).collect();
+        let reversed: Vec<u32> = (0..n).rev().collect();
+        let few_runs: Vec<u32> = (n / 2..n).chain(0..n / 2).collect();
+        let sawtooth: Vec<u32> = (0..n).map(|i| i % 8).collect();
+        let random: Vec<u32> = (0..n).map(|i| i.wrapping_mul(2_654_435_761) >> 7).collect();
+
+        assert_eq!(pattern_of(&sorted), Some(InputPattern::Sorted));
+        assert_eq!(pattern_of(&reversed), Some(InputPattern::Reversed));
+        assert_eq!(plan_of(&few_runs).0, InputPattern::FewRuns);
+        assert_eq!(pattern_of(&sawtooth), Some(InputPattern::Sawtooth));
+        assert_eq!(pattern_of(&random), Some(InputPattern::Random));
+        assert_eq!(pattern_of(&sorted[..DETECT_MIN_LEN - 1]), None);
+        let descending = SortConfig::new().descending();
+        assert_eq!(sample(&reversed, &descending), Some(InputPattern::Sorted));
+    }
+
+    fn plan_of(keys: &[u32]) -> (InputPattern, Option<Shortcut>) {
+        plan(keys, &SortConfig::new(), true).expect("long enough to sample")
+    }
+
+    #[test]
+    fn test_sorted_sample_is_verified() {
+        // Sorted wherever sampled, but not in full
+        let mut keys: Vec<u32> = (0..DETECT_MIN_LEN as u32 * 2).collect();
+        keys.swap(100, 101);
+        assert_eq!(pattern_of(&keys), Some(InputPattern::Sorted));
+        assert!(matches!(plan_of(&keys), (InputPattern::FewRuns, None)));
+
+        keys.swap(100, 101);
+        assert!(matches!(
+            plan_of(&keys),
+            (InputPattern::Sorted, Some(Shortcut::Sorted))
+        ));
+    }
+
+    #[test]
+    fn test_reverse_shortcut_requires_strict_order() {
+        let mut keys: Vec<u32> = (0..DETECT_MIN_LEN as u32 * 2).rev().collect();
+        assert!(matches!(
+            plan_of(&keys),
+            (InputPattern::Reversed, Some(Shortcut::Reverse))
+        ));
+
+        keys[500] = keys[501];
+        assert!(matches!(
+            plan_of(&keys),
+            (InputPattern::Reversed, Some(Shortcut::Permute(_)))
+        ));
+        let no_permutation = plan(&keys, &SortConfig::new(), false).unwrap();
+        assert!(matches!(no_permutation, (InputPattern::Reversed, None)));
+    }
+
+    #[test]
+    fn test_thresholds() {
+        let n = 4 * DETECT_MIN_LEN as u32;
+        let sawtooth: Vec<u32> = (0..n).map(|i| i % 8).collect();
+        let strict = SortConfig::new().min_run_len(16);
+        assert_eq!(sample(&sawtooth, &strict), Some(InputPattern::Random));
+        let capped = SortConfig::new().max_tiles(100);
+        assert_eq!(sample(&sawtooth, &capped), Some(InputPattern::Random));
+        let lenient = SortConfig::new().min_run_len(0).max_tiles(n as usize);
+        assert_eq!(sample(&sawtooth, &lenient), Some(InputPattern::Sawtooth));
+
+        let random: Vec<u32> = (0..n).map(|i| i.wrapping_mul(2_654_435_761) >> 7).collect();
+        assert_eq!(sample(&random, &lenient), Some(InputPattern::Sawtooth));
+    }
+
+    #[test]
+    fn test_disabled() {
+        let keys: Vec<u32> = (0..DETECT_MIN_LEN as u32).collect();
+        let config = SortConfig::new().detect_patterns(false);
+        assert!(shortcut(&keys, &config, true).is_none());
+    }
+}
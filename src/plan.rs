@@ -0,0 +1,496 @@
+//! Separate scan and restructure phases.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::Range;
+
+use crate::config::{Order, SortConfig, Strategy};
+use crate::opcount;
+use crate::sorter;
+use crate::tile_index::TileIndex;
+use crate::view::SortedView;
+
+/// The result of scanning a slice: where each tile goes in sorted order.
+///
+/// Created by [`scan`] or [`scan_with_config`]. A plan can be inspected
+/// through [`stats`](SortPlan::stats) before committing to a sort, and
+/// applied with [`apply`](SortPlan::apply) to the scanned slice or to any
+/// other slice holding the same elements in the same order, as often as
+/// needed.
+///
+/// With the `serde` feature a plan can be serialized, so the run structure of
+/// a file can be computed once and shipped to readers. Deserializing checks
+/// that the tiles cover every element exactly once.
+#[derive(Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "SerializedPlan")
+)]
+pub struct SortPlan {
+    tile_index: TileIndex,
+    elements: usize,
+    config: SortConfig,
+}
+
+/// Summary of a [`SortPlan`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlanStats {
+    /// Number of elements scanned.
+    pub elements: usize,
+    /// Number of tiles after splitting; `1` means the slice is already sorted.
+    pub tiles: usize,
+    /// Length of the longest tile.
+    pub largest_tile: usize,
+}
+
+/// Scan `data` by key and return a plan for sorting it, without modifying it.
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::Order;
+///
+/// let mut data = vec![4, 5, 6, 1, 2, 3];
+/// let plan = tilesort::scan(&data, |&x| x, Order::Ascending);
+/// assert_eq!(plan.stats().tiles, 2);
+///
+/// // Apply to a copy, then to the original
+/// let mut copy = data.clone();
+/// plan.apply(&mut copy);
+/// plan.apply(&mut data);
+/// assert_eq!(data, vec![1, 2, 3, 4, 5, 6]);
+/// assert_eq!(copy, data);
+/// ```
+pub fn scan<T, K, F>(data: &[T], key_fn: F, order: Order) -> SortPlan
+where
+    K: Ord,
+    F: Fn(&T) -> K,
+{
+    scan_with_config(data, key_fn, &SortConfig::new().order(order))
+}
+
+/// Scan `data` by key with the given options.
+///
+/// The options also govern [`SortPlan::apply`], e.g. whether it restructures
+/// indirectly.
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::SortConfig;
+///
+/// let data: Vec<u32> = (0..1000).rev().collect();
+/// let plan = tilesort::scan_with_config(&data, |&x| x, &SortConfig::new());
+/// // Every element is its own tile: maybe not worth it
+/// assert_eq!(plan.stats().tiles, 1000);
+/// ```
+pub fn scan_with_config<T, K, F>(data: &[T], key_fn: F, config: &SortConfig) -> SortPlan
+where
+    K: Ord,
+    F: Fn(&T) -> K,
+{
+    SortPlan {
+        tile_index: sorter::scan_plan(data, &key_fn, config),
+        elements: data.len(),
+        config: config.clone(),
+    }
+}
+
+/// Find an element equal to `key` in `data`, which `plan` was scanned from,
+/// without sorting it.
+///
+/// Returns the input position of the first such element in sorted order,
+/// or `None` if there is none. Only the one tile whose key range can hold
+/// `key` is searched, by bisection, so a lookup takes a logarithmic number
+/// of comparisons however the tiles are scattered. The plan must have been
+/// scanned by the elements themselves; see [`find_by_key`] otherwise.
+///
+/// # Panics
+///
+/// Panics if `data.len()` differs from [`SortPlan::len`].
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::Order;
+///
+/// let data = vec![40, 41, 42, 10, 11, 12, 30];
+/// let plan = tilesort::scan(&data, |&x| x, Order::Ascending);
+/// assert_eq!(tilesort::find(&data, &plan, &11), Some(4));
+/// assert_eq!(tilesort::find(&data, &plan, &20), None);
+/// ```
+pub fn find<T: Ord>(data: &[T], plan: &SortPlan, key: &T) -> Option<usize> {
+    search(data, plan, |element| element.cmp(key))
+}
+
+/// Find an element whose key is `key` in `data`, which `plan` was scanned
+/// from by `key_fn`, without sorting it.
+///
+/// Like [`find`], returning the input position of the first match in sorted
+/// order. `key_fn` must compute the same keys as when the plan was made.
+///
+/// # Panics
+///
+/// Panics if `data.len()` differs from [`SortPlan::len`].
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::Order;
+///
+/// let events = vec![(3, "c"), (4, "d"), (1, "a"), (2, "b")];
+/// let plan = tilesort::scan(&events, |&(time, _)| time, Order::Ascending);
+/// let found = tilesort::find_by_key(&events, &plan, &2, |&(time, _)| time);
+/// assert_eq!(found.map(|i| events[i].1), Some("b"));
+/// ```
+pub fn find_by_key<T, K, F>(data: &[T], plan: &SortPlan, key: &K, key_fn: F) -> Option<usize>
+where
+    K: Ord,
+    F: Fn(&T) -> K,
+{
+    search(data, plan, |element| {
+        opcount::extract_key(&key_fn, element).cmp(key)
+    })
+}
+
+/// Sorted position of the first element of `data`, which `plan` was
+/// scanned from, for which `pred` is false, without sorting it.
+///
+/// As [`slice::partition_point`] does for a sorted slice, this assumes `pred`
+/// holds for a prefix of the elements in sorted order, such as all keys below
+/// a bound, and answers in a logarithmic number of calls. The elements before
+/// the point are the first that many of [`SortPlan::sorted_indices`].
+///
+/// # Panics
+///
+/// Panics if `data.len()` differs from [`SortPlan::len`].
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::Order;
+///
+/// let data = vec![40, 41, 42, 10, 11, 12, 30];
+/// let plan = tilesort::scan(&data, |&x| x, Order::Ascending);
+/// assert_eq!(tilesort::partition_point(&data, &plan, |&x| x < 35), 4);
+///
+/// let below: Vec<i32> = plan.sorted_indices().take(4).map(|i| data[i]).collect();
+/// assert_eq!(below, vec![10, 11, 12, 30]);
+/// ```
+pub fn partition_point<T>(data: &[T], plan: &SortPlan, mut pred: impl FnMut(&T) -> bool) -> usize {
+    assert_eq!(
+        data.len(),
+        plan.elements,
+        "plan was built for a slice of a different length"
+    );
+    plan.tile_index
+        .sorted_partition_point(|position| pred(&data[position]))
+}
+
+/// Search the tiles of `plan` with `cmp`, which orders an element of `data`
+/// against the target in ascending order.
+fn search<T>(data: &[T], plan: &SortPlan, cmp: impl Fn(&T) -> Ordering) -> Option<usize> {
+    assert_eq!(
+        data.len(),
+        plan.elements,
+        "plan was built for a slice of a different length"
+    );
+    let descending = plan.config.order.is_descending();
+    plan.tile_index.find(|position| {
+        opcount::comparisons(1);
+        let ordering = cmp(&data[position]);
+        if descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    })
+}
+
+impl SortPlan {
+    /// Build a plan for `data` from runs already known to be sorted, such as
+    /// the extents of shards that were each sorted upstream, skipping the
+    /// search for tile boundaries.
+    ///
+    /// `runs` must cover `data` in order, each starting where the previous
+    /// one ended, and each must be sorted by `key_fn` in the direction of
+    /// `config`; empty runs are ignored. This is checked with one comparison
+    /// per element, about as many as a scan, but the runs are inserted as
+    /// given rather than being rediscovered. Use
+    /// [`from_runs_unchecked`](SortPlan::from_runs_unchecked) to skip the
+    /// check.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`InvalidRuns`] if the runs do not cover `data` in order or
+    /// one of them is not sorted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tilesort::{SortConfig, SortPlan};
+    ///
+    /// // Three shards, each sorted
+    /// let mut data = vec![5, 8, 9, 1, 2, 7, 3, 4];
+    /// let runs = [0..3, 3..6, 6..8];
+    /// let plan = SortPlan::from_runs(&data, &runs, |&x| x, &SortConfig::new())?;
+    /// assert_eq!(plan.stats().tiles, 5);
+    /// plan.apply(&mut data);
+    /// assert_eq!(data, vec![1, 2, 3, 4, 5, 7, 8, 9]);
+    ///
+    /// assert!(SortPlan::from_runs(&data, &[0..4, 5..8], |&x| x, &SortConfig::new()).is_err());
+    /// # Ok::<(), tilesort::InvalidRuns>(())
+    /// ```
+    pub fn from_runs<T, K, F>(
+        data: &[T],
+        runs: &[Range<usize>],
+        key_fn: F,
+        config: &SortConfig,
+    ) -> Result<SortPlan, InvalidRuns>
+    where
+        K: Ord,
+        F: Fn(&T) -> K,
+    {
+        Ok(SortPlan {
+            tile_index: sorter::plan_from_runs(data, runs, &key_fn, config, true)?,
+            elements: data.len(),
+            config: config.clone(),
+        })
+    }
+
+    /// Build a plan for `data` from runs known to be sorted, like
+    /// [`from_runs`](SortPlan::from_runs), without checking them.
+    ///
+    /// Only the comparisons needed to place the runs are made. If the runs
+    /// do not cover `data` in order or are not sorted, the plan sorts `data`
+    /// incorrectly, or panics here or when applied; with the `verify`
+    /// feature, it panics here.
+    pub fn from_runs_unchecked<T, K, F>(
+        data: &[T],
+        runs: &[Range<usize>],
+        key_fn: F,
+        config: &SortConfig,
+    ) -> SortPlan
+    where
+        K: Ord,
+        F: Fn(&T) -> K,
+    {
+        let tile_index = sorter::plan_from_runs(data, runs, &key_fn, config, false)
+            .expect("unchecked runs are never rejected");
+        SortPlan {
+            tile_index,
+            elements: data.len(),
+            config: config.clone(),
+        }
+    }
+
+    /// Number of elements the plan was built for.
+    pub fn len(&self) -> usize {
+        self.elements
+    }
+
+    /// Returns `true` if the plan was built for an empty slice.
+    pub fn is_empty(&self) -> bool {
+        self.elements == 0
+    }
+
+    /// The scanned tiles, in sorted order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tilesort::{IdentityKey, Order};
+    ///
+    /// let data = vec![30, 31, 10, 11, 12, 5];
+    /// let plan = tilesort::scan(&data, |&x| x, Order::Ascending);
+    /// let tiles: Vec<_> = plan.tile_index().iter().map(|tile| tile.range()).collect();
+    /// assert_eq!(tiles, vec![5..6, 2..5, 0..2]);
+    ///
+    /// let second = plan.tile_index().iter().nth(1).unwrap();
+    /// assert_eq!(second.key_range(&data, IdentityKey), 10..=12);
+    /// ```
+    pub fn tile_index(&self) -> &TileIndex {
+        &self.tile_index
+    }
+
+    /// A view of `data`, the scanned slice, in sorted order, for searching
+    /// and iterating without sorting it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `data.len()` differs from [`len`](SortPlan::len).
+    pub fn view<'a, T>(&self, data: &'a [T]) -> SortedView<'a, T> {
+        assert_eq!(
+            data.len(),
+            self.elements,
+            "plan was built for a slice of a different length"
+        );
+        SortedView::new(data, &self.tile_index, self.config.order.is_descending())
+    }
+
+    /// Tile and element counts of the plan.
+    pub fn stats(&self) -> PlanStats {
+        PlanStats {
+            elements: self.elements,
+            tiles: self.tile_index.len(),
+            largest_tile: self
+                .tile_index
+                .iter()
+                .map(|tile| tile.len())
+                .max()
+                .unwrap_or(0),
+        }
+    }
+
+    /// Input positions of the elements in sorted order.
+    pub fn sorted_indices(&self) -> impl Iterator<Item = usize> + '_ {
+        self.tile_index.sorted_indices()
+    }
+
+    /// Bring the plan up to date with `data`, which holds the scanned
+    /// elements followed by newly appended ones, scanning only the new
+    /// elements.
+    ///
+    /// `key_fn` must compute the same keys as when the plan was made. The old
+    /// part of `data` is checked against the plan first, which takes one pass
+    /// over its keys but none of the tile insertions of a full rescan. If it
+    /// no longer matches, or `data` is shorter than the plan, [`StalePlan`] is
+    /// returned and the plan is left unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tilesort::Order;
+    ///
+    /// let mut log = vec![10, 11, 12, 1, 2];
+    /// let mut plan = tilesort::scan(&log, |&x| x, Order::Ascending);
+    ///
+    /// log.extend([5, 6, 13]);
+    /// plan.extend(&log, |&x| x).unwrap();
+    /// plan.apply(&mut log);
+    /// assert_eq!(log, vec![1, 2, 5, 6, 10, 11, 12, 13]);
+    /// ```
+    pub fn extend<T, K, F>(&mut self, data: &[T], key_fn: F) -> Result<(), StalePlan>
+    where
+        K: Ord,
+        F: Fn(&T) -> K,
+    {
+        if !sorter::extend_plan(
+            &mut self.tile_index,
+            self.elements,
+            data,
+            &key_fn,
+            &self.config,
+        ) {
+            return Err(StalePlan);
+        }
+        self.elements = data.len();
+        Ok(())
+    }
+
+    /// Sort `data` according to the plan.
+    ///
+    /// `data` should hold the scanned elements in their scanned order; it is
+    /// rearranged the same way regardless. Returns the [`Strategy`] used,
+    /// which depends on the plan's [`SortConfig::max_aux_memory`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `data.len()` differs from [`len`](SortPlan::len).
+    pub fn apply<T: Clone>(&self, data: &mut [T]) -> Strategy {
+        assert_eq!(
+            data.len(),
+            self.elements,
+            "plan was built for a slice of a different length"
+        );
+        sorter::apply_plan(data, &self.tile_index, &self.config)
+    }
+}
+
+/// Error returned by [`SortPlan::extend`] when the data no longer matches the
+/// plan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StalePlan;
+
+impl fmt::Display for StalePlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("sort plan does not match the data")
+    }
+}
+
+impl std::error::Error for StalePlan {}
+
+/// Error returned by [`SortPlan::from_runs`] when the runs do not describe
+/// the data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvalidRuns {
+    /// Run number `run` does not start where the previous one ended (or at
+    /// `0` for the first), or extends past the end of the data.
+    Misaligned {
+        /// Position of the run in the list of runs.
+        run: usize,
+    },
+    /// The runs end before the end of the data.
+    Incomplete,
+    /// Run number `run` is not sorted in the configured direction.
+    Unsorted {
+        /// Position of the run in the list of runs.
+        run: usize,
+    },
+}
+
+impl fmt::Display for InvalidRuns {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidRuns::Misaligned { run } => write!(f, "run {} is not contiguous", run),
+            InvalidRuns::Incomplete => f.write_str("runs do not cover the data"),
+            InvalidRuns::Unsorted { run } => write!(f, "run {} is not sorted", run),
+        }
+    }
+}
+
+impl std::error::Error for InvalidRuns {}
+
+/// Unvalidated form of a deserialized [`SortPlan`].
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct SerializedPlan {
+    tile_index: TileIndex,
+    elements: usize,
+    config: SortConfig,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<SerializedPlan> for SortPlan {
+    type Error = String;
+
+    fn try_from(plan: SerializedPlan) -> Result<Self, String> {
+        // The tiles, ordered by input position, must tile 0..elements
+        let mut ranges: Vec<(usize, usize)> = plan
+            .tile_index
+            .iter()
+            .map(|tile| (tile.start(), tile.len()))
+            .collect();
+        ranges.sort_unstable();
+        let mut covered = 0;
+        for (start, len) in ranges {
+            if start != covered {
+                return Err(format!("tiles do not cover element {}", covered.min(start)));
+            }
+            covered = start.checked_add(len).ok_or("tile end overflows")?;
+        }
+        if covered != plan.elements {
+            return Err(format!(
+                "tiles cover {} elements, expected {}",
+                covered, plan.elements
+            ));
+        }
+
+        Ok(SortPlan {
+            tile_index: plan.tile_index,
+            elements: plan.elements,
+            config: plan.config,
+        })
+    }
+}
@@ -0,0 +1,302 @@
+//! Progress reporting for long-running sorts.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::mem;
+use std::time::{Duration, Instant};
+
+/// A progress update passed to the callback of
+/// [`tilesort_with_progress`](crate::tilesort_with_progress) and
+/// [`tilesort_by_key_with_progress`](crate::tilesort_by_key_with_progress).
+///
+/// Updates arrive every few thousand elements: first a series of
+/// [`Scanning`](Progress::Scanning) updates, then a series of
+/// [`Restructuring`](Progress::Restructuring) updates. Each phase ends with an
+/// update at 100%. Slices with fewer than two elements report nothing.
+///
+/// [`sort_file_with_progress`](crate::sort_file_with_progress) reports a
+/// series of [`Spilling`](Progress::Spilling) updates instead, then a series
+/// of [`Merging`](Progress::Merging) updates, each phase starting at its
+/// first update and ending at 100%.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Progress {
+    /// Phase 1: detecting tiles.
+    Scanning {
+        /// Elements scanned so far.
+        processed: usize,
+        /// Total number of elements.
+        total: usize,
+    },
+    /// Phase 2: copying tiles into sorted order.
+    Restructuring {
+        /// Bytes copied so far (elements times `size_of::<T>()`).
+        bytes_copied: usize,
+        /// Total number of bytes to copy.
+        total_bytes: usize,
+    },
+    /// Reading the input of an external sort in runs, which are sorted and
+    /// spilled to disk as they are read.
+    Spilling {
+        /// Bytes of the input read so far, including any skipped because a
+        /// checkpoint already covers them.
+        bytes_read: u64,
+        /// Length of the input.
+        total_bytes: u64,
+    },
+    /// Merging the spilled runs of an external sort into its output.
+    Merging {
+        /// Bytes of the input merged so far, counted again by each merge
+        /// pass.
+        bytes_merged: u64,
+        /// Length of the input, times the number of merge passes.
+        total_bytes: u64,
+    },
+}
+
+impl Progress {
+    /// Completion of the current phase, from `0.0` to `100.0`.
+    pub fn percent(&self) -> f64 {
+        let (done, total) = self.done_and_total();
+        if total == 0 {
+            100.0
+        } else {
+            done as f64 * 100.0 / total as f64
+        }
+    }
+
+    /// Units of work done and in total for the current phase: elements while
+    /// scanning, bytes otherwise.
+    fn done_and_total(&self) -> (u64, u64) {
+        match *self {
+            Progress::Scanning { processed, total } => (processed as u64, total as u64),
+            Progress::Restructuring {
+                bytes_copied,
+                total_bytes,
+            } => (bytes_copied as u64, total_bytes as u64),
+            Progress::Spilling {
+                bytes_read,
+                total_bytes,
+            } => (bytes_read, total_bytes),
+            Progress::Merging {
+                bytes_merged,
+                total_bytes,
+            } => (bytes_merged, total_bytes),
+        }
+    }
+}
+
+/// Default span of recent updates [`EtaEstimator`] measures throughput over.
+pub const DEFAULT_ETA_WINDOW: Duration = Duration::from_secs(5);
+
+/// Estimated time remaining in a phase, as returned by
+/// [`EtaEstimator::update`].
+///
+/// Displays as hours, minutes and seconds, such as `1m20s` or `2h05m00s`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Eta(pub Duration);
+
+impl fmt::Display for Eta {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Round up, so a phase about to end does not show `0s`
+        let secs = self.0.as_secs() + u64::from(self.0.subsec_nanos() > 0);
+        let (hours, minutes, secs) = (secs / 3600, secs / 60 % 60, secs % 60);
+        if hours > 0 {
+            write!(f, "{}h{:02}m{:02}s", hours, minutes, secs)
+        } else if minutes > 0 {
+            write!(f, "{}m{:02}s", minutes, secs)
+        } else {
+            write!(f, "{}s", secs)
+        }
+    }
+}
+
+/// Throughput-based estimate of the time left in the current phase of a sort.
+///
+/// Feed it every [`Progress`] update from
+/// [`tilesort_with_progress`](crate::tilesort_with_progress) or
+/// [`sort_file_with_progress`](crate::sort_file_with_progress). It measures
+/// throughput over the updates of the last [`DEFAULT_ETA_WINDOW`] (or the
+/// window passed to [`with_window`](Self::with_window)), so the estimate
+/// follows changes in speed rather than averaging over the whole phase, and
+/// starts afresh when a new phase begins, as scanning and restructuring, or
+/// spilling and merging, run at very different rates.
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::{EtaEstimator, Progress, SortConfig};
+///
+/// let mut data: Vec<u32> = (0..100_000).rev().collect();
+/// let mut eta = EtaEstimator::new();
+/// tilesort::tilesort_with_progress(&mut data, &SortConfig::new(), |p| {
+///     if let Some(remaining) = eta.update(&p) {
+///         let phase = match p {
+///             Progress::Scanning { .. } => "scanned",
+///             _ => "sorted",
+///         };
+///         let _line = format!("{} {:.0}% (eta {})", phase, p.percent(), remaining);
+///     }
+/// });
+/// ```
+#[derive(Debug, Clone)]
+pub struct EtaEstimator {
+    window: Duration,
+    /// Recent updates of the current phase, oldest first, as the time and
+    /// the units of work done by then.
+    samples: VecDeque<(Instant, u64)>,
+    phase: Option<mem::Discriminant<Progress>>,
+}
+
+impl Default for EtaEstimator {
+    fn default() -> Self {
+        Self::with_window(DEFAULT_ETA_WINDOW)
+    }
+}
+
+impl EtaEstimator {
+    /// Create an estimator measuring throughput over [`DEFAULT_ETA_WINDOW`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an estimator measuring throughput over the updates of the last
+    /// `window`.
+    pub fn with_window(window: Duration) -> Self {
+        Self {
+            window,
+            samples: VecDeque::new(),
+            phase: None,
+        }
+    }
+
+    /// Record `progress` as of now, returning the estimated time left in its
+    /// phase.
+    ///
+    /// Returns `None` until the phase has made measurable progress, and a
+    /// zero [`Eta`] once it is complete.
+    pub fn update(&mut self, progress: &Progress) -> Option<Eta> {
+        self.update_at(progress, Instant::now())
+    }
+
+    /// Record `progress` as of the time `now`, returning the estimated time
+    /// left in its phase. See [`update`](Self::update).
+    pub fn update_at(&mut self, progress: &Progress, now: Instant) -> Option<Eta> {
+        let (done, total) = progress.done_and_total();
+        let phase = mem::discriminant(progress);
+        let restarted = self.samples.back().is_some_and(|&(_, last)| done < last);
+        if self.phase != Some(phase) || restarted {
+            self.phase = Some(phase);
+            self.samples.clear();
+        }
+        self.samples.push_back((now, done));
+        // Drop samples older than the window, keeping one at or before its
+        // start so the rate spans the whole window
+        while self.samples.len() > 2
+            && now.saturating_duration_since(self.samples[1].0) >= self.window
+        {
+            self.samples.pop_front();
+        }
+
+        if done >= total {
+            return Some(Eta(Duration::ZERO));
+        }
+        let rate = self.rate()?;
+        Some(Eta(Duration::from_secs_f64((total - done) as f64 / rate)))
+    }
+
+    /// Recent throughput of the current phase, in units of work per second:
+    /// elements while scanning, bytes otherwise. `None` until the
+    /// phase has made measurable progress.
+    pub fn rate(&self) -> Option<f64> {
+        let (&(start, first), &(end, last)) = (self.samples.front()?, self.samples.back()?);
+        let elapsed = end.saturating_duration_since(start).as_secs_f64();
+        if last <= first || elapsed <= 0.0 {
+            return None;
+        }
+        Some((last - first) as f64 / elapsed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scanned(processed: usize) -> Progress {
+        Progress::Scanning {
+            processed,
+            total: 1000,
+        }
+    }
+
+    #[test]
+    fn test_percent() {
+        let scanning = Progress::Scanning {
+            processed: 25,
+            total: 100,
+        };
+        assert_eq!(scanning.percent(), 25.0);
+
+        let restructuring = Progress::Restructuring {
+            bytes_copied: 64,
+            total_bytes: 64,
+        };
+        assert_eq!(restructuring.percent(), 100.0);
+    }
+
+    #[test]
+    fn test_percent_of_nothing_is_complete() {
+        let empty = Progress::Restructuring {
+            bytes_copied: 0,
+            total_bytes: 0,
+        };
+        assert_eq!(empty.percent(), 100.0);
+    }
+
+    #[test]
+    fn test_eta_from_window_rate() {
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let mut eta = EtaEstimator::with_window(Duration::from_secs(10));
+        assert_eq!(eta.update_at(&scanned(0), at(0)), None);
+        // 100 elements a second, 800 left
+        assert_eq!(
+            eta.update_at(&scanned(200), at(2)),
+            Some(Eta(Duration::from_secs(8)))
+        );
+        // Slowing to 10 a second: the first 20 seconds fall out of the window
+        eta.update_at(&scanned(300), at(20));
+        let remaining = eta.update_at(&scanned(400), at(30)).unwrap();
+        assert_eq!(eta.rate(), Some(10.0));
+        assert_eq!(remaining, Eta(Duration::from_secs(60)));
+        assert_eq!(
+            eta.update_at(&scanned(1000), at(31)),
+            Some(Eta(Duration::ZERO))
+        );
+    }
+
+    #[test]
+    fn test_eta_restarts_each_phase() {
+        let start = Instant::now();
+        let mut eta = EtaEstimator::new();
+        eta.update_at(&scanned(0), start);
+        eta.update_at(&scanned(500), start + Duration::from_secs(1));
+        let copying = Progress::Restructuring {
+            bytes_copied: 0,
+            total_bytes: 8000,
+        };
+        assert_eq!(
+            eta.update_at(&copying, start + Duration::from_secs(2)),
+            None
+        );
+        assert_eq!(eta.rate(), None);
+    }
+
+    #[test]
+    fn test_eta_display() {
+        let show = |secs: f64| Eta(Duration::from_secs_f64(secs)).to_string();
+        assert_eq!(show(0.0), "0s");
+        assert_eq!(show(0.2), "1s");
+        assert_eq!(show(80.0), "1m20s");
+        assert_eq!(show(7500.0), "2h05m00s");
+    }
+}
@@ -0,0 +1,120 @@
+//! Vectorized run-boundary detection for primitive keys.
+//!
+//! The scan looks for descent points: positions where a key is smaller than
+//! its predecessor (larger, for descending sorts). For primitive integers the
+//! comparisons are done a block of [`LANES`] adjacent pairs at a time with no
+//! early exit inside the block, a shape LLVM lowers to packed vector compares
+//! on every target with SIMD registers. Only a block that contains a descent
+//! is rescanned element by element.
+
+/// Number of adjacent pairs compared per block.
+const LANES: usize = 16;
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// Primitive integer keys eligible for the vectorized scan of
+/// [`tilesort_simd`](crate::tilesort_simd).
+///
+/// Implemented for the fixed-width integer types. Floating-point data can be
+/// sorted by mapping each value to its total-order bit pattern
+/// (`f64::to_bits` with the sign handling of `f64::total_cmp`) first.
+pub trait SimdKey: sealed::Sealed + Ord + Copy {}
+
+macro_rules! impl_simd_key {
+    ($($ty:ty),*) => {
+        $(
+            impl sealed::Sealed for $ty {}
+            impl SimdKey for $ty {}
+        )*
+    };
+}
+
+impl_simd_key!(i8, i16, i32, i64, u8, u16, u32, u64);
+
+/// Index of the first element after `start` that ends the run beginning at
+/// `start`, or `keys.len()` if the run extends to the end.
+pub(crate) fn run_end<K: SimdKey>(keys: &[K], start: usize, reverse: bool) -> usize {
+    if reverse {
+        run_end_by(keys, start, |prev, next| next > prev)
+    } else {
+        run_end_by(keys, start, |prev, next| next < prev)
+    }
+}
+
+fn run_end_by<K: SimdKey>(keys: &[K], start: usize, breaks: impl Fn(K, K) -> bool) -> usize {
+    let mut idx = start + 1;
+
+    // Skip whole blocks without a descent
+    while idx + LANES <= keys.len() {
+        let prev = &keys[idx - 1..idx - 1 + LANES];
+        let next = &keys[idx..idx + LANES];
+        let any_break = prev
+            .iter()
+            .zip(next)
+            .fold(false, |found, (&p, &n)| found | breaks(p, n));
+        if any_break {
+            break;
+        }
+        idx += LANES;
+    }
+
+    // Locate the descent within the block, or finish the tail
+    while idx < keys.len() {
+        if breaks(keys[idx - 1], keys[idx]) {
+            return idx;
+        }
+        idx += 1;
+    }
+    keys.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_end_scalar(keys: &[i32], start: usize, reverse: bool) -> usize {
+        (start + 1..keys.len())
+            .find(|&idx| {
+                if reverse {
+                    keys[idx] > keys[idx - 1]
+                } else {
+                    keys[idx] < keys[idx - 1]
+                }
+            })
+            .unwrap_or(keys.len())
+    }
+
+    #[test]
+    fn test_run_end_matches_scalar() {
+        let keys: Vec<i32> = (0..200).map(|i| (i * 37) % 53).collect();
+        for reverse in [false, true] {
+            for start in 0..keys.len() {
+                assert_eq!(
+                    run_end(&keys, start, reverse),
+                    run_end_scalar(&keys, start, reverse),
+                    "start {} reverse {}",
+                    start,
+                    reverse
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_run_end_long_run() {
+        let mut keys: Vec<u64> = (0..100).collect();
+        assert_eq!(run_end(&keys, 0, false), 100);
+        keys[70] = 0;
+        assert_eq!(run_end(&keys, 0, false), 70);
+        assert_eq!(run_end(&keys, 70, false), 100);
+    }
+
+    #[test]
+    fn test_run_end_equal_keys_continue() {
+        let keys = [5u8; 40];
+        assert_eq!(run_end(&keys, 0, false), 40);
+        assert_eq!(run_end(&keys, 0, true), 40);
+    }
+}
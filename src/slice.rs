@@ -0,0 +1,141 @@
+//! Drop-in replacements for the standard library's slice sorts.
+//!
+//! Each function here has the name, generic bounds and behaviour of the
+//! `[T]` method it is named after, backed by tilesort, so switching a call
+//! site is a matter of `v.sort_by_key(f)` becoming
+//! `tilesort::slice::sort_by_key(&mut v, f)`. None of them need `T: Clone`:
+//! elements are moved into place with swaps.
+//!
+//! ```
+//! use tilesort::slice;
+//!
+//! let mut v = vec![-5i32, 4, 1, -3, 2];
+//! slice::sort_by_key(&mut v, |k| k.abs());
+//! assert_eq!(v, [1, 2, -3, 4, -5]);
+//!
+//! slice::sort_by(&mut v, |a, b| b.cmp(a));
+//! assert_eq!(v, [4, 2, 1, -3, -5]);
+//! ```
+//!
+//! As with the standard library, the `unstable` variants may reorder equal
+//! elements; here that allows fewer tile splits rather than an in-place
+//! quicksort.
+
+use std::cmp::Ordering;
+
+use crate::config::SortConfig;
+use crate::container::Pairs;
+use crate::sorter;
+
+/// Sort the slice, preserving the order of equal elements. Mirrors
+/// [`slice::sort`](https://doc.rust-lang.org/std/primitive.slice.html#method.sort).
+pub fn sort<T: Ord>(v: &mut [T]) {
+    sorter::tilesort_container_impl(v, &SortConfig::new());
+}
+
+/// Sort the slice with a comparison function, preserving the order of equal
+/// elements. Mirrors
+/// [`slice::sort_by`](https://doc.rust-lang.org/std/primitive.slice.html#method.sort_by).
+pub fn sort_by<T, F>(v: &mut [T], compare: F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    sorter::tilesort_by_comparator_impl(v, compare, &SortConfig::new());
+}
+
+/// Sort the slice with a key extraction function, preserving the order of
+/// equal elements. Mirrors
+/// [`slice::sort_by_key`](https://doc.rust-lang.org/std/primitive.slice.html#method.sort_by_key).
+///
+/// The key function is called exactly once per element.
+pub fn sort_by_key<T, K, F>(v: &mut [T], f: F)
+where
+    F: FnMut(&T) -> K,
+    K: Ord,
+{
+    sort_by_extracted_keys(v, f, &SortConfig::new());
+}
+
+/// Sort the slice with a key extraction function, calling it once per
+/// element. Mirrors
+/// [`slice::sort_by_cached_key`](https://doc.rust-lang.org/std/primitive.slice.html#method.sort_by_cached_key);
+/// identical to [`sort_by_key`], which never calls the key function twice
+/// either.
+pub fn sort_by_cached_key<T, K, F>(v: &mut [T], f: F)
+where
+    F: FnMut(&T) -> K,
+    K: Ord,
+{
+    sort_by_key(v, f);
+}
+
+/// Sort the slice, possibly reordering equal elements. Mirrors
+/// [`slice::sort_unstable`](https://doc.rust-lang.org/std/primitive.slice.html#method.sort_unstable).
+pub fn sort_unstable<T: Ord>(v: &mut [T]) {
+    sorter::tilesort_container_impl(v, &SortConfig::new().unstable());
+}
+
+/// Sort the slice with a comparison function, possibly reordering equal
+/// elements. Mirrors
+/// [`slice::sort_unstable_by`](https://doc.rust-lang.org/std/primitive.slice.html#method.sort_unstable_by).
+pub fn sort_unstable_by<T, F>(v: &mut [T], compare: F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    sorter::tilesort_by_comparator_impl(v, compare, &SortConfig::new().unstable());
+}
+
+/// Sort the slice with a key extraction function, possibly reordering equal
+/// elements. Mirrors
+/// [`slice::sort_unstable_by_key`](https://doc.rust-lang.org/std/primitive.slice.html#method.sort_unstable_by_key).
+pub fn sort_unstable_by_key<T, K, F>(v: &mut [T], f: F)
+where
+    F: FnMut(&T) -> K,
+    K: Ord,
+{
+    sort_by_extracted_keys(v, f, &SortConfig::new().unstable());
+}
+
+/// Extract every key once, then sort the keys and `v` together.
+fn sort_by_extracted_keys<T, K, F>(v: &mut [T], f: F, config: &SortConfig)
+where
+    F: FnMut(&T) -> K,
+    K: Ord,
+{
+    let mut keys: Vec<K> = v.iter().map(f).collect();
+    sorter::tilesort_container_impl(
+        &mut Pairs {
+            keys: &mut keys,
+            values: v,
+        },
+        config,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Element that can be neither cloned nor copied.
+    #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+    struct Owned(u32);
+
+    #[test]
+    fn test_sort_without_clone() {
+        let mut v: Vec<Owned> = [3, 1, 2].into_iter().map(Owned).collect();
+        sort(&mut v);
+        assert_eq!(v, vec![Owned(1), Owned(2), Owned(3)]);
+    }
+
+    #[test]
+    fn test_key_function_may_mutate() {
+        let mut calls = 0;
+        let mut v = vec![5, 6, 7, 1, 2];
+        sort_by_key(&mut v, |&x| {
+            calls += 1;
+            x
+        });
+        assert_eq!(v, vec![1, 2, 5, 6, 7]);
+        assert_eq!(calls, 5);
+    }
+}
@@ -0,0 +1,102 @@
+//! Insertion sort for slices too short for a tile index to pay off.
+
+use crate::array::STACK_SORT_MAX_LEN;
+use crate::config::SortConfig;
+use crate::pattern::Shortcut;
+use crate::tile_index::precedes;
+
+/// Default for [`SortConfig::insertion_sort_threshold`].
+pub(crate) const INSERTION_SORT_THRESHOLD: usize = 32;
+
+/// Source positions of a permutation, on the stack for short inputs.
+// Boxing the inline variant would defeat its purpose
+#[allow(clippy::large_enum_variant)]
+pub(crate) enum Sources {
+    Inline([usize; STACK_SORT_MAX_LEN], usize),
+    Heap(Vec<usize>),
+}
+
+impl AsRef<[usize]> for Sources {
+    fn as_ref(&self) -> &[usize] {
+        match self {
+            Sources::Inline(sources, len) => &sources[..*len],
+            Sources::Heap(sources) => sources,
+        }
+    }
+}
+
+impl AsMut<[usize]> for Sources {
+    fn as_mut(&mut self) -> &mut [usize] {
+        match self {
+            Sources::Inline(sources, len) => &mut sources[..*len],
+            Sources::Heap(sources) => sources,
+        }
+    }
+}
+
+/// Sort `keys` by insertion if there are fewer than the configured
+/// threshold, returning the permutation that sorts them.
+///
+/// The elements are not touched: the insertion sort runs over their
+/// positions, so a panicking comparison leaves the input as it was.
+pub(crate) fn insertion_sort<K: Ord>(keys: &[K], config: &SortConfig) -> Option<Shortcut> {
+    if keys.len() >= config.insertion_sort_threshold {
+        return None;
+    }
+    let mut sources = if keys.len() <= STACK_SORT_MAX_LEN {
+        Sources::Inline([0; STACK_SORT_MAX_LEN], keys.len())
+    } else {
+        Sources::Heap(vec![0; keys.len()])
+    };
+    let positions = sources.as_mut();
+    let reverse = config.order.is_descending();
+
+    for idx in 0..positions.len() {
+        // Shift earlier positions up past every key this one precedes;
+        // equal keys stay in front, keeping the sort stable
+        let mut slot = idx;
+        while slot > 0 && precedes(&keys[idx], &keys[positions[slot - 1]], reverse) {
+            positions[slot] = positions[slot - 1];
+            slot -= 1;
+        }
+        positions[slot] = idx;
+    }
+    let shortcut = Shortcut::Permute(sources);
+    #[cfg(feature = "verify")]
+    crate::verify::verify_shortcut(&shortcut, keys, reverse);
+    Some(shortcut)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sources_of(keys: &[i32], config: &SortConfig) -> Vec<usize> {
+        match insertion_sort(keys, config) {
+            Some(Shortcut::Permute(mut sources)) => sources.as_mut().to_vec(),
+            _ => panic!("expected an insertion sort"),
+        }
+    }
+
+    #[test]
+    fn test_stable_permutation() {
+        let keys = [3, 1, 2, 1, 3];
+        assert_eq!(sources_of(&keys, &SortConfig::new()), vec![1, 3, 2, 0, 4]);
+        assert_eq!(
+            sources_of(&keys, &SortConfig::new().descending()),
+            vec![0, 4, 2, 1, 3]
+        );
+    }
+
+    #[test]
+    fn test_threshold() {
+        let keys: Vec<i32> = (0..100).rev().collect();
+        assert!(insertion_sort(&keys, &SortConfig::new()).is_none());
+        let config = SortConfig::new().insertion_sort_threshold(101);
+        assert_eq!(
+            sources_of(&keys, &config),
+            (0..100).rev().collect::<Vec<_>>()
+        );
+        assert!(insertion_sort(&keys[..3], &config.insertion_sort_threshold(0)).is_none());
+    }
+}
@@ -1,55 +1,1350 @@
 //! Core tilesort algorithm implementation.
+//!
+//! # Panic safety
+//!
+//! User code runs in three places: key extraction, key comparison (`Ord`) and
+//! element cloning. The first two only happen during the scan phase, before
+//! `data` is touched. Cloning happens during restructuring into a separate
+//! buffer, which is moved into `data` only once it is complete. A panic from
+//! any of them therefore unwinds with `data` in its original order and every
+//! element dropped exactly once.
+//!
+//! With the `verify` feature, the finished tile index, or the order a
+//! shortcut or counting sort found, is checked against the keys before any
+//! element moves, so a splitting bug panics the same way.
+//!
+//! Cancellation takes the same exit: the token is polled during the scan and
+//! while filling the restructure buffer, never while moving elements back.
 
-use crate::key_extractor::KeyExtractor;
-use crate::tile_index::{Tile, TileIndex};
-use log::{debug, info};
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::{HashMap, VecDeque};
+use std::mem;
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+use crate::cancel::{self, CancellationToken, Cancelled, CHECK_INTERVAL};
+use crate::columns::{Column, KeyColumn, Row};
+use crate::config::{KeyCost, SortConfig, Strategy};
+use crate::container::{apply_permutation, permute_with, RandomAccess};
+use crate::integer::{counting_sort_order, sort_random_segments, IntegerKey};
+use crate::key_extractor::{KeyExtractor, TryKeyExtractor};
+use crate::keys::{MemcmpOrd, PrefixKey};
+use crate::logging::{debug, info};
+use crate::observer::Observer;
+use crate::opcount;
+use crate::pattern::{self, Shortcut};
+use crate::plan::InvalidRuns;
+use crate::progress::Progress;
+use crate::small;
+use crate::tile_index::{precedes, Tile, TileIndex};
+
+/// Cancellation and progress hooks, polled every [`CHECK_INTERVAL`] elements,
+/// and an observer told of every phase and tile.
+#[derive(Default)]
+pub(crate) struct Hooks<'a> {
+    pub(crate) cancel: Option<&'a CancellationToken>,
+    pub(crate) progress: Option<&'a mut dyn FnMut(Progress)>,
+    pub(crate) observer: Option<&'a mut dyn Observer>,
+}
+
+impl Hooks<'_> {
+    /// Fail if the sort was cancelled, otherwise report `progress`.
+    fn checkpoint(&mut self, progress: Progress) -> Result<(), Cancelled> {
+        cancel::check(self.cancel)?;
+        if let Some(report) = self.progress.as_mut() {
+            report(progress);
+        }
+        Ok(())
+    }
+
+    /// Pass an event to the observer, if there is one.
+    fn observe(&mut self, event: impl FnOnce(&mut dyn Observer)) {
+        if let Some(observer) = self.observer.as_deref_mut() {
+            event(observer);
+        }
+    }
+
+    /// Insert a tile found by the scan, telling the observer of it and of
+    /// any splits.
+    fn insert_tile<K: Ord>(
+        &mut self,
+        tile_index: &mut TileIndex,
+        tile: Tile,
+        element_keys: &[K],
+        reverse: bool,
+    ) {
+        match self.observer.as_deref_mut() {
+            None => tile_index.insert_tile(tile, element_keys, reverse),
+            Some(observer) => {
+                observer.on_tile_detected(tile.start()..tile.start() + tile.len());
+                let on_split = &mut |at| observer.on_split(at);
+                tile_index.insert_tile_observed(tile, element_keys, reverse, on_split);
+            }
+        }
+    }
+}
 
 /// Main tilesort implementation with custom key extraction.
 ///
-/// # Arguments
-/// * `data` - The slice to sort
-/// * `key_extractor` - Extracts sort keys from elements
-/// * `reverse` - If true, sort in descending order; if false, ascending
-pub(crate) fn tilesort_impl_with_key<T, K, E>(data: &mut [T], key_extractor: E, reverse: bool)
+/// # Arguments
+/// * `data` - The slice to sort
+/// * `key_extractor` - Extracts sort keys from elements
+/// * `config` - Sort options (direction, ...)
+pub(crate) fn tilesort_impl_with_key<T, K, E>(
+    data: &mut [T],
+    key_extractor: &E,
+    config: &SortConfig,
+) where
+    T: Clone,
+    K: Ord,
+    E: KeyExtractor<T, K>,
+{
+    never_cancelled(tilesort_impl_with_key_hooks(
+        data,
+        key_extractor,
+        config,
+        &mut Hooks::default(),
+    ));
+}
+
+/// Tilesort with custom key extraction, observed through `hooks`.
+pub(crate) fn tilesort_impl_with_key_hooks<T, K, E>(
+    data: &mut [T],
+    key_extractor: &E,
+    config: &SortConfig,
+    hooks: &mut Hooks<'_>,
+) -> Result<(), Cancelled>
+where
+    T: Clone,
+    K: Ord,
+    E: KeyExtractor<T, K>,
+{
+    if data.len() <= 1 {
+        return cancel::check(hooks.cancel);
+    }
+    if let Some(shortcut) = insertion_shortcut(data, key_extractor, config) {
+        return take_shortcut(data, shortcut, hooks);
+    }
+    let config = &resolve_key_cost(config, || time_sample_keys(data, key_extractor));
+    // Only keys compared in place are sampled, so no key is extracted twice
+    let shortcut = key_extractor
+        .borrowed_keys(data)
+        .and_then(|keys| pattern_shortcut::<T, K>(keys, config));
+    if let Some(shortcut) = shortcut {
+        return take_shortcut(data, shortcut, hooks);
+    }
+
+    // Phase 1: Scan and build tile index
+    let tile_index = scan_phase(data, key_extractor, config, hooks)?;
+
+    // Phase 2: Restructure using the tile index
+    restructure(data, &tile_index, config, hooks)?;
+    Ok(())
+}
+
+/// Insertion sort `data` by key if it is shorter than the configured
+/// threshold.
+fn insertion_shortcut<T, K, E>(
+    data: &[T],
+    key_extractor: &E,
+    config: &SortConfig,
+) -> Option<Shortcut>
+where
+    K: Ord,
+    E: KeyExtractor<T, K>,
+{
+    if data.len() >= config.insertion_sort_threshold {
+        return None;
+    }
+    match key_extractor.borrowed_keys(data) {
+        Some(keys) => small::insertion_sort(keys, config),
+        None => {
+            let keys: Vec<K> = data
+                .iter()
+                .map(|element| opcount::extract_key(key_extractor, element))
+                .collect();
+            small::insertion_sort(&keys, config)
+        }
+    }
+}
+
+/// Key extractor comparing elements in place, for sorts that need no
+/// `Clone` bound. Only its borrowed keys are ever used.
+pub(crate) struct InPlaceKey;
+
+impl<T> KeyExtractor<T, T> for InPlaceKey {
+    fn extract_key(&self, _item: &T) -> T {
+        unreachable!("in-place keys are always borrowed")
+    }
+
+    fn borrowed_keys<'a>(&self, data: &'a [T]) -> Option<&'a [T]> {
+        Some(data)
+    }
+}
+
+/// How to read `data` in sorted order without rearranging it.
+// Returned once per sort and consumed at once, so never worth boxing
+#[allow(clippy::large_enum_variant)]
+enum ReadOrder {
+    /// Found by insertion sort or pattern detection
+    Shortcut(Shortcut),
+    /// Found by scanning
+    Tiles(TileIndex),
+}
+
+/// The sorted order of `data`, for the sorts that leave it in place.
+fn read_order<T, K, E>(data: &[T], key_extractor: &E, config: &SortConfig) -> ReadOrder
+where
+    K: Ord,
+    E: KeyExtractor<T, K>,
+{
+    let shortcut = if data.len() <= 1 {
+        Some(Shortcut::Sorted)
+    } else {
+        insertion_shortcut(data, key_extractor, config).or_else(|| {
+            key_extractor
+                .borrowed_keys(data)
+                .and_then(|keys| pattern_shortcut::<T, K>(keys, config))
+        })
+    };
+    match shortcut {
+        Some(shortcut) => ReadOrder::Shortcut(shortcut),
+        None => ReadOrder::Tiles(never_cancelled(scan_phase(
+            data,
+            key_extractor,
+            config,
+            &mut Hooks::default(),
+        ))),
+    }
+}
+
+/// Sorted copy of `data`, cloning each element once, straight into its
+/// sorted position.
+///
+/// Sorting a copy in place would clone every element twice: once for the
+/// copy and once more into the restructuring buffer.
+pub(crate) fn tilesorted_impl_with_key<T, K, E>(
+    data: &[T],
+    key_extractor: &E,
+    config: &SortConfig,
+) -> Vec<T>
+where
+    T: Clone,
+    K: Ord,
+    E: KeyExtractor<T, K>,
+{
+    copy_in_order(data, read_order(data, key_extractor, config))
+}
+
+/// `data` itself if it is already sorted, and a sorted copy otherwise.
+pub(crate) fn tilesorted_cow_impl<'a, T, K, E>(
+    data: &'a [T],
+    key_extractor: &E,
+    config: &SortConfig,
+) -> Cow<'a, [T]>
+where
+    T: Clone,
+    K: Ord,
+    E: KeyExtractor<T, K>,
+{
+    match read_order(data, key_extractor, config) {
+        ReadOrder::Shortcut(shortcut) if shortcut.is_identity() => Cow::Borrowed(data),
+        ReadOrder::Tiles(tile_index) if tile_index.len() <= 1 => Cow::Borrowed(data),
+        order => Cow::Owned(copy_in_order(data, order)),
+    }
+}
+
+/// Clone the elements of `data` into a new vector in `order`.
+fn copy_in_order<T: Clone>(data: &[T], order: ReadOrder) -> Vec<T> {
+    let sorted = match order {
+        ReadOrder::Shortcut(shortcut) => {
+            // Shortcuts only move elements, so apply them to a plain copy
+            let mut sorted = data.to_vec();
+            shortcut.apply(&mut sorted);
+            sorted
+        }
+        ReadOrder::Tiles(tile_index) => {
+            info!("Copying {} tiles", tile_index.len());
+            let mut sorted = Vec::with_capacity(data.len());
+            for tile in tile_index.iter() {
+                sorted.extend_from_slice(&data[tile.range()]);
+            }
+            sorted
+        }
+    };
+    opcount::element_clones(data.len());
+    sorted
+}
+
+/// References to the elements of `data` in sorted order, without cloning or
+/// moving any element.
+pub(crate) fn sorted_refs_impl<'a, T, K, E>(
+    data: &'a [T],
+    key_extractor: &E,
+    config: &SortConfig,
+) -> Vec<&'a T>
+where
+    K: Ord,
+    E: KeyExtractor<T, K>,
+{
+    match read_order(data, key_extractor, config) {
+        ReadOrder::Shortcut(shortcut) => shortcut.sorted_refs(data),
+        ReadOrder::Tiles(tile_index) => tile_index
+            .iter()
+            .flat_map(|tile| &data[tile.range()])
+            .collect(),
+    }
+}
+
+/// Sort the fixed-width records packed in `buf` by the bytes at `key_range`
+/// within each record, moving whole records.
+///
+/// Keys are compared in place as byte slices. Each tile is copied to a
+/// buffer with a single copy, and the buffer copied back over `buf`.
+pub(crate) fn sort_records_impl(
+    buf: &mut [u8],
+    record_len: usize,
+    key_range: Range<usize>,
+    config: &SortConfig,
+) {
+    assert!(record_len > 0, "record length must be positive");
+    assert_eq!(
+        buf.len() % record_len,
+        0,
+        "buffer length is not a multiple of the record length"
+    );
+    assert!(
+        key_range.start <= key_range.end && key_range.end <= record_len,
+        "key range is not within a record"
+    );
+
+    let keys: Vec<&[u8]> = buf
+        .chunks_exact(record_len)
+        .map(|record| &record[key_range.clone()])
+        .collect();
+    let order = read_order(&keys, &InPlaceKey, config);
+    drop(keys);
+
+    let mut sorted = Vec::with_capacity(buf.len());
+    match order {
+        ReadOrder::Shortcut(shortcut) if shortcut.is_identity() => return,
+        ReadOrder::Tiles(tile_index) if tile_index.len() <= 1 => return,
+        ReadOrder::Shortcut(shortcut) => {
+            for source in shortcut.sources(buf.len() / record_len) {
+                sorted.extend_from_slice(&buf[source * record_len..(source + 1) * record_len]);
+            }
+        }
+        ReadOrder::Tiles(tile_index) => {
+            info!("Copying {} tiles of records", tile_index.len());
+            for tile in tile_index.iter() {
+                let range = tile.range();
+                sorted.extend_from_slice(&buf[range.start * record_len..range.end * record_len]);
+            }
+        }
+    }
+    buf.copy_from_slice(&sorted);
+}
+
+/// Input position of the element at each sorted position of `data`.
+pub(crate) fn sorted_sources<T, K, E>(
+    data: &[T],
+    key_extractor: &E,
+    config: &SortConfig,
+) -> Vec<usize>
+where
+    K: Ord,
+    E: KeyExtractor<T, K>,
+{
+    match read_order(data, key_extractor, config) {
+        ReadOrder::Shortcut(shortcut) => shortcut.sources(data.len()),
+        ReadOrder::Tiles(tile_index) => tile_index.sorted_indices().collect(),
+    }
+}
+
+/// Sort `data` in place and return the sorted position of each element's
+/// input position.
+///
+/// The sorted order is found without moving anything and then applied with
+/// swaps, so elements are never cloned.
+pub(crate) fn sort_with_permutation_impl<T, K, E>(
+    data: &mut [T],
+    key_extractor: &E,
+    config: &SortConfig,
+) -> Vec<usize>
+where
+    K: Ord,
+    E: KeyExtractor<T, K>,
+{
+    let sources = sorted_sources(data, key_extractor, config);
+    let mut permutation = vec![0; data.len()];
+    for (position, &source) in sources.iter().enumerate() {
+        permutation[source] = position;
+    }
+    apply_permutation(data, sources);
+    permutation
+}
+
+/// Tilesort implementation with fallible key extraction.
+///
+/// All keys are extracted before any element is moved, so on error `data` is
+/// left in its original order.
+pub(crate) fn try_tilesort_impl_with_key<T, K, Error, E>(
+    data: &mut [T],
+    key_extractor: &E,
+    config: &SortConfig,
+) -> Result<(), Error>
+where
+    T: Clone,
+    K: Ord,
+    E: TryKeyExtractor<T, K, Error>,
+{
+    if data.len() <= 1 {
+        // Still validate the key so a lone malformed element is reported
+        if let Some(element) = data.first() {
+            opcount::try_extract_key(key_extractor, element)?;
+        }
+        return Ok(());
+    }
+
+    let started = Instant::now();
+    let element_keys = data
+        .iter()
+        .map(|element| opcount::try_extract_key(key_extractor, element))
+        .collect::<Result<Vec<K>, Error>>()?;
+    let elapsed = started.elapsed();
+    let config = &resolve_key_cost(config, || (elapsed, element_keys.len()));
+
+    // Phase 1: Scan the extracted keys and build tile index
+    let tile_index = never_cancelled(scan_phase_without_key(
+        &element_keys,
+        config,
+        &mut Hooks::default(),
+    ));
+
+    // Phase 2: Restructure using the tile index
+    never_cancelled(restructure(
+        data,
+        &tile_index,
+        config,
+        &mut Hooks::default(),
+    ));
+
+    Ok(())
+}
+
+/// Tilesort by precomputed keys, where `keys[i]` belongs to `data[i]`.
+///
+/// # Panics
+/// Panics if the lengths of `data` and `keys` differ.
+pub(crate) fn tilesort_impl_with_keys<T: Clone, K: Ord>(
+    data: &mut [T],
+    keys: &[K],
+    config: &SortConfig,
+) {
+    assert_eq!(
+        data.len(),
+        keys.len(),
+        "keys must have the same length as data"
+    );
+    if data.len() <= 1 {
+        return;
+    }
+
+    // Phase 1: Scan the given keys and build tile index
+    let tile_index = never_cancelled(scan_phase_without_key(keys, config, &mut Hooks::default()));
+
+    // Phase 2: Restructure using the tile index
+    never_cancelled(restructure(
+        data,
+        &tile_index,
+        config,
+        &mut Hooks::default(),
+    ));
+}
+
+/// Number of keys timed up front for [`KeyCost::Auto`].
+const KEY_COST_SAMPLE: usize = 16;
+
+/// Mean extraction time above which [`KeyCost::Auto`] treats keys as expensive.
+const EXPENSIVE_KEY: Duration = Duration::from_micros(1);
+
+/// `config` with indirect restructuring switched on if its keys are expensive.
+///
+/// `measure` is only called for [`KeyCost::Auto`] and returns how long it took
+/// to extract how many keys.
+pub(crate) fn resolve_key_cost(
+    config: &SortConfig,
+    measure: impl FnOnce() -> (Duration, usize),
+) -> SortConfig {
+    let expensive = match config.key_cost {
+        KeyCost::Cheap => false,
+        KeyCost::Expensive => true,
+        KeyCost::Auto => {
+            let (elapsed, keys) = measure();
+            elapsed.as_nanos() > EXPENSIVE_KEY.as_nanos() * keys as u128
+        }
+    };
+    if expensive && !config.indirect {
+        debug!("Key extraction is expensive, restructuring indirectly");
+    }
+    config.clone().indirect(config.indirect || expensive)
+}
+
+/// Time the extraction of the first few keys of `data`.
+fn time_sample_keys<T, K, E: KeyExtractor<T, K>>(
+    data: &[T],
+    key_extractor: &E,
+) -> (Duration, usize) {
+    let sample = &data[..data.len().min(KEY_COST_SAMPLE)];
+    let started = Instant::now();
+    for element in sample {
+        drop(opcount::extract_key(key_extractor, element));
+    }
+    (started.elapsed(), sample.len())
+}
+
+/// Main tilesort implementation (no custom key function).
+///
+/// # Arguments
+/// * `data` - The slice to sort
+/// * `config` - Sort options (direction, ...)
+pub(crate) fn tilesort_impl<T: Ord + Clone>(data: &mut [T], config: &SortConfig) {
+    never_cancelled(tilesort_impl_hooks(data, config, &mut Hooks::default()));
+}
+
+/// Tilesort (no custom key function), observed through `hooks`.
+pub(crate) fn tilesort_impl_hooks<T: Ord + Clone>(
+    data: &mut [T],
+    config: &SortConfig,
+    hooks: &mut Hooks<'_>,
+) -> Result<(), Cancelled> {
+    if data.len() <= 1 {
+        return cancel::check(hooks.cancel);
+    }
+    let shortcut =
+        small::insertion_sort(data, config).or_else(|| pattern_shortcut::<T, T>(data, config));
+    if let Some(shortcut) = shortcut {
+        return take_shortcut(data, shortcut, hooks);
+    }
+
+    // Phase 1: Scan and build tile index
+    let tile_index = scan_phase_without_key(data, config, hooks)?;
+
+    // Phase 2: Restructure using the tile index
+    restructure(data, &tile_index, config, hooks)?;
+    Ok(())
+}
+
+/// A cheaper sort than the tile index for `keys`, the keys of a slice of
+/// `T`, if [`pattern::shortcut`] finds one within the memory budget.
+fn pattern_shortcut<T, K: Ord>(keys: &[K], config: &SortConfig) -> Option<Shortcut> {
+    let within_budget = config.strategy::<T>(keys.len()) != Strategy::Rotate;
+    pattern::shortcut(keys, config, within_budget)
+}
+
+/// Finish a sort through `shortcut`, reporting both phases as complete.
+fn take_shortcut<T>(
+    data: &mut [T],
+    shortcut: Shortcut,
+    hooks: &mut Hooks<'_>,
+) -> Result<(), Cancelled> {
+    hooks.observe(|observer| observer.on_scan_start(data.len()));
+    hooks.checkpoint(scan_progress(0, data.len()))?;
+    hooks.checkpoint(scan_progress(data.len(), data.len()))?;
+    hooks.observe(|observer| observer.on_scan_end());
+    hooks.observe(|observer| observer.on_restructure_start());
+    shortcut.apply(data);
+    if let Some(report) = hooks.progress.as_mut() {
+        report(restructure_progress::<T>(data.len(), data.len()));
+    }
+    hooks.observe(|observer| observer.on_restructure_end());
+    Ok(())
+}
+
+/// Tilesort by [`PrefixKey`]s borrowed from the elements.
+pub(crate) fn tilesort_str_prefix_impl<T, F>(data: &mut [T], key_fn: &F, config: &SortConfig)
+where
+    T: Clone,
+    F: for<'a> Fn(&'a T) -> &'a str,
+{
+    if data.len() <= 1 {
+        return;
+    }
+
+    // Phase 1: Scan the prefix keys, which borrow `data` until the scan is done
+    let tile_index = {
+        let element_keys: Vec<PrefixKey<'_>> = data
+            .iter()
+            .map(|element| PrefixKey::new(key_fn(element)))
+            .collect();
+        never_cancelled(scan_phase_without_key(
+            &element_keys,
+            config,
+            &mut Hooks::default(),
+        ))
+    };
+
+    // Phase 2: Restructure using the tile index
+    never_cancelled(restructure(
+        data,
+        &tile_index,
+        config,
+        &mut Hooks::default(),
+    ));
+}
+
+/// Tilesort by bytes borrowed from each element, compared lexicographically.
+pub(crate) fn tilesort_memcmp_impl<T, K, F>(data: &mut [T], key_fn: &F, config: &SortConfig)
+where
+    T: Clone,
+    K: MemcmpOrd + ?Sized,
+    F: for<'a> Fn(&'a T) -> &'a K,
+{
+    if data.len() <= 1 {
+        return;
+    }
+
+    // Phase 1: Scan the byte keys, which borrow `data` until the scan is done
+    let tile_index = {
+        let element_keys: Vec<&[u8]> = data
+            .iter()
+            .map(|element| key_fn(element).ordered_bytes())
+            .collect();
+        never_cancelled(scan_phase_without_key(
+            &element_keys,
+            config,
+            &mut Hooks::default(),
+        ))
+    };
+
+    // Phase 2: Restructure using the tile index
+    never_cancelled(restructure(
+        data,
+        &tile_index,
+        config,
+        &mut Hooks::default(),
+    ));
+}
+
+/// Tilesort by an integer key, counting the keys instead when their range is
+/// small enough (see [`counting_sort_order`]) and radix sorting stretches of
+/// random keys before the scan (see [`sort_random_segments`]).
+pub(crate) fn tilesort_integer_impl_with_key<T, K, E>(
+    data: &mut [T],
+    key_extractor: &E,
+    config: &SortConfig,
+) where
+    T: Clone,
+    K: IntegerKey,
+    E: KeyExtractor<T, K>,
+{
+    if data.len() <= 1 {
+        return;
+    }
+
+    let mut element_keys: Vec<K> = data
+        .iter()
+        .map(|element| opcount::extract_key(key_extractor, element))
+        .collect();
+    if let Some(sources) = counting_sort_order(&element_keys, config) {
+        debug!("Key range is small, counting sort");
+        #[cfg(feature = "verify")]
+        crate::verify::verify_order(&sources, &element_keys, config.order.is_descending());
+        drop(element_keys);
+        apply_permutation(data, sources);
+        return;
+    }
+
+    let reverse = config.order.is_descending();
+    let segments = sort_random_segments(&mut element_keys, data, reverse);
+    debug!("Radix sorted {} random segments", segments);
+
+    // Phase 1: Scan the extracted keys and build tile index
+    let tile_index = never_cancelled(scan_phase_without_key(
+        &element_keys,
+        config,
+        &mut Hooks::default(),
+    ));
+    drop(element_keys);
+
+    // Phase 2: Restructure using the tile index
+    never_cancelled(restructure(
+        data,
+        &tile_index,
+        config,
+        &mut Hooks::default(),
+    ));
+}
+
+/// Tilesort of primitive integers using the vectorized scan.
+#[cfg(feature = "simd")]
+pub(crate) fn tilesort_simd_impl<T: crate::simd::SimdKey>(data: &mut [T], config: &SortConfig) {
+    if data.len() <= 1 {
+        return;
+    }
+
+    // Phase 1: Scan and build tile index
+    let tile_index = never_cancelled(scan_phase_simd(data, config, &mut Hooks::default()));
+
+    // Phase 2: Restructure using the tile index
+    never_cancelled(restructure(
+        data,
+        &tile_index,
+        config,
+        &mut Hooks::default(),
+    ));
+}
+
+/// Phase 1 on its own, for [`SortPlan`](crate::SortPlan).
+pub(crate) fn scan_plan<T, K, E>(data: &[T], key_extractor: &E, config: &SortConfig) -> TileIndex
+where
+    K: Ord,
+    E: KeyExtractor<T, K>,
+{
+    if data.is_empty() {
+        return TileIndex::new(config.stable, config.inline_tiles);
+    }
+    never_cancelled(scan_phase(
+        data,
+        key_extractor,
+        config,
+        &mut Hooks::default(),
+    ))
+}
+
+/// Extend a tile index covering `data[..scanned]` to all of `data`, scanning
+/// only the new suffix.
+///
+/// Returns `false`, leaving the index untouched, if the index does not match
+/// the keys of the prefix.
+pub(crate) fn extend_plan<T, K, E>(
+    tile_index: &mut TileIndex,
+    scanned: usize,
+    data: &[T],
+    key_extractor: &E,
+    config: &SortConfig,
+) -> bool
+where
+    K: Ord,
+    E: KeyExtractor<T, K>,
+{
+    let extracted: Vec<K>;
+    let element_keys = match key_extractor.borrowed_keys(data) {
+        Some(keys) => keys,
+        None => {
+            extracted = data
+                .iter()
+                .map(|element| opcount::extract_key(key_extractor, element))
+                .collect();
+            &extracted
+        }
+    };
+
+    let reverse = config.order.is_descending();
+    if scanned > data.len() || !tile_index.is_consistent_with(&element_keys[..scanned], reverse) {
+        return false;
+    }
+    if scanned == data.len() {
+        return true;
+    }
+
+    let hooks = &mut Hooks::default();
+    let mut tile_start_idx: Option<usize> = None;
+    for idx in scanned..data.len() {
+        process_tile_boundaries(
+            tile_index,
+            &mut tile_start_idx,
+            idx,
+            element_keys,
+            reverse,
+            hooks,
+            None,
+        );
+    }
+    add_last_tile(tile_index, &tile_start_idx, element_keys, reverse, hooks);
+
+    #[cfg(feature = "verify")]
+    crate::verify::verify_tile_index(tile_index, element_keys, reverse);
+
+    true
+}
+
+/// Build a tile index from runs already known to be sorted, inserting each
+/// as one tile instead of scanning for boundaries.
+///
+/// With `check`, the runs must cover `data` in order and each must be sorted
+/// in the configured direction; otherwise they are trusted.
+pub(crate) fn plan_from_runs<T, K, E>(
+    data: &[T],
+    runs: &[Range<usize>],
+    key_extractor: &E,
+    config: &SortConfig,
+    check: bool,
+) -> Result<TileIndex, InvalidRuns>
+where
+    K: Ord,
+    E: KeyExtractor<T, K>,
+{
+    if check {
+        let mut end = 0;
+        for (run, range) in runs.iter().enumerate() {
+            if range.start != end || range.end < range.start || range.end > data.len() {
+                return Err(InvalidRuns::Misaligned { run });
+            }
+            end = range.end;
+        }
+        if end != data.len() {
+            return Err(InvalidRuns::Incomplete);
+        }
+    }
+
+    let extracted: Vec<K>;
+    let element_keys = match key_extractor.borrowed_keys(data) {
+        Some(keys) => keys,
+        None => {
+            extracted = data
+                .iter()
+                .map(|element| opcount::extract_key(key_extractor, element))
+                .collect();
+            &extracted
+        }
+    };
+
+    let reverse = config.order.is_descending();
+    let mut tile_index = TileIndex::new(config.stable, config.inline_tiles);
+    // Each run is at least one tile
+    tile_index.reserve(runs.len());
+    for (run, range) in runs.iter().enumerate() {
+        if range.is_empty() {
+            continue;
+        }
+        let keys = &element_keys[range.clone()];
+        if check
+            && keys
+                .windows(2)
+                .any(|pair| precedes(&pair[1], &pair[0], reverse))
+        {
+            return Err(InvalidRuns::Unsorted { run });
+        }
+        tile_index.insert_tile(Tile::new(range.start, range.len()), element_keys, reverse);
+    }
+
+    #[cfg(feature = "verify")]
+    crate::verify::verify_tile_index(&tile_index, element_keys, reverse);
+
+    Ok(tile_index)
+}
+
+/// Phase 2 on its own, for [`SortPlan`](crate::SortPlan).
+pub(crate) fn apply_plan<T: Clone>(
+    data: &mut [T],
+    tile_index: &TileIndex,
+    config: &SortConfig,
+) -> Strategy {
+    never_cancelled(restructure(data, tile_index, config, &mut Hooks::default()))
+}
+
+/// Tilesort by key that also drops elements whose key equals the previous one.
+///
+/// Returns the number of retained elements, which are moved to the front of
+/// `data`; the rest are left in an unspecified order. Of each run of equal
+/// keys the first element in input order is kept.
+pub(crate) fn tilesort_dedup_impl_with_key<T, K, E>(
+    data: &mut [T],
+    key_extractor: &E,
+    config: &SortConfig,
+) -> usize
+where
+    T: Clone,
+    K: Ord,
+    E: KeyExtractor<T, K>,
+{
+    if data.len() <= 1 {
+        return data.len();
+    }
+
+    let element_keys: Vec<K> = data
+        .iter()
+        .map(|element| opcount::extract_key(key_extractor, element))
+        .collect();
+    let mut equal_to_previous = vec![false; data.len()];
+    let tile_index = never_cancelled(scan_phase_marking_equal(
+        &element_keys,
+        config,
+        &mut Hooks::default(),
+        Some(&mut equal_to_previous),
+    ));
+
+    let retained = dedup_phase(data, &tile_index, &element_keys, &equal_to_previous);
+    move_into_place(data, retained)
+}
+
+/// Tilesort (no custom key function) that also drops duplicate elements.
+///
+/// See [`tilesort_dedup_impl_with_key`] for the result layout.
+pub(crate) fn tilesort_dedup_impl<T: Ord + Clone>(data: &mut [T], config: &SortConfig) -> usize {
+    if data.len() <= 1 {
+        return data.len();
+    }
+
+    let mut equal_to_previous = vec![false; data.len()];
+    let tile_index = never_cancelled(scan_phase_marking_equal(
+        data,
+        config,
+        &mut Hooks::default(),
+        Some(&mut equal_to_previous),
+    ));
+
+    let retained = dedup_phase(data, &tile_index, data, &equal_to_previous);
+    move_into_place(data, retained)
+}
+
+/// Tilesort by key, returning the ranges of equal-key groups in the output.
+pub(crate) fn tilesort_grouped_impl_with_key<T, K, E>(
+    data: &mut [T],
+    key_extractor: &E,
+    config: &SortConfig,
+) -> Vec<Range<usize>>
+where
+    T: Clone,
+    K: Ord,
+    E: KeyExtractor<T, K>,
+{
+    let element_keys: Vec<K> = data
+        .iter()
+        .map(|element| opcount::extract_key(key_extractor, element))
+        .collect();
+    if data.len() <= 1 {
+        return group_ranges_of_sorted(&element_keys);
+    }
+
+    let tile_index = never_cancelled(scan_phase_without_key(
+        &element_keys,
+        config,
+        &mut Hooks::default(),
+    ));
+    let groups = group_ranges(&tile_index, &element_keys);
+
+    never_cancelled(restructure(
+        data,
+        &tile_index,
+        config,
+        &mut Hooks::default(),
+    ));
+    groups
+}
+
+/// Tilesort (no custom key function), returning the ranges of equal elements.
+pub(crate) fn tilesort_grouped_impl<T: Ord + Clone>(
+    data: &mut [T],
+    config: &SortConfig,
+) -> Vec<Range<usize>> {
+    if data.len() <= 1 {
+        return group_ranges_of_sorted(data);
+    }
+
+    let tile_index = never_cancelled(scan_phase_without_key(data, config, &mut Hooks::default()));
+    let groups = group_ranges(&tile_index, data);
+
+    never_cancelled(restructure(
+        data,
+        &tile_index,
+        config,
+        &mut Hooks::default(),
+    ));
+    groups
+}
+
+/// Move the elements satisfying `pred` before those that do not, keeping the
+/// relative order within each side, and return how many satisfy it.
+///
+/// Each run of elements on the same side becomes a tile keyed by which side
+/// it belongs to, so input that is already nearly partitioned has few tiles.
+/// The tiles are applied with swaps, so nothing is cloned.
+pub(crate) fn stable_partition_impl<T, F>(data: &mut [T], mut pred: F, config: &SortConfig) -> usize
+where
+    F: FnMut(&T) -> bool,
+{
+    // `false` sorts first, so key each element by whether it fails `pred`
+    let fails: Vec<bool> = data.iter().map(|element| !pred(element)).collect();
+    let count = fails.iter().filter(|&&fails| !fails).count();
+
+    let mut tile_index = TileIndex::new(true, config.inline_tiles);
+    let mut start = 0;
+    for end in 1..=fails.len() {
+        if end == fails.len() || fails[end] != fails[start] {
+            tile_index.insert_tile(Tile::new(start, end - start), &fails, false);
+            start = end;
+        }
+    }
+
+    if tile_index.len() > 1 {
+        never_cancelled(restructure_indirect(
+            data,
+            &tile_index,
+            &mut Hooks::default(),
+        ));
+    }
+    count
+}
+
+/// Move the `k` smallest elements by key, in sorted order, to the front of `data`.
+///
+/// Only the elements that end up in the first `k` positions are placed, by
+/// swapping, so nothing is cloned; the rest are left in an unspecified order.
+pub(crate) fn partial_sort_impl_with_key<T, K, E>(
+    data: &mut [T],
+    k: usize,
+    key_extractor: &E,
+    config: &SortConfig,
+) where
+    K: Ord,
+    E: KeyExtractor<T, K>,
+{
+    if data.len() <= 1 || k == 0 {
+        return;
+    }
+
+    let tile_index = never_cancelled(scan_phase(
+        data,
+        key_extractor,
+        config,
+        &mut Hooks::default(),
+    ));
+    let sources: Vec<usize> = tile_index.sorted_indices().take(k).collect();
+    place_front(data, &sources);
+}
+
+/// Partial sort (no custom key function); see [`partial_sort_impl_with_key`].
+pub(crate) fn partial_sort_impl<T: Ord>(data: &mut [T], k: usize, config: &SortConfig) {
+    if data.len() <= 1 || k == 0 {
+        return;
+    }
+
+    let tile_index = never_cancelled(scan_phase_without_key(data, config, &mut Hooks::default()));
+    let sources: Vec<usize> = tile_index.sorted_indices().take(k).collect();
+    place_front(data, &sources);
+}
+
+/// Index in `data` of the element that would be at position `n` after sorting.
+///
+/// Only the scan phase runs; `data` is not modified.
+pub(crate) fn select_nth_impl_with_key<T, K, E>(
+    data: &[T],
+    n: usize,
+    key_extractor: &E,
+    config: &SortConfig,
+) -> Option<usize>
+where
+    K: Ord,
+    E: KeyExtractor<T, K>,
+{
+    if n >= data.len() {
+        return None;
+    }
+
+    let tile_index = never_cancelled(scan_phase(
+        data,
+        key_extractor,
+        config,
+        &mut Hooks::default(),
+    ));
+    tile_index.sorted_index(n)
+}
+
+/// Select the `n`th element (no custom key function).
+pub(crate) fn select_nth_impl<T: Ord>(data: &[T], n: usize, config: &SortConfig) -> Option<usize> {
+    if n >= data.len() {
+        return None;
+    }
+
+    let tile_index = never_cancelled(scan_phase_without_key(data, config, &mut Hooks::default()));
+    tile_index.sorted_index(n)
+}
+
+/// Returns `true` if `data` is already in the order `config` sorts it into,
+/// comparing the elements themselves.
+pub(crate) fn is_sorted_impl<T: Ord>(data: &[T], config: &SortConfig) -> bool {
+    sorted_until_impl(data, config) == data.len()
+}
+
+/// Length of the longest sorted prefix of `data`, in the direction given by
+/// `config`.
+pub(crate) fn sorted_until_impl<T: Ord>(data: &[T], config: &SortConfig) -> usize {
+    let reverse = config.order.is_descending();
+    data.windows(2)
+        .position(|pair| precedes(&pair[1], &pair[0], reverse))
+        .map_or(data.len(), |i| i + 1)
+}
+
+/// Like [`is_sorted_impl`], comparing the keys of `key_extractor`.
+pub(crate) fn is_sorted_impl_with_key<T, K, E>(
+    data: &[T],
+    key_extractor: &E,
+    config: &SortConfig,
+) -> bool
+where
+    K: Ord,
+    E: KeyExtractor<T, K>,
+{
+    sorted_until_impl_with_key(data, key_extractor, config) == data.len()
+}
+
+/// Like [`sorted_until_impl`], comparing the keys of `key_extractor`.
+///
+/// Keys the extractor lends are compared in place; otherwise each key is
+/// extracted once, keeping only the previous one, and the check stops at the
+/// first pair out of order.
+pub(crate) fn sorted_until_impl_with_key<T, K, E>(
+    data: &[T],
+    key_extractor: &E,
+    config: &SortConfig,
+) -> usize
 where
+    K: Ord,
+    E: KeyExtractor<T, K>,
+{
+    if let Some(keys) = key_extractor.borrowed_keys(data) {
+        return sorted_until_impl(keys, config);
+    }
+    let reverse = config.order.is_descending();
+
+    let mut elements = data.iter();
+    let Some(first) = elements.next() else {
+        return 0;
+    };
+    let mut prev = opcount::extract_key(key_extractor, first);
+    for (i, element) in elements.enumerate() {
+        let key = opcount::extract_key(key_extractor, element);
+        if precedes(&key, &prev, reverse) {
+            return i + 1;
+        }
+        prev = key;
+    }
+    data.len()
+}
+
+/// Tilesort a `VecDeque` by key without making it contiguous.
+///
+/// The deque's two slices are scanned as one logical sequence, so a tile may
+/// run across the wrap-around point, and the sorted elements are written back
+/// through the deque's own iterator.
+pub(crate) fn tilesort_deque_impl_with_key<T, K, E>(
+    deque: &mut VecDeque<T>,
+    key_extractor: &E,
+    config: &SortConfig,
+) where
     T: Clone,
     K: Ord,
     E: KeyExtractor<T, K>,
 {
-    if data.len() <= 1 {
+    if deque.len() <= 1 {
         return;
     }
 
-    // Phase 1: Scan and build tile index
-    let tile_index = scan_phase(data, key_extractor, reverse);
+    let element_keys: Vec<K> = deque
+        .iter()
+        .map(|element| opcount::extract_key(key_extractor, element))
+        .collect();
+    let tile_index = never_cancelled(scan_phase_without_key(
+        &element_keys,
+        config,
+        &mut Hooks::default(),
+    ));
+    drop(element_keys);
 
-    // Phase 2: Restructure using the tile index
-    restructure_phase(data, &tile_index);
+    restructure_deque(deque, &tile_index);
 }
 
-/// Main tilesort implementation (no custom key function).
+/// Tilesort a `VecDeque` (no custom key function) without making it contiguous.
+pub(crate) fn tilesort_deque_impl<T: Ord + Clone>(deque: &mut VecDeque<T>, config: &SortConfig) {
+    if deque.len() <= 1 {
+        return;
+    }
+
+    // Compare the elements in place through references
+    let element_keys: Vec<&T> = deque.iter().collect();
+    let tile_index = never_cancelled(scan_phase_without_key(
+        &element_keys,
+        config,
+        &mut Hooks::default(),
+    ));
+    drop(element_keys);
+
+    restructure_deque(deque, &tile_index);
+}
+
+/// Tilesort any [`RandomAccess`] container by key, moving elements with swaps.
+pub(crate) fn tilesort_container_impl_with_key<C, K, E>(
+    container: &mut C,
+    key_extractor: &E,
+    config: &SortConfig,
+) where
+    C: RandomAccess + ?Sized,
+    K: Ord,
+    E: KeyExtractor<C::Item, K>,
+{
+    if container.len() <= 1 {
+        return;
+    }
+
+    let element_keys: Vec<K> = (0..container.len())
+        .map(|idx| opcount::extract_key(key_extractor, container.get(idx)))
+        .collect();
+    let tile_index = never_cancelled(scan_phase_without_key(
+        &element_keys,
+        config,
+        &mut Hooks::default(),
+    ));
+    drop(element_keys);
+
+    apply_permutation(container, tile_index.sorted_indices().collect::<Vec<_>>());
+}
+
+/// Tilesort any [`RandomAccess`] container (no custom key function).
+pub(crate) fn tilesort_container_impl<C>(container: &mut C, config: &SortConfig)
+where
+    C: RandomAccess + ?Sized,
+    C::Item: Ord,
+{
+    if container.len() <= 1 {
+        return;
+    }
+
+    // Compare the elements in place through references
+    let element_keys: Vec<&C::Item> = (0..container.len()).map(|idx| container.get(idx)).collect();
+    let tile_index = never_cancelled(scan_phase_without_key(
+        &element_keys,
+        config,
+        &mut Hooks::default(),
+    ));
+    drop(element_keys);
+
+    apply_permutation(container, tile_index.sorted_indices().collect::<Vec<_>>());
+}
+
+/// Sort the rows of a table by `keys`, applying the same permutation to each
+/// of the key and value columns.
 ///
-/// # Arguments
-/// * `data` - The slice to sort
-/// * `reverse` - If true, sort in descending order; if false, ascending
-pub(crate) fn tilesort_impl<T: Ord + Clone>(data: &mut [T], reverse: bool) {
+/// The sorted order is found once, without moving anything, and then applied
+/// to each column with one pass of swaps.
+pub(crate) fn sort_columns_impl(
+    keys: &mut [&mut dyn KeyColumn],
+    values: &mut [&mut dyn Column],
+    config: &SortConfig,
+) {
+    let mut lens = keys
+        .iter()
+        .map(|column| column.len())
+        .chain(values.iter().map(|column| column.len()));
+    let len = lens.next().unwrap_or(0);
+    assert!(
+        lens.all(|other| other == len),
+        "columns must have the same length"
+    );
+    if len <= 1 {
+        return;
+    }
+
+    let rows: Vec<Row<'_, '_>> = (0..len).map(|row| Row { row, keys }).collect();
+    let sources = match small::insertion_sort(&rows, config) {
+        Some(shortcut) => shortcut.sources(len),
+        None => never_cancelled(scan_phase_without_key(&rows, config, &mut Hooks::default()))
+            .sorted_indices()
+            .collect(),
+    };
+    drop(rows);
+
+    for column in keys.iter_mut() {
+        permute_with(sources.clone(), |a, b| column.swap(a, b));
+    }
+    for column in values.iter_mut() {
+        permute_with(sources.clone(), |a, b| column.swap(a, b));
+    }
+}
+
+/// An element compared through a caller-supplied comparison function.
+struct Compared<'a, T, F> {
+    element: &'a T,
+    compare: &'a RefCell<F>,
+}
+
+impl<T, F: FnMut(&T, &T) -> Ordering> Ord for Compared<'_, T, F> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.compare.borrow_mut())(self.element, other.element)
+    }
+}
+
+impl<T, F: FnMut(&T, &T) -> Ordering> PartialOrd for Compared<'_, T, F> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T, F: FnMut(&T, &T) -> Ordering> PartialEq for Compared<'_, T, F> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T, F: FnMut(&T, &T) -> Ordering> Eq for Compared<'_, T, F> {}
+
+/// Tilesort a slice with a comparison function, moving elements with swaps.
+///
+/// The permutation is only applied once the scan has finished, so a panic in
+/// `compare` leaves `data` in its original order.
+pub(crate) fn tilesort_by_comparator_impl<T, F>(data: &mut [T], compare: F, config: &SortConfig)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
     if data.len() <= 1 {
         return;
     }
 
-    // Phase 1: Scan and build tile index
-    let tile_index = scan_phase_without_key(data, reverse);
+    let compare = RefCell::new(compare);
+    let element_keys: Vec<Compared<'_, T, F>> = data
+        .iter()
+        .map(|element| Compared {
+            element,
+            compare: &compare,
+        })
+        .collect();
+    if let Some(shortcut) = small::insertion_sort(&element_keys, config) {
+        drop(element_keys);
+        shortcut.apply(data);
+        return;
+    }
+    let tile_index = never_cancelled(scan_phase_without_key(
+        &element_keys,
+        config,
+        &mut Hooks::default(),
+    ));
+    drop(element_keys);
 
-    // Phase 2: Restructure using the tile index
-    restructure_phase(data, &tile_index);
+    apply_permutation(data, tile_index.sorted_indices().collect::<Vec<_>>());
 }
 
+/// Unwrap the result of a sort run without a cancellation token.
+pub(crate) fn never_cancelled<R>(result: Result<R, Cancelled>) -> R {
+    match result {
+        Ok(value) => value,
+        Err(Cancelled) => unreachable!("a sort without a cancellation token cannot be cancelled"),
+    }
+}
+
+///
+/// If `equal_to_previous` is given, each element found equal to the one
+/// before it is marked there, which the same comparison tells for free.
 fn process_tile_boundaries<K: Ord>(
     tile_index: &mut TileIndex,
     tile_start_idx: &mut Option<usize>,
     idx: usize,
     element_keys: &[K],
     reverse: bool,
+    hooks: &mut Hooks<'_>,
+    equal_to_previous: Option<&mut [bool]>,
 ) {
     if let Some(start_idx) = tile_start_idx {
         let prev_index: usize = if idx == 0 {
@@ -62,16 +1357,21 @@ fn process_tile_boundaries<K: Ord>(
         let prev_key = &element_keys[prev_index];
 
         // Check if out of order
+        opcount::comparisons(1);
+        let ordering = element_keys[idx].cmp(prev_key);
         let finish_tile = if reverse {
-            &element_keys[idx] > prev_key // For descending sort
+            ordering == Ordering::Greater // For descending sort
         } else {
-            &element_keys[idx] < prev_key // For ascending sort
+            ordering == Ordering::Less // For ascending sort
         };
+        if let Some(marks) = equal_to_previous {
+            marks[idx] = ordering == Ordering::Equal;
+        }
 
         if finish_tile {
             let count = idx - *start_idx;
             let new_tile = Tile::new(*start_idx, count);
-            tile_index.insert_tile(new_tile, element_keys, reverse);
+            hooks.insert_tile(tile_index, new_tile, element_keys, reverse);
             *tile_start_idx = None;
         }
     }
@@ -86,27 +1386,75 @@ fn add_last_tile<K: Ord>(
     tile_start_idx: &Option<usize>,
     element_keys: &[K],
     reverse: bool,
+    hooks: &mut Hooks<'_>,
 ) {
     let start_idx =
         tile_start_idx.expect("There should be at least one tile index before the end of the data");
     let elements_count = element_keys.len();
     let count = elements_count - start_idx;
     let new_tile = Tile::new(start_idx, count);
-    tile_index.insert_tile(new_tile, element_keys, reverse);
+    hooks.insert_tile(tile_index, new_tile, element_keys, reverse);
 }
 
 /// Phase 1: Scan through the data and build the tile index.
-fn scan_phase<T, K, E>(data: &[T], key_extractor: E, reverse: bool) -> TileIndex
+///
+/// Keys the extractor can lend directly (such as [`IdentityKey`]'s) are
+/// compared in place; otherwise they are extracted into a buffer.
+///
+/// [`IdentityKey`]: crate::IdentityKey
+fn scan_phase<T, K, E>(
+    data: &[T],
+    key_extractor: &E,
+    config: &SortConfig,
+    hooks: &mut Hooks<'_>,
+) -> Result<TileIndex, Cancelled>
+where
+    K: Ord,
+    E: KeyExtractor<T, K>,
+{
+    match key_extractor.borrowed_keys(data) {
+        Some(keys) => scan_phase_without_key(keys, config, hooks),
+        None => scan_phase_extracting(data, key_extractor, config, hooks),
+    }
+}
+
+/// Phase 1, extracting every key into a buffer kept for the insertions.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        name = "scan_phase",
+        level = "debug",
+        skip_all,
+        fields(
+            elements = data.len(),
+            reverse = config.order.is_descending(),
+            stable = config.stable,
+            tiles = tracing::field::Empty
+        )
+    )
+)]
+fn scan_phase_extracting<T, K, E>(
+    data: &[T],
+    key_extractor: &E,
+    config: &SortConfig,
+    hooks: &mut Hooks<'_>,
+) -> Result<TileIndex, Cancelled>
 where
     K: Ord,
     E: KeyExtractor<T, K>,
 {
-    let mut tile_index = TileIndex::new();
+    let reverse = config.order.is_descending();
+    let mut tile_index = new_tile_index(data.len(), config);
     let mut element_keys: Vec<K> = Vec::with_capacity(data.len());
     let mut tile_start_idx: Option<usize> = None;
+    hooks.observe(|observer| observer.on_scan_start(data.len()));
 
     for (idx, element) in data.iter().enumerate() {
-        let key = key_extractor.extract_key(element);
+        if idx % CHECK_INTERVAL == 0 {
+            hooks.checkpoint(scan_progress(idx, data.len()))?;
+        }
+
+        let key = opcount::extract_key(key_extractor, element);
         element_keys.push(key);
 
         process_tile_boundaries(
@@ -115,46 +1463,410 @@ where
             idx,
             &element_keys,
             reverse,
+            hooks,
+            None,
         );
     }
 
     // Add the last tile
-    add_last_tile(&mut tile_index, &tile_start_idx, &element_keys, reverse);
+    add_last_tile(
+        &mut tile_index,
+        &tile_start_idx,
+        &element_keys,
+        reverse,
+        hooks,
+    );
+    hooks.checkpoint(scan_progress(data.len(), data.len()))?;
+    hooks.observe(|observer| observer.on_scan_end());
 
-    tile_index
+    #[cfg(feature = "verify")]
+    crate::verify::verify_tile_index(&tile_index, &element_keys, reverse);
+
+    #[cfg(feature = "tracing")]
+    tracing::Span::current().record("tiles", tile_index.len());
+
+    Ok(tile_index)
+}
+
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        name = "scan_phase",
+        level = "debug",
+        skip_all,
+        fields(
+            elements = data.len(),
+            reverse = config.order.is_descending(),
+            stable = config.stable,
+            tiles = tracing::field::Empty
+        )
+    )
+)]
+fn scan_phase_without_key<T>(
+    data: &[T],
+    config: &SortConfig,
+    hooks: &mut Hooks<'_>,
+) -> Result<TileIndex, Cancelled>
+where
+    T: Ord,
+{
+    scan_phase_marking_equal(data, config, hooks, None)
 }
 
-fn scan_phase_without_key<T>(data: &[T], reverse: bool) -> TileIndex
+/// Phase 1 over keys already at hand, also marking in `equal_to_previous`
+/// each element equal to the one before it, if given.
+fn scan_phase_marking_equal<T>(
+    data: &[T],
+    config: &SortConfig,
+    hooks: &mut Hooks<'_>,
+    mut equal_to_previous: Option<&mut [bool]>,
+) -> Result<TileIndex, Cancelled>
 where
     T: Ord,
 {
-    let mut tile_index = TileIndex::new();
+    let reverse = config.order.is_descending();
+    let mut tile_index = new_tile_index(data.len(), config);
     let mut tile_start_idx: Option<usize> = None;
+    hooks.observe(|observer| observer.on_scan_start(data.len()));
 
     for (idx, _) in data.iter().enumerate() {
-        process_tile_boundaries(&mut tile_index, &mut tile_start_idx, idx, data, reverse);
+        if idx % CHECK_INTERVAL == 0 {
+            hooks.checkpoint(scan_progress(idx, data.len()))?;
+        }
+
+        process_tile_boundaries(
+            &mut tile_index,
+            &mut tile_start_idx,
+            idx,
+            data,
+            reverse,
+            hooks,
+            equal_to_previous.as_deref_mut(),
+        );
     }
 
     // Add the last tile
-    add_last_tile(&mut tile_index, &tile_start_idx, data, reverse);
+    add_last_tile(&mut tile_index, &tile_start_idx, data, reverse, hooks);
+    hooks.checkpoint(scan_progress(data.len(), data.len()))?;
+    hooks.observe(|observer| observer.on_scan_end());
+
+    #[cfg(feature = "verify")]
+    crate::verify::verify_tile_index(&tile_index, data, reverse);
+
+    #[cfg(feature = "tracing")]
+    tracing::Span::current().record("tiles", tile_index.len());
+
+    Ok(tile_index)
+}
+
+/// Phase 1 for primitive keys: jump from one run boundary to the next with
+/// [`run_end`](crate::simd::run_end) instead of testing each element.
+#[cfg(feature = "simd")]
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        name = "scan_phase",
+        level = "debug",
+        skip_all,
+        fields(
+            elements = data.len(),
+            reverse = config.order.is_descending(),
+            stable = config.stable,
+            tiles = tracing::field::Empty
+        )
+    )
+)]
+fn scan_phase_simd<T: crate::simd::SimdKey>(
+    data: &[T],
+    config: &SortConfig,
+    hooks: &mut Hooks<'_>,
+) -> Result<TileIndex, Cancelled> {
+    let reverse = config.order.is_descending();
+    let mut tile_index = new_tile_index(data.len(), config);
+    let mut next_check = 0;
+    let mut start = 0;
+    hooks.observe(|observer| observer.on_scan_start(data.len()));
+
+    while start < data.len() {
+        if start >= next_check {
+            hooks.checkpoint(scan_progress(start, data.len()))?;
+            next_check = start + CHECK_INTERVAL;
+        }
+
+        let end = crate::simd::run_end(data, start, reverse);
+        opcount::comparisons(end - start);
+        hooks.insert_tile(
+            &mut tile_index,
+            Tile::new(start, end - start),
+            data,
+            reverse,
+        );
+        start = end;
+    }
+    hooks.checkpoint(scan_progress(data.len(), data.len()))?;
+    hooks.observe(|observer| observer.on_scan_end());
+
+    #[cfg(feature = "verify")]
+    crate::verify::verify_tile_index(&tile_index, data, reverse);
+
+    #[cfg(feature = "tracing")]
+    tracing::Span::current().record("tiles", tile_index.len());
+
+    Ok(tile_index)
+}
 
+/// An empty tile index for `len` elements, allocated for the tiles
+/// [`SortConfig::capacity_hint`] expects.
+pub(crate) fn new_tile_index(len: usize, config: &SortConfig) -> TileIndex {
+    let mut tile_index = TileIndex::new(config.stable, config.inline_tiles);
+    tile_index.reserve(config.capacity_hint.expected_tiles(len));
     tile_index
 }
 
+fn scan_progress(processed: usize, total: usize) -> Progress {
+    Progress::Scanning { processed, total }
+}
+
+/// Phase 2, with the [`Strategy`] chosen by `config` for the size of `data`.
+fn restructure<T: Clone>(
+    data: &mut [T],
+    tile_index: &TileIndex,
+    config: &SortConfig,
+    hooks: &mut Hooks<'_>,
+) -> Result<Strategy, Cancelled> {
+    let strategy = config.strategy::<T>(data.len());
+    hooks.observe(|observer| observer.on_restructure_start());
+    match strategy {
+        Strategy::Buffered => restructure_phase(data, tile_index, hooks)?,
+        Strategy::Indirect => restructure_indirect(data, tile_index, hooks)?,
+        Strategy::Rotate => {
+            #[cfg(feature = "verify")]
+            crate::verify::verify_moves(
+                &tile_index.sorted_indices().collect::<Vec<_>>(),
+                |indices| {
+                    never_cancelled(restructure_rotate(
+                        indices,
+                        tile_index,
+                        &mut Hooks::default(),
+                    ))
+                },
+            );
+            restructure_rotate(data, tile_index, hooks)?
+        }
+    }
+    hooks.observe(|observer| observer.on_restructure_end());
+    Ok(strategy)
+}
+
+/// Phase 2 for indirect mode: collect the sorted order as a vector of source
+/// indices and apply it with one cycle-following pass of swaps.
+///
+/// Each element is swapped into its final position once and nothing is
+/// cloned. No user code runs while the permutation is applied, so the slice is
+/// never left partially sorted.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(elements = data.len(), tiles = tile_index.len())
+    )
+)]
+fn restructure_indirect<T>(
+    data: &mut [T],
+    tile_index: &TileIndex,
+    hooks: &mut Hooks<'_>,
+) -> Result<(), Cancelled> {
+    info!("Restructuring indirectly with {} tiles", tile_index.len());
+
+    let sources: Vec<usize> = tile_index.sorted_indices().collect();
+    hooks.checkpoint(restructure_progress::<T>(0, data.len()))?;
+    apply_permutation(data, sources);
+    hooks.checkpoint(restructure_progress::<T>(data.len(), data.len()))
+}
+
+/// Tile counts up to which [`restructure_rotate`] rotates the tiles into
+/// place one by one rather than merge sorting them.
+const ROTATE_EACH_MAX_TILES: usize = 16;
+
+/// Phase 2 without auxiliary memory: move the tiles into sorted order by
+/// rotating the elements between them.
+///
+/// A few tiles are rotated, in sorted order, from their current position to
+/// the end of the sorted prefix, which moves each element at most once per
+/// tile. More are merge sorted by their position in the sorted order,
+/// merging by rotation as an in-place merge without a buffer does, which
+/// moves each element O(log² t) times for t tiles. Either way the only
+/// memory used is a list of the tiles, and as with indirect mode, no user
+/// code runs while elements are moved.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(elements = data.len(), tiles = tile_index.len())
+    )
+)]
+fn restructure_rotate<T>(
+    data: &mut [T],
+    tile_index: &TileIndex,
+    hooks: &mut Hooks<'_>,
+) -> Result<(), Cancelled> {
+    info!("Restructuring by rotation with {} tiles", tile_index.len());
+
+    hooks.checkpoint(restructure_progress::<T>(0, data.len()))?;
+    if tile_index.len() <= ROTATE_EACH_MAX_TILES {
+        rotate_each_tile(data, tile_index);
+    } else {
+        // The tiles in input order, each with its place in the sorted order
+        let mut tiles: Vec<(usize, Block)> = tile_index
+            .iter()
+            .enumerate()
+            .map(|(rank, tile)| {
+                (
+                    tile.start(),
+                    Block {
+                        rank,
+                        len: tile.len(),
+                    },
+                )
+            })
+            .collect();
+        tiles.sort_unstable_by_key(|&(start, _)| start);
+        let mut blocks: Vec<Block> = tiles.into_iter().map(|(_, block)| block).collect();
+        sort_blocks(data, &mut blocks);
+    }
+    hooks.checkpoint(restructure_progress::<T>(data.len(), data.len()))
+}
+
+/// Rotate each tile, in sorted order, from its current position to the end
+/// of the sorted prefix.
+///
+/// Rotations keep the unplaced tiles in their original relative order, so a
+/// tile's current position is the end of the prefix plus the lengths of the
+/// unplaced tiles before it.
+fn rotate_each_tile<T>(data: &mut [T], tile_index: &TileIndex) {
+    // Unplaced tiles as (original start, length), in original order
+    let mut unplaced: Vec<(usize, usize)> = tile_index
+        .iter()
+        .map(|tile| (tile.start(), tile.len()))
+        .collect();
+    unplaced.sort_unstable();
+
+    let mut placed = 0;
+    for tile in tile_index.iter() {
+        let position = unplaced
+            .binary_search(&(tile.start(), tile.len()))
+            .expect("every tile is unplaced until its turn");
+        let skipped: usize = unplaced[..position].iter().map(|&(_, len)| len).sum();
+        if skipped > 0 {
+            data[placed..placed + skipped + tile.len()].rotate_right(tile.len());
+            opcount::element_moves(skipped + tile.len());
+        }
+        unplaced.remove(position);
+        placed += tile.len();
+    }
+}
+
+/// A tile moved as a unit by [`sort_blocks`]: its length, and its position
+/// in the sorted order.
+#[derive(Debug, Clone, Copy)]
+struct Block {
+    rank: usize,
+    len: usize,
+}
+
+fn blocks_len(blocks: &[Block]) -> usize {
+    blocks.iter().map(|block| block.len).sum()
+}
+
+/// Merge sort `blocks`, the consecutive runs making up `data`, by rank,
+/// moving the elements of `data` along with them.
+fn sort_blocks<T>(data: &mut [T], blocks: &mut [Block]) {
+    if blocks.len() <= 1 {
+        return;
+    }
+    let mid = blocks.len() / 2;
+    let split = blocks_len(&blocks[..mid]);
+    sort_blocks(&mut data[..split], &mut blocks[..mid]);
+    sort_blocks(&mut data[split..], &mut blocks[mid..]);
+    merge_blocks(data, blocks, mid);
+}
+
+/// Merge the rank-ordered `blocks[..mid]` and `blocks[mid..]`, the runs
+/// making up `data`, without a buffer.
+///
+/// The middle block of the longer side splits both sides in two; rotating
+/// the inner halves past each other leaves two smaller merges, the first of
+/// which recurses while the second continues the loop.
+fn merge_blocks<T>(mut data: &mut [T], mut blocks: &mut [Block], mut mid: usize) {
+    loop {
+        let (left, right) = (mid, blocks.len() - mid);
+        if left == 0 || right == 0 || blocks[mid - 1].rank < blocks[mid].rank {
+            return;
+        }
+        let (cut_left, cut_right) = if left >= right {
+            let cut_left = left / 2;
+            let rank = blocks[cut_left].rank;
+            (
+                cut_left,
+                mid + blocks[mid..].partition_point(|block| block.rank < rank),
+            )
+        } else {
+            let cut_right = mid + right / 2;
+            let rank = blocks[cut_right].rank;
+            (
+                blocks[..mid].partition_point(|block| block.rank < rank),
+                cut_right,
+            )
+        };
+
+        let start = blocks_len(&blocks[..cut_left]);
+        let moved = blocks_len(&blocks[cut_left..mid]);
+        let end = start + moved + blocks_len(&blocks[mid..cut_right]);
+        data[start..end].rotate_left(moved);
+        opcount::element_moves(end - start);
+        blocks[cut_left..cut_right].rotate_left(mid - cut_left);
+
+        let new_mid = cut_left + (cut_right - mid);
+        let (front_data, back_data) = mem::take(&mut data).split_at_mut(end - moved);
+        let (front, back) = mem::take(&mut blocks).split_at_mut(new_mid);
+        merge_blocks(front_data, front, cut_left);
+        data = back_data;
+        blocks = back;
+        mid -= cut_left;
+    }
+}
+
 /// Phase 2: Use the tile index to reconstruct the sorted array.
-fn restructure_phase<T>(data: &mut [T], tile_index: &TileIndex)
+///
+/// The tiles are first cloned into a separate buffer in sorted order; only once
+/// that has succeeded are the elements moved back into `data`. Moving cannot
+/// panic, so if `T::clone` panics part-way, `data` is left in its original
+/// order and the partially built buffer is dropped normally. The same holds
+/// when the sort is cancelled while the buffer is being filled.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(elements = data.len(), tiles = tile_index.len())
+    )
+)]
+fn restructure_phase<T>(
+    data: &mut [T],
+    tile_index: &TileIndex,
+    hooks: &mut Hooks<'_>,
+) -> Result<(), Cancelled>
 where
     T: Clone,
 {
     info!("Restructuring with {} tiles", tile_index.len());
 
-    // Create a copy of the original data
-    let original = data.to_vec();
-
     // Copy tiles in sorted order
-    let mut write_pos = 0;
+    let mut sorted = Vec::with_capacity(data.len());
     for (i, tile) in tile_index.iter().enumerate() {
-        let start = tile.start_idx();
+        let start = tile.start();
         let end = start + tile.len();
 
         debug!(
@@ -162,10 +1874,180 @@ where
             i,
             start,
             tile.len(),
-            write_pos
+            sorted.len()
         );
 
-        data[write_pos..write_pos + tile.len()].clone_from_slice(&original[start..end]);
-        write_pos += tile.len();
+        for chunk in data[start..end].chunks(CHECK_INTERVAL) {
+            hooks.checkpoint(restructure_progress::<T>(sorted.len(), data.len()))?;
+            sorted.extend_from_slice(chunk);
+            opcount::element_clones(chunk.len());
+        }
+    }
+
+    debug_assert_eq!(sorted.len(), data.len());
+    hooks.checkpoint(restructure_progress::<T>(sorted.len(), data.len()))?;
+
+    // Move the sorted elements back into place
+    for (slot, element) in data.iter_mut().zip(sorted) {
+        *slot = element;
+    }
+    opcount::element_moves(data.len());
+
+    Ok(())
+}
+
+/// Phase 2 for deques: like [`restructure_phase`], but reading tiles through
+/// `VecDeque::range` so they may wrap around the end of the ring buffer.
+fn restructure_deque<T: Clone>(deque: &mut VecDeque<T>, tile_index: &TileIndex) {
+    info!("Restructuring deque with {} tiles", tile_index.len());
+
+    // Replay on a deque split into the same two slices, so tiles cross the
+    // seam where they do here
+    #[cfg(feature = "verify")]
+    crate::verify::verify_moves(
+        &tile_index.sorted_indices().collect::<Vec<_>>(),
+        |indices| {
+            let mut replay: VecDeque<usize> = VecDeque::with_capacity(deque.len());
+            let (front, _) = deque.as_slices();
+            replay.extend(&indices[front.len()..]);
+            for &index in indices[..front.len()].iter().rev() {
+                replay.push_front(index);
+            }
+            restructure_deque_elements(&mut replay, tile_index);
+            for (slot, index) in indices.iter_mut().zip(replay) {
+                *slot = index;
+            }
+        },
+    );
+    restructure_deque_elements(deque, tile_index);
+}
+
+/// The copies and moves of [`restructure_deque`].
+fn restructure_deque_elements<T: Clone>(deque: &mut VecDeque<T>, tile_index: &TileIndex) {
+    let mut sorted = Vec::with_capacity(deque.len());
+    for tile in tile_index.iter() {
+        let start = tile.start();
+        sorted.extend(deque.range(start..start + tile.len()).cloned());
+        opcount::element_clones(tile.len());
+    }
+
+    debug_assert_eq!(sorted.len(), deque.len());
+
+    for (slot, element) in deque.iter_mut().zip(sorted) {
+        *slot = element;
+    }
+    opcount::element_moves(deque.len());
+}
+
+/// Phase 2 for deduplicating sorts: clone the first element of each run of
+/// equal keys, walking the tiles in sorted order.
+///
+/// Each tile is a sorted stretch of the input, so within it an element is a
+/// duplicate exactly when the scan found it equal to the one before it, as
+/// `equal_to_previous` records; keys are compared only where a tile begins,
+/// with the last key of the tile before. Duplicates are never cloned.
+fn dedup_phase<T, K>(
+    data: &[T],
+    tile_index: &TileIndex,
+    element_keys: &[K],
+    equal_to_previous: &[bool],
+) -> Vec<T>
+where
+    T: Clone,
+    K: Ord,
+{
+    info!("Deduplicating with {} tiles", tile_index.len());
+
+    let mut retained = Vec::with_capacity(data.len());
+    let mut last_key: Option<&K> = None;
+    for tile in tile_index.iter() {
+        let start = tile.start();
+        for idx in start..start + tile.len() {
+            let key = &element_keys[idx];
+            let duplicate = if idx > start {
+                equal_to_previous[idx]
+            } else if let Some(last_key) = last_key {
+                opcount::comparisons(1);
+                last_key == key
+            } else {
+                false
+            };
+            if !duplicate {
+                opcount::element_clones(1);
+                retained.push(data[idx].clone());
+            }
+            last_key = Some(key);
+        }
+    }
+
+    retained
+}
+
+/// Ranges of equal keys in the output described by `tile_index`, computed
+/// before restructuring by walking the keys in tile order.
+fn group_ranges<K: Ord>(tile_index: &TileIndex, element_keys: &[K]) -> Vec<Range<usize>> {
+    let sorted_keys = tile_index
+        .iter()
+        .flat_map(|tile| &element_keys[tile.start()..tile.start() + tile.len()]);
+    group_ranges_in(sorted_keys)
+}
+
+fn group_ranges_of_sorted<K: Ord>(sorted_keys: &[K]) -> Vec<Range<usize>> {
+    group_ranges_in(sorted_keys.iter())
+}
+
+fn group_ranges_in<'a, K: Ord + 'a>(sorted_keys: impl Iterator<Item = &'a K>) -> Vec<Range<usize>> {
+    let mut groups: Vec<Range<usize>> = Vec::new();
+    let mut last_key: Option<&K> = None;
+    for (position, key) in sorted_keys.enumerate() {
+        opcount::comparisons(1);
+        match groups.last_mut() {
+            Some(group) if last_key == Some(key) => group.end = position + 1,
+            _ => groups.push(position..position + 1),
+        }
+        last_key = Some(key);
+    }
+    groups
+}
+
+/// Swap the elements at input indices `sources` into positions `0..sources.len()`.
+///
+/// Only displaced elements are tracked, so this needs memory proportional to
+/// `sources.len()` rather than to `data.len()`.
+fn place_front<T>(data: &mut [T], sources: &[usize]) {
+    // Current position of each displaced element, and the input index of the
+    // element at each position it was displaced to
+    let mut position_of: HashMap<usize, usize> = HashMap::new();
+    let mut origin_at: HashMap<usize, usize> = HashMap::new();
+
+    for (target, &source) in sources.iter().enumerate() {
+        // Positions before `target` hold other sources, so this is >= target
+        let current = position_of.get(&source).copied().unwrap_or(source);
+        if current == target {
+            continue;
+        }
+        data.swap(target, current);
+        opcount::element_moves(2);
+
+        let displaced = origin_at.get(&target).copied().unwrap_or(target);
+        position_of.insert(displaced, current);
+        origin_at.insert(current, displaced);
+    }
+}
+
+/// Move `elements` to the front of `data`, returning how many were moved.
+fn move_into_place<T>(data: &mut [T], elements: Vec<T>) -> usize {
+    let len = elements.len();
+    for (slot, element) in data.iter_mut().zip(elements) {
+        *slot = element;
+    }
+    opcount::element_moves(len);
+    len
+}
+
+fn restructure_progress<T>(copied: usize, total: usize) -> Progress {
+    Progress::Restructuring {
+        bytes_copied: copied * mem::size_of::<T>(),
+        total_bytes: total * mem::size_of::<T>(),
     }
 }
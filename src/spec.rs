@@ -0,0 +1,183 @@
+//! Sort orders parsed from strings such as `"name,-price"`.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::erased::KeyExtractorDyn;
+use crate::key_extractor::KeyExtractor;
+
+/// Builds the extractor for one field, ascending (`false`) or descending.
+type FieldFactory<'a, T> = Box<dyn Fn(bool) -> Box<dyn KeyExtractorDyn<T> + 'a> + 'a>;
+
+/// Error returned by [`SortSpec::parse`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SortSpecError {
+    /// A field name that was not registered with [`SortSpec::field`].
+    UnknownField(String),
+    /// An empty entry, as in `"name,,price"` or `"name,"`.
+    EmptyField,
+}
+
+impl fmt::Display for SortSpecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SortSpecError::UnknownField(name) => write!(f, "unknown sort field: {}", name),
+            SortSpecError::EmptyField => f.write_str("empty sort field"),
+        }
+    }
+}
+
+impl std::error::Error for SortSpecError {}
+
+/// The fields a runtime sort order may name, and how to read each of them.
+///
+/// Register each field's accessor with [`field`](SortSpec::field), then
+/// turn a spec string, such as a query parameter, into an extractor with
+/// [`parse`](SortSpec::parse). A spec is a comma-separated list of field
+/// names, each optionally prefixed with `+` (ascending, the default) or `-`
+/// (descending); earlier fields take precedence and later ones break ties.
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::SortSpec;
+///
+/// #[derive(Debug, Clone, PartialEq)]
+/// struct Product {
+///     name: String,
+///     price: u32,
+///     created_at: u64,
+/// }
+///
+/// let spec = SortSpec::new()
+///     .field("name", |p: &Product| p.name.clone())
+///     .field("price", |p: &Product| p.price)
+///     .field("created_at", |p: &Product| p.created_at);
+///
+/// let product = |name: &str, price, created_at| Product {
+///     name: name.into(),
+///     price,
+///     created_at,
+/// };
+/// let (mug, cup, pot) = (product("mug", 8, 3), product("cup", 8, 1), product("pot", 20, 2));
+/// let mut products = vec![mug.clone(), cup.clone(), pot.clone()];
+///
+/// tilesort::tilesort_by_extractor(&mut products, spec.parse("-price,+created_at")?);
+/// assert_eq!(products, vec![pot, cup, mug]);
+///
+/// assert!(spec.parse("name,colour").is_err());
+/// # Ok::<(), tilesort::SortSpecError>(())
+/// ```
+pub struct SortSpec<'a, T> {
+    fields: HashMap<String, FieldFactory<'a, T>>,
+}
+
+impl<T> Default for SortSpec<'_, T> {
+    fn default() -> Self {
+        SortSpec {
+            fields: HashMap::new(),
+        }
+    }
+}
+
+impl<'a, T> SortSpec<'a, T> {
+    /// Create a spec with no fields.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register `name` as a field read by `key_fn`, replacing any earlier
+    /// field of that name.
+    pub fn field<K, F>(mut self, name: &str, key_fn: F) -> Self
+    where
+        K: Ord + 'static,
+        F: Fn(&T) -> K + Clone + 'a,
+    {
+        let factory = move |descending: bool| {
+            if descending {
+                key_fn.clone().reversed().boxed()
+            } else {
+                key_fn.clone().boxed()
+            }
+        };
+        self.fields.insert(name.to_string(), Box::new(factory));
+        self
+    }
+
+    /// Parse `spec` into an extractor comparing by each named field in turn.
+    ///
+    /// Whitespace around each entry is ignored, and an empty or all-blank
+    /// spec gives an extractor under which every element is equal, so a
+    /// stable sort leaves the input as it is.
+    pub fn parse(
+        &self,
+        spec: &str,
+    ) -> Result<Vec<Box<dyn KeyExtractorDyn<T> + 'a>>, SortSpecError> {
+        if spec.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+        spec.split(',')
+            .map(|entry| {
+                let entry = entry.trim();
+                let (descending, name) = match entry.as_bytes().first() {
+                    Some(b'-') => (true, &entry[1..]),
+                    Some(b'+') => (false, &entry[1..]),
+                    _ => (false, entry),
+                };
+                if name.is_empty() {
+                    return Err(SortSpecError::EmptyField);
+                }
+                let factory = self
+                    .fields
+                    .get(name)
+                    .ok_or_else(|| SortSpecError::UnknownField(name.to_string()))?;
+                Ok(factory(descending))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec<'a>() -> SortSpec<'a, (u8, i32)> {
+        SortSpec::new()
+            .field("a", |p: &(u8, i32)| p.0)
+            .field("b", |p: &(u8, i32)| p.1)
+    }
+
+    #[test]
+    fn test_directions() {
+        let spec = spec();
+        let ascending = spec.parse("+a, b").unwrap();
+        assert!(ascending.extract_key(&(1, 9)) < ascending.extract_key(&(2, 0)));
+        assert!(ascending.extract_key(&(1, 0)) < ascending.extract_key(&(1, 9)));
+
+        let descending = spec.parse("-a,b").unwrap();
+        assert!(descending.extract_key(&(2, 9)) < descending.extract_key(&(1, 0)));
+    }
+
+    #[test]
+    fn test_errors() {
+        let spec = spec();
+        assert_eq!(
+            spec.parse("a,c").err(),
+            Some(SortSpecError::UnknownField("c".to_string()))
+        );
+        assert_eq!(spec.parse("a,").err(), Some(SortSpecError::EmptyField));
+        assert_eq!(spec.parse("-").err(), Some(SortSpecError::EmptyField));
+        assert_eq!(
+            SortSpecError::UnknownField("c".to_string()).to_string(),
+            "unknown sort field: c"
+        );
+    }
+
+    #[test]
+    fn test_empty_spec() {
+        let spec = spec();
+        let none = spec.parse("  ").unwrap();
+        assert!(none.is_empty());
+        assert_eq!(none.extract_key(&(1, 2)), none.extract_key(&(3, 4)));
+    }
+}
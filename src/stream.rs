@@ -0,0 +1,189 @@
+//! Incremental sorting of elements that arrive one at a time.
+
+use std::mem;
+
+use crate::config::SortConfig;
+use crate::key_extractor::KeyExtractor;
+use crate::opcount;
+use crate::sorter;
+use crate::tile_index::{Tile, TileIndex};
+
+/// Sorter fed one element at a time, for pipelines such as async streams
+/// that must not block while waiting for the rest of the input.
+///
+/// It holds its elements in memory and implements no `Stream` trait itself,
+/// so it works with any runtime: the pipeline pushes from its own loop.
+///
+/// Each [`push`](StreamSorter::push) extracts one key and compares it with the
+/// previous one, recording a tile whenever a run ends, so the scan is done by
+/// the time the input ends and [`finish`](StreamSorter::finish) only has to
+/// restructure. With a [`window`](StreamSorter::window), memory is bounded:
+/// every `n` elements are sorted on their own and handed back.
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::{IdentityKey, StreamSorter};
+///
+/// let mut sorter = StreamSorter::new(IdentityKey);
+/// for reading in [7, 8, 9, 1, 2, 3] {
+///     // e.g. `while let Some(reading) = stream.next().await`
+///     sorter.push(reading);
+/// }
+/// assert_eq!(sorter.finish(), vec![1, 2, 3, 7, 8, 9]);
+///
+/// // Sorted batches of at most three elements
+/// let mut sorter = StreamSorter::new(|&(t, _): &(u32, char)| t).window(3);
+/// let mut batches = Vec::new();
+/// for event in [(3, 'c'), (1, 'a'), (2, 'b'), (5, 'e'), (4, 'd')] {
+///     batches.extend(sorter.push(event));
+/// }
+/// batches.push(sorter.finish());
+/// assert_eq!(
+///     batches,
+///     vec![vec![(1, 'a'), (2, 'b'), (3, 'c')], vec![(4, 'd'), (5, 'e')]]
+/// );
+/// ```
+pub struct StreamSorter<T, K, E> {
+    extractor: E,
+    config: SortConfig,
+    window: Option<usize>,
+    data: Vec<T>,
+    keys: Vec<K>,
+    tile_index: TileIndex,
+    run_start: usize,
+}
+
+impl<T, K, E> StreamSorter<T, K, E>
+where
+    T: Clone,
+    K: Ord,
+    E: KeyExtractor<T, K>,
+{
+    /// Create a sorter ordering elements by `extractor`, with no window.
+    pub fn new(extractor: E) -> Self {
+        Self::with_config(extractor, SortConfig::new())
+    }
+
+    /// Create a sorter with explicit options.
+    pub fn with_config(extractor: E, config: SortConfig) -> Self {
+        let tile_index = TileIndex::new(config.stable, config.inline_tiles);
+        StreamSorter {
+            extractor,
+            config,
+            window: None,
+            data: Vec::new(),
+            keys: Vec::new(),
+            tile_index,
+            run_start: 0,
+        }
+    }
+
+    /// Sort and return every `size` elements separately, instead of holding
+    /// the whole input. A `size` of zero is treated as one.
+    pub fn window(mut self, size: usize) -> Self {
+        self.window = Some(size.max(1));
+        self
+    }
+
+    /// Number of elements buffered since the last batch was returned.
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Returns `true` if no elements are buffered.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Add an element, returning the sorted window if it is now full.
+    pub fn push(&mut self, element: T) -> Option<Vec<T>> {
+        let key = opcount::extract_key(&self.extractor, &element);
+        if let Some(prev_key) = self.keys.last() {
+            opcount::comparisons(1);
+            let finish_tile = if self.config.order.is_descending() {
+                key > *prev_key
+            } else {
+                key < *prev_key
+            };
+            if finish_tile {
+                self.insert_run();
+            }
+        }
+        self.keys.push(key);
+        self.data.push(element);
+
+        match self.window {
+            Some(size) if self.data.len() >= size => Some(self.flush()),
+            _ => None,
+        }
+    }
+
+    /// Sort and return the buffered elements, leaving the sorter empty and
+    /// ready for more.
+    pub fn flush(&mut self) -> Vec<T> {
+        if !self.data.is_empty() {
+            self.insert_run();
+        }
+
+        #[cfg(feature = "verify")]
+        crate::verify::verify_tile_index(
+            &self.tile_index,
+            &self.keys,
+            self.config.order.is_descending(),
+        );
+
+        let tile_index = mem::replace(
+            &mut self.tile_index,
+            TileIndex::new(self.config.stable, self.config.inline_tiles),
+        );
+        let mut data = mem::take(&mut self.data);
+        self.keys.clear();
+        self.run_start = 0;
+
+        sorter::apply_plan(&mut data, &tile_index, &self.config);
+        data
+    }
+
+    /// Sort and return the remaining elements once the input has ended.
+    pub fn finish(mut self) -> Vec<T> {
+        self.flush()
+    }
+
+    /// Record the run from `run_start` to the last buffered element as a tile.
+    fn insert_run(&mut self) {
+        let end = self.keys.len();
+        let tile = Tile::new(self.run_start, end - self.run_start);
+        self.tile_index
+            .insert_tile(tile, &self.keys, self.config.order.is_descending());
+        self.run_start = end;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IdentityKey;
+
+    #[test]
+    fn test_empty() {
+        let sorter: StreamSorter<i32, i32, IdentityKey> = StreamSorter::new(IdentityKey);
+        assert!(sorter.is_empty());
+        assert_eq!(sorter.finish(), Vec::<i32>::new());
+    }
+
+    #[test]
+    fn test_flush_resets() {
+        let mut sorter = StreamSorter::with_config(IdentityKey, SortConfig::new().descending());
+        for x in [1, 2, 3] {
+            sorter.push(x);
+        }
+        assert_eq!(sorter.len(), 3);
+        assert_eq!(sorter.flush(), vec![3, 2, 1]);
+        assert!(sorter.is_empty());
+        for x in [5, 9] {
+            sorter.push(x);
+        }
+        assert_eq!(sorter.finish(), vec![9, 5]);
+    }
+}
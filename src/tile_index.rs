@@ -1,7 +1,21 @@
-use log::debug;
+use std::cmp::Ordering;
+use std::mem;
+use std::ops::{Range, RangeInclusive};
+
+use crate::key_extractor::KeyExtractor;
+use crate::logging::debug;
+use crate::opcount;
 
 /// Represents a contiguous sorted block (tile) in the input data.
-#[derive(Debug, Clone)]
+///
+/// A tile is just a range of input positions. Its first and last keys are
+/// read from the extracted key array on demand, so creating or splitting a
+/// tile never clones a key or allocates.
+///
+/// Tiles are inspected through [`TileIndex::iter`], on the index of a
+/// [`SortPlan`](crate::SortPlan).
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Tile {
     /// Starting index in the original array
     start_index: usize,
@@ -14,7 +28,8 @@ impl Tile {
         Tile { start_index, count }
     }
 
-    pub(crate) fn start_idx(&self) -> usize {
+    /// Input position of the tile's first element.
+    pub fn start(&self) -> usize {
         self.start_index
     }
 
@@ -27,10 +42,37 @@ impl Tile {
         self.end_idx() - 1
     }
 
-    pub(crate) fn len(&self) -> usize {
+    /// Number of elements in the tile; never zero.
+    pub fn len(&self) -> usize {
         self.count
     }
 
+    /// Returns `true` if the tile holds no elements, which no scanned tile does.
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Input positions covered by the tile.
+    pub fn range(&self) -> Range<usize> {
+        self.start_index..self.end_idx()
+    }
+
+    /// Keys of the tile's first and last elements in `data`, the slice the
+    /// tile was scanned from. All the tile's keys lie within this range, in
+    /// the direction of the sort.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tile lies outside `data`.
+    pub fn key_range<T, K, E>(&self, data: &[T], key_extractor: E) -> RangeInclusive<K>
+    where
+        E: KeyExtractor<T, K>,
+    {
+        let first = key_extractor.extract_key(&data[self.start_index]);
+        let last = key_extractor.extract_key(&data[self.end_idx() - 1]);
+        first..=last
+    }
+
     /// Get the key of the first element (the "tile key")
     pub(crate) fn tile_key<'a, K>(&self, element_keys: &'a [K]) -> &'a K {
         &element_keys[self.start_index]
@@ -41,227 +83,709 @@ impl Tile {
         &element_keys[self.start_index + self.count - 1]
     }
 
-    /// Binary search to find the split point in a tile.
-    pub(crate) fn find_split_point<K: Ord>(
+    /// Index of the first element that does not sort before `split_key`.
+    pub(crate) fn lower_bound<K: Ord>(
         &self,
         element_keys: &[K],
         split_key: &K,
         reverse: bool,
     ) -> usize {
-        let start = self.start_index;
-        let end = self.start_index + self.count;
-        let slice = &element_keys[start..end];
-        let result = slice.binary_search_by(|elem| {
-            if reverse {
-                split_key.cmp(elem)
-            } else {
-                elem.cmp(split_key)
-            }
-        });
+        let slice = &element_keys[self.start_index..self.end_idx()];
+        self.start_index + slice.partition_point(|key| precedes(key, split_key, reverse))
+    }
+
+    /// Index of the first element that sorts after `split_key`.
+    pub(crate) fn upper_bound<K: Ord>(
+        &self,
+        element_keys: &[K],
+        split_key: &K,
+        reverse: bool,
+    ) -> usize {
+        let slice = &element_keys[self.start_index..self.end_idx()];
+        self.start_index + slice.partition_point(|key| !precedes(split_key, key, reverse))
+    }
+}
 
-        match result {
-            Ok(idx) => start + idx,
-            Err(idx) => start + idx,
+/// Index of some tile in `tiles` for which `cmp` returns `Equal`, or else of
+/// the first for which it returns `Greater`.
+fn search_tiles(tiles: &[Tile], cmp: impl Fn(&Tile) -> Ordering) -> usize {
+    // `slice::binary_search_by` does not promise to stop at the first match
+    let (mut low, mut high) = (0, tiles.len());
+    while low < high {
+        let mid = low + (high - low) / 2;
+        match cmp(&tiles[mid]) {
+            Ordering::Less => low = mid + 1,
+            Ordering::Greater => high = mid,
+            Ordering::Equal => return mid,
+        }
+    }
+    low
+}
+
+/// Order of `a` relative to `b` in the requested direction.
+fn compare<K: Ord>(a: &K, b: &K, reverse: bool) -> Ordering {
+    opcount::comparisons(1);
+    if reverse {
+        b.cmp(a)
+    } else {
+        a.cmp(b)
+    }
+}
+
+/// Returns `true` if `a` sorts strictly before `b` in the requested direction.
+pub(crate) fn precedes<K: Ord>(a: &K, b: &K, reverse: bool) -> bool {
+    opcount::comparisons(1);
+    if reverse {
+        a > b
+    } else {
+        a < b
+    }
+}
+
+/// Largest number of tiles kept in one bucket before it is split in half.
+const BUCKET_CAPACITY: usize = 512;
+
+/// Most tiles a [`TileIndex`] holds inline, before allocating its first
+/// bucket. See [`SortConfig::inline_tiles`](crate::SortConfig::inline_tiles).
+pub const MAX_INLINE_TILES: usize = 16;
+
+/// Tiles stored inside the index itself, so small indexes never allocate.
+#[derive(Debug, Clone)]
+struct InlineTiles {
+    tiles: [Tile; MAX_INLINE_TILES],
+    len: usize,
+}
+
+impl InlineTiles {
+    fn new() -> Self {
+        InlineTiles {
+            tiles: [Tile::new(0, 0); MAX_INLINE_TILES],
+            len: 0,
+        }
+    }
+
+    fn as_slice(&self) -> &[Tile] {
+        &self.tiles[..self.len]
+    }
+
+    fn insert(&mut self, offset: usize, tile: Tile) {
+        self.tiles.copy_within(offset..self.len, offset + 1);
+        self.tiles[offset] = tile;
+        self.len += 1;
+    }
+}
+
+/// Location of a tile in a [`TileIndex`]: a bucket and an offset within it.
+///
+/// An offset equal to the bucket's length refers to the slot just past its
+/// last tile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Position {
+    bucket: usize,
+    offset: usize,
+}
+
+impl Position {
+    fn next(self) -> Position {
+        Position {
+            bucket: self.bucket,
+            offset: self.offset + 1,
         }
     }
 }
 
 /// A collection of tiles maintained in sorted order by tile key.
 ///
-/// This is a newtype wrapper around Vec<Tile> to allow easy replacement
-/// with a different data structure if needed.
-#[derive(Debug)]
+/// The tiles are stored in two levels: a list of buckets, each holding up to
+/// a few hundred consecutive tiles. Lookups binary search the buckets
+/// by their last tile and then the bucket itself, and an insertion only
+/// shifts the tiles of one bucket, splitting it once it is full. This keeps
+/// inserts cheap with millions of tiles, where a single `Vec` would move
+/// half the index on every split.
+///
+/// The first few tiles (up to the configured inline capacity) are kept in an
+/// array inside the index rather than in a bucket, so sorting nearly sorted
+/// data allocates nothing for the index. They move to the first bucket once
+/// there are more.
+///
+/// A scanned index is available from [`SortPlan::tile_index`](crate::SortPlan::tile_index)
+/// for inspection; it is only built and modified by the sort itself.
+///
+/// With the `serde` feature the index serializes as its flat list of tiles.
+#[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "SerializedTileIndex", try_from = "SerializedTileIndex")
+)]
 pub struct TileIndex {
-    tiles: Vec<Tile>,
+    /// Tiles in sorted order while there are no buckets
+    inline: InlineTiles,
+    /// Number of tiles held inline before the first bucket is allocated
+    inline_capacity: usize,
+    /// Tiles in sorted order; no bucket is empty
+    buckets: Vec<Vec<Tile>>,
+    /// Total number of tiles
+    len: usize,
+    /// Tiles expected, to size the first bucket (see [`TileIndex::reserve`])
+    expected: usize,
+    /// Keep equal keys in input order (see [`TileIndex::insert_tile`])
+    stable: bool,
 }
 
 impl TileIndex {
-    pub(crate) fn new() -> Self {
-        TileIndex { tiles: Vec::new() }
+    /// An empty index holding up to `inline_tiles` tiles (at most
+    /// [`MAX_INLINE_TILES`]) before it allocates.
+    pub(crate) fn new(stable: bool, inline_tiles: usize) -> Self {
+        TileIndex {
+            inline: InlineTiles::new(),
+            inline_capacity: inline_tiles.min(MAX_INLINE_TILES),
+            buckets: Vec::new(),
+            len: 0,
+            expected: 0,
+            stable,
+        }
     }
 
-    pub(crate) fn len(&self) -> usize {
-        self.tiles.len()
+    /// Allocate for about `tiles` tiles in all, once the inline tiles are
+    /// exceeded.
+    ///
+    /// The first bucket is sized for them up to [`BUCKET_CAPACITY`], and the
+    /// list of buckets for as many as they would fill. Both stay within
+    /// [`estimated_bytes`](TileIndex::estimated_bytes) for `tiles` tiles.
+    pub(crate) fn reserve(&mut self, tiles: usize) {
+        if tiles <= self.inline_capacity {
+            return;
+        }
+        self.expected = tiles;
+        self.buckets.reserve(tiles / (BUCKET_CAPACITY / 2) + 1);
     }
 
-    fn is_empty(&self) -> bool {
-        self.tiles.is_empty()
+    /// Upper bound on the heap memory of an index holding `tiles` tiles, of
+    /// which up to `inline_tiles` are stored without allocating.
+    ///
+    /// While there is a single bucket, its capacity is at most the next power
+    /// of two. After that every bucket holds at least half of
+    /// [`BUCKET_CAPACITY`] tiles, and its capacity may have doubled past
+    /// `BUCKET_CAPACITY` just before it was split.
+    pub(crate) fn estimated_bytes(tiles: usize, inline_tiles: usize) -> usize {
+        let tile = mem::size_of::<Tile>();
+        let bucket = mem::size_of::<Vec<Tile>>();
+        if tiles <= inline_tiles.min(MAX_INLINE_TILES) {
+            0
+        } else if tiles <= BUCKET_CAPACITY {
+            tiles.next_power_of_two().max(4) * tile + 4 * bucket
+        } else {
+            let buckets = tiles / (BUCKET_CAPACITY / 2);
+            buckets.saturating_mul((2 * BUCKET_CAPACITY + 4) * tile + 2 * bucket)
+        }
     }
 
-    fn get(&self, index: usize) -> Option<&Tile> {
-        self.tiles.get(index)
+    #[cfg(all(test, feature = "verify"))]
+    pub(crate) fn from_tiles(tiles: Vec<Tile>) -> Self {
+        Self::from_sorted_tiles(tiles, true)
     }
 
-    pub(crate) fn iter(&self) -> impl Iterator<Item = &Tile> {
-        self.tiles.iter()
+    /// Build an index from tiles already in sorted order.
+    #[cfg(any(feature = "serde", all(test, feature = "verify")))]
+    fn from_sorted_tiles(tiles: Vec<Tile>, stable: bool) -> Self {
+        TileIndex {
+            inline: InlineTiles::new(),
+            inline_capacity: MAX_INLINE_TILES,
+            len: tiles.len(),
+            expected: 0,
+            buckets: tiles
+                .chunks(BUCKET_CAPACITY)
+                .map(<[Tile]>::to_vec)
+                .collect(),
+            stable,
+        }
     }
 
-    fn insert(&mut self, index: usize, tile: Tile) {
-        self.tiles.insert(index, tile);
+    /// Number of tiles.
+    pub fn len(&self) -> usize {
+        self.len
     }
 
-    fn push(&mut self, tile: Tile) {
-        self.tiles.push(tile);
+    /// Returns `true` for the index of an empty slice.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
     }
 
-    /// Insert a new tile into the tile index, potentially splitting the new tile if it spans multiple positions.
-    pub fn insert_tile<K: Ord>(&mut self, new_tile: Tile, element_keys: &[K], reverse: bool) {
-        // If this is the first tile, just add it
-        if self.is_empty() {
-            self.push(new_tile);
-            return;
+    /// Returns `true` while the tiles are held inline.
+    fn is_inline(&self) -> bool {
+        self.buckets.is_empty()
+    }
+
+    /// The tiles of bucket `bucket`; the inline tiles count as bucket 0.
+    fn bucket(&self, bucket: usize) -> Option<&[Tile]> {
+        if self.is_inline() {
+            (bucket == 0).then(|| self.inline.as_slice())
+        } else {
+            self.buckets.get(bucket).map(Vec::as_slice)
+        }
+    }
+
+    /// The tile at `position`, if any.
+    fn get(&self, position: Position) -> Option<&Tile> {
+        let bucket = self.bucket(position.bucket)?;
+        match bucket.get(position.offset) {
+            Some(tile) => Some(tile),
+            // Just past the end of a bucket: the next bucket's first tile
+            None => self.bucket(position.bucket + 1)?.first(),
         }
+    }
 
-        // Find where the new tile's start (tile_key) should be inserted
-        // Also check for overlaps with existing tiles
-        let mut insert_position = self.len(); // Default to end
+    /// The tile at `position`, which must hold one.
+    fn get_mut(&mut self, position: Position) -> &mut Tile {
+        if self.is_inline() {
+            &mut self.inline.tiles[..self.inline.len][position.offset]
+        } else {
+            &mut self.buckets[position.bucket][position.offset]
+        }
+    }
 
-        for i in 0..self.len() {
-            let current = self.get(i).unwrap();
+    /// The position just before `position`, unless it is the first.
+    fn prev(&self, position: Position) -> Option<Position> {
+        if position.offset > 0 {
+            Some(Position {
+                bucket: position.bucket,
+                offset: position.offset - 1,
+            })
+        } else if position.bucket > 0 {
+            Some(Position {
+                bucket: position.bucket - 1,
+                offset: self.buckets[position.bucket - 1].len() - 1,
+            })
+        } else {
+            None
+        }
+    }
 
-            let should_insert_before = if reverse {
-                new_tile.tile_key(element_keys) > current.tile_key(element_keys)
-            } else {
-                new_tile.tile_key(element_keys) < current.tile_key(element_keys)
+    /// Position of the first tile for which `pred` is false, assuming `pred`
+    /// holds for a prefix of the tiles; the end of the index if none.
+    fn partition_point(&self, mut pred: impl FnMut(&Tile) -> bool) -> Position {
+        if self.is_inline() {
+            return Position {
+                bucket: 0,
+                offset: self.inline.as_slice().partition_point(pred),
             };
+        }
+        let bucket = self
+            .buckets
+            .partition_point(|bucket| pred(bucket.last().expect("buckets are never empty")));
+        match self.buckets.get(bucket) {
+            Some(tiles) => Position {
+                bucket,
+                offset: tiles.partition_point(pred),
+            },
+            None => self.end(),
+        }
+    }
 
-            if should_insert_before {
-                insert_position = i;
-                break;
+    /// Position of some tile for which `cmp` returns `Equal`, or else of the
+    /// first tile for which it returns `Greater`, assuming the tiles are
+    /// ordered by `cmp`. Unlike [`partition_point`](TileIndex::partition_point)
+    /// the search stops at the first equal tile it meets, which with many
+    /// equal keys is usually after a few steps.
+    fn search(&self, cmp: impl Fn(&Tile) -> Ordering) -> Position {
+        if self.is_inline() {
+            return Position {
+                bucket: 0,
+                offset: search_tiles(self.inline.as_slice(), cmp),
+            };
+        }
+        // Find the first bucket whose last tile is not less, stopping early
+        // at an equal one
+        let (mut low, mut high) = (0, self.buckets.len());
+        while low < high {
+            let mid = low + (high - low) / 2;
+            let last = self.buckets[mid].len() - 1;
+            match cmp(&self.buckets[mid][last]) {
+                Ordering::Less => low = mid + 1,
+                Ordering::Greater => high = mid,
+                Ordering::Equal => {
+                    return Position {
+                        bucket: mid,
+                        offset: last,
+                    }
+                }
             }
+        }
+        match self.buckets.get(low) {
+            Some(tiles) => Position {
+                bucket: low,
+                offset: search_tiles(tiles, cmp),
+            },
+            None => self.end(),
+        }
+    }
+
+    /// The slot just past the last tile.
+    fn end(&self) -> Position {
+        match self.buckets.last() {
+            Some(last) => Position {
+                bucket: self.buckets.len() - 1,
+                offset: last.len(),
+            },
+            None => Position {
+                bucket: 0,
+                offset: self.inline.len,
+            },
+        }
+    }
+
+    /// The tiles in sorted order: the elements of each tile, taken tile by
+    /// tile in this order, are the sorted slice.
+    pub fn iter(&self) -> impl Iterator<Item = &Tile> {
+        // At most one of the two is non-empty
+        self.inline
+            .as_slice()
+            .iter()
+            .chain(self.buckets.iter().flatten())
+    }
 
-            // Check if the new tile falls within this existing tile's range
-            // This means we need to split the EXISTING tile
-            let new_within_existing = if reverse {
-                new_tile.tile_key(element_keys) < current.tile_key(element_keys)
-                    && new_tile.tile_key(element_keys) > current.end_key(element_keys)
+    /// Input position of the first element, in sorted order, for which
+    /// `cmp` returns `Equal`, where `cmp` orders the element at an input
+    /// position against the target in the direction of the sort.
+    pub(crate) fn find(&self, cmp: impl Fn(usize) -> Ordering) -> Option<usize> {
+        // The tiles hold consecutive key ranges, so only the first tile not
+        // ending before the target can hold it
+        let tile =
+            self.get(self.partition_point(|tile| cmp(tile.end_index()) == Ordering::Less))?;
+        let (mut low, mut high) = (tile.start(), tile.end_idx());
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if cmp(mid) == Ordering::Less {
+                low = mid + 1;
             } else {
-                new_tile.tile_key(element_keys) > current.tile_key(element_keys)
-                    && new_tile.tile_key(element_keys) < current.end_key(element_keys)
-            };
+                high = mid;
+            }
+        }
+        // The tile's last element is not less, so `low` is within it
+        (cmp(low) == Ordering::Equal).then_some(low)
+    }
+
+    /// Sorted position of the first element for which `pred`, given an
+    /// element's input position, is false, assuming it holds for a prefix of
+    /// the elements in sorted order.
+    pub(crate) fn sorted_partition_point(&self, mut pred: impl FnMut(usize) -> bool) -> usize {
+        let position = self.partition_point(|tile| pred(tile.end_index()));
+        let before = if self.is_inline() {
+            position.offset
+        } else {
+            let full: usize = self.buckets[..position.bucket].iter().map(Vec::len).sum();
+            full + position.offset
+        };
+        let offset: usize = self.iter().take(before).map(Tile::len).sum();
+        match self.get(position) {
+            // The tile's last element fails `pred`, so the point is within it
+            Some(tile) => {
+                let (mut low, mut high) = (tile.start(), tile.end_index());
+                while low < high {
+                    let mid = low + (high - low) / 2;
+                    if pred(mid) {
+                        low = mid + 1;
+                    } else {
+                        high = mid;
+                    }
+                }
+                offset + low - tile.start()
+            }
+            None => offset,
+        }
+    }
+
+    /// Input indices of the elements in sorted order.
+    pub(crate) fn sorted_indices(&self) -> impl Iterator<Item = usize> + '_ {
+        self.iter().flat_map(|tile| tile.start()..tile.end_idx())
+    }
+
+    /// Input index of the element at sorted position `n`, found by walking
+    /// the tiles rather than the elements.
+    pub(crate) fn sorted_index(&self, mut n: usize) -> Option<usize> {
+        for tile in self.iter() {
+            if n < tile.len() {
+                return Some(tile.start() + n);
+            }
+            n -= tile.len();
+        }
+        None
+    }
+
+    /// Returns `true` if every tile lies within `element_keys`, is internally
+    /// in order, and the tiles are in order relative to each other (with
+    /// equal keys in input order when stable). Does not check coverage.
+    pub(crate) fn is_consistent_with<K: Ord>(&self, element_keys: &[K], reverse: bool) -> bool {
+        let mut prev: Option<&Tile> = None;
+        for tile in self.iter() {
+            if tile.is_empty() || tile.end_idx() > element_keys.len() {
+                return false;
+            }
+            let keys = &element_keys[tile.start()..tile.end_idx()];
+            if keys
+                .windows(2)
+                .any(|pair| precedes(&pair[1], &pair[0], reverse))
+            {
+                return false;
+            }
+            if let Some(prev) = prev {
+                let (prev_end, next_start) =
+                    (prev.end_key(element_keys), tile.tile_key(element_keys));
+                if precedes(next_start, prev_end, reverse)
+                    || (self.stable && next_start == prev_end && tile.start() < prev.start())
+                {
+                    return false;
+                }
+            }
+            prev = Some(tile);
+        }
+        true
+    }
+
+    /// Insert `tile` at `position`, returning where it ended up once a full
+    /// bucket has been split.
+    fn insert(&mut self, position: Position, tile: Tile) -> Position {
+        self.len += 1;
+        if self.is_inline() {
+            if self.len <= self.inline_capacity {
+                self.inline.insert(position.offset, tile);
+                return position;
+            }
+            // Out of inline space: move the tiles into the first bucket,
+            // which holds far fewer than `BUCKET_CAPACITY` of them. A power
+            // of two keeps its capacity within `estimated_bytes` as it grows.
+            let expected = self.expected.min(BUCKET_CAPACITY);
+            let mut tiles = Vec::with_capacity(self.len.max(expected).next_power_of_two());
+            tiles.extend_from_slice(self.inline.as_slice());
+            tiles.insert(position.offset, tile);
+            self.inline.len = 0;
+            self.buckets.push(tiles);
+            return position;
+        }
+
+        let bucket = &mut self.buckets[position.bucket];
+        bucket.insert(position.offset, tile);
+        if bucket.len() <= BUCKET_CAPACITY {
+            return position;
+        }
+
+        let half = bucket.len() / 2;
+        let upper = bucket.split_off(half);
+        self.buckets.insert(position.bucket + 1, upper);
+        if position.offset < half {
+            position
+        } else {
+            Position {
+                bucket: position.bucket + 1,
+                offset: position.offset - half,
+            }
+        }
+    }
+
+    /// Insert a new tile into the tile index, potentially splitting the new tile if it spans multiple positions.
+    ///
+    /// Tiles must be inserted in input order. In a stable index, elements of the
+    /// new tile that compare equal to elements already in the index are placed
+    /// after them. An unstable index lets equal keys land on either side,
+    /// whichever needs fewer splits.
+    pub(crate) fn insert_tile<K: Ord>(
+        &mut self,
+        new_tile: Tile,
+        element_keys: &[K],
+        reverse: bool,
+    ) {
+        self.insert_tile_observed(new_tile, element_keys, reverse, &mut |_| {});
+    }
 
-            if new_within_existing {
+    /// Insert a new tile as [`insert_tile`](Self::insert_tile) does, calling
+    /// `on_split` with the input position of the first element after each
+    /// split.
+    pub(crate) fn insert_tile_observed<K: Ord>(
+        &mut self,
+        new_tile: Tile,
+        element_keys: &[K],
+        reverse: bool,
+        on_split: &mut dyn FnMut(usize),
+    ) {
+        // Each split leaves a remainder to insert; loop rather than recurse,
+        // as a long tile may split once per tile already in the index
+        let mut pending = Some(new_tile);
+        let mut depth = 0;
+        while let Some(tile) = pending {
+            pending = self.insert_tile_at_depth(tile, element_keys, reverse, depth, on_split);
+            depth += 1;
+        }
+    }
+
+    /// Insert a tile, or as much of it as precedes the next tile, returning the
+    /// rest. `depth` counts the splits that led to this insertion.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "insert_tile",
+            level = "trace",
+            skip_all,
+            fields(start = new_tile.start(), count = new_tile.len(), depth = depth)
+        )
+    )]
+    fn insert_tile_at_depth<K: Ord>(
+        &mut self,
+        new_tile: Tile,
+        element_keys: &[K],
+        reverse: bool,
+        depth: usize,
+        on_split: &mut dyn FnMut(usize),
+    ) -> Option<Tile> {
+        #[cfg(not(feature = "tracing"))]
+        let _ = depth;
+
+        // If this is the first tile, just add it
+        if self.is_empty() {
+            self.insert(self.end(), new_tile);
+            return None;
+        }
+
+        // Tiles are ordered by tile key, so find the first one that sorts after
+        // the new tile's start. When stable, tiles with an equal key stay in
+        // front: their elements come earlier in the input. When unstable, the
+        // new tile may go in front of any tile with an equal key, so the
+        // search stops at the first one found; with heavily duplicated keys
+        // that saves most of the search through a long run of equal tiles.
+        let new_key = new_tile.tile_key(element_keys);
+        let mut insert_position = if self.stable {
+            self.partition_point(|tile| !precedes(new_key, tile.tile_key(element_keys), reverse))
+        } else {
+            self.search(|tile| compare(tile.tile_key(element_keys), new_key, reverse))
+        };
+
+        // Check if the new tile starts within the preceding tile's range
+        // This means we need to split the EXISTING tile
+        if let Some(prev) = self.prev(insert_position) {
+            let prev_tile = self.get(prev).expect("previous position holds a tile");
+            if precedes(new_key, prev_tile.end_key(element_keys), reverse) {
                 debug!(
-                    "New tile falls within existing tile at position {}, splitting existing",
-                    i
+                    "New tile falls within existing tile {:?}, splitting existing",
+                    prev
                 );
-                self.split_existing_and_insert(i, new_tile, element_keys, reverse);
-                return;
+                // The new tile goes between the two pieces
+                insert_position = self.split_existing(prev, new_key, element_keys, reverse);
+                on_split(self.get(insert_position).expect("split piece").start());
             }
         }
 
-        // Check if the new tile's range extends beyond where it should fit
+        // Check if the new tile's range extends to or beyond the next tile's start
         // This means we need to split the NEW tile
-        for i in insert_position..self.len() {
-            let existing = self.get(i).unwrap();
-
-            // Check if the new tile's end_key extends past this existing tile's start
-            let overlaps = if reverse {
-                new_tile.end_key(element_keys) < existing.tile_key(element_keys)
+        // (when unstable, ending on the next tile's start key is fine)
+        let overlaps = self.get(insert_position).is_some_and(|next| {
+            let new_end = new_tile.end_key(element_keys);
+            let next_key = next.tile_key(element_keys);
+            if self.stable {
+                !precedes(new_end, next_key, reverse)
             } else {
-                new_tile.end_key(element_keys) > existing.tile_key(element_keys)
-            };
-
-            if overlaps {
-                // The new tile spans multiple positions - we need to split it
-                debug!("New tile spans multiple positions, splitting new tile");
-                self.split_new_tile_and_insert(new_tile, element_keys, insert_position, i, reverse);
-                return;
+                precedes(next_key, new_end, reverse)
             }
+        });
+
+        if overlaps {
+            // The new tile spans multiple positions - we need to split it
+            debug!("New tile spans multiple positions, splitting new tile");
+            let rest =
+                self.split_new_tile_and_insert(new_tile, element_keys, insert_position, reverse);
+            on_split(rest.start());
+            return Some(rest);
         }
 
         // No conflict, insert normally
         self.insert(insert_position, new_tile);
+        None
     }
 
-    /// Split the new tile at the boundary and recursively insert pieces.
+    /// Split the new tile at the next tile's start, insert the first piece and
+    /// return the rest.
     fn split_new_tile_and_insert<K: Ord>(
         &mut self,
         new_tile: Tile,
         element_keys: &[K],
-        insert_position: usize,
-        overlapping_tile_index: usize,
+        insert_position: Position,
         reverse: bool,
-    ) {
-        // Find the split point - where does the overlapping tile's range begin?
-        let overlapping_tile = self.get(overlapping_tile_index).unwrap();
-        let split_key = overlapping_tile.tile_key(element_keys);
+    ) -> Tile {
+        opcount::tile_splits(1);
+
+        // Find the split point - where does the next tile's range begin?
+        let split_key = self
+            .get(insert_position)
+            .expect("the new tile overlaps a following tile")
+            .tile_key(element_keys);
 
         debug!(
             "Splitting new tile at start={}, count={}",
-            new_tile.start_idx(),
+            new_tile.start(),
             new_tile.len()
         );
 
-        // Find where in the new tile we should split
-        let split_point = new_tile.find_split_point(element_keys, split_key, reverse);
+        // When stable, elements equal to the split key belong after the next
+        // tile's start; otherwise they can stay in the first piece
+        let split_point = if self.stable {
+            new_tile.lower_bound(element_keys, split_key, reverse)
+        } else {
+            new_tile.upper_bound(element_keys, split_key, reverse)
+        };
 
         debug!("Split point: {}", split_point);
 
-        // Create the two pieces
-        if split_point == new_tile.start_idx() {
-            // Split point is at the start - shouldn't happen, but handle gracefully
-            debug!("Split point at start, inserting whole tile");
-            self.insert(insert_position, new_tile);
-            return;
-        }
+        // The new tile starts before (or, when unstable, at) `split_key` and
+        // ends at (when stable) or after it, so both pieces are non-empty
+        debug_assert!(split_point > new_tile.start() && split_point < new_tile.end_idx());
 
-        if split_point >= new_tile.end_idx() {
-            // Split point is beyond the end - shouldn't happen, but handle gracefully
-            debug!("Split point beyond end, inserting whole tile");
-            self.insert(insert_position, new_tile);
-            return;
-        }
-
-        let first_piece = Tile::new(new_tile.start_idx(), split_point - new_tile.start_idx());
+        let first_piece = Tile::new(new_tile.start(), split_point - new_tile.start());
 
         let second_piece = Tile::new(
             split_point,
-            (new_tile.start_idx() + new_tile.len()) - split_point,
+            (new_tile.start() + new_tile.len()) - split_point,
         );
 
         debug!(
             "Split into: piece1(start={}, count={}), piece2(start={}, count={})",
-            first_piece.start_idx(),
+            first_piece.start(),
             first_piece.len(),
-            second_piece.start_idx(),
+            second_piece.start(),
             second_piece.len()
         );
 
         // Insert the first piece at the current position
         self.insert(insert_position, first_piece);
-
-        // Recursively insert the second piece
-        self.insert_tile(second_piece, element_keys, reverse);
+        second_piece
     }
 
-    /// Split an existing tile and insert the new tile between the pieces.
-    fn split_existing_and_insert<K: Ord>(
+    /// Split an existing tile in two after the last element not sorting after
+    /// `split_key`, leaving the pieces adjacent in the index. Returns the
+    /// position of the second piece.
+    fn split_existing<K: Ord>(
         &mut self,
-        tile_idx: usize,
-        new_tile: Tile,
+        position: Position,
+        split_key: &K,
         element_keys: &[K],
         reverse: bool,
-    ) {
-        // Get the tile to split (need to clone it as we'll be modifying the index)
-        let original_tile = self.get(tile_idx).unwrap().clone();
+    ) -> Position {
+        opcount::tile_splits(1);
+        let original_tile = *self.get(position).expect("split position holds a tile");
 
         debug!(
-            "Splitting existing tile at idx={}, start={}, count={}",
-            tile_idx, original_tile.start_index, original_tile.count
+            "Splitting existing tile at {:?}, start={}, count={}",
+            position, original_tile.start_index, original_tile.count
         );
 
-        // Find where to split the existing tile (at the new tile's start key)
-        let split_point =
-            original_tile.find_split_point(element_keys, new_tile.tile_key(element_keys), reverse);
+        // Elements equal to the split key stay in the first piece
+        let split_point = original_tile.upper_bound(element_keys, split_key, reverse);
 
         debug!("Split point: {}", split_point);
 
-        if split_point == original_tile.start_index || split_point >= original_tile.end_idx() {
-            // Shouldn't happen, but handle gracefully
-            debug!("Invalid split point, inserting without splitting");
-            return;
-        }
+        // The tile starts at or before `split_key` and ends after it, so both
+        // pieces are non-empty
+        debug_assert!(
+            split_point > original_tile.start_index && split_point < original_tile.end_idx()
+        );
 
         // Create the two pieces of the existing tile
         let first_piece = Tile::new(
@@ -274,16 +798,200 @@ impl TileIndex {
             (original_tile.start_index + original_tile.count) - split_point,
         );
 
-        // Remove the original tile
-        self.tiles.remove(tile_idx);
+        *self.get_mut(position) = first_piece;
+        self.insert(position.next(), second_piece)
+    }
+}
+
+/// Serialized form of a [`TileIndex`]: its tiles in sorted order.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SerializedTileIndex {
+    tiles: Vec<Tile>,
+    stable: bool,
+}
+
+#[cfg(feature = "serde")]
+impl From<TileIndex> for SerializedTileIndex {
+    fn from(index: TileIndex) -> Self {
+        SerializedTileIndex {
+            tiles: index.iter().copied().collect(),
+            stable: index.stable,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<SerializedTileIndex> for TileIndex {
+    type Error = String;
 
-        // Insert the first piece at the original position
-        self.insert(tile_idx, first_piece);
+    fn try_from(serialized: SerializedTileIndex) -> Result<Self, String> {
+        if let Some(tile) = serialized.tiles.iter().find(|tile| tile.count == 0) {
+            return Err(format!("empty tile at index {}", tile.start_index));
+        }
+        Ok(TileIndex::from_sorted_tiles(
+            serialized.tiles,
+            serialized.stable,
+        ))
+    }
+}
 
-        // Recursively insert the new tile (might need further splitting)
-        self.insert_tile(new_tile, element_keys, reverse);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index_runs(keys: &[u32], stable: bool) -> TileIndex {
+        let mut index = TileIndex::new(stable, MAX_INLINE_TILES);
+        let mut start = 0;
+        for idx in 1..=keys.len() {
+            if idx == keys.len() || keys[idx] < keys[idx - 1] {
+                index.insert_tile(Tile::new(start, idx - start), keys, false);
+                start = idx;
+            }
+        }
+        index
+    }
+
+    fn sorted_positions(index: &TileIndex) -> Vec<usize> {
+        index
+            .iter()
+            .flat_map(|tile| tile.start()..tile.start() + tile.len())
+            .collect()
+    }
+
+    #[test]
+    fn test_stable_keeps_equal_keys_in_input_order() {
+        let keys = [1, 2, 2, 1, 2, 3];
+        let index = index_runs(&keys, true);
+        assert_eq!(sorted_positions(&index), vec![0, 3, 1, 2, 4, 5]);
+    }
+
+    #[test]
+    fn test_splitting_does_not_clone_keys() {
+        /// A key that can only be compared.
+        #[derive(PartialEq, Eq, PartialOrd, Ord)]
+        struct Key(String);
+
+        let keys: Vec<Key> = ["b", "d", "f", "a", "c", "e", "g"]
+            .into_iter()
+            .map(|k| Key(k.to_string()))
+            .collect();
+        let mut index = TileIndex::new(true, MAX_INLINE_TILES);
+        index.insert_tile(Tile::new(0, 3), &keys, false);
+        index.insert_tile(Tile::new(3, 4), &keys, false);
+
+        assert_eq!(index.len(), 7);
+        let ordered: Vec<&str> = sorted_positions(&index)
+            .into_iter()
+            .map(|i| keys[i].0.as_str())
+            .collect();
+        assert_eq!(ordered, vec!["a", "b", "c", "d", "e", "f", "g"]);
+    }
+
+    #[test]
+    fn test_full_buckets_split() {
+        // Interleave two ascending runs so every tile splits into single elements
+        let n = 4 * BUCKET_CAPACITY as u32;
+        let keys: Vec<u32> = (0..n)
+            .map(|i| i * 2)
+            .chain((0..n).map(|i| i * 2 + 1))
+            .collect();
+        let index = index_runs(&keys, true);
+
+        assert_eq!(index.len(), keys.len());
+        assert!(index.buckets.len() > 1);
+        assert!(index
+            .buckets
+            .iter()
+            .all(|bucket| !bucket.is_empty() && bucket.len() <= BUCKET_CAPACITY));
+
+        let ordered: Vec<u32> = sorted_positions(&index)
+            .into_iter()
+            .map(|i| keys[i])
+            .collect();
+        assert_eq!(ordered, (0..2 * n).collect::<Vec<u32>>());
+        assert_eq!(index.sorted_index(3), Some(n as usize + 1));
+    }
+
+    #[test]
+    fn test_inline_tiles_move_to_bucket_when_full() {
+        // Descending pairs: every run becomes its own tile
+        let keys: Vec<u32> = (0..2 * MAX_INLINE_TILES as u32).rev().collect();
+        for capacity in [0, 1, MAX_INLINE_TILES] {
+            let mut index = TileIndex::new(true, capacity);
+            for (tiles, start) in (1..).zip(0..keys.len()) {
+                index.insert_tile(Tile::new(start, 1), &keys, false);
+                assert_eq!(index.is_inline(), tiles <= capacity);
+            }
+            let ordered: Vec<u32> = sorted_positions(&index)
+                .into_iter()
+                .map(|i| keys[i])
+                .collect();
+            assert_eq!(ordered, (0..keys.len() as u32).collect::<Vec<u32>>());
+        }
+    }
+
+    #[test]
+    fn test_estimated_bytes_bounds_allocation() {
+        let heap_bytes = |index: &TileIndex| {
+            index.buckets.capacity() * mem::size_of::<Vec<Tile>>()
+                + index
+                    .buckets
+                    .iter()
+                    .map(|bucket| bucket.capacity() * mem::size_of::<Tile>())
+                    .sum::<usize>()
+        };
+        for n in [1u32, 3, 100, 600, 5_000] {
+            let keys: Vec<u32> = (0..n)
+                .map(|i| i * 2)
+                .chain((0..n).map(|i| i * 2 + 1))
+                .collect();
+            let index = index_runs(&keys, true);
+            assert!(
+                heap_bytes(&index) <= TileIndex::estimated_bytes(index.len(), MAX_INLINE_TILES)
+            );
+        }
+    }
+
+    #[test]
+    fn test_reserve_allocates_first_bucket_once() {
+        let keys: Vec<u32> = (0..400).rev().collect();
+        let mut index = TileIndex::new(true, MAX_INLINE_TILES);
+        index.reserve(keys.len());
+        index.insert_tile(Tile::new(0, 1), &keys, false);
+        for start in 1..MAX_INLINE_TILES + 1 {
+            index.insert_tile(Tile::new(start, 1), &keys, false);
+        }
+        let capacity = index.buckets[0].capacity();
+        assert!(capacity >= keys.len());
+        for start in MAX_INLINE_TILES + 1..keys.len() {
+            index.insert_tile(Tile::new(start, 1), &keys, false);
+        }
+        assert_eq!(index.buckets[0].capacity(), capacity);
+        assert!(
+            capacity * mem::size_of::<Tile>()
+                <= TileIndex::estimated_bytes(keys.len(), MAX_INLINE_TILES)
+        );
+
+        // Too few tiles to leave the inline storage: nothing to reserve
+        let mut small = TileIndex::new(true, MAX_INLINE_TILES);
+        small.reserve(MAX_INLINE_TILES);
+        assert_eq!(small.buckets.capacity(), 0);
+    }
 
-        // Recursively insert the second piece
-        self.insert_tile(second_piece, element_keys, reverse);
+    #[test]
+    fn test_unstable_splits_less_on_touching_duplicates() {
+        // The second run ends on the first run's start key
+        let keys = [2, 2, 3, 1, 2];
+        let stable = index_runs(&keys, true);
+        let unstable = index_runs(&keys, false);
+        assert_eq!(stable.len(), 4);
+        assert_eq!(unstable.len(), 2);
+
+        let ordered: Vec<u32> = sorted_positions(&unstable)
+            .into_iter()
+            .map(|i| keys[i])
+            .collect();
+        assert_eq!(ordered, vec![1, 2, 2, 2, 3]);
     }
 }
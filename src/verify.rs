@@ -0,0 +1,335 @@
+//! Self-checks of each sort's result, enabled by the `verify` feature.
+//!
+//! Restructuring copies each tile's elements, in tile order, into the output.
+//! So if the tiles partition the input indices, the output is a permutation of
+//! the input, and if the keys are ordered when walked in tile order, so is the
+//! output. Checking the index before restructuring therefore verifies the
+//! result without re-extracting keys or comparing multisets.
+//!
+//! Sorts that find their order without a tile index (insertion sort, pattern
+//! shortcuts and counting sort) list it as the source index of each output
+//! position instead, which is checked against the keys the same way. The
+//! moves that carry out an order without copying tiles (cycle-following
+//! permutations, rotations and deque restructuring) are then replayed on the
+//! indices themselves, so a slip in them is caught before any element moves.
+use crate::array::STACK_SORT_MAX_LEN;
+use crate::opcount;
+use crate::pattern::Shortcut;
+use crate::small::Sources;
+use crate::tile_index::TileIndex;
+
+/// Number of order entries shown around a failure.
+const EXCERPT: usize = 16;
+
+/// Panic with a dump of `tile_index` unless it describes a correct sort of
+/// `element_keys`.
+pub(crate) fn verify_tile_index<K: Ord>(tile_index: &TileIndex, element_keys: &[K], reverse: bool) {
+    if let Err(problem) = check_tile_index(tile_index, element_keys, reverse) {
+        panic!(
+            "tilesort verification failed: {}\nelements: {}, reverse: {}\ntile index: {:#?}",
+            problem,
+            element_keys.len(),
+            reverse,
+            tile_index
+        );
+    }
+}
+
+fn check_tile_index<K: Ord>(
+    tile_index: &TileIndex,
+    element_keys: &[K],
+    reverse: bool,
+) -> Result<(), String> {
+    // The tiles must cover every index exactly once
+    let mut ranges: Vec<(usize, usize)> = tile_index
+        .iter()
+        .map(|tile| (tile.start(), tile.start() + tile.len()))
+        .collect();
+    ranges.sort_unstable();
+
+    let mut next = 0;
+    for &(start, end) in &ranges {
+        if start == end {
+            return Err(format!("empty tile at index {}", start));
+        }
+        if start < next {
+            return Err(format!("tiles overlap at index {}", start));
+        }
+        if start > next {
+            return Err(format!(
+                "indices {}..{} are not covered by any tile",
+                next, start
+            ));
+        }
+        next = end;
+    }
+    if next != element_keys.len() {
+        return Err(format!(
+            "tiles cover {} of {} elements",
+            next,
+            element_keys.len()
+        ));
+    }
+
+    // Walking the tiles in order must visit the keys in sorted order
+    let mut position = 0;
+    let mut previous: Option<&K> = None;
+    for tile in tile_index.iter() {
+        for key in &element_keys[tile.start()..tile.start() + tile.len()] {
+            if let Some(previous) = previous {
+                let out_of_order = if reverse {
+                    key > previous
+                } else {
+                    key < previous
+                };
+                if out_of_order {
+                    return Err(format!("output is out of order at position {}", position));
+                }
+            }
+            previous = Some(key);
+            position += 1;
+        }
+    }
+
+    Ok(())
+}
+
+/// Panic unless `order`, the input index each output position takes its
+/// element from, is a permutation of the input visiting `element_keys` in
+/// sorted order.
+pub(crate) fn verify_order<K: Ord>(order: &[usize], element_keys: &[K], reverse: bool) {
+    if let Err((position, problem)) = check_order(order, element_keys, reverse) {
+        panic!(
+            "tilesort verification failed: {}\nelements: {}, reverse: {}\norder from position {}: {:?}",
+            problem,
+            element_keys.len(),
+            reverse,
+            excerpt_start(position),
+            excerpt(order, position)
+        );
+    }
+}
+
+/// Panic unless `shortcut`, found for `element_keys`, sorts them.
+pub(crate) fn verify_shortcut<K: Ord>(shortcut: &Shortcut, element_keys: &[K], reverse: bool) {
+    let mut order = identity(element_keys.len());
+    match shortcut {
+        Shortcut::Sorted => verify_order(order.as_ref(), element_keys, reverse),
+        Shortcut::Reverse => {
+            order.as_mut().reverse();
+            verify_order(order.as_ref(), element_keys, reverse)
+        }
+        Shortcut::Permute(sources) => verify_order(sources.as_ref(), element_keys, reverse),
+    }
+}
+
+/// Panic unless `moves`, replayed on the indices `0..expected.len()`, leaves
+/// position `i` holding `expected[i]`, so that making the same moves on the
+/// elements puts them in that order.
+///
+/// The replay's operations are not counted by the `opcount` feature.
+pub(crate) fn verify_moves(expected: &[usize], moves: impl FnOnce(&mut [usize])) {
+    let mut indices = identity(expected.len());
+    let indices = indices.as_mut();
+    opcount::unrecorded(|| moves(indices));
+    if let Some(position) = (0..expected.len()).find(|&i| indices[i] != expected[i]) {
+        panic!(
+            "tilesort verification failed: position {} receives index {} instead of {}\nelements: {}\nexpected from position {}: {:?}\nreceived: {:?}",
+            position,
+            indices[position],
+            expected[position],
+            expected.len(),
+            excerpt_start(position),
+            excerpt(expected, position),
+            excerpt(indices, position)
+        );
+    }
+}
+
+fn check_order<K: Ord>(
+    order: &[usize],
+    element_keys: &[K],
+    reverse: bool,
+) -> Result<(), (usize, String)> {
+    if order.len() != element_keys.len() {
+        return Err((
+            order.len().min(element_keys.len()),
+            format!(
+                "order lists {} of {} elements",
+                order.len(),
+                element_keys.len()
+            ),
+        ));
+    }
+
+    // Every input index must appear exactly once
+    let mut seen = scratch(order.len());
+    for (position, &index) in order.iter().enumerate() {
+        match seen.as_mut().get_mut(index) {
+            None => {
+                return Err((
+                    position,
+                    format!("position {} takes index {}, past the end", position, index),
+                ))
+            }
+            Some(1) => {
+                return Err((
+                    position,
+                    format!(
+                        "index {} is taken twice, again at position {}",
+                        index, position
+                    ),
+                ))
+            }
+            Some(slot) => *slot = 1,
+        }
+    }
+
+    // Following the order must visit the keys in sorted order
+    for (position, pair) in order.windows(2).enumerate() {
+        let (previous, key) = (&element_keys[pair[0]], &element_keys[pair[1]]);
+        let out_of_order = if reverse {
+            key > previous
+        } else {
+            key < previous
+        };
+        if out_of_order {
+            return Err((
+                position + 1,
+                format!("output is out of order at position {}", position + 1),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// `len` zeros, on the stack for inputs short enough to sort there, so that
+/// checking never allocates where the sort does not.
+fn scratch(len: usize) -> Sources {
+    if len <= STACK_SORT_MAX_LEN {
+        Sources::Inline([0; STACK_SORT_MAX_LEN], len)
+    } else {
+        Sources::Heap(vec![0; len])
+    }
+}
+
+/// The indices `0..len`, kept as [`scratch`] keeps them.
+fn identity(len: usize) -> Sources {
+    let mut indices = scratch(len);
+    for (i, slot) in indices.as_mut().iter_mut().enumerate() {
+        *slot = i;
+    }
+    indices
+}
+
+/// A copy of `indices` for replaying moves that use them up, kept as
+/// [`scratch`] keeps them.
+pub(crate) fn copied(indices: &[usize]) -> Sources {
+    let mut copy = scratch(indices.len());
+    copy.as_mut().copy_from_slice(indices);
+    copy
+}
+
+fn excerpt_start(position: usize) -> usize {
+    position.saturating_sub(EXCERPT / 2)
+}
+
+/// The entries of `order` around `position`.
+fn excerpt(order: &[usize], position: usize) -> &[usize] {
+    let start = excerpt_start(position).min(order.len());
+    &order[start..(start + EXCERPT).min(order.len())]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tile_index::Tile;
+
+    fn index(tiles: &[(usize, usize)]) -> TileIndex {
+        TileIndex::from_tiles(
+            tiles
+                .iter()
+                .map(|&(start, count)| Tile::new(start, count))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_accepts_correct_index() {
+        let keys = [4, 5, 1, 2, 3];
+        verify_tile_index(&index(&[(2, 3), (0, 2)]), &keys, false);
+        verify_tile_index(&index(&[(2, 3), (0, 2)]), &[-1, -2, 2, 1, 0], true);
+    }
+
+    #[test]
+    #[should_panic(expected = "not covered")]
+    fn test_rejects_gap() {
+        verify_tile_index(&index(&[(0, 2), (3, 2)]), &[1, 2, 3, 4, 5], false);
+    }
+
+    #[test]
+    #[should_panic(expected = "tiles overlap")]
+    fn test_rejects_overlap() {
+        verify_tile_index(&index(&[(0, 3), (2, 3)]), &[1, 2, 3, 4, 5], false);
+    }
+
+    #[test]
+    #[should_panic(expected = "tiles cover 4 of 5")]
+    fn test_rejects_missing_tail() {
+        verify_tile_index(&index(&[(0, 4)]), &[1, 2, 3, 4, 5], false);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of order at position 2")]
+    fn test_rejects_misordered_tiles() {
+        verify_tile_index(&index(&[(0, 2), (2, 3)]), &[4, 5, 1, 2, 3], false);
+    }
+
+    #[test]
+    fn test_accepts_correct_order() {
+        verify_order(&[1, 3, 0, 2], &[5, 1, 7, 2], false);
+        verify_order(&[2, 0, 3, 1], &[5, 1, 7, 2], true);
+        verify_order(&[], &[0u8; 0], false);
+    }
+
+    #[test]
+    #[should_panic(expected = "index 0 is taken twice, again at position 2")]
+    fn test_rejects_repeated_index() {
+        verify_order(&[0, 1, 0], &[1, 1, 1], false);
+    }
+
+    #[test]
+    #[should_panic(expected = "position 1 takes index 3, past the end")]
+    fn test_rejects_index_past_end() {
+        verify_order(&[0, 3, 1], &[1, 2, 3], false);
+    }
+
+    #[test]
+    #[should_panic(expected = "order lists 2 of 3 elements")]
+    fn test_rejects_short_order() {
+        verify_order(&[0, 1], &[1, 2, 3], false);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of order at position 2")]
+    fn test_rejects_misordered_order() {
+        verify_order(&[0, 1, 2], &[1, 3, 2], false);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of order at position 1")]
+    fn test_rejects_wrong_shortcut() {
+        verify_shortcut(&Shortcut::Reverse, &[1, 2, 3], false);
+    }
+
+    #[test]
+    fn test_accepts_faithful_moves() {
+        verify_moves(&[2, 0, 1], |indices| indices.rotate_right(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "position 0 receives index 1 instead of 2")]
+    fn test_rejects_unfaithful_moves() {
+        verify_moves(&[2, 0, 1], |indices| indices.rotate_left(1));
+    }
+}
@@ -0,0 +1,192 @@
+//! Read-only access to scanned data in sorted order, without moving it.
+
+use std::ops::{Bound, Range, RangeBounds};
+
+use crate::tile_index::{Tile, TileIndex};
+
+/// The elements of a slice in sorted order, read through its tiles.
+///
+/// Created by [`SortPlan::view`](crate::SortPlan::view). The slice is never
+/// reordered: sorted position `n` is looked up in the tile holding it, so
+/// read-mostly data that is nearly sorted can be searched and iterated in
+/// order without paying for a restructure. Lookups bisect first the tiles and
+/// then one tile, taking a logarithmic number of comparisons.
+///
+/// Searches follow the direction the plan was scanned in: for a descending
+/// plan, [`binary_search_by_key`](SortedView::binary_search_by_key) and
+/// [`range`](SortedView::range) expect keys to decrease along the view.
+///
+/// # Examples
+///
+/// ```
+/// use tilesort::Order;
+///
+/// let data = vec![50, 60, 70, 10, 20, 30, 40];
+/// let plan = tilesort::scan(&data, |&x| x, Order::Ascending);
+/// let view = plan.view(&data);
+///
+/// assert_eq!(view.get(2), Some(&30));
+/// assert_eq!(view.binary_search_by_key(&60, |&x| x), Ok(5));
+/// assert_eq!(view.binary_search_by_key(&35, |&x| x), Err(3));
+/// let middle: Vec<i32> = view.range(25..=55, |&x| x).copied().collect();
+/// assert_eq!(middle, vec![30, 40, 50]);
+///
+/// // The data itself is untouched
+/// assert_eq!(data, vec![50, 60, 70, 10, 20, 30, 40]);
+/// ```
+#[derive(Debug)]
+pub struct SortedView<'a, T> {
+    data: &'a [T],
+    /// The tiles in sorted order
+    tiles: Vec<Tile>,
+    /// Sorted position of the first element of each tile
+    offsets: Vec<usize>,
+    descending: bool,
+}
+
+impl<'a, T> SortedView<'a, T> {
+    pub(crate) fn new(data: &'a [T], tile_index: &TileIndex, descending: bool) -> Self {
+        let tiles: Vec<Tile> = tile_index.iter().copied().collect();
+        let offsets = tiles
+            .iter()
+            .scan(0, |offset, tile| {
+                let start = *offset;
+                *offset += tile.len();
+                Some(start)
+            })
+            .collect();
+        SortedView {
+            data,
+            tiles,
+            offsets,
+            descending,
+        }
+    }
+
+    /// Number of elements in the view.
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Returns `true` if the view holds no elements.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// The element at sorted position `n`, or `None` past the end.
+    pub fn get(&self, n: usize) -> Option<&'a T> {
+        self.input_index(n).map(|index| &self.data[index])
+    }
+
+    /// Input position of the element at sorted position `n`, or `None` past
+    /// the end.
+    pub fn input_index(&self, n: usize) -> Option<usize> {
+        if n >= self.len() {
+            return None;
+        }
+        let tile = self.tile_at(n);
+        Some(self.tiles[tile].start() + n - self.offsets[tile])
+    }
+
+    /// The elements in sorted order.
+    pub fn iter(&self) -> impl Iterator<Item = &'a T> + '_ {
+        self.iter_positions(0..self.len())
+    }
+
+    /// Sorted position of the first element for which `pred` is false,
+    /// assuming `pred` holds for a prefix of the view, as
+    /// [`slice::partition_point`] does for a slice.
+    pub fn partition_point(&self, mut pred: impl FnMut(&T) -> bool) -> usize {
+        // The first tile whose last element fails `pred` holds the point
+        let tile = self
+            .tiles
+            .partition_point(|tile| pred(&self.data[tile.range().end - 1]));
+        match self.tiles.get(tile) {
+            Some(found) => self.offsets[tile] + self.data[found.range()].partition_point(pred),
+            None => self.len(),
+        }
+    }
+
+    /// Binary search the view for an element whose key, computed by `f`, is
+    /// `b`, as [`slice::binary_search_by_key`] does for a slice.
+    ///
+    /// Returns `Ok` with the sorted position of the first match, or `Err`
+    /// with the position where an element with key `b` would be inserted to
+    /// keep the view sorted.
+    pub fn binary_search_by_key<B, F>(&self, b: &B, mut f: F) -> Result<usize, usize>
+    where
+        B: Ord,
+        F: FnMut(&T) -> B,
+    {
+        let descending = self.descending;
+        let position = self.partition_point(|element| {
+            let key = f(element);
+            if descending {
+                key > *b
+            } else {
+                key < *b
+            }
+        });
+        match self.get(position) {
+            Some(element) if f(element) == *b => Ok(position),
+            _ => Err(position),
+        }
+    }
+
+    /// The elements whose key, computed by `key_fn`, lies in `range`, in
+    /// sorted order.
+    pub fn range<K, R, F>(&self, range: R, key_fn: F) -> impl Iterator<Item = &'a T> + '_
+    where
+        K: Ord,
+        R: RangeBounds<K>,
+        F: Fn(&T) -> K,
+    {
+        let below = |element: &T| match range.start_bound() {
+            Bound::Included(start) => key_fn(element) < *start,
+            Bound::Excluded(start) => key_fn(element) <= *start,
+            Bound::Unbounded => false,
+        };
+        let above = |element: &T| match range.end_bound() {
+            Bound::Included(end) => key_fn(element) > *end,
+            Bound::Excluded(end) => key_fn(element) >= *end,
+            Bound::Unbounded => false,
+        };
+        let (first, last) = if self.descending {
+            (
+                self.partition_point(above),
+                self.partition_point(|element| !below(element)),
+            )
+        } else {
+            (
+                self.partition_point(below),
+                self.partition_point(|element| !above(element)),
+            )
+        };
+        // An empty or inverted range has its end before its start
+        self.iter_positions(first..last.max(first))
+    }
+
+    /// Index into `tiles` of the tile holding sorted position `n`, which
+    /// must be in the view.
+    fn tile_at(&self, n: usize) -> usize {
+        self.offsets.partition_point(|&offset| offset <= n) - 1
+    }
+
+    /// The elements at sorted positions `positions`, walking the tiles.
+    fn iter_positions(&self, positions: Range<usize>) -> impl Iterator<Item = &'a T> + '_ {
+        let (data, start, len) = (self.data, positions.start, positions.len());
+        let first = if positions.is_empty() {
+            self.tiles.len()
+        } else {
+            self.tile_at(positions.start)
+        };
+        self.tiles[first..]
+            .iter()
+            .zip(&self.offsets[first..])
+            .flat_map(move |(tile, &offset)| {
+                let skip = start.saturating_sub(offset);
+                &data[tile.start() + skip..tile.range().end]
+            })
+            .take(len)
+    }
+}
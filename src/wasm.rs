@@ -0,0 +1,67 @@
+//! WebAssembly exports for sorting JavaScript typed arrays.
+//!
+//! Build with `wasm-pack build --features wasm`. Each function takes a typed
+//! array and an optional `reverse` flag; wasm-bindgen copies the array into
+//! WebAssembly memory and writes the sorted elements back into it.
+//!
+//! ```js
+//! import { sortFloat64Array } from "tilesort";
+//!
+//! const prices = new Float64Array([10.5, 11.0, 12.25, 9.75, 10.0]);
+//! sortFloat64Array(prices, true);
+//! // prices is now [12.25, 11, 10.5, 10, 9.75]
+//! ```
+
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::config::{Order, SortConfig};
+use crate::float::f64_total_order_key;
+use crate::key_extractor::IdentityKey;
+use crate::sorter;
+
+/// Translate the optional `reverse` argument into a sort configuration.
+fn sort_config(reverse: Option<bool>) -> SortConfig {
+    let order = if reverse.unwrap_or(false) {
+        Order::Descending
+    } else {
+        Order::Ascending
+    };
+    SortConfig::new().order(order)
+}
+
+/// Sort a `Float64Array` in place, ascending unless `reverse` is `true`.
+///
+/// Numbers follow IEEE 754 total order, like `TypedArray.prototype.sort`:
+/// `-0` sorts before `0`, and `NaN` after `Infinity`.
+#[wasm_bindgen(js_name = sortFloat64Array)]
+pub fn sort_float64_array(data: &mut [f64], reverse: Option<bool>) {
+    let extractor = |&x: &f64| f64_total_order_key(x);
+    sorter::tilesort_impl_with_key(data, &extractor, &sort_config(reverse));
+}
+
+/// Sort an `Int32Array` in place, ascending unless `reverse` is `true`.
+#[wasm_bindgen(js_name = sortInt32Array)]
+pub fn sort_int32_array(data: &mut [i32], reverse: Option<bool>) {
+    sorter::tilesort_impl_with_key(data, &IdentityKey, &sort_config(reverse));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_float64_array() {
+        let mut data = vec![3.0, f64::NAN, 4.0, 0.0, -0.0, 1.0];
+        sort_float64_array(&mut data, None);
+        assert_eq!(&data[..5], &[-0.0, 0.0, 1.0, 3.0, 4.0]);
+        assert!(data[0].is_sign_negative() && data[1].is_sign_positive());
+        assert!(data[5].is_nan());
+    }
+
+    #[test]
+    fn test_int32_array_reverse() {
+        let mut data = vec![1, 2, 3, -5, -4, 10];
+        sort_int32_array(&mut data, Some(true));
+        assert_eq!(data, vec![10, 3, 2, 1, -4, -5]);
+    }
+}
@@ -0,0 +1,109 @@
+// Integration tests for the presortedness statistics
+use std::cell::Cell;
+
+use rand::prelude::*;
+use test_log::test;
+
+use tilesort::{
+    count_inversions, run_length_histogram, run_length_histogram_with_config, IdentityKey,
+    SortConfig,
+};
+
+fn brute_force_inversions<T: Ord>(data: &[T]) -> u64 {
+    let mut count = 0;
+    for (i, a) in data.iter().enumerate() {
+        count += data[i + 1..].iter().filter(|b| a > *b).count() as u64;
+    }
+    count
+}
+
+#[test]
+fn test_inversions_match_brute_force() {
+    let mut rng = StdRng::seed_from_u64(29);
+    for _ in 0..20 {
+        let len = rng.random_range(0..500);
+        let data: Vec<i16> = (0..len).map(|_| rng.random_range(-50..50)).collect();
+        assert_eq!(
+            count_inversions(&data, IdentityKey),
+            brute_force_inversions(&data)
+        );
+    }
+}
+
+#[test]
+fn test_inversions_of_tiled_input() {
+    // Every element of the second tile precedes every element of the first
+    let data: Vec<u32> = (1_000..2_000).chain(0..1_000).collect();
+    assert_eq!(count_inversions(&data, IdentityKey), 1_000 * 1_000);
+}
+
+#[test]
+fn test_inversions_extract_each_key_once() {
+    let calls = Cell::new(0);
+    let data: Vec<i32> = (0..300).map(|i| (i * 37) % 101 - 50).collect();
+    let inversions = count_inversions(&data, |x: &i32| {
+        calls.set(calls.get() + 1);
+        x.abs()
+    });
+    let keys: Vec<i32> = data.iter().map(|x| x.abs()).collect();
+    assert_eq!(inversions, brute_force_inversions(&keys));
+    assert_eq!(calls.get(), data.len());
+}
+
+#[test]
+fn test_histogram_runs_match_manual_scan() {
+    let mut rng = StdRng::seed_from_u64(31);
+    for _ in 0..20 {
+        let len = rng.random_range(0..500);
+        let data: Vec<i16> = (0..len).map(|_| rng.random_range(-50..50)).collect();
+        let histogram = run_length_histogram(&data, IdentityKey);
+        let descents = data.windows(2).filter(|pair| pair[1] < pair[0]).count();
+        let expected_runs = if data.is_empty() { 0 } else { descents + 1 };
+        assert_eq!(histogram.runs(), expected_runs);
+        assert_eq!(histogram.elements(), data.len());
+        assert_eq!(
+            histogram.buckets().map(|(_, count)| count).sum::<usize>(),
+            expected_runs
+        );
+    }
+}
+
+#[test]
+fn test_histogram_of_sorted_and_empty_input() {
+    let sorted: Vec<u32> = (0..1_000).collect();
+    let histogram = run_length_histogram(&sorted, IdentityKey);
+    assert_eq!(histogram.runs(), 1);
+    assert_eq!(histogram.longest(), 1_000);
+    assert_eq!(
+        histogram.buckets().collect::<Vec<_>>(),
+        vec![(512..=1023, 1)]
+    );
+
+    let empty = run_length_histogram::<u32, _, _>(&[], IdentityKey);
+    assert_eq!(empty.runs(), 0);
+    assert_eq!(empty.longest(), 0);
+    assert_eq!(empty.mean(), 0.0);
+    assert_eq!(empty.buckets().count(), 0);
+}
+
+#[test]
+fn test_histogram_descending() {
+    let data: Vec<u32> = (0..100).rev().chain((0..50).rev()).collect();
+    let histogram =
+        run_length_histogram_with_config(&data, IdentityKey, &SortConfig::new().descending());
+    assert_eq!(histogram.runs(), 2);
+    assert_eq!(histogram.mean(), 75.0);
+    assert_eq!(run_length_histogram(&data, IdentityKey).runs(), 149);
+}
+
+#[test]
+fn test_histogram_extracts_each_key_once() {
+    let calls = Cell::new(0);
+    let data: Vec<i32> = (0..300).map(|i| (i * 37) % 101 - 50).collect();
+    let histogram = run_length_histogram(&data, |x: &i32| {
+        calls.set(calls.get() + 1);
+        x.abs()
+    });
+    assert_eq!(histogram.elements(), data.len());
+    assert_eq!(calls.get(), data.len());
+}
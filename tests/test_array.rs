@@ -0,0 +1,80 @@
+// Integration tests for fixed-size array sorting
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use rand::prelude::*;
+
+use tilesort::{tilesort_array, tilesort_array_mut};
+
+/// Allocator counting the allocations made by the current thread.
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations_during(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.with(Cell::get);
+    f();
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+#[test]
+fn test_small_arrays_do_not_allocate() {
+    let mut rng = StdRng::seed_from_u64(11);
+    let mut arr = [0u16; 64];
+    for slot in arr.iter_mut() {
+        *slot = rng.random();
+    }
+    let mut expected = arr;
+    expected.sort();
+
+    let allocations = allocations_during(|| tilesort_array_mut(&mut arr));
+    assert_eq!(allocations, 0);
+    assert_eq!(arr, expected);
+}
+
+#[test]
+fn test_matches_std_sort() {
+    let mut rng = StdRng::seed_from_u64(12);
+    for _ in 0..100 {
+        let mut arr = [(0u8, 0usize); 40];
+        for (tag, slot) in (0..).zip(arr.iter_mut()) {
+            *slot = (rng.random_range(0..8), tag);
+        }
+        let mut expected = arr;
+        expected.sort();
+        assert_eq!(tilesort_array(arr), expected);
+    }
+}
+
+#[test]
+fn test_large_array() {
+    let mut arr = [0i64; 1000];
+    for (value, slot) in (0..).zip(arr.iter_mut()) {
+        *slot = (value % 100) * 10 - value / 100;
+    }
+    let mut expected = arr;
+    expected.sort();
+    assert_eq!(tilesort_array(arr), expected);
+}
+
+#[test]
+fn test_non_clone_elements() {
+    let arr = [String::from("c"), String::from("a"), String::from("b")];
+    assert_eq!(tilesort_array(arr), ["a", "b", "c"]);
+}
@@ -0,0 +1,101 @@
+// Integration tests for the async external sort
+#![cfg(feature = "async")]
+use std::fs;
+use std::future::Future;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake};
+use std::thread::{self, Thread};
+
+use rand::prelude::*;
+use test_log::test;
+
+use tilesort::{nonblocking, sort_file, ExternalSortConfig};
+
+/// A fresh directory for one test's files.
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("tilesort-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+struct Unpark(Thread);
+
+impl Wake for Unpark {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Poll `future` to completion on this thread, counting the polls.
+fn block_on<F: Future>(future: F) -> (F::Output, usize) {
+    let waker = Arc::new(Unpark(thread::current())).into();
+    let mut cx = Context::from_waker(&waker);
+    let mut future = std::pin::pin!(future);
+    let mut polls = 0;
+    loop {
+        polls += 1;
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return (output, polls),
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+#[test]
+fn test_async_sort_file_matches_blocking() {
+    let dir = scratch_dir("async-lines");
+    let mut rng = StdRng::seed_from_u64(591);
+    let contents: String = (0..5000)
+        .map(|_| format!("key-{:04}\n", rng.random_range(0..2000)))
+        .collect();
+    fs::write(dir.join("in.txt"), &contents).unwrap();
+
+    let config = ExternalSortConfig::new().run_size(4096).temp_dir(&dir);
+    let blocking = sort_file(dir.join("in.txt"), dir.join("blocking.txt"), &config).unwrap();
+    let future = nonblocking::sort_file(dir.join("in.txt"), dir.join("async.txt"), &config);
+    let (written, polls) = block_on(future);
+
+    assert_eq!(written.unwrap(), blocking);
+    assert!(polls >= 1);
+    assert_eq!(
+        fs::read(dir.join("async.txt")).unwrap(),
+        fs::read(dir.join("blocking.txt")).unwrap()
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_async_sort_file_does_not_block_the_poller() {
+    let dir = scratch_dir("async-pending");
+    fs::write(dir.join("in.txt"), "b\na\n").unwrap();
+
+    // Nothing runs until the first poll, and that poll returns at once
+    let config = ExternalSortConfig::new().temp_dir(&dir);
+    let mut future = Box::pin(nonblocking::sort_file(
+        dir.join("in.txt"),
+        dir.join("out.txt"),
+        &config,
+    ));
+    assert!(!dir.join("out.txt").exists());
+    let waker = Arc::new(Unpark(thread::current())).into();
+    let written = match future.as_mut().poll(&mut Context::from_waker(&waker)) {
+        Poll::Ready(written) => written,
+        Poll::Pending => block_on(future).0,
+    };
+    assert_eq!(written.unwrap(), 2);
+    assert_eq!(fs::read_to_string(dir.join("out.txt")).unwrap(), "a\nb\n");
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_async_sort_file_reports_errors() {
+    let dir = scratch_dir("async-missing");
+    let config = ExternalSortConfig::new().temp_dir(&dir);
+    let future = nonblocking::sort_file(dir.join("missing.txt"), dir.join("out.txt"), &config);
+    let (result, _) = block_on(future);
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::NotFound);
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -193,6 +193,17 @@ fn test_tilesorted_by_key_reverse_abs() {
     assert_eq!(data, vec![-5, -3, -1, 2, 4]); // Original unchanged
 }
 
+#[test]
+fn test_tilesort_by_key_without_clone() {
+    // Keys only need `Ord`
+    #[derive(PartialEq, Eq, PartialOrd, Ord)]
+    struct Rank(u32);
+
+    let mut data = vec![30, 10, 20];
+    tilesort_by_key(&mut data, |&x| Rank(x));
+    assert_eq!(data, vec![10, 20, 30]);
+}
+
 #[test]
 fn test_tilesort_by_key_struct() {
     #[derive(Debug, Clone, PartialEq)]
@@ -267,3 +278,52 @@ fn test_tile_boundary_counting() {
     tilesort(&mut data);
     assert_eq!(data, vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
 }
+
+#[test]
+fn test_tilesort_by_str_prefix_long_strings() {
+    use tilesort::{tilesort_by_str_prefix, tilesort_by_str_prefix_with_config, SortConfig};
+
+    // Shared 8-byte prefixes force the full-string fallback
+    let mut data: Vec<String> = (0..300)
+        .map(|i| {
+            format!(
+                "{}-{:05}",
+                if i % 2 == 0 { "prefix_b" } else { "prefix_a" },
+                (i * 37) % 300
+            )
+        })
+        .collect();
+    let mut expected = data.clone();
+    expected.sort();
+    tilesort_by_str_prefix(&mut data, |s| s.as_str());
+    assert_eq!(data, expected);
+
+    expected.reverse();
+    tilesort_by_str_prefix_with_config(&mut data, |s| s.as_str(), &SortConfig::new().descending());
+    assert_eq!(data, expected);
+}
+
+#[test]
+fn test_identity_extractor_compares_in_place() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tilesort::{tilesort_by_extractor, IdentityKey};
+
+    static CLONES: AtomicUsize = AtomicUsize::new(0);
+
+    #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+    struct Counted(u32);
+
+    impl Clone for Counted {
+        fn clone(&self) -> Self {
+            CLONES.fetch_add(1, Ordering::Relaxed);
+            Counted(self.0)
+        }
+    }
+
+    // Long enough to go through the tile index rather than insertion sort
+    let mut data: Vec<Counted> = (20..40).chain(0..20).map(Counted).collect();
+    tilesort_by_extractor(&mut data, IdentityKey);
+    assert_eq!(data, (0..40).map(Counted).collect::<Vec<_>>());
+    // Restructuring clones each element once; no keys are extracted
+    assert_eq!(CLONES.load(Ordering::Relaxed), 40);
+}
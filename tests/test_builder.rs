@@ -0,0 +1,111 @@
+// Integration tests for the multi-key sort builder
+
+use test_log::test;
+
+use tilesort::Tilesort;
+
+#[derive(Debug, Clone, PartialEq)]
+struct Record {
+    last_name: String,
+    first_name: String,
+    age: u32,
+}
+
+fn record(last_name: &str, first_name: &str, age: u32) -> Record {
+    Record {
+        last_name: last_name.to_string(),
+        first_name: first_name.to_string(),
+        age,
+    }
+}
+
+fn names(rows: &[Record]) -> Vec<String> {
+    rows.iter()
+        .map(|r| format!("{} {} {}", r.first_name, r.last_name, r.age))
+        .collect()
+}
+
+#[test]
+fn test_single_key() {
+    let mut data = vec![3, 4, 5, 1, 2];
+    Tilesort::by(|x: &i32| *x).sort(&mut data);
+    assert_eq!(data, vec![1, 2, 3, 4, 5]);
+}
+
+#[test]
+fn test_single_key_desc() {
+    let mut data = vec![3, 4, 5, 1, 2];
+    Tilesort::by_desc(|x: &i32| *x).sort(&mut data);
+    assert_eq!(data, vec![5, 4, 3, 2, 1]);
+}
+
+#[test]
+fn test_then_by_mixed_directions() {
+    let mut rows = vec![
+        record("Smith", "Jane", 30),
+        record("Jones", "Bob", 40),
+        record("Smith", "Adam", 25),
+        record("Smith", "Jane", 45),
+        record("Adams", "Zoe", 22),
+    ];
+
+    Tilesort::by(|r: &Record| r.last_name.clone())
+        .then_by(|r| r.first_name.clone())
+        .then_by_desc(|r| r.age)
+        .sort(&mut rows);
+
+    assert_eq!(
+        names(&rows),
+        vec![
+            "Zoe Adams 22",
+            "Bob Jones 40",
+            "Adam Smith 25",
+            "Jane Smith 45",
+            "Jane Smith 30",
+        ]
+    );
+}
+
+#[test]
+fn test_desc_then_asc() {
+    let mut data = vec![(1, 'b'), (2, 'a'), (1, 'a'), (2, 'c'), (3, 'z')];
+    Tilesort::by_desc(|p: &(i32, char)| p.0)
+        .then_by(|p| p.1)
+        .sort(&mut data);
+    assert_eq!(data, vec![(3, 'z'), (2, 'a'), (2, 'c'), (1, 'a'), (1, 'b')]);
+}
+
+#[test]
+fn test_sorted_leaves_input_unchanged() {
+    let data = vec![
+        record("B", "x", 1),
+        record("A", "y", 2),
+        record("A", "x", 3),
+    ];
+    let sorter = Tilesort::by(|r: &Record| r.last_name.clone()).then_by(|r| r.first_name.clone());
+
+    let sorted = sorter.sorted(&data);
+
+    assert_eq!(names(&sorted), vec!["x A 3", "y A 2", "x B 1"]);
+    assert_eq!(names(&data), vec!["x B 1", "y A 2", "x A 3"]);
+}
+
+#[test]
+fn test_extractor_combinators() {
+    use tilesort::{tilesort_by_extractor, KeyExtractor};
+
+    let mut rows = vec![
+        record("Smith", "Jane", 30),
+        record("Jones", "Bob", 40),
+        record("Smith", "Al", 25),
+    ];
+    let extractor = (|r: &Record| r.last_name.clone())
+        .reversed()
+        .then((|r: &Record| r.first_name.clone()).map(|name| name.len()));
+    tilesort_by_extractor(&mut rows, extractor);
+
+    assert_eq!(
+        names(&rows),
+        vec!["Al Smith 25", "Jane Smith 30", "Bob Jones 40"]
+    );
+}
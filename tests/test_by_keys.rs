@@ -0,0 +1,40 @@
+// Integration tests for sorting by precomputed keys
+
+use std::cmp::Reverse;
+
+use test_log::test;
+
+use tilesort::{tilesort_by_key, tilesort_by_keys, tilesort_by_keys_with_config, SortConfig};
+
+#[test]
+fn test_by_keys_matches_by_key() {
+    let mut data: Vec<u32> = (0..500).map(|i| (i * 37) % 101).collect();
+    let keys: Vec<u32> = data.iter().map(|x| x % 10).collect();
+    let mut expected = data.clone();
+    tilesort_by_key(&mut expected, |x| x % 10);
+
+    tilesort_by_keys(&mut data, &keys);
+    assert_eq!(data, expected);
+}
+
+#[test]
+fn test_by_keys_leaves_keys_alone() {
+    let mut data = vec!['x', 'y', 'z'];
+    let keys = vec![Reverse(1), Reverse(3), Reverse(2)];
+    tilesort_by_keys(&mut data, &keys);
+    assert_eq!(data, vec!['y', 'z', 'x']);
+    assert_eq!(keys, vec![Reverse(1), Reverse(3), Reverse(2)]);
+}
+
+#[test]
+fn test_by_keys_stable_descending() {
+    let mut data = vec!['a', 'b', 'c', 'd'];
+    tilesort_by_keys_with_config(&mut data, &[1, 2, 1, 2], &SortConfig::new().descending());
+    assert_eq!(data, vec!['b', 'd', 'a', 'c']);
+}
+
+#[test]
+#[should_panic(expected = "same length")]
+fn test_by_keys_length_mismatch() {
+    tilesort_by_keys(&mut [1, 2, 3], &[1, 2]);
+}
@@ -0,0 +1,120 @@
+// Integration tests for cooperative cancellation
+
+use std::cell::Cell;
+
+use test_log::test;
+
+use tilesort::{
+    tilesort_by_key_cancellable, tilesort_cancellable, CancellationToken, Cancelled, SortConfig,
+};
+
+/// Nearly-sorted input large enough to span several cancellation checks.
+fn tiled_input() -> Vec<u32> {
+    (0..5)
+        .rev()
+        .flat_map(|tile| (tile * 10_000)..(tile * 10_000 + 10_000))
+        .collect()
+}
+
+#[test]
+fn test_not_cancelled_sorts() {
+    let token = CancellationToken::new();
+    let mut data = tiled_input();
+    let mut expected = data.clone();
+    expected.sort();
+
+    assert_eq!(
+        tilesort_cancellable(&mut data, &SortConfig::new(), &token),
+        Ok(())
+    );
+    assert_eq!(data, expected);
+    assert!(!token.is_cancelled());
+}
+
+#[test]
+fn test_cancelled_before_start() {
+    let token = CancellationToken::new();
+    token.cancel();
+
+    let mut data = tiled_input();
+    let original = data.clone();
+    assert_eq!(
+        tilesort_cancellable(&mut data, &SortConfig::new(), &token),
+        Err(Cancelled)
+    );
+    assert_eq!(data, original);
+
+    let mut single = vec![1];
+    assert_eq!(
+        tilesort_cancellable(&mut single, &SortConfig::new(), &token),
+        Err(Cancelled)
+    );
+}
+
+#[test]
+fn test_cancelled_during_scan() {
+    let token = CancellationToken::new();
+    let seen = Cell::new(0usize);
+    let mut data = tiled_input();
+    let original = data.clone();
+
+    let result = tilesort_by_key_cancellable(
+        &mut data,
+        |&x| {
+            seen.set(seen.get() + 1);
+            if seen.get() == 20_000 {
+                token.cancel();
+            }
+            x
+        },
+        &SortConfig::new(),
+        &token,
+    );
+
+    assert_eq!(result, Err(Cancelled));
+    assert_eq!(data, original);
+    assert!(seen.get() < original.len(), "scan should stop early");
+}
+
+#[test]
+fn test_cancelled_during_restructure() {
+    thread_local! {
+        static CLONES: Cell<usize> = const { Cell::new(0) };
+        static TOKEN: CancellationToken = CancellationToken::new();
+    }
+
+    /// Element that cancels the sort after a number of clones.
+    #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+    struct Element(u32);
+
+    impl Clone for Element {
+        fn clone(&self) -> Self {
+            CLONES.with(|clones| {
+                clones.set(clones.get() + 1);
+                if clones.get() == 15_000 {
+                    TOKEN.with(|token| token.cancel());
+                }
+            });
+            Element(self.0)
+        }
+    }
+
+    let mut data: Vec<Element> = tiled_input().into_iter().map(Element).collect();
+    let original = data.clone();
+    CLONES.with(|clones| clones.set(0));
+
+    let result = TOKEN.with(|token| tilesort_cancellable(&mut data, &SortConfig::new(), token));
+
+    assert_eq!(result, Err(Cancelled));
+    assert_eq!(data, original);
+    assert!(CLONES.with(|clones| clones.get()) < original.len());
+}
+
+#[test]
+fn test_token_clones_share_state() {
+    let token = CancellationToken::new();
+    let other = token.clone();
+    other.cancel();
+    assert!(token.is_cancelled());
+    assert_eq!(Cancelled.to_string(), "sort was cancelled");
+}
@@ -0,0 +1,499 @@
+// Integration tests for the `tilesort` command-line sort
+#![cfg(feature = "cli")]
+
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+use rand::prelude::*;
+use test_log::test;
+
+/// A fresh directory for one test's files.
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("tilesort-cli-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Run `tilesort` with `args`, feeding it `stdin`.
+fn tilesort(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_tilesort"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+/// Run `tilesort` and return what it printed, checking it succeeded.
+fn stdout(args: &[&str], stdin: &str) -> String {
+    let output = tilesort(args, stdin);
+    assert!(output.status.success(), "{:?}", output);
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_sorts_standard_input() {
+    assert_eq!(stdout(&[], "pear\napple\nfig"), "apple\nfig\npear\n");
+    assert_eq!(stdout(&["-"], ""), "");
+}
+
+#[test]
+fn test_sorts_file_larger_than_buffer() {
+    let dir = scratch_dir("external");
+    let mut rng = StdRng::seed_from_u64(608);
+    let mut lines: Vec<String> = (0..3000)
+        .map(|_| format!("line-{:05}", rng.random_range(0..10000)))
+        .collect();
+    let contents: String = lines.iter().map(|line| format!("{}\n", line)).collect();
+    fs::write(dir.join("in.txt"), contents).unwrap();
+    let runs = dir.join("runs");
+    fs::create_dir(&runs).unwrap();
+
+    // A 2 KiB buffer spills dozens of runs into the temporary directory
+    let (input, output) = (dir.join("in.txt"), dir.join("out.txt"));
+    let args = [
+        input.to_str().unwrap(),
+        "-o",
+        output.to_str().unwrap(),
+        "--buffer-size",
+        "2",
+        "-T",
+        runs.to_str().unwrap(),
+    ];
+    assert_eq!(stdout(&args, ""), "");
+    lines.sort();
+    let expected: String = lines.iter().map(|line| format!("{}\n", line)).collect();
+    assert_eq!(fs::read_to_string(&output).unwrap(), expected);
+    assert_eq!(fs::read_dir(&runs).unwrap().count(), 0);
+
+    // The output may replace the input
+    let args = [
+        input.to_str().unwrap(),
+        "-o",
+        input.to_str().unwrap(),
+        "-S",
+        "1K",
+    ];
+    assert_eq!(stdout(&args, ""), "");
+    assert_eq!(fs::read_to_string(&input).unwrap(), expected);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_concatenates_inputs() {
+    let dir = scratch_dir("concatenate");
+    let file = dir.join("in.txt");
+    fs::write(&file, "b\nd\n").unwrap();
+    let file = file.to_str().unwrap();
+    assert_eq!(stdout(&[file, "-", file], "c\na\n"), "a\nb\nb\nc\nd\nd\n");
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_errors_exit_with_status_2() {
+    let output = tilesort(&["no/such/file"], "");
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with("tilesort: no/such/file: "), "{}", stderr);
+
+    for size in ["x", "-1K", "1.5Kb"] {
+        let output = tilesort(&[&format!("--buffer-size={}", size)], "");
+        assert_eq!(output.status.code(), Some(2), "{}", size);
+    }
+}
+
+#[test]
+fn test_key_type_ip() {
+    let log = "10.0.0.10 GET /b\n[2001:db8::1]:443 GET /c\n10.0.0.9:8080 GET /a\n- GET /d\n10.0.0.9 GET /e\n";
+    assert_eq!(
+        stdout(&["--key-type=ip"], log),
+        "- GET /d\n10.0.0.9:8080 GET /a\n10.0.0.9 GET /e\n10.0.0.10 GET /b\n[2001:db8::1]:443 GET /c\n"
+    );
+    assert_eq!(
+        stdout(&["--key-type", "text"], log),
+        "- GET /d\n10.0.0.10 GET /b\n10.0.0.9 GET /e\n10.0.0.9:8080 GET /a\n[2001:db8::1]:443 GET /c\n"
+    );
+}
+
+#[test]
+fn test_key_type_datetime() {
+    let log = "2024-03-01T12:00:02Z b\n2024-03-01T13:00:01+02:00 a\ncontinued\n2024-03-01T12:00:01.5Z c\n";
+    let expected = "continued\n2024-03-01T13:00:01+02:00 a\n2024-03-01T12:00:01.5Z c\n2024-03-01T12:00:02Z b\n";
+    assert_eq!(stdout(&["--key-type=datetime"], log), expected);
+    assert_eq!(
+        stdout(&["--key-type=datetime:rfc3339", "-S", "1b"], log),
+        expected
+    );
+    assert_eq!(
+        stdout(
+            &["--key-type=datetime:epoch-millis", "-r"],
+            "1709292600000 a\n999 b\n1709292600001 c\n"
+        ),
+        "1709292600001 c\n1709292600000 a\n999 b\n"
+    );
+    assert_eq!(
+        stdout(&["-k2", "--key-type=datetime:epoch-millis"], "a 20\nb 3\n"),
+        "b 3\na 20\n"
+    );
+
+    let output = tilesort(&["--key-type=datetime:%Y"], "");
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn test_key_sort_spills_and_merges() {
+    let mut rng = StdRng::seed_from_u64(639);
+    let mut addresses: Vec<u32> = (0..500).map(|_| rng.random()).collect();
+    let lines: String = addresses
+        .iter()
+        .map(|&a| format!("{}\n", std::net::Ipv4Addr::from(a)))
+        .collect();
+    // One line per run, merged in several passes
+    let sorted = stdout(&["--key-type=ip", "-S", "1b"], &lines);
+    addresses.sort();
+    let expected: String = addresses
+        .iter()
+        .map(|&a| format!("{}\n", std::net::Ipv4Addr::from(a)))
+        .collect();
+    assert_eq!(sorted, expected);
+
+    let output = tilesort(&["--key-type=mac"], "");
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn test_human_numeric_sort() {
+    let du = "1.2G\t./video\n150M\t./photos\n2.3K\t./notes\n4.0K\t./src\n512\t./README\n";
+    let expected = "512\t./README\n2.3K\t./notes\n4.0K\t./src\n150M\t./photos\n1.2G\t./video\n";
+    assert_eq!(stdout(&["-h"], du), expected);
+    assert_eq!(stdout(&["--human-numeric-sort", "-S", "1b"], du), expected);
+    // As for GNU `sort -h`, the suffix ranks before the number
+    assert_eq!(
+        stdout(&["-h"], "1M\n2000K\n1500\n-1K\n-2\n"),
+        "-1K\n-2\n1500\n2000K\n1M\n"
+    );
+    assert_eq!(stdout(&["-hr"], "1M\n2000K\n"), "1M\n2000K\n");
+
+    // `-h` is not help, which only `--help` prints
+    let output = tilesort(&["--help"], "");
+    assert!(output.status.success());
+    let help = String::from_utf8(output.stdout).unwrap();
+    assert!(help.contains("-h, --human-numeric-sort"), "{}", help);
+}
+
+#[test]
+fn test_field_keys() {
+    let passwd = "root:x:0:0\ndaemon:x:1:1\nzed:x:1000:100\namy:x:1000:10\n";
+    let by_uid_then_name = "amy:x:1000:10\nzed:x:1000:100\ndaemon:x:1:1\nroot:x:0:0\n";
+    assert_eq!(
+        stdout(&["-t:", "-k3,3nr", "-k1,1"], passwd),
+        by_uid_then_name
+    );
+    // The global options apply to keys without modifiers of their own
+    assert_eq!(
+        stdout(&["-t", ":", "-n", "-r", "-k3,3", "-k1,1r"], passwd),
+        "zed:x:1000:100\namy:x:1000:10\ndaemon:x:1:1\nroot:x:0:0\n"
+    );
+    // Equal keys keep their input order
+    assert_eq!(
+        stdout(&["--field-separator=:", "--key=3,3", "-S", "1b"], passwd),
+        "root:x:0:0\ndaemon:x:1:1\nzed:x:1000:100\namy:x:1000:10\n"
+    );
+    assert_eq!(
+        stdout(&["-k2h", "-k1,1r"], "x 2K\ny 1M\nz 2048\nw 3\n"),
+        "w 3\nz 2048\nx 2K\ny 1M\n"
+    );
+    assert_eq!(
+        stdout(&["-k2", "--key-type=ip", "-r"], "a 10.0.0.10\nb 10.0.0.9\n"),
+        "a 10.0.0.10\nb 10.0.0.9\n"
+    );
+}
+
+#[test]
+fn test_global_modifiers() {
+    assert_eq!(stdout(&["-n"], "10\n9\n-1.5\nx\n"), "-1.5\nx\n9\n10\n");
+    assert_eq!(stdout(&["-f"], "b\nA\na\nB\n"), "A\na\nb\nB\n");
+    assert_eq!(stdout(&["-rf"], "b\nA\na\nB\n"), "b\nB\nA\na\n");
+    assert_eq!(stdout(&["-r"], "b\na\nc\n"), "c\nb\na\n");
+    assert_eq!(stdout(&["-r", "-S", "1b"], "b\na\nc\n"), "c\nb\na\n");
+}
+
+#[test]
+fn test_invalid_keys() {
+    for args in [
+        &["-k0"][..],
+        &["-k2b"],
+        &["-k2hn"],
+        &["-h", "-n"],
+        &["-t", "ab"],
+    ] {
+        let output = tilesort(args, "");
+        assert_eq!(output.status.code(), Some(2), "{:?}", args);
+    }
+    let output = tilesort(&["-n", "-h"], "");
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "tilesort: -n cannot be combined with -h or --key-type\n"
+    );
+    let output = tilesort(&["-k", "2x"], "");
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "tilesort: -k 2x: invalid key specification: unsupported modifier\n"
+    );
+}
+
+#[test]
+fn test_unique_count() {
+    let words = "pear\napple\npear\nfig\napple\npear\n";
+    let expected = "2\tapple\n1\tfig\n3\tpear\n";
+    assert_eq!(stdout(&["--unique", "--count"], words), expected);
+    // Groups split across runs are counted together
+    assert_eq!(stdout(&["-u", "--count", "-S", "1b"], words), expected);
+    assert_eq!(stdout(&["-u"], words), "apple\nfig\npear\n");
+    assert_eq!(
+        stdout(&["-u", "-r", "-S", "12b"], words),
+        "pear\nfig\napple\n"
+    );
+
+    // The first line of each group is kept
+    let log = "10.0.0.2 GET /\n10.0.0.1 GET /a\n10.0.0.2 GET /b\n";
+    for size in ["1M", "1b"] {
+        assert_eq!(
+            stdout(&["-k1,1", "-u", "--count", "-S", size], log),
+            "1\t10.0.0.1 GET /a\n2\t10.0.0.2 GET /\n"
+        );
+    }
+
+    let output = tilesort(&["--count"], "");
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn test_unique_count_merges_in_passes() {
+    let mut rng = StdRng::seed_from_u64(642);
+    let words: Vec<u32> = (0..400).map(|_| rng.random_range(0..50)).collect();
+    let lines: String = words.iter().map(|w| format!("w{:02}\n", w)).collect();
+    let mut counts = std::collections::BTreeMap::new();
+    for w in &words {
+        *counts.entry(*w).or_insert(0) += 1;
+    }
+    let expected: String = counts
+        .iter()
+        .map(|(w, count)| format!("{}\tw{:02}\n", count, w))
+        .collect();
+    // Hundreds of runs, each a line, merged in several passes
+    assert_eq!(stdout(&["-u", "--count", "-S", "1b"], &lines), expected);
+}
+
+#[test]
+fn test_check() {
+    let output = tilesort(&["-c"], "a\nb\nb\nd\nc\ne\n");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "tilesort: -:5: disorder: c\n"
+    );
+
+    // The line number counts across runs
+    let output = tilesort(&["--check", "-n", "-S", "1b"], "1\n9\n10\n2\n");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "tilesort: -:4: disorder: 2\n"
+    );
+
+    let output = tilesort(&["-C"], "b\na\n");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty() && output.stderr.is_empty());
+
+    for args in [&["-c"][..], &["-C", "-S", "1b"], &["-c", "-r", "-S", "1b"]] {
+        let sorted = if args.contains(&"-r") {
+            "b\nb\na\n"
+        } else {
+            "a\nb\nb\n"
+        };
+        let output = tilesort(args, sorted);
+        assert!(output.status.success(), "{:?}", args);
+        assert!(output.stdout.is_empty() && output.stderr.is_empty());
+    }
+
+    // With --unique, equal lines are out of order
+    let output = tilesort(&["-c", "-u", "-S", "1b"], "a\nb\nb\n");
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "tilesort: -:3: disorder: b\n"
+    );
+}
+
+#[test]
+fn test_check_file() {
+    let dir = scratch_dir("check");
+    let file = dir.join("in.txt");
+    fs::write(&file, "x 1\ny 3\nz 2\n").unwrap();
+    let name = file.to_str().unwrap();
+    let output = tilesort(&["-c", "-k2,2n", name], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        format!("tilesort: {}:3: disorder: z 2\n", name)
+    );
+    assert!(tilesort(&["-C", "-k1,1", name], "").status.success());
+
+    for args in [
+        &["-c", name, name][..],
+        &["-c", "-o", name],
+        &["-C", "-u", "--count"],
+    ] {
+        assert_eq!(tilesort(args, "").status.code(), Some(2), "{:?}", args);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_merge() {
+    let dir = scratch_dir("merge");
+    let (a, b) = (dir.join("a.txt"), dir.join("b.txt"));
+    fs::write(&a, "a\nc\ne\n").unwrap();
+    fs::write(&b, "b\nc\nd\n").unwrap();
+    let (a, b) = (a.to_str().unwrap(), b.to_str().unwrap());
+    assert_eq!(stdout(&["-m", a, b], ""), "a\nb\nc\nc\nd\ne\n");
+    assert_eq!(stdout(&["--merge", a, "-"], "b\nd\n"), "a\nb\nc\nd\ne\n");
+    assert_eq!(
+        stdout(&["-m", "-u", "--count", a, b], ""),
+        "1\ta\n1\tb\n2\tc\n1\td\n1\te\n"
+    );
+
+    // Inputs sorted by a key merge by the same key
+    let (x, y) = (dir.join("x.txt"), dir.join("y.txt"));
+    fs::write(&x, "p:30\nq:10\n").unwrap();
+    fs::write(&y, "r:20\ns:10\nt:5\n").unwrap();
+    let (x, y) = (x.to_str().unwrap(), y.to_str().unwrap());
+    assert_eq!(
+        stdout(&["-m", "-t:", "-k2,2", "-n", "-r", x, y], ""),
+        "p:30\nr:20\nq:10\ns:10\nt:5\n"
+    );
+    let z = dir.join("z.txt");
+    fs::write(&z, "e\nc\na\n").unwrap();
+    assert_eq!(
+        stdout(&["-m", "-r", "-", z.to_str().unwrap()], "f\nd\n"),
+        "f\ne\nd\nc\na\n"
+    );
+
+    // The output may be an input
+    let args = ["-m", b, a, "-o", a];
+    assert_eq!(stdout(&args, ""), "");
+    assert_eq!(fs::read_to_string(a).unwrap(), "a\nb\nc\nc\nd\ne\n");
+
+    // Inputs out of order are an error
+    let output = tilesort(&["-m", "-t:", "-k2,2n", x, y], "");
+    assert_eq!(output.status.code(), Some(2));
+    let output = tilesort(&["-m", "-c", x], "");
+    assert_eq!(output.status.code(), Some(2));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_progress() {
+    let mut rng = StdRng::seed_from_u64(632);
+    let mut numbers: Vec<u32> = (0..20_000)
+        .map(|_| rng.random_range(0..1_000_000))
+        .collect();
+    let lines: String = numbers.iter().map(|n| format!("{:07}\n", n)).collect();
+    numbers.sort();
+    let expected: String = numbers.iter().map(|n| format!("{:07}\n", n)).collect();
+
+    for args in [
+        &["--progress", "-S", "16K"][..],
+        &["--progress", "-S", "16K", "-n"],
+    ] {
+        let output = tilesort(args, &lines);
+        assert!(output.status.success(), "{:?}", args);
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), expected);
+
+        // Each phase counts up to 100%, a line per percent at most
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        for phase in ["sorted", "merged"] {
+            let percents: Vec<u32> = stderr
+                .lines()
+                .filter_map(|line| line.strip_prefix(phase))
+                .map(|rest| {
+                    let (percent, eta) = rest.trim_start().split_once('%').unwrap();
+                    assert!(eta.is_empty() || eta.starts_with(" (eta "), "{}", rest);
+                    percent.parse().unwrap()
+                })
+                .collect();
+            assert!(percents.len() > 2, "{:?}: {}", args, stderr);
+            assert!(percents.windows(2).all(|w| w[0] < w[1]), "{}", stderr);
+            assert_eq!(percents.last(), Some(&100), "{}", stderr);
+        }
+        assert!(stderr.contains("merged 100% (eta 0s)"), "{}", stderr);
+    }
+
+    // Nothing is reported without the flag
+    assert!(tilesort(&["-S", "16K"], &lines).stderr.is_empty());
+    let output = tilesort(&["--progress", "-c"], "");
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn test_checkpoint_and_resume() {
+    let dir = scratch_dir("resume");
+    let mut rng = StdRng::seed_from_u64(633);
+    let mut lines: Vec<String> = (0..2000)
+        .map(|_| format!("line-{:05}", rng.random_range(0..10000)))
+        .collect();
+    let contents: String = lines.iter().map(|line| format!("{}\n", line)).collect();
+    let input = dir.join("in.txt");
+    fs::write(&input, contents).unwrap();
+    let checkpoint = dir.join("checkpoint");
+    let (input, checkpoint) = (input.to_str().unwrap(), checkpoint.to_str().unwrap());
+
+    // Interrupted in the final merge, as the output's directory is missing
+    let missing = dir.join("missing/out.txt");
+    let args = ["-S", "4K", "--checkpoint-dir", checkpoint, input];
+    let output = tilesort(
+        &[&args[..], &["-o", missing.to_str().unwrap()]].concat(),
+        "",
+    );
+    assert_eq!(output.status.code(), Some(2));
+    let manifest = fs::read_to_string(dir.join("checkpoint/manifest")).unwrap();
+    let runs = manifest
+        .lines()
+        .filter(|line| line.starts_with("run "))
+        .count();
+    assert!(runs > 2, "{}", manifest);
+
+    // A marker in place of the first run shows it is merged again, not
+    // sorted again
+    let first = dir.join("checkpoint/run-00000000");
+    let replaced = fs::read_to_string(&first).unwrap().lines().count();
+    fs::write(&first, "!marker\n").unwrap();
+    let resumed = stdout(&[&args[..], &["--resume"]].concat(), "");
+    assert_eq!(resumed.lines().next(), Some("!marker"));
+    assert_eq!(resumed.lines().count(), lines.len() - replaced + 1);
+    assert!(!dir.join("checkpoint").exists());
+
+    // A sort that is not interrupted leaves no checkpoint behind
+    let sorted = stdout(&args, "");
+    lines.sort();
+    assert_eq!(sorted.lines().collect::<Vec<_>>(), lines);
+    assert!(!dir.join("checkpoint").exists());
+
+    for args in [&["--resume"][..], &["-n", "--checkpoint-dir", checkpoint]] {
+        let output = tilesort(args, "");
+        assert_eq!(output.status.code(), Some(2), "{:?}", args);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,97 @@
+// Integration tests for sorting struct-of-arrays tables by key columns
+
+use std::collections::VecDeque;
+
+use rand::prelude::*;
+use test_log::test;
+
+use tilesort::{sort_columns, sort_columns_with_config, SortConfig};
+
+/// Neither `Clone` nor `Copy`, so the columns can only be swapped.
+#[derive(Debug, PartialEq)]
+struct Payload(usize);
+
+#[test]
+fn test_columns_match_sorted_rows() {
+    let mut rng = StdRng::seed_from_u64(625);
+    for len in [0, 1, 2, 10, 3_000] {
+        // Nearly time-ordered, with a coarse first key full of ties
+        let mut time: Vec<u32> = (0..len as u32)
+            .map(|i| i + rng.random_range(0..20))
+            .collect();
+        let mut station: Vec<u8> = (0..len).map(|_| rng.random_range(0..4)).collect();
+        let mut reading: Vec<Payload> = (0..len).map(Payload).collect();
+
+        let mut rows: Vec<(u8, u32, usize)> = (0..len).map(|i| (station[i], time[i], i)).collect();
+        rows.sort_by_key(|&(station, time, _)| (station, time));
+
+        sort_columns(
+            &mut [&mut station.as_mut_slice(), &mut time.as_mut_slice()],
+            &mut [&mut reading.as_mut_slice()],
+        );
+        let sorted: Vec<(u8, u32, usize)> = (0..len)
+            .map(|i| (station[i], time[i], reading[i].0))
+            .collect();
+        assert_eq!(sorted, rows, "{}", len);
+    }
+}
+
+#[test]
+fn test_mixed_column_types() {
+    let mut keys = VecDeque::from(vec![3, 1, 2]);
+    let mut names = vec!["c", "a", "b"];
+    let mut scores: Vec<f64> = vec![0.3, 0.1, 0.2];
+    // A slice is passed as a mutable reference to it
+    let mut score_slice = &mut scores[..];
+
+    sort_columns(
+        &mut [&mut keys],
+        &mut [&mut names.as_mut_slice(), &mut score_slice],
+    );
+    assert_eq!(keys, VecDeque::from(vec![1, 2, 3]));
+    assert_eq!(names, vec!["a", "b", "c"]);
+    assert_eq!(scores, vec![0.1, 0.2, 0.3]);
+}
+
+#[test]
+fn test_descending_columns_are_stable() {
+    let mut key = vec![1, 2, 1, 2, 3];
+    let mut id = vec![0, 1, 2, 3, 4];
+    let config = SortConfig::new().descending();
+    sort_columns_with_config(
+        &mut [&mut key.as_mut_slice()],
+        &mut [&mut id.as_mut_slice()],
+        &config,
+    );
+    assert_eq!(key, vec![3, 2, 2, 1, 1]);
+    assert_eq!(id, vec![4, 1, 3, 0, 2]);
+}
+
+#[test]
+fn test_values_only_keep_their_order() {
+    let mut values = vec![3, 1, 2];
+    sort_columns(&mut [], &mut [&mut values.as_mut_slice()]);
+    assert_eq!(values, vec![3, 1, 2]);
+}
+
+#[test]
+#[should_panic(expected = "same length")]
+fn test_column_length_mismatch() {
+    let mut keys = vec![2, 1];
+    let mut values = vec![1, 2, 3];
+    sort_columns(
+        &mut [&mut keys.as_mut_slice()],
+        &mut [&mut values.as_mut_slice()],
+    );
+}
+
+#[test]
+fn test_vec_swap_is_not_shadowed() {
+    #[allow(unused_imports)]
+    use tilesort::*;
+    // Neither column trait is implemented for `Vec`, so this is the slice's
+    // `swap` rather than an ambiguous trait method
+    let mut data: Vec<u8> = (1..=3).collect();
+    data.swap(0, 2);
+    assert_eq!(data, [3, 2, 1]);
+}
@@ -0,0 +1,213 @@
+// Integration tests for compressing the runs of an external sort
+#![cfg(feature = "compression")]
+use std::fs;
+use std::io::{self, BufRead, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicI32, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use rand::prelude::*;
+use test_log::test;
+
+use tilesort::{sort_file, ExternalSortConfig, RunCodec, RunEncoder};
+
+/// A fresh directory for one test's files.
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("tilesort-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Lines padded with long runs of zeros, which run-length encoding shrinks.
+fn padded_lines(rng: &mut StdRng, len: usize) -> Vec<String> {
+    (0..len)
+        .map(|_| format!("{:0>60}", rng.random_range(0..100_000)))
+        .collect()
+}
+
+#[derive(Debug, Default)]
+struct Calls {
+    encoders: AtomicUsize,
+    decoders: AtomicUsize,
+    level: AtomicI32,
+}
+
+/// Run-length encoding as `(count, byte)` pairs, counting its calls.
+#[derive(Debug, Default)]
+struct RunLength(Arc<Calls>);
+
+struct Encoder<'a> {
+    writer: &'a mut dyn Write,
+    run: Option<(u8, u8)>,
+}
+
+impl Write for Encoder<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for &byte in buf {
+            self.run = match self.run {
+                Some((count, last)) if last == byte && count < u8::MAX => Some((count + 1, byte)),
+                Some((count, last)) => {
+                    self.writer.write_all(&[count, last])?;
+                    Some((1, byte))
+                }
+                None => Some((1, byte)),
+            };
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+impl RunEncoder for Encoder<'_> {
+    fn finish(mut self: Box<Self>) -> io::Result<()> {
+        if let Some((count, byte)) = self.run.take() {
+            self.writer.write_all(&[count, byte])?;
+        }
+        self.writer.flush()
+    }
+}
+
+struct Decoder<'a> {
+    reader: Box<dyn BufRead + 'a>,
+    pending: (u8, u8),
+}
+
+impl Read for Decoder<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pending.0 == 0 {
+            let mut pair = [0; 2];
+            match self.reader.read_exact(&mut pair) {
+                Ok(()) => self.pending = (pair[0], pair[1]),
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(0),
+                Err(e) => return Err(e),
+            }
+        }
+        let len = buf.len().min(self.pending.0 as usize);
+        buf[..len].fill(self.pending.1);
+        self.pending.0 -= len as u8;
+        Ok(len)
+    }
+}
+
+impl RunCodec for RunLength {
+    fn name(&self) -> &str {
+        "rle"
+    }
+
+    fn encoder<'a>(
+        &self,
+        writer: &'a mut dyn Write,
+        level: i32,
+    ) -> io::Result<Box<dyn RunEncoder + 'a>> {
+        self.0.encoders.fetch_add(1, Ordering::Relaxed);
+        self.0.level.store(level, Ordering::Relaxed);
+        Ok(Box::new(Encoder { writer, run: None }))
+    }
+
+    fn decoder<'a>(&self, reader: Box<dyn BufRead + 'a>) -> io::Result<Box<dyn Read + 'a>> {
+        self.0.decoders.fetch_add(1, Ordering::Relaxed);
+        Ok(Box::new(Decoder {
+            reader,
+            pending: (0, 0),
+        }))
+    }
+}
+
+fn write_input(dir: &Path, lines: &[String]) {
+    let contents: String = lines.iter().map(|line| format!("{}\n", line)).collect();
+    fs::write(dir.join("in.txt"), contents).unwrap();
+}
+
+#[test]
+fn test_compressed_runs_sort_as_uncompressed() {
+    let dir = scratch_dir("compressed-runs");
+    let mut rng = StdRng::seed_from_u64(607);
+    let lines = padded_lines(&mut rng, 4000);
+    write_input(&dir, &lines);
+
+    let calls = Arc::new(Calls::default());
+    let config = ExternalSortConfig::new()
+        .run_size(8000)
+        .batch_size(4)
+        .workers(3)
+        .temp_dir(&dir)
+        .compression(RunLength(Arc::clone(&calls)), 9);
+    let written = sort_file(dir.join("in.txt"), dir.join("out.txt"), &config).unwrap();
+
+    let mut expected = lines;
+    expected.sort();
+    assert_eq!(written, expected.len() as u64);
+    let sorted = fs::read_to_string(dir.join("out.txt")).unwrap();
+    assert_eq!(sorted.lines().collect::<Vec<_>>(), expected);
+
+    // Every run and intermediate merge is encoded once and decoded once
+    let encoders = calls.encoders.load(Ordering::Relaxed);
+    assert!(encoders > 4);
+    assert_eq!(calls.decoders.load(Ordering::Relaxed), encoders);
+    assert_eq!(calls.level.load(Ordering::Relaxed), 9);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_compressed_runs_are_smaller_and_resume() {
+    let dir = scratch_dir("compressed-resume");
+    let mut rng = StdRng::seed_from_u64(6070);
+    let lines = padded_lines(&mut rng, 2000);
+    write_input(&dir, &lines);
+
+    // Fail in the final merge, leaving the compressed runs spilled
+    let config = ExternalSortConfig::new()
+        .run_size(8000)
+        .checkpoint_dir(dir.join("checkpoint"))
+        .compression(RunLength::default(), 1);
+    let err = sort_file(dir.join("in.txt"), dir.join("missing/out.txt"), &config).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+    let spilled: u64 = fs::read_dir(dir.join("checkpoint"))
+        .unwrap()
+        .map(|entry| entry.unwrap())
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("run-"))
+        .map(|entry| entry.metadata().unwrap().len())
+        .sum();
+    let input = fs::metadata(dir.join("in.txt")).unwrap().len();
+    assert!(spilled * 2 < input, "{} of {} bytes", spilled, input);
+
+    // The runs are only resumed with the codec that wrote them
+    let plain = config.clone().resume(true);
+    let plain = ExternalSortConfig {
+        compression: None,
+        ..plain
+    };
+    let err = sort_file(dir.join("in.txt"), dir.join("out.txt"), &plain).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+    sort_file(
+        dir.join("in.txt"),
+        dir.join("out.txt"),
+        &config.resume(true),
+    )
+    .unwrap();
+    let mut expected = lines;
+    expected.sort();
+    let sorted = fs::read_to_string(dir.join("out.txt")).unwrap();
+    assert_eq!(sorted.lines().collect::<Vec<_>>(), expected);
+    assert!(!dir.join("checkpoint").exists());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_compression_settings_compare_by_codec_and_level() {
+    let config = ExternalSortConfig::new().compression(RunLength::default(), 3);
+    assert_eq!(
+        config,
+        ExternalSortConfig::new().compression(RunLength::default(), 3)
+    );
+    assert_ne!(
+        config,
+        ExternalSortConfig::new().compression(RunLength::default(), 4)
+    );
+    assert_ne!(config, ExternalSortConfig::new());
+}
@@ -0,0 +1,140 @@
+// Integration tests for SortConfig-driven entry points
+
+use test_log::test;
+
+use tilesort::{
+    tilesort_by_key_with_config, tilesort_with_config, tilesorted_by_key_with_config,
+    tilesorted_with_config, CapacityHint, Order, SortConfig, Tilesort,
+};
+
+#[test]
+fn test_default_config_is_ascending() {
+    assert_eq!(SortConfig::new().order, Order::Ascending);
+    assert_eq!(SortConfig::default(), SortConfig::new().ascending());
+}
+
+#[test]
+fn test_default_config_is_stable() {
+    assert!(SortConfig::new().stable);
+    assert!(!SortConfig::new().unstable().stable);
+    assert_eq!(SortConfig::new().unstable().stable(true), SortConfig::new());
+}
+
+#[test]
+fn test_order_helpers() {
+    assert!(Order::Descending.is_descending());
+    assert!(!Order::Ascending.is_descending());
+    assert_eq!(Order::Ascending.reversed(), Order::Descending);
+    assert_eq!(Order::Descending.reversed(), Order::Ascending);
+}
+
+#[test]
+fn test_with_config_ascending() {
+    let mut data = vec![3, 4, 5, 1, 2];
+    tilesort_with_config(&mut data, &SortConfig::new());
+    assert_eq!(data, vec![1, 2, 3, 4, 5]);
+}
+
+#[test]
+fn test_with_config_descending() {
+    let mut data = vec![3, 4, 5, 1, 2];
+    let config = SortConfig {
+        order: Order::Descending,
+        ..SortConfig::default()
+    };
+    tilesort_with_config(&mut data, &config);
+    assert_eq!(data, vec![5, 4, 3, 2, 1]);
+}
+
+#[test]
+fn test_by_key_with_config() {
+    let mut data = vec![-5i32, -3, -1, 2, 4];
+    tilesort_by_key_with_config(&mut data, |&x| x.abs(), &SortConfig::new().descending());
+    assert_eq!(data, vec![-5, 4, -3, 2, -1]);
+}
+
+#[test]
+fn test_sorted_with_config_leaves_input() {
+    let data = vec![3, 4, 5, 1, 2];
+    let sorted = tilesorted_with_config(&data, &SortConfig::new().descending());
+    assert_eq!(sorted, vec![5, 4, 3, 2, 1]);
+    assert_eq!(data, vec![3, 4, 5, 1, 2]);
+
+    let sorted = tilesorted_by_key_with_config(&data, |&x| x, &SortConfig::new());
+    assert_eq!(sorted, vec![1, 2, 3, 4, 5]);
+}
+
+#[test]
+fn test_builder_with_descending_config() {
+    let mut data = vec![(1, 'b'), (2, 'a'), (1, 'a'), (3, 'z')];
+    Tilesort::by(|p: &(i32, char)| p.0)
+        .then_by(|p| p.1)
+        .with_config(SortConfig::new().descending())
+        .sort(&mut data);
+    assert_eq!(data, vec![(3, 'z'), (2, 'a'), (1, 'b'), (1, 'a')]);
+}
+
+#[test]
+fn test_insertion_sort_threshold_matches_tile_index() {
+    // Short inputs of tagged duplicates, in both directions
+    for len in 0..12 {
+        let data: Vec<(u32, usize)> = (0..len).map(|i| ((i * 7 % 5) as u32, i)).collect();
+        for order in [Order::Ascending, Order::Descending] {
+            let insertion = SortConfig::new().order(order);
+            let tiles = insertion.clone().insertion_sort_threshold(0);
+            assert_eq!(
+                tilesorted_by_key_with_config(&data, |&(k, _)| k, &insertion),
+                tilesorted_by_key_with_config(&data, |&(k, _)| k, &tiles),
+                "len {} {:?}",
+                len,
+                order
+            );
+            assert_eq!(
+                tilesorted_with_config(&data, &insertion),
+                tilesorted_with_config(&data, &tiles)
+            );
+        }
+    }
+}
+
+#[test]
+fn test_capacity_hints_do_not_change_result() {
+    let data: Vec<(u32, usize)> = (0..5_000).map(|i| ((i * 31 % 1_000) as u32, i)).collect();
+    let expected = tilesorted_by_key_with_config(&data, |&(k, _)| k, &SortConfig::new());
+    for config in [
+        SortConfig::new().assume_runs(0),
+        SortConfig::new().assume_runs(200),
+        SortConfig::new().assume_runs(1_000_000),
+        SortConfig::new().assume_sorted_fraction(0.96),
+        SortConfig::new().assume_sorted_fraction(0.0),
+        SortConfig::new().assume_sorted_fraction(f64::NAN),
+    ] {
+        assert_eq!(
+            tilesorted_by_key_with_config(&data, |&(k, _)| k, &config),
+            expected,
+            "{:?}",
+            config.capacity_hint
+        );
+    }
+}
+
+#[test]
+fn test_capacity_hint_setters() {
+    assert_eq!(SortConfig::new().capacity_hint, CapacityHint::None);
+    assert_eq!(
+        SortConfig::new().assume_runs(64).capacity_hint,
+        CapacityHint::Runs(64)
+    );
+    assert_eq!(
+        SortConfig::new().assume_sorted_fraction(0.99).capacity_hint,
+        CapacityHint::SortedPerMillion(990_000)
+    );
+    assert_eq!(
+        SortConfig::new().assume_sorted_fraction(2.0).capacity_hint,
+        CapacityHint::SortedPerMillion(1_000_000)
+    );
+    assert_eq!(
+        SortConfig::new().assume_sorted_fraction(-1.0).capacity_hint,
+        CapacityHint::SortedPerMillion(0)
+    );
+}
@@ -0,0 +1,84 @@
+// Integration tests for sorting custom random-access containers
+
+use rand::prelude::*;
+use test_log::test;
+
+use tilesort::{tilesort_container, tilesort_container_by_key, RandomAccess};
+
+/// Element that cannot be cloned, to show containers are sorted with swaps.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct NoClone(u32);
+
+/// Two parallel columns sorted together, as in a columnar store.
+struct Columns {
+    ids: Vec<u32>,
+    names: Vec<&'static str>,
+}
+
+impl RandomAccess for Columns {
+    type Item = u32;
+
+    fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    fn get(&self, index: usize) -> &u32 {
+        &self.ids[index]
+    }
+
+    fn swap(&mut self, a: usize, b: usize) {
+        self.ids.swap(a, b);
+        self.names.swap(a, b);
+    }
+}
+
+#[test]
+fn test_container_slice_without_clone() {
+    let mut data: Vec<NoClone> = [4, 5, 6, 1, 2, 3].into_iter().map(NoClone).collect();
+    tilesort_container(data.as_mut_slice());
+    let values: Vec<u32> = data.iter().map(|x| x.0).collect();
+    assert_eq!(values, vec![1, 2, 3, 4, 5, 6]);
+}
+
+#[test]
+fn test_columnar_store_moves_rows_together() {
+    let mut columns = Columns {
+        ids: vec![30, 31, 10, 20],
+        names: vec!["c", "d", "a", "b"],
+    };
+    tilesort_container(&mut columns);
+    assert_eq!(columns.ids, vec![10, 20, 30, 31]);
+    assert_eq!(columns.names, vec!["a", "b", "c", "d"]);
+}
+
+#[test]
+fn test_container_by_key_is_stable() {
+    let mut data = vec![(2, 'a'), (1, 'b'), (2, 'c'), (1, 'd')];
+    tilesort_container_by_key(data.as_mut_slice(), |&(key, _): &(u8, char)| key);
+    assert_eq!(data, vec![(1, 'b'), (1, 'd'), (2, 'a'), (2, 'c')]);
+}
+
+#[test]
+fn test_container_matches_sort() {
+    let mut rng = StdRng::seed_from_u64(0xc0de);
+    for _ in 0..1_000 {
+        let len = rng.random_range(0..50);
+        let data: Vec<u16> = (0..len).map(|_| rng.random_range(0..30)).collect();
+
+        let mut expected = data.clone();
+        expected.sort();
+
+        let mut actual = data.clone();
+        tilesort_container(actual.as_mut_slice());
+        assert_eq!(actual, expected);
+    }
+}
+
+#[test]
+fn test_vec_methods_are_not_shadowed() {
+    // `RandomAccess` is in scope, but `Vec` keeps the slice methods
+    let mut data: Vec<u8> = (1..=3).collect();
+    data.swap(0, 2);
+    assert_eq!(data.get(2), Some(&1));
+    assert_eq!(data.get(3), None);
+}
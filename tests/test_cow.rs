@@ -0,0 +1,67 @@
+// Integration tests for sorts that borrow input that is already sorted
+
+use std::borrow::Cow;
+
+use test_log::test;
+
+use tilesort::{
+    tilesorted_cow, tilesorted_cow_by_key, tilesorted_cow_by_key_with_config,
+    tilesorted_cow_with_config, SortConfig,
+};
+
+fn is_borrowed<T: Clone>(cow: Cow<'_, [T]>) -> bool {
+    matches!(cow, Cow::Borrowed(_))
+}
+
+#[test]
+fn test_sorted_input_is_borrowed() {
+    let no_detection = SortConfig::new().detect_patterns(false);
+    for len in [0, 1, 2, 10, 31, 32, 100, 5_000] {
+        let data: Vec<u32> = (0..len).map(|i| i / 3).collect();
+        let sorted = tilesorted_cow(&data);
+        assert!(std::ptr::eq(sorted.as_ptr(), data.as_ptr()));
+        assert!(is_borrowed(sorted), "len {}", len);
+        assert!(is_borrowed(tilesorted_cow_with_config(
+            &data,
+            &no_detection
+        )));
+
+        let descending: Vec<u32> = data.iter().rev().copied().collect();
+        let config = SortConfig::new().descending();
+        assert!(is_borrowed(tilesorted_cow_with_config(
+            &descending,
+            &config
+        )));
+    }
+}
+
+#[test]
+fn test_unsorted_input_is_copied() {
+    for len in [7, 10, 100, 5_000] {
+        let data: Vec<u32> = (0..len).map(|i| (i * 37) % len).collect();
+        let mut expected = data.clone();
+        expected.sort();
+
+        let sorted = tilesorted_cow(&data);
+        assert_eq!(*sorted, *expected);
+        assert!(!is_borrowed(sorted), "len {}", len);
+
+        let reversed: Vec<u32> = (0..len).rev().collect();
+        let sorted = tilesorted_cow(&reversed);
+        assert_eq!(sorted.into_owned(), (0..len).collect::<Vec<u32>>());
+    }
+}
+
+#[test]
+fn test_cow_by_key() {
+    let words = ["a", "fig", "pear", "kiwi", "banana"];
+    assert!(is_borrowed(tilesorted_cow_by_key(&words, |w| w.len())));
+
+    let sorted = tilesorted_cow_by_key(&words, |w| w.to_string());
+    assert_eq!(*sorted, ["a", "banana", "fig", "kiwi", "pear"]);
+
+    // Equal keys stay in input order, so no copy is needed
+    let config = SortConfig::new().descending();
+    let sorted = tilesorted_cow_by_key_with_config(&words, |w| w.len() / 10, &config);
+    assert!(is_borrowed(sorted));
+}
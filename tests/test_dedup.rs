@@ -0,0 +1,56 @@
+// Integration tests for sorting with deduplication
+
+use rand::prelude::*;
+use test_log::test;
+
+use tilesort::{tilesort_dedup, tilesort_dedup_by_key};
+
+#[test]
+fn test_dedup_empty_and_single() {
+    let mut empty: Vec<i32> = vec![];
+    assert_eq!(tilesort_dedup(&mut empty), 0);
+
+    let mut single = vec![7];
+    assert_eq!(tilesort_dedup(&mut single), 1);
+    assert_eq!(single, vec![7]);
+}
+
+#[test]
+fn test_dedup_across_tiles() {
+    let mut data = vec![1, 3, 3, 5, 2, 3, 5, 5, 6];
+    let len = tilesort_dedup(&mut data);
+    assert_eq!(&data[..len], &[1, 2, 3, 5, 6]);
+    assert_eq!(data.len(), 9);
+}
+
+#[test]
+fn test_dedup_all_equal() {
+    let mut data = vec![4; 10];
+    assert_eq!(tilesort_dedup(&mut data), 1);
+    assert_eq!(data[0], 4);
+}
+
+#[test]
+fn test_dedup_by_key_keeps_first_occurrence() {
+    let mut data = vec![(2, 'a'), (1, 'b'), (2, 'c'), (1, 'd'), (3, 'e')];
+    let len = tilesort_dedup_by_key(&mut data, |&(key, _)| key);
+    assert_eq!(&data[..len], &[(1, 'b'), (2, 'a'), (3, 'e')]);
+}
+
+#[test]
+fn test_dedup_matches_sort_then_dedup() {
+    let mut rng = StdRng::seed_from_u64(0xded);
+    for _ in 0..2_000 {
+        let len = rng.random_range(0..50);
+        let data: Vec<u8> = (0..len).map(|_| rng.random_range(0..8)).collect();
+
+        let mut expected = data.clone();
+        expected.sort();
+        expected.dedup();
+
+        let mut actual = data.clone();
+        let retained = tilesort_dedup(&mut actual);
+        actual.truncate(retained);
+        assert_eq!(actual, expected, "input: {:?}", data);
+    }
+}
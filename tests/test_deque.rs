@@ -0,0 +1,65 @@
+// Integration tests for sorting VecDeque without make_contiguous
+
+use std::collections::VecDeque;
+
+use rand::prelude::*;
+use test_log::test;
+
+use tilesort::{tilesort_deque, tilesort_deque_by_key};
+
+/// Build a deque holding `values` whose storage wraps around after `split`.
+fn wrapped(values: &[u32], split: usize) -> VecDeque<u32> {
+    let mut deque = VecDeque::with_capacity(values.len());
+    deque.extend(&values[split..]);
+    for &value in values[..split].iter().rev() {
+        deque.push_front(value);
+    }
+    deque
+}
+
+#[test]
+fn test_deque_is_not_made_contiguous() {
+    let mut deque = wrapped(&[5, 6, 7, 1, 2, 3, 4], 4);
+    let (front, back) = deque.as_slices();
+    assert!(!front.is_empty() && !back.is_empty());
+
+    tilesort_deque(&mut deque);
+    assert_eq!(deque, [1, 2, 3, 4, 5, 6, 7]);
+}
+
+#[test]
+fn test_tile_across_wrap_point() {
+    // 3, 4, 5 straddles the two slices
+    let values = [3, 4, 5, 1, 2];
+    for split in 0..=values.len() {
+        let mut deque = wrapped(&values, split);
+        tilesort_deque(&mut deque);
+        assert_eq!(deque, [1, 2, 3, 4, 5], "split at {}", split);
+    }
+}
+
+#[test]
+fn test_deque_by_key_is_stable() {
+    let mut deque: VecDeque<(u8, char)> = VecDeque::from(vec![(2, 'a'), (1, 'b')]);
+    deque.push_front((1, 'c'));
+    deque.push_front((2, 'd'));
+    tilesort_deque_by_key(&mut deque, |&(key, _)| key);
+    assert_eq!(deque, [(1, 'c'), (1, 'b'), (2, 'd'), (2, 'a')]);
+}
+
+#[test]
+fn test_deque_matches_sort() {
+    let mut rng = StdRng::seed_from_u64(0xd0e);
+    for _ in 0..1_000 {
+        let len = rng.random_range(0..40);
+        let values: Vec<u32> = (0..len).map(|_| rng.random_range(0..20)).collect();
+        let split = rng.random_range(0..=len);
+
+        let mut deque = wrapped(&values, split);
+        tilesort_deque(&mut deque);
+
+        let mut expected = values.clone();
+        expected.sort();
+        assert!(deque.iter().eq(expected.iter()));
+    }
+}
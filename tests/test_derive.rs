@@ -0,0 +1,67 @@
+// Integration tests for #[derive(TileKey)]
+#![cfg(feature = "derive")]
+
+use std::cmp::Reverse;
+
+use test_log::test;
+
+use tilesort::{tilesort_by_extractor, ByTileKey, TileKey};
+
+#[derive(Debug, Clone, PartialEq, TileKey)]
+struct Row {
+    #[tile_key(order = 2)]
+    first_name: String,
+    #[tile_key(order = 1)]
+    last_name: String,
+    #[tile_key(order = 3, desc)]
+    age: u32,
+    note: &'static str,
+}
+
+fn row(last_name: &str, first_name: &str, age: u32) -> Row {
+    Row {
+        first_name: first_name.to_string(),
+        last_name: last_name.to_string(),
+        age,
+        note: "",
+    }
+}
+
+#[derive(TileKey)]
+struct Pair(#[tile_key(desc)] i32, #[tile_key] char);
+
+#[derive(TileKey)]
+struct Wrapper<T> {
+    #[tile_key]
+    value: T,
+}
+
+#[test]
+fn test_derived_key_respects_order_and_direction() {
+    let key = row("Smith", "Jane", 30).tile_key();
+    assert_eq!(key, ("Smith".to_string(), "Jane".to_string(), Reverse(30)));
+}
+
+#[test]
+fn test_derived_key_sort() {
+    let mut rows = vec![
+        row("Smith", "Jane", 30),
+        row("Jones", "Bob", 40),
+        row("Smith", "Adam", 25),
+        row("Smith", "Jane", 45),
+    ];
+    tilesort_by_extractor(&mut rows, ByTileKey);
+
+    let ages: Vec<u32> = rows.iter().map(|r| r.age).collect();
+    assert_eq!(ages, vec![40, 25, 45, 30]);
+}
+
+#[test]
+fn test_tuple_struct_declaration_order() {
+    assert_eq!(Pair(3, 'a').tile_key(), (Reverse(3), 'a'));
+}
+
+#[test]
+fn test_generic_struct() {
+    assert_eq!(Wrapper { value: "x" }.tile_key(), ("x",));
+}
@@ -0,0 +1,90 @@
+// Integration tests for boxed, type-erased key extractors
+use rand::prelude::*;
+use test_log::test;
+
+use tilesort::{tilesort_by_extractor, KeyExtractor, KeyExtractorDyn, SortSpec, SortSpecError};
+
+#[derive(Debug, Clone, PartialEq)]
+struct Row {
+    group: u8,
+    score: i32,
+    name: String,
+}
+
+/// Build the extractor for a comma-separated list of column names, each
+/// optionally prefixed with `-` for descending order.
+fn order_from_spec(spec: &str) -> Vec<Box<dyn KeyExtractorDyn<Row>>> {
+    spec.split(',')
+        .map(|column| {
+            let (descending, column) = match column.strip_prefix('-') {
+                Some(column) => (true, column),
+                None => (false, column),
+            };
+            match (column, descending) {
+                ("group", false) => (|row: &Row| row.group).boxed(),
+                ("group", true) => (|row: &Row| row.group).reversed().boxed(),
+                ("score", false) => (|row: &Row| row.score).boxed(),
+                ("score", true) => (|row: &Row| row.score).reversed().boxed(),
+                ("name", false) => (|row: &Row| row.name.clone()).boxed(),
+                ("name", true) => (|row: &Row| row.name.clone()).reversed().boxed(),
+                _ => panic!("unknown column {}", column),
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn test_runtime_order_matches_static_sort() {
+    let mut rng = StdRng::seed_from_u64(611);
+    let rows: Vec<Row> = (0..300)
+        .map(|i| Row {
+            group: rng.random_range(0..4),
+            score: rng.random_range(-5..5),
+            name: format!("row{}", i % 17),
+        })
+        .collect();
+
+    let mut dynamic = rows.clone();
+    tilesort_by_extractor(&mut dynamic, order_from_spec("group,-score"));
+
+    // Ties that neither key breaks stay in input order
+    let mut expected = rows;
+    expected.sort_by_key(|row| (row.group, std::cmp::Reverse(row.score)));
+    assert_eq!(dynamic, expected);
+}
+
+#[test]
+fn test_single_boxed_extractor() {
+    let mut words = vec!["ccc", "a", "bb"];
+    let by_len: Box<dyn KeyExtractorDyn<&str>> = (|w: &&str| w.len()).boxed();
+    tilesort_by_extractor(&mut words, by_len);
+    assert_eq!(words, vec!["a", "bb", "ccc"]);
+}
+
+#[test]
+fn test_sort_spec_matches_manual_order() {
+    let spec = SortSpec::new()
+        .field("group", |row: &Row| row.group)
+        .field("score", |row: &Row| row.score)
+        .field("name", |row: &Row| row.name.clone());
+    let mut rng = StdRng::seed_from_u64(612);
+    let rows: Vec<Row> = (0..300)
+        .map(|i| Row {
+            group: rng.random_range(0..4),
+            score: rng.random_range(-5..5),
+            name: format!("row{}", i % 17),
+        })
+        .collect();
+
+    for order in ["group,-score", "-name,+group,score", "score"] {
+        let mut parsed = rows.clone();
+        tilesort_by_extractor(&mut parsed, spec.parse(order).unwrap());
+        let mut manual = rows.clone();
+        tilesort_by_extractor(&mut manual, order_from_spec(&order.replace('+', "")));
+        assert_eq!(parsed, manual, "{}", order);
+    }
+    assert_eq!(
+        spec.parse("group,size").err(),
+        Some(SortSpecError::UnknownField("size".to_string()))
+    );
+}
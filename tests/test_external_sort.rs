@@ -0,0 +1,331 @@
+// Integration tests for sorting files larger than memory
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use rand::prelude::*;
+use test_log::test;
+
+use tilesort::{sort_file, sort_file_with_progress, ExternalSortConfig, Progress, RecordFormat};
+
+/// A fresh directory for one test's files.
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("tilesort-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn random_lines(rng: &mut StdRng, len: usize) -> Vec<String> {
+    (0..len)
+        .map(|_| format!("key-{:04}", rng.random_range(0..2000)))
+        .collect()
+}
+
+#[test]
+fn test_sort_file_matches_sort() {
+    let dir = scratch_dir("external-lines");
+    let mut rng = StdRng::seed_from_u64(631);
+    let lines = random_lines(&mut rng, 5000);
+    let contents: String = lines.iter().map(|line| format!("{}\n", line)).collect();
+    fs::write(dir.join("in.txt"), contents).unwrap();
+
+    for workers in [1, 3] {
+        // About a hundred runs, spilled and merged
+        let config = ExternalSortConfig::new()
+            .run_size(1500)
+            .buffer_size(64)
+            .workers(workers)
+            .temp_dir(dir.join("runs"));
+        let written = sort_file(dir.join("in.txt"), dir.join("out.txt"), &config).unwrap();
+
+        let mut expected = lines.clone();
+        expected.sort();
+        assert_eq!(written, expected.len() as u64);
+        let sorted = fs::read_to_string(dir.join("out.txt")).unwrap();
+        assert_eq!(sorted.lines().collect::<Vec<_>>(), expected);
+        // The run files are gone
+        assert_eq!(fs::read_dir(dir.join("runs")).unwrap().count(), 0);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_sort_file_merges_in_batches() {
+    let dir = scratch_dir("external-batches");
+    let mut rng = StdRng::seed_from_u64(6310);
+    let lines = random_lines(&mut rng, 5000);
+    let contents: String = lines.iter().map(|line| format!("{}\n", line)).collect();
+    fs::write(dir.join("in.txt"), contents).unwrap();
+    let mut expected = lines.clone();
+    expected.sort();
+
+    // About a hundred runs, merged four at a time over several passes
+    for batch_size in [0, 4, 99] {
+        let config = ExternalSortConfig::new()
+            .run_size(1500)
+            .batch_size(batch_size)
+            .temp_dir(dir.join("runs"));
+        let written = sort_file(dir.join("in.txt"), dir.join("out.txt"), &config).unwrap();
+        assert_eq!(written, expected.len() as u64);
+        let sorted = fs::read_to_string(dir.join("out.txt")).unwrap();
+        assert_eq!(
+            sorted.lines().collect::<Vec<_>>(),
+            expected,
+            "{}",
+            batch_size
+        );
+        assert_eq!(fs::read_dir(dir.join("runs")).unwrap().count(), 0);
+    }
+
+    // A checkpoint's intermediate files are removed with its runs
+    let config = ExternalSortConfig::new()
+        .run_size(1500)
+        .batch_size(3)
+        .checkpoint_dir(dir.join("checkpoint"));
+    sort_file(dir.join("in.txt"), dir.join("out.txt"), &config).unwrap();
+    let sorted = fs::read_to_string(dir.join("out.txt")).unwrap();
+    assert_eq!(sorted.lines().collect::<Vec<_>>(), expected);
+    assert!(!dir.join("checkpoint").exists());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_sort_file_reports_progress() {
+    let dir = scratch_dir("external-progress");
+    let mut rng = StdRng::seed_from_u64(632);
+    let lines = random_lines(&mut rng, 5000);
+    let contents: String = lines.iter().map(|line| format!("{}\n", line)).collect();
+    fs::write(dir.join("in.txt"), &contents).unwrap();
+    let len = contents.len() as u64;
+
+    // About 27 runs merged 4 at a time take two passes before the final merge
+    let config = ExternalSortConfig::new()
+        .run_size(6000)
+        .batch_size(4)
+        .temp_dir(&dir);
+    let mut updates = Vec::new();
+    sort_file_with_progress(dir.join("in.txt"), dir.join("out.txt"), &config, |p| {
+        updates.push(p)
+    })
+    .unwrap();
+
+    let merging = updates
+        .iter()
+        .position(|p| matches!(p, Progress::Merging { .. }))
+        .unwrap();
+    let (spilling, merging) = updates.split_at(merging);
+    let read: Vec<u64> = spilling
+        .iter()
+        .map(|p| match *p {
+            Progress::Spilling {
+                bytes_read,
+                total_bytes,
+            } => {
+                assert_eq!(total_bytes, len);
+                bytes_read
+            }
+            other => panic!("{:?} while spilling", other),
+        })
+        .collect();
+    assert!(read.len() > 20);
+    assert_eq!((read[0], read[read.len() - 1]), (0, len));
+    assert!(read.windows(2).all(|w| w[0] < w[1]));
+
+    let merged: Vec<u64> = merging
+        .iter()
+        .map(|p| match *p {
+            Progress::Merging {
+                bytes_merged,
+                total_bytes,
+            } => {
+                assert_eq!(total_bytes, 3 * len);
+                bytes_merged
+            }
+            other => panic!("{:?} while merging", other),
+        })
+        .collect();
+    assert_eq!((merged[0], merged[merged.len() - 1]), (0, 3 * len));
+    assert!(merged.windows(2).all(|w| w[0] <= w[1]));
+    assert!(merged.len() > 3);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_sort_file_length_prefixed_descending() {
+    let dir = scratch_dir("external-prefixed");
+    let encode = |records: &[&[u8]]| -> Vec<u8> {
+        records
+            .iter()
+            .flat_map(|record| {
+                (record.len() as u32)
+                    .to_le_bytes()
+                    .into_iter()
+                    .chain(record.iter().copied())
+            })
+            .collect()
+    };
+    fs::write(
+        dir.join("in.bin"),
+        encode(&[b"m", b"z\n", b"", b"m\0", b"x"]),
+    )
+    .unwrap();
+
+    let config = ExternalSortConfig::new()
+        .format(RecordFormat::LengthPrefixed)
+        .descending()
+        .run_size(1)
+        .temp_dir(&dir);
+    assert_eq!(
+        sort_file(dir.join("in.bin"), dir.join("out.bin"), &config).unwrap(),
+        5
+    );
+    assert_eq!(
+        fs::read(dir.join("out.bin")).unwrap(),
+        encode(&[b"z\n", b"x", b"m\0", b"m", b""])
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_sort_file_single_run_and_empty() {
+    let dir = scratch_dir("external-small");
+    fs::write(dir.join("in.txt"), "b\na\nc").unwrap();
+    let config = ExternalSortConfig::new().temp_dir(&dir);
+    assert_eq!(
+        sort_file(dir.join("in.txt"), dir.join("out.txt"), &config).unwrap(),
+        3
+    );
+    assert_eq!(
+        fs::read_to_string(dir.join("out.txt")).unwrap(),
+        "a\nb\nc\n"
+    );
+
+    fs::write(dir.join("empty.txt"), "").unwrap();
+    assert_eq!(
+        sort_file(dir.join("empty.txt"), dir.join("out.txt"), &config).unwrap(),
+        0
+    );
+    assert_eq!(fs::read_to_string(dir.join("out.txt")).unwrap(), "");
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_sort_file_truncated_input() {
+    let dir = scratch_dir("external-truncated");
+    let mut bytes = Vec::new();
+    for _ in 0..100 {
+        bytes.extend_from_slice(&[2, 0, 0, 0, b'h', b'i']);
+    }
+    bytes.extend_from_slice(&[9, 0, 0, 0, b'x']);
+    fs::write(dir.join("in.bin"), bytes).unwrap();
+
+    let config = ExternalSortConfig::new()
+        .format(RecordFormat::LengthPrefixed)
+        .run_size(64)
+        .workers(2)
+        .temp_dir(dir.join("runs"));
+    let err = sort_file(dir.join("in.bin"), dir.join("out.bin"), &config).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    assert_eq!(fs::read_dir(dir.join("runs")).unwrap().count(), 0);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_sort_file_missing_input() {
+    let dir = scratch_dir("external-missing");
+    let err = sort_file(
+        dir.join("absent.txt"),
+        dir.join("out.txt"),
+        &ExternalSortConfig::new(),
+    )
+    .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// Sort `lines` with a checkpoint, failing in the final merge because the
+/// output's directory is missing, so every run is left spilled.
+fn interrupted_sort(dir: &Path, lines: &[String]) -> ExternalSortConfig {
+    let contents: String = lines.iter().map(|line| format!("{}\n", line)).collect();
+    fs::write(dir.join("in.txt"), contents).unwrap();
+    let config = ExternalSortConfig::new()
+        .run_size(2000)
+        .workers(3)
+        .checkpoint_dir(dir.join("checkpoint"));
+    let err = sort_file(dir.join("in.txt"), dir.join("missing/out.txt"), &config).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+    assert!(dir.join("checkpoint/manifest").exists());
+    config.resume(true)
+}
+
+#[test]
+fn test_sort_file_resume_reuses_spilled_runs() {
+    let dir = scratch_dir("external-resume");
+    let mut rng = StdRng::seed_from_u64(633);
+    let lines = random_lines(&mut rng, 3000);
+    let config = interrupted_sort(&dir, &lines);
+
+    // A marker in the first run shows it is merged again, not re-sorted
+    fs::write(dir.join("checkpoint/run-00000000"), "!marker\n").unwrap();
+    sort_file(dir.join("in.txt"), dir.join("out.txt"), &config).unwrap();
+    let sorted = fs::read_to_string(dir.join("out.txt")).unwrap();
+    assert_eq!(sorted.lines().next(), Some("!marker"));
+    assert!(!dir.join("checkpoint").exists());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_sort_file_resume_redoes_unlisted_runs() {
+    let dir = scratch_dir("external-resume-partial");
+    let mut rng = StdRng::seed_from_u64(6330);
+    let lines = random_lines(&mut rng, 3000);
+    let config = interrupted_sort(&dir, &lines);
+
+    // Keep runs 0, 1 and 3 as if the rest were in flight, with a line cut
+    // short by the sort being killed
+    let manifest_path = dir.join("checkpoint/manifest");
+    let manifest = fs::read_to_string(&manifest_path).unwrap();
+    let mut kept: String = manifest
+        .lines()
+        .filter(|line| {
+            let kept_run = ["run 0 ", "run 1 ", "run 3 "]
+                .iter()
+                .any(|prefix| line.starts_with(prefix));
+            !line.starts_with("run ") || kept_run
+        })
+        .map(|line| format!("{}\n", line))
+        .collect();
+    kept.push_str("run 7 12");
+    fs::write(&manifest_path, kept).unwrap();
+    fs::write(dir.join("checkpoint/run-00000002"), "stale\n").unwrap();
+
+    let written = sort_file(dir.join("in.txt"), dir.join("out.txt"), &config).unwrap();
+    let mut expected = lines.clone();
+    expected.sort();
+    assert_eq!(written, expected.len() as u64);
+    let sorted = fs::read_to_string(dir.join("out.txt")).unwrap();
+    assert_eq!(sorted.lines().collect::<Vec<_>>(), expected);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_sort_file_resume_rejects_other_settings() {
+    let dir = scratch_dir("external-resume-settings");
+    let mut rng = StdRng::seed_from_u64(6331);
+    let lines = random_lines(&mut rng, 500);
+    let config = interrupted_sort(&dir, &lines);
+
+    let other = config.clone().run_size(4000);
+    let err = sort_file(dir.join("in.txt"), dir.join("out.txt"), &other).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+    // Without resuming, the checkpoint is started afresh
+    let fresh = other.resume(false);
+    sort_file(dir.join("in.txt"), dir.join("out.txt"), &fresh).unwrap();
+    let mut expected = lines;
+    expected.sort();
+    let sorted = fs::read_to_string(dir.join("out.txt")).unwrap();
+    assert_eq!(sorted.lines().collect::<Vec<_>>(), expected);
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,84 @@
+// Integration tests for sorting with equal-key group boundaries
+
+use rand::prelude::*;
+use test_log::test;
+
+use tilesort::keys::FieldKey;
+use tilesort::{tilesort_grouped, tilesort_grouped_by_extractor, tilesort_grouped_by_key};
+
+#[test]
+fn test_grouped_empty_and_single() {
+    let mut empty: Vec<i32> = vec![];
+    assert!(tilesort_grouped(&mut empty).is_empty());
+
+    let mut single = vec![1];
+    assert_eq!(tilesort_grouped(&mut single), vec![0..1]);
+}
+
+#[test]
+fn test_grouped_distinct() {
+    let mut data = vec![3, 4, 1, 2];
+    assert_eq!(tilesort_grouped(&mut data), vec![0..1, 1..2, 2..3, 3..4]);
+    assert_eq!(data, vec![1, 2, 3, 4]);
+}
+
+#[test]
+fn test_grouped_by_key_records() {
+    let mut data = vec![("ops", 3), ("dev", 1), ("ops", 1), ("dev", 2), ("qa", 9)];
+    let groups = tilesort_grouped_by_key(&mut data, |&(team, _)| team);
+    assert_eq!(groups, vec![0..2, 2..4, 4..5]);
+    assert_eq!(&data[groups[1].clone()], &[("ops", 3), ("ops", 1)]);
+}
+
+#[test]
+fn test_grouped_by_extractor_counts() {
+    let mut rng = StdRng::seed_from_u64(642);
+    let words = ["apple", "Banana", "cherry", "banana", "APPLE"];
+    let mut lines: Vec<String> = (0..500)
+        .map(|i| format!("{} {}", words[rng.random_range(0..words.len())], i))
+        .collect();
+    let mut expected: Vec<(usize, String)> = words
+        .iter()
+        .map(|w| {
+            let count = lines
+                .iter()
+                .filter(|line| line.split(' ').next().unwrap().eq_ignore_ascii_case(w))
+                .count();
+            (count, w.to_ascii_uppercase())
+        })
+        .collect();
+    expected.sort_by(|a, b| a.1.cmp(&b.1));
+    expected.dedup();
+
+    let groups = tilesort_grouped_by_extractor(&mut lines, FieldKey::parse("1,1f").unwrap());
+    let counts: Vec<(usize, String)> = groups
+        .iter()
+        .map(|g| {
+            let first = lines[g.start].split(' ').next().unwrap();
+            (g.len(), first.to_ascii_uppercase())
+        })
+        .collect();
+    assert_eq!(counts, expected);
+    assert_eq!(groups.last().map(|g| g.end), Some(500));
+}
+
+#[test]
+fn test_grouped_matches_chunk_by() {
+    let mut rng = StdRng::seed_from_u64(0x6a0);
+    for _ in 0..2_000 {
+        let len = rng.random_range(0..50);
+        let mut data: Vec<u8> = (0..len).map(|_| rng.random_range(0..6)).collect();
+        let groups = tilesort_grouped(&mut data);
+
+        assert!(data.windows(2).all(|w| w[0] <= w[1]));
+        let mut expected = Vec::new();
+        let mut start = 0;
+        for idx in 1..=data.len() {
+            if idx == data.len() || data[idx] != data[start] {
+                expected.push(start..idx);
+                start = idx;
+            }
+        }
+        assert_eq!(groups, expected);
+    }
+}
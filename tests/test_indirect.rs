@@ -0,0 +1,48 @@
+// Integration tests for indirect (permutation-based) restructuring
+
+use test_log::test;
+
+use tilesort::{tilesort_by_key_with_config, tilesort_with_config, SortConfig};
+
+/// Element whose `Clone` panics, to check that indirect mode never clones.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct NoClone(u32);
+
+impl Clone for NoClone {
+    fn clone(&self) -> Self {
+        panic!("indirect restructuring must not clone");
+    }
+}
+
+#[test]
+fn test_indirect_matches_buffered() {
+    let original: Vec<u32> = (0..10_000).map(|i| (i * 7919) % 1_000).collect();
+    let mut buffered = original.clone();
+    let mut indirect = original;
+    tilesort_with_config(&mut buffered, &SortConfig::new());
+    tilesort_with_config(&mut indirect, &SortConfig::new().indirect(true));
+    assert_eq!(indirect, buffered);
+}
+
+#[test]
+fn test_indirect_does_not_clone() {
+    let mut data: Vec<NoClone> = [5, 6, 7, 1, 2, 3, 4].into_iter().map(NoClone).collect();
+    tilesort_with_config(&mut data, &SortConfig::new().indirect(true));
+    let values: Vec<u32> = data.iter().map(|e| e.0).collect();
+    assert_eq!(values, vec![1, 2, 3, 4, 5, 6, 7]);
+}
+
+#[test]
+fn test_indirect_by_key_is_stable() {
+    let mut data = vec![(2, 'a'), (1, 'b'), (2, 'c'), (1, 'd')];
+    let config = SortConfig::new().descending().indirect(true);
+    tilesort_by_key_with_config(&mut data, |&(k, _)| k, &config);
+    assert_eq!(data, vec![(2, 'a'), (2, 'c'), (1, 'b'), (1, 'd')]);
+}
+
+#[test]
+fn test_indirect_large_elements() {
+    let mut data: Vec<[u64; 32]> = (0..200u64).rev().map(|i| [i; 32]).collect();
+    tilesort_with_config(&mut data, &SortConfig::new().indirect(true));
+    assert!(data.windows(2).all(|w| w[0] <= w[1]));
+}
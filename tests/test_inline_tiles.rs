@@ -0,0 +1,75 @@
+// Integration tests for the tile index's inline storage
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use tilesort::{tilesort_with_config, SortConfig, MAX_INLINE_TILES};
+
+/// Allocator counting the allocations made by the current thread.
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations_sorting(data: &[u32], config: &SortConfig) -> usize {
+    let mut data = data.to_vec();
+    let before = ALLOCATIONS.with(Cell::get);
+    tilesort_with_config(&mut data, config);
+    let allocations = ALLOCATIONS.with(Cell::get) - before;
+    assert!(data.windows(2).all(|pair| pair[0] <= pair[1]));
+    allocations
+}
+
+/// `tiles` ascending runs of ten elements, in descending order of their keys.
+fn runs(tiles: u32) -> Vec<u32> {
+    (0..tiles)
+        .rev()
+        .flat_map(|tile| tile * 10..tile * 10 + 10)
+        .collect()
+}
+
+#[test]
+fn test_few_tiles_allocate_no_index() {
+    // Same element count, so the key and restructuring buffers match
+    let two_tiles: Vec<u32> = (0..MAX_INLINE_TILES as u32 * 5)
+        .map(|i| i + 1_000)
+        .chain(0..MAX_INLINE_TILES as u32 * 5)
+        .collect();
+    let data = runs(MAX_INLINE_TILES as u32);
+    assert_eq!(data.len(), two_tiles.len());
+
+    let inline = allocations_sorting(&data, &SortConfig::new());
+    assert_eq!(inline, allocations_sorting(&two_tiles, &SortConfig::new()));
+    let heap = allocations_sorting(&data, &SortConfig::new().inline_tiles(0));
+    assert!(heap > inline);
+}
+
+#[test]
+fn test_many_tiles_spill_to_buckets() {
+    let data = runs(4 * MAX_INLINE_TILES as u32);
+    let inline = allocations_sorting(&data, &SortConfig::new());
+    let heap = allocations_sorting(&data, &SortConfig::new().inline_tiles(0));
+    assert!(inline <= heap);
+}
+
+#[test]
+fn test_capacity_is_clamped() {
+    let config = SortConfig::new().inline_tiles(usize::MAX);
+    let data = runs(MAX_INLINE_TILES as u32 + 1);
+    let clamped = allocations_sorting(&data, &config);
+    assert_eq!(clamped, allocations_sorting(&data, &SortConfig::new()));
+}
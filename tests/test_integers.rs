@@ -0,0 +1,82 @@
+// Integration tests for the integer sorts and their counting-sort fallback
+
+use rand::prelude::*;
+use test_log::test;
+
+use tilesort::{
+    tilesort_by_integer_key, tilesort_by_integer_key_with_config, tilesort_integers,
+    tilesort_integers_with_config, SortConfig,
+};
+
+#[test]
+fn test_integers_small_domain() {
+    let mut rng = StdRng::seed_from_u64(3);
+    let mut data: Vec<u8> = (0..10_000).map(|_| rng.random_range(0..8)).collect();
+    let mut expected = data.clone();
+    expected.sort();
+    tilesort_integers(&mut data);
+    assert_eq!(data, expected);
+}
+
+#[test]
+fn test_integers_wide_domain_uses_tiles() {
+    let mut data: Vec<i64> = (1_000..2_000).chain(-5_000_000..-4_999_000).collect();
+    let mut expected = data.clone();
+    expected.sort();
+    tilesort_integers(&mut data);
+    assert_eq!(data, expected);
+}
+
+#[test]
+fn test_integers_threshold_disabled_matches() {
+    let original: Vec<i16> = (0..2_000).map(|i| ((i * 31) % 17) as i16 - 8).collect();
+    let mut counted = original.clone();
+    let mut tiled = original;
+    tilesort_integers(&mut counted);
+    tilesort_integers_with_config(&mut tiled, &SortConfig::new().counting_sort_threshold(0));
+    assert_eq!(counted, tiled);
+}
+
+#[test]
+fn test_by_integer_key_is_stable() {
+    let mut data: Vec<(u16, usize)> = (0..1_000).map(|i| ((i % 5) as u16, i)).collect();
+    tilesort_by_integer_key(&mut data, |&(status, _)| status);
+    assert!(data
+        .windows(2)
+        .all(|w| w[0].0 < w[1].0 || (w[0].0 == w[1].0 && w[0].1 < w[1].1)));
+}
+
+#[test]
+fn test_by_integer_key_descending_is_stable() {
+    let mut data = vec![(1u32, 'a'), (3, 'b'), (1, 'c'), (3, 'd'), (2, 'e')];
+    let config = SortConfig::new().descending();
+    tilesort_by_integer_key_with_config(&mut data, |&(k, _)| k, &config);
+    assert_eq!(data, vec![(3, 'b'), (3, 'd'), (2, 'e'), (1, 'a'), (1, 'c')]);
+}
+
+#[test]
+fn test_integers_mixed_sorted_and_random() {
+    let mut rng = StdRng::seed_from_u64(5);
+    let mut data: Vec<(i64, usize)> = Vec::new();
+    for block in 0..20 {
+        if block % 2 == 0 {
+            let start = rng.random_range(-1_000_000..1_000_000);
+            data.extend((start..start + 500).map(|k| (k, 0)));
+        } else {
+            data.extend((0..500).map(|_| (rng.random_range(-1_000_000..1_000_000), 0)));
+        }
+    }
+    for (idx, entry) in data.iter_mut().enumerate() {
+        entry.1 = idx;
+    }
+
+    let mut expected = data.clone();
+    expected.sort_by_key(|&(k, _)| k);
+    tilesort_by_integer_key(&mut data, |&(k, _)| k);
+    assert_eq!(data, expected);
+
+    let mut descending = expected.clone();
+    descending.sort_by_key(|&(k, _)| std::cmp::Reverse(k));
+    tilesort_by_integer_key_with_config(&mut data, |&(k, _)| k, &SortConfig::new().descending());
+    assert_eq!(data, descending);
+}
@@ -0,0 +1,106 @@
+// Integration tests for the sortedness checks
+use std::cell::Cell;
+
+use rand::prelude::*;
+use test_log::test;
+
+use tilesort::{
+    is_sorted, is_sorted_by_extractor, is_sorted_by_key, is_sorted_by_key_with_config,
+    is_sorted_with_config, sorted_until, sorted_until_by_extractor, tilesort_by_key_with_config,
+    IdentityKey, SortConfig,
+};
+
+#[test]
+fn test_trivial_inputs_are_sorted() {
+    assert!(is_sorted::<i32>(&[]));
+    assert!(is_sorted(&[1]));
+    assert!(is_sorted(&[1, 1, 1]));
+    assert!(is_sorted_with_config(
+        &[1, 1, 1],
+        &SortConfig::new().descending()
+    ));
+}
+
+#[test]
+fn test_stops_at_first_pair_out_of_order() {
+    let calls = Cell::new(0);
+    let data: Vec<u32> = (0..1_000).rev().collect();
+    let sorted = is_sorted_by_key(&data, |&x| {
+        calls.set(calls.get() + 1);
+        x
+    });
+    assert!(!sorted);
+    assert_eq!(calls.get(), 2);
+
+    // A sorted input costs one extraction per element
+    calls.set(0);
+    let sorted = is_sorted_by_key_with_config(
+        &data,
+        |&x| {
+            calls.set(calls.get() + 1);
+            x
+        },
+        &SortConfig::new().descending(),
+    );
+    assert!(sorted);
+    assert_eq!(calls.get(), data.len());
+}
+
+#[test]
+fn test_agrees_with_sort() {
+    let mut rng = StdRng::seed_from_u64(41);
+    for config in [SortConfig::new(), SortConfig::new().descending()] {
+        for _ in 0..50 {
+            let mut data: Vec<(u8, u8)> = (0..rng.random_range(0..40))
+                .map(|_| (rng.random_range(0..4), rng.random()))
+                .collect();
+            let key = |&(k, _): &(u8, u8)| k;
+            let was_sorted = is_sorted_by_key_with_config(&data, key, &config);
+            let original = data.clone();
+            tilesort_by_key_with_config(&mut data, key, &config);
+            // Sorting is stable, so sorted input is left as it was
+            assert_eq!(was_sorted, data == original);
+            assert!(is_sorted_by_key_with_config(&data, key, &config));
+            assert!(is_sorted_by_extractor(&data, key, &config));
+        }
+    }
+}
+
+#[test]
+fn test_sorted_until_finds_first_violation() {
+    assert_eq!(sorted_until::<i32>(&[]), 0);
+    assert_eq!(sorted_until(&[7]), 1);
+    assert_eq!(sorted_until(&[3, 1, 2]), 1);
+
+    let mut rng = StdRng::seed_from_u64(643);
+    for config in [SortConfig::new(), SortConfig::new().descending()] {
+        for _ in 0..200 {
+            let data: Vec<u8> = (0..rng.random_range(0..30))
+                .map(|_| rng.random_range(0..5))
+                .collect();
+            // IdentityKey lends its keys; the closure extracts them one by one
+            let n = sorted_until_by_extractor(&data, IdentityKey, &config);
+            let extracted = sorted_until_by_extractor(&data, |&x: &u8| x, &config);
+            assert_eq!(n, extracted);
+            assert!(is_sorted_with_config(&data[..n], &config));
+            if n < data.len() {
+                assert!(!is_sorted_with_config(&data[..n + 1], &config));
+            }
+        }
+    }
+}
+
+#[test]
+fn test_identity_extractor() {
+    let words = ["apple", "banana", "cherry"];
+    assert!(is_sorted_by_extractor(
+        &words,
+        IdentityKey,
+        &SortConfig::new()
+    ));
+    assert!(!is_sorted_by_extractor(
+        &words,
+        IdentityKey,
+        &SortConfig::new().descending()
+    ));
+}
@@ -0,0 +1,53 @@
+// Integration tests for the lazy k-smallest iterator
+
+use rand::prelude::*;
+use test_log::test;
+
+use tilesort::{k_smallest, IdentityKey, KeyExtractor};
+
+#[test]
+fn test_k_smallest_leaves_input_untouched() {
+    let data = vec![7, 8, 9, 1, 2, 3];
+    let smallest: Vec<i32> = k_smallest(&data, 2, IdentityKey).copied().collect();
+    assert_eq!(smallest, vec![1, 2]);
+    assert_eq!(data, vec![7, 8, 9, 1, 2, 3]);
+}
+
+#[test]
+fn test_k_smallest_with_combinators() {
+    let data = vec![(1, 'x'), (3, 'y'), (2, 'z'), (3, 'w')];
+    let largest_first = (|p: &(i32, char)| p.0).reversed();
+    let top: Vec<char> = k_smallest(&data, 3, largest_first)
+        .map(|&(_, tag)| tag)
+        .collect();
+    assert_eq!(top, vec!['y', 'w', 'z']);
+}
+
+#[test]
+fn test_k_smallest_is_lazy_per_element() {
+    let data: Vec<u32> = (1_000..2_000).chain(0..1_000).collect();
+    let mut iter = k_smallest(&data, 1_500, IdentityKey);
+    assert_eq!(iter.next(), Some(&0));
+    assert_eq!(iter.nth(999), Some(&1_000));
+    assert_eq!(iter.len(), 499);
+}
+
+#[test]
+fn test_k_smallest_matches_sort() {
+    let mut rng = StdRng::seed_from_u64(0x4b5);
+    for _ in 0..2_000 {
+        let len = rng.random_range(0..60);
+        let k = rng.random_range(0..=len + 2);
+        let data: Vec<(u8, usize)> = (0..len).map(|i| (rng.random_range(0..10), i)).collect();
+
+        // Stable sort by the first field only
+        let mut expected = data.clone();
+        expected.sort_by_key(|&(key, _)| key);
+        expected.truncate(k);
+
+        let actual: Vec<(u8, usize)> = k_smallest(&data, k, |p: &(u8, usize)| p.0)
+            .copied()
+            .collect();
+        assert_eq!(actual, expected);
+    }
+}
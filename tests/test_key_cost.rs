@@ -0,0 +1,61 @@
+// Integration tests for choosing the restructuring strategy by key cost
+
+use std::thread;
+use std::time::Duration;
+
+use test_log::test;
+
+use tilesort::{tilesort_by_key_with_config, try_tilesort_by_key_with_config, KeyCost, SortConfig};
+
+/// Element whose `Clone` panics, so a test fails if restructuring clones.
+#[derive(Debug, PartialEq, Eq)]
+struct NoClone(u32);
+
+impl Clone for NoClone {
+    fn clone(&self) -> Self {
+        panic!("expected indirect restructuring");
+    }
+}
+
+fn slow_key(element: &NoClone) -> u32 {
+    thread::sleep(Duration::from_micros(20));
+    element.0
+}
+
+fn values(data: &[NoClone]) -> Vec<u32> {
+    data.iter().map(|e| e.0).collect()
+}
+
+#[test]
+fn test_expensive_keys_restructure_indirectly() {
+    let mut data: Vec<NoClone> = [4, 5, 6, 1, 2, 3].into_iter().map(NoClone).collect();
+    let config = SortConfig::new().key_cost(KeyCost::Expensive);
+    tilesort_by_key_with_config(&mut data, |e| e.0, &config);
+    assert_eq!(values(&data), vec![1, 2, 3, 4, 5, 6]);
+}
+
+#[test]
+fn test_auto_detects_slow_keys() {
+    let mut data: Vec<NoClone> = [3, 1, 2].into_iter().map(NoClone).collect();
+    let config = SortConfig::new().key_cost(KeyCost::Auto);
+    tilesort_by_key_with_config(&mut data, slow_key, &config);
+    assert_eq!(values(&data), vec![1, 2, 3]);
+}
+
+#[test]
+fn test_auto_detects_slow_fallible_keys() {
+    let mut data: Vec<NoClone> = [2, 3, 1].into_iter().map(NoClone).collect();
+    let config = SortConfig::new().key_cost(KeyCost::Auto);
+    let result: Result<(), ()> =
+        try_tilesort_by_key_with_config(&mut data, |e| Ok(slow_key(e)), &config);
+    assert_eq!(result, Ok(()));
+    assert_eq!(values(&data), vec![1, 2, 3]);
+}
+
+#[test]
+fn test_cheap_keys_sort_normally() {
+    let mut data = vec![(2, 'a'), (1, 'b'), (2, 'c')];
+    let config = SortConfig::new().key_cost(KeyCost::Auto);
+    tilesort_by_key_with_config(&mut data, |&(k, _)| k, &config);
+    assert_eq!(data, vec![(1, 'b'), (2, 'a'), (2, 'c')]);
+}
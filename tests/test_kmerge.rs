@@ -0,0 +1,79 @@
+// Integration tests for merging already sorted inputs
+
+use rand::prelude::*;
+use test_log::test;
+
+use tilesort::{kmerge, kmerge_owned, IdentityKey, KeyExtractor, Order};
+
+fn sorted_shards(rng: &mut StdRng) -> Vec<Vec<(u8, usize)>> {
+    let shards = rng.random_range(0..8);
+    let mut tag = 0;
+    (0..shards)
+        .map(|_| {
+            let len = rng.random_range(0..40);
+            let mut shard: Vec<u8> = (0..len).map(|_| rng.random_range(0..16)).collect();
+            shard.sort();
+            shard
+                .into_iter()
+                .map(|key| {
+                    tag += 1;
+                    (key, tag)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[test]
+fn test_kmerge_matches_stable_sort() {
+    let mut rng = StdRng::seed_from_u64(0x6d65);
+    for _ in 0..500 {
+        let shards = sorted_shards(&mut rng);
+        // Tags increase across shards, so a stable sort by key gives the
+        // merged order with ties in input order
+        let mut expected: Vec<(u8, usize)> = shards.concat();
+        expected.sort_by_key(|&(key, _)| key);
+
+        let slices: Vec<&[(u8, usize)]> = shards.iter().map(Vec::as_slice).collect();
+        let key = |&(key, _): &(u8, usize)| key;
+        let merged: Vec<(u8, usize)> = kmerge(&slices, key, Order::Ascending).copied().collect();
+        assert_eq!(merged, expected);
+
+        let owned: Vec<(u8, usize)> = kmerge_owned(shards, key, Order::Ascending).collect();
+        assert_eq!(owned, expected);
+    }
+}
+
+#[test]
+fn test_kmerge_descending() {
+    let a = [9, 5, 1];
+    let b = [8, 7, 2, 0];
+    let merged: Vec<i32> = kmerge(&[&a, &b], IdentityKey, Order::Descending)
+        .copied()
+        .collect();
+    assert_eq!(merged, vec![9, 8, 7, 5, 2, 1, 0]);
+
+    // A reversed extractor over descending inputs merges the same way
+    let merged: Vec<i32> = kmerge(&[&a, &b], (|x: &i32| *x).reversed(), Order::Ascending)
+        .copied()
+        .collect();
+    assert_eq!(merged, vec![9, 8, 7, 5, 2, 1, 0]);
+}
+
+#[test]
+fn test_kmerge_owned_is_lazy() {
+    // Unbounded sorted inputs still merge element by element
+    let evens = (0u64..).map(|i| i * 2);
+    let odds = (0u64..).map(|i| i * 2 + 1);
+    let merged: Vec<u64> = kmerge_owned(
+        [
+            Box::new(evens) as Box<dyn Iterator<Item = u64>>,
+            Box::new(odds),
+        ],
+        IdentityKey,
+        Order::Ascending,
+    )
+    .take(6)
+    .collect();
+    assert_eq!(merged, vec![0, 1, 2, 3, 4, 5]);
+}
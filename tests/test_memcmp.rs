@@ -0,0 +1,72 @@
+// Integration tests for sorting by keys compared as raw bytes
+
+use std::borrow::Cow;
+
+use rand::prelude::*;
+use test_log::test;
+
+use tilesort::keys::MemcmpOrd;
+use tilesort::{tilesort_by_memcmp_key, tilesort_by_memcmp_key_with_config, SortConfig};
+
+/// An `i64` encoded so that its bytes sort as its value: big-endian with the
+/// sign bit flipped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Encoded([u8; 8]);
+
+impl Encoded {
+    fn new(value: i64) -> Self {
+        Encoded(((value as u64) ^ (1 << 63)).to_be_bytes())
+    }
+}
+
+impl MemcmpOrd for Encoded {
+    fn ordered_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+#[test]
+fn test_encoded_integers_sort_by_value() {
+    let mut rng = StdRng::seed_from_u64(629);
+    let values: Vec<i64> = (0..3_000)
+        .map(|i| i * 1_000 - 1_500_000 + rng.random_range(-5_000..5_000))
+        .collect();
+    let mut rows: Vec<(Encoded, usize)> = values
+        .iter()
+        .enumerate()
+        .map(|(i, &value)| (Encoded::new(value), i))
+        .collect();
+    tilesort_by_memcmp_key(&mut rows, |row| &row.0);
+
+    let mut expected: Vec<(i64, usize)> = values.iter().copied().zip(0..).collect();
+    expected.sort_by_key(|&(value, _)| value);
+    let found: Vec<usize> = rows.iter().map(|&(_, i)| i).collect();
+    assert_eq!(found, expected.iter().map(|&(_, i)| i).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_strings_match_str_order() {
+    let mut rng = StdRng::seed_from_u64(6290);
+    let words: Vec<String> = (0..2_000)
+        .map(|_| {
+            let len = rng.random_range(0..6);
+            (0..len).map(|_| rng.random_range('a'..='e')).collect()
+        })
+        .collect();
+
+    for config in [SortConfig::new(), SortConfig::new().descending()] {
+        let mut expected = words.clone();
+        expected.sort();
+        if config.order.is_descending() {
+            // Stable descending: equal strings keep their input order
+            expected.reverse();
+        }
+        let mut sorted = words.clone();
+        tilesort_by_memcmp_key_with_config(&mut sorted, |word| word.as_str(), &config);
+        assert_eq!(sorted, expected);
+    }
+
+    let mut cows: Vec<Cow<'_, [u8]>> = vec![Cow::Borrowed(b"b"), Cow::Owned(b"a".to_vec())];
+    tilesort_by_memcmp_key(&mut cows, |cow| cow);
+    assert_eq!(cows, vec![Cow::Borrowed(b"a"), Cow::Borrowed(b"b")]);
+}
@@ -0,0 +1,124 @@
+// Integration tests for restructuring under a memory budget
+
+use rand::prelude::*;
+use test_log::test;
+
+use tilesort::{
+    estimate_memory_with_config, scan_with_config, tilesort_by_key_with_config, SortConfig,
+    Strategy,
+};
+
+/// Element whose `Clone` panics, to check that rotation never clones.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct NoClone(u32);
+
+impl Clone for NoClone {
+    fn clone(&self) -> Self {
+        panic!("rotation must not clone");
+    }
+}
+
+fn tiled_data(seed: u64) -> Vec<(u32, usize)> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut keys: Vec<u32> = Vec::new();
+    for _ in 0..60 {
+        let start = rng.random_range(0..500);
+        let len = rng.random_range(1..80);
+        keys.extend(start..start + len);
+    }
+    keys.into_iter().zip(0..).collect()
+}
+
+#[test]
+fn test_rotate_matches_buffered() {
+    for config in [
+        SortConfig::new(),
+        SortConfig::new().descending(),
+        SortConfig::new().unstable(),
+    ] {
+        let data = tiled_data(23);
+        let mut expected = data.clone();
+        tilesort_by_key_with_config(&mut expected, |&(k, _)| k, &config);
+
+        let mut rotated = data;
+        tilesort_by_key_with_config(&mut rotated, |&(k, _)| k, &config.max_aux_memory(0));
+        assert_eq!(rotated, expected);
+    }
+}
+
+#[test]
+fn test_rotate_does_not_clone() {
+    let mut data: Vec<NoClone> = [5, 6, 7, 1, 2, 3, 4].into_iter().map(NoClone).collect();
+    tilesort::tilesort_with_config(&mut data, &SortConfig::new().max_aux_memory(0));
+    let values: Vec<u32> = data.iter().map(|x| x.0).collect();
+    assert_eq!(values, vec![1, 2, 3, 4, 5, 6, 7]);
+}
+
+#[test]
+fn test_strategy_follows_budget() {
+    let data: Vec<[u64; 4]> = (0..100u64).rev().map(|i| [i; 4]).collect();
+    let buffered = Strategy::Buffered.aux_memory::<[u64; 4]>(data.len());
+    let indirect = Strategy::Indirect.aux_memory::<[u64; 4]>(data.len());
+    assert_eq!(buffered, 3_200);
+    assert_eq!(indirect, 100 * std::mem::size_of::<usize>());
+
+    for (config, strategy) in [
+        (SortConfig::new(), Strategy::Buffered),
+        (
+            SortConfig::new().max_aux_memory(buffered),
+            Strategy::Buffered,
+        ),
+        (
+            SortConfig::new().max_aux_memory(buffered - 1),
+            Strategy::Indirect,
+        ),
+        (SortConfig::new().indirect(true), Strategy::Indirect),
+        (
+            SortConfig::new().max_aux_memory(indirect - 1),
+            Strategy::Rotate,
+        ),
+    ] {
+        let plan = scan_with_config(&data, |x| x[0], &config);
+        let mut sorted = data.clone();
+        assert_eq!(plan.apply(&mut sorted), strategy);
+        assert!(sorted.windows(2).all(|w| w[0] <= w[1]));
+    }
+}
+
+#[test]
+fn test_estimate_predicts_strategy() {
+    let data = tiled_data(29);
+    let size = std::mem::size_of::<(u32, usize)>();
+    for budget in [usize::MAX, data.len() * size - 1, 0] {
+        let config = SortConfig::new().max_aux_memory(budget);
+        let plan = scan_with_config(&data, |&(k, _)| k, &config);
+        let estimate = estimate_memory_with_config(
+            data.len(),
+            size,
+            std::mem::size_of::<u32>(),
+            plan.stats().tiles,
+            &config,
+        );
+        assert!(estimate.restructure <= budget);
+
+        let mut sorted = data.clone();
+        assert_eq!(plan.apply(&mut sorted), estimate.strategy);
+    }
+}
+
+#[test]
+fn test_rotate_many_tiles() {
+    // Random input has a tile every couple of elements, and pattern
+    // detection may not take the cheaper path under the budget
+    let mut rng = StdRng::seed_from_u64(592);
+    for len in [17, 100, 1_000, 200_000] {
+        let data: Vec<(u32, usize)> = (0..len).map(|i| (rng.random_range(0..1_000), i)).collect();
+        let mut expected = data.clone();
+        expected.sort_by_key(|&(k, _)| k);
+
+        let mut rotated = data;
+        let config = SortConfig::new().max_aux_memory(0);
+        tilesort_by_key_with_config(&mut rotated, |&(k, _)| k, &config);
+        assert_eq!(rotated, expected, "{}", len);
+    }
+}
@@ -0,0 +1,164 @@
+// Integration tests for merging sorted files
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use rand::prelude::*;
+use test_log::test;
+
+use tilesort::keys::FieldKey;
+use tilesort::{merge_files, merge_files_by_key, KeyExtractor, MergeConfig, RecordFormat};
+
+/// A fresh directory for one test's files.
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("tilesort-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn write_shards(dir: &Path, shards: &[Vec<String>]) -> Vec<PathBuf> {
+    shards
+        .iter()
+        .enumerate()
+        .map(|(i, shard)| {
+            let path = dir.join(format!("shard-{}.txt", i));
+            let contents: String = shard.iter().map(|line| format!("{}\n", line)).collect();
+            fs::write(&path, contents).unwrap();
+            path
+        })
+        .collect()
+}
+
+#[test]
+fn test_merge_lines_matches_sort() {
+    let dir = scratch_dir("merge-lines");
+    let mut rng = StdRng::seed_from_u64(0x6669);
+    let shards: Vec<Vec<String>> = (0..5)
+        .map(|_| {
+            let len = rng.random_range(0..200);
+            let mut shard: Vec<String> = (0..len)
+                .map(|_| format!("key-{:03}", rng.random_range(0..300)))
+                .collect();
+            shard.sort();
+            shard
+        })
+        .collect();
+    let inputs = write_shards(&dir, &shards);
+
+    // A tiny buffer exercises refills in the middle of records
+    let config = MergeConfig::new().buffer_size(7);
+    let written = merge_files(&inputs, dir.join("out.txt"), &config).unwrap();
+
+    let mut expected = shards.concat();
+    expected.sort();
+    assert_eq!(written, expected.len() as u64);
+    let merged = fs::read_to_string(dir.join("out.txt")).unwrap();
+    assert_eq!(merged.lines().collect::<Vec<_>>(), expected);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_merge_length_prefixed_descending() {
+    let dir = scratch_dir("merge-prefixed");
+    let encode = |records: &[&[u8]]| -> Vec<u8> {
+        records
+            .iter()
+            .flat_map(|record| {
+                (record.len() as u32)
+                    .to_le_bytes()
+                    .into_iter()
+                    .chain(record.iter().copied())
+            })
+            .collect()
+    };
+    fs::write(dir.join("a.bin"), encode(&[b"z\n", b"m", b""])).unwrap();
+    fs::write(dir.join("b.bin"), encode(&[b"x", b"m\0"])).unwrap();
+    let inputs = [dir.join("a.bin"), dir.join("b.bin")];
+
+    let config = MergeConfig::new()
+        .format(RecordFormat::LengthPrefixed)
+        .descending();
+    assert_eq!(
+        merge_files(&inputs, dir.join("out.bin"), &config).unwrap(),
+        5
+    );
+    assert_eq!(
+        fs::read(dir.join("out.bin")).unwrap(),
+        encode(&[b"z\n", b"x", b"m\0", b"m", b""])
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_merge_rejects_unsorted_input() {
+    let dir = scratch_dir("merge-unsorted");
+    let inputs = write_shards(
+        &dir,
+        &[
+            vec!["a".to_string(), "c".to_string()],
+            vec!["d".to_string(), "b".to_string()],
+        ],
+    );
+    let err = merge_files(&inputs, dir.join("out.txt"), &MergeConfig::new()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_merge_by_key_is_stable() {
+    let dir = scratch_dir("merge-by-key");
+    let mut rng = StdRng::seed_from_u64(644);
+    // Lines "<shard> <position> <score>", each shard sorted by descending score
+    let shards: Vec<Vec<String>> = (0..4)
+        .map(|shard| {
+            let mut scores: Vec<u32> = (0..rng.random_range(0..100))
+                .map(|_| rng.random_range(0..50))
+                .collect();
+            scores.sort_by(|a, b| b.cmp(a));
+            scores
+                .iter()
+                .enumerate()
+                .map(|(i, score)| format!("{} {} {}", shard, i, score))
+                .collect()
+        })
+        .collect();
+    let inputs = write_shards(&dir, &shards);
+
+    let by_score = FieldKey::parse("3,3n").unwrap();
+    let key_fn = |record: &[u8]| by_score.extract_key(&String::from_utf8_lossy(record));
+    let config = MergeConfig::new().descending().buffer_size(5);
+    let written = merge_files_by_key(&inputs, dir.join("out.txt"), &config, key_fn).unwrap();
+
+    // Equal scores keep input order: by shard, then by position
+    let mut expected = shards.concat();
+    expected.sort_by_key(|line| std::cmp::Reverse(key_fn(line.as_bytes())));
+    assert_eq!(written, expected.len() as u64);
+    let merged = fs::read_to_string(dir.join("out.txt")).unwrap();
+    assert_eq!(merged.lines().collect::<Vec<_>>(), expected);
+
+    // Ascending by score, the same inputs are out of order
+    let err = merge_files_by_key(&inputs, dir.join("out.txt"), &MergeConfig::new(), key_fn);
+    let descends = |shard: &Vec<String>| {
+        shard
+            .windows(2)
+            .any(|w| key_fn(w[0].as_bytes()) != key_fn(w[1].as_bytes()))
+    };
+    if shards.iter().any(descends) {
+        assert_eq!(err.unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_merge_missing_input() {
+    let dir = scratch_dir("merge-missing");
+    let err = merge_files(
+        &[dir.join("absent.txt")],
+        dir.join("out.txt"),
+        &MergeConfig::new(),
+    )
+    .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,71 @@
+// Integration tests for sorting ndarray lanes (requires the `ndarray` feature)
+#![cfg(feature = "ndarray")]
+
+use ndarray::{Array2, Array3, Axis};
+use rand::prelude::*;
+use test_log::test;
+
+use tilesort::{sort_axis_by_key, sort_rows_by_column, sorted_axis_indices};
+
+/// Rows of `(timestamp, row id, payload)`, nearly in timestamp order.
+fn nearly_time_ordered(seed: u64, rows: usize) -> Array2<i64> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    Array2::from_shape_fn((rows, 3), |(row, column)| match column {
+        0 => row as i64 * 10 + rng.random_range(0..25),
+        1 => row as i64,
+        _ => rng.random_range(-100..100),
+    })
+}
+
+#[test]
+fn test_rows_match_std_sort() {
+    for rows in [0, 1, 2, 50, 2_000] {
+        let mut matrix = nearly_time_ordered(626 + rows as u64, rows);
+        let mut expected: Vec<Vec<i64>> = matrix.rows().into_iter().map(|r| r.to_vec()).collect();
+        expected.sort_by_key(|row| row[0]);
+
+        let order = sorted_axis_indices(&matrix, Axis(0), |row| row[0]);
+        let selected: Vec<Vec<i64>> = order.iter().map(|&row| matrix.row(row).to_vec()).collect();
+        assert_eq!(selected, expected);
+
+        sort_rows_by_column(&mut matrix, 0, |&time| time);
+        let sorted: Vec<Vec<i64>> = matrix.rows().into_iter().map(|r| r.to_vec()).collect();
+        assert_eq!(sorted, expected, "{}", rows);
+    }
+}
+
+#[test]
+fn test_sort_is_stable() {
+    // Few distinct timestamps, so many ties
+    let mut matrix = nearly_time_ordered(6260, 500);
+    matrix.column_mut(0).mapv_inplace(|time| time / 1_000);
+    sort_rows_by_column(&mut matrix, 0, |&time| time);
+    assert!(matrix
+        .rows()
+        .into_iter()
+        .zip(matrix.rows().into_iter().skip(1))
+        .all(|(a, b)| (a[0], a[1]) < (b[0], b[1])));
+}
+
+#[test]
+fn test_sort_along_other_axes() {
+    let mut cube =
+        Array3::from_shape_fn((2, 4, 3), |(i, j, k)| (i * 100 + (3 - j) * 10 + k) as u32);
+    sort_axis_by_key(&mut cube, Axis(1), |plane| plane[[0, 0]]);
+    for i in 0..2 {
+        for j in 0..4 {
+            for k in 0..3 {
+                assert_eq!(cube[[i, j, k]], (i * 100 + j * 10 + k) as u32);
+            }
+        }
+    }
+
+    // Sorting a non-contiguous view reorders the underlying matrix
+    let mut matrix =
+        Array2::from_shape_vec((3, 4), vec![4, 3, 2, 1, 8, 7, 6, 5, 0, 0, 0, 0]).unwrap();
+    let mut view = matrix.view_mut();
+    view.invert_axis(Axis(0));
+    sort_axis_by_key(&mut view, Axis(1), |column| column[2]);
+    assert_eq!(matrix.row(0).to_vec(), vec![1, 2, 3, 4]);
+    assert_eq!(matrix.row(1).to_vec(), vec![5, 6, 7, 8]);
+}
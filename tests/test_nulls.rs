@@ -0,0 +1,89 @@
+// Integration tests for sorting nullable columns to indices
+
+use std::cmp::Reverse;
+
+use rand::prelude::*;
+use test_log::test;
+
+use tilesort::{sort_to_indices, sort_to_indices_with_config, NullOrder, SortConfig};
+
+/// Pack `valid` into an Arrow-style bitmap, least significant bit first.
+fn bitmap(valid: &[bool]) -> Vec<u8> {
+    let mut bits = vec![0u8; (valid.len() + 7) / 8];
+    for (i, _) in valid.iter().enumerate().filter(|(_, &valid)| valid) {
+        bits[i / 8] |= 1 << (i % 8);
+    }
+    bits
+}
+
+fn expected(values: &[u32], valid: &[bool], nulls: NullOrder, descending: bool) -> Vec<usize> {
+    let (mut present, absent): (Vec<usize>, Vec<usize>) =
+        (0..values.len()).partition(|&i| valid[i]);
+    if descending {
+        present.sort_by_key(|&i| Reverse(values[i]));
+    } else {
+        present.sort_by_key(|&i| values[i]);
+    }
+    match nulls {
+        NullOrder::First => absent.into_iter().chain(present).collect(),
+        NullOrder::Last => present.into_iter().chain(absent).collect(),
+    }
+}
+
+#[test]
+fn test_matches_reference_kernel() {
+    let mut rng = StdRng::seed_from_u64(627);
+    for len in [0, 1, 7, 8, 9, 100, 4_000] {
+        // Nearly sorted values, with nulls in bursts and scattered
+        let values: Vec<u32> = (0..len as u32)
+            .map(|i| i + rng.random_range(0..30))
+            .collect();
+        let valid: Vec<bool> = (0..len)
+            .map(|i| i % 500 >= 40 && rng.random_range(0..20) != 0)
+            .collect();
+        let validity = bitmap(&valid);
+
+        for nulls in [NullOrder::First, NullOrder::Last] {
+            assert_eq!(
+                sort_to_indices(&values, Some(&validity), nulls),
+                expected(&values, &valid, nulls, false),
+                "{} {:?}",
+                len,
+                nulls
+            );
+            let config = SortConfig::new().descending();
+            assert_eq!(
+                sort_to_indices_with_config(&values, Some(&validity), nulls, &config),
+                expected(&values, &valid, nulls, true),
+                "{} {:?} descending",
+                len,
+                nulls
+            );
+        }
+    }
+}
+
+#[test]
+fn test_without_validity() {
+    let values = vec![3, 1, 2, 1];
+    assert_eq!(
+        sort_to_indices(&values, None, NullOrder::First),
+        vec![1, 3, 2, 0]
+    );
+}
+
+#[test]
+fn test_null_slots_are_ignored() {
+    // Whatever sits under a null never affects the order
+    let validity = [0b0101];
+    let a = sort_to_indices(&[5, 99, 4, 0], Some(&validity), NullOrder::Last);
+    let b = sort_to_indices(&[5, 0, 4, 99], Some(&validity), NullOrder::Last);
+    assert_eq!(a, vec![2, 0, 1, 3]);
+    assert_eq!(a, b);
+}
+
+#[test]
+#[should_panic(expected = "shorter than the values")]
+fn test_short_bitmap() {
+    sort_to_indices(&[1; 9], Some(&[0xff]), NullOrder::Last);
+}
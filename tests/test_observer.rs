@@ -0,0 +1,141 @@
+// Integration tests for observing the events of a sort
+
+use std::ops::Range;
+
+use rand::prelude::*;
+use test_log::test;
+
+use tilesort::{tilesort_by_key_with_observer, tilesort_with_observer, Observer, SortConfig};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Event {
+    ScanStart(usize),
+    Tile(Range<usize>),
+    Split(usize),
+    ScanEnd,
+    RestructureStart,
+    RestructureEnd,
+}
+
+#[derive(Default)]
+struct Recorder(Vec<Event>);
+
+impl Observer for Recorder {
+    fn on_scan_start(&mut self, elements: usize) {
+        self.0.push(Event::ScanStart(elements));
+    }
+
+    fn on_tile_detected(&mut self, tile: Range<usize>) {
+        self.0.push(Event::Tile(tile));
+    }
+
+    fn on_split(&mut self, at: usize) {
+        self.0.push(Event::Split(at));
+    }
+
+    fn on_scan_end(&mut self) {
+        self.0.push(Event::ScanEnd);
+    }
+
+    fn on_restructure_start(&mut self) {
+        self.0.push(Event::RestructureStart);
+    }
+
+    fn on_restructure_end(&mut self) {
+        self.0.push(Event::RestructureEnd);
+    }
+}
+
+#[test]
+fn test_events_in_order() {
+    let mut rng = StdRng::seed_from_u64(634);
+    let mut data: Vec<u32> = (0..2000).collect();
+    // Shuffle whole blocks, so the tiles overlap and split
+    let mut blocks: Vec<Vec<u32>> = data.chunks(100).map(|block| block.to_vec()).collect();
+    blocks.shuffle(&mut rng);
+    data = blocks.concat();
+    for _ in 0..20 {
+        let (a, b) = (rng.random_range(0..2000), rng.random_range(0..2000));
+        data.swap(a, b);
+    }
+
+    let mut recorder = Recorder::default();
+    tilesort_with_observer(&mut data, &SortConfig::new(), &mut recorder);
+    assert!(data.windows(2).all(|w| w[0] <= w[1]));
+
+    let events = recorder.0;
+    assert_eq!(events.first(), Some(&Event::ScanStart(2000)));
+    let scan_end = events.iter().position(|e| *e == Event::ScanEnd).unwrap();
+    assert_eq!(
+        &events[scan_end..],
+        &[
+            Event::ScanEnd,
+            Event::RestructureStart,
+            Event::RestructureEnd
+        ]
+    );
+
+    // The detected tiles cover the input in order, and each split falls
+    // within a tile already detected
+    let mut covered = 0;
+    let mut splits = 0;
+    for event in &events[1..scan_end] {
+        match event {
+            Event::Tile(tile) => {
+                assert_eq!(tile.start, covered);
+                assert!(tile.end > tile.start);
+                covered = tile.end;
+            }
+            Event::Split(at) => {
+                assert!(*at > 0 && *at < covered);
+                splits += 1;
+            }
+            other => panic!("unexpected event {:?} during the scan", other),
+        }
+    }
+    assert_eq!(covered, 2000);
+    assert!(splits > 0);
+}
+
+#[test]
+fn test_split_positions() {
+    // The last tile falls inside the first, splitting it before 100
+    let mut data: Vec<u32> = (0..50).chain(100..150).chain(50..100).collect();
+    let mut recorder = Recorder::default();
+    tilesort_with_observer(&mut data, &SortConfig::new(), &mut recorder);
+    assert_eq!(
+        recorder.0[1..4],
+        [Event::Tile(0..100), Event::Tile(100..150), Event::Split(50)]
+    );
+}
+
+#[test]
+fn test_by_key_and_shortcut() {
+    let mut words: Vec<String> = (0..300).rev().map(|i| format!("{:03}", i)).collect();
+    let mut recorder = Recorder::default();
+    tilesort_by_key_with_observer(&mut words, |w| w.clone(), &SortConfig::new(), &mut recorder);
+    assert!(words.windows(2).all(|w| w[0] <= w[1]));
+    assert_eq!(recorder.0.first(), Some(&Event::ScanStart(300)));
+    assert_eq!(recorder.0.last(), Some(&Event::RestructureEnd));
+
+    // A short slice is sorted by insertion, which reports the phases
+    // without tiles
+    let mut data: Vec<u32> = (0..10).rev().collect();
+    let mut recorder = Recorder::default();
+    tilesort_with_observer(&mut data, &SortConfig::new(), &mut recorder);
+    assert_eq!(data, (0..10).collect::<Vec<_>>());
+    assert_eq!(
+        recorder.0,
+        vec![
+            Event::ScanStart(10),
+            Event::ScanEnd,
+            Event::RestructureStart,
+            Event::RestructureEnd
+        ]
+    );
+
+    // Slices too short to sort report nothing
+    let mut recorder = Recorder::default();
+    tilesort_with_observer(&mut [1u8], &SortConfig::new(), &mut recorder);
+    assert!(recorder.0.is_empty());
+}
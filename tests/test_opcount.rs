@@ -0,0 +1,244 @@
+// Integration tests for operation counting (requires the `opcount` feature)
+#![cfg(feature = "opcount")]
+
+use test_log::test;
+
+use tilesort::{
+    find, scan, stable_partition, tilesort_by_key, tilesort_dedup, tilesort_refs_by_key,
+    tilesort_with_config, tilesort_with_permutation, tilesorted, tilesorted_by_key, tilesorted_cow,
+    tilesorted_with_config, IdentityKey, Order, SortConfig, SortStats,
+};
+
+#[test]
+fn test_key_extracted_once_per_element() {
+    let mut data: Vec<u32> = (500..1_000).chain(0..500).collect();
+    let ((), stats) = SortStats::measure(|| tilesort_by_key(&mut data, |&x| x / 10));
+    assert_eq!(stats.key_extractions, 1_000);
+    assert_eq!(stats.key_clones, 0);
+    assert_eq!(stats.element_clones, 1_000);
+    assert_eq!(stats.element_moves, 1_000);
+}
+
+#[test]
+fn test_identity_keys_compared_in_place() {
+    let mut slice = vec![3, 4, 1, 2];
+    let ((), stats) = SortStats::measure(|| tilesort::tilesort(&mut slice));
+    assert_eq!(stats.key_extractions, 0);
+    assert_eq!(stats.key_clones, 0);
+
+    // Lazy iteration keeps its keys, so the identity extractor clones them
+    let data = [3, 4, 1, 2];
+    let (smallest, stats) = SortStats::measure(|| {
+        tilesort::k_smallest(&data, 2, IdentityKey)
+            .copied()
+            .collect::<Vec<i32>>()
+    });
+    assert_eq!(smallest, vec![1, 2]);
+    assert_eq!(stats.key_clones, stats.key_extractions);
+    assert!(stats.key_clones >= 4);
+}
+
+#[test]
+fn test_interleaved_runs_split_tiles() {
+    // Two interleaved runs: every element of the second run lands between
+    // two elements of the first
+    let mut data: Vec<u32> = (0..100)
+        .map(|i| i * 2)
+        .chain((0..100).map(|i| i * 2 + 1))
+        .collect();
+    let ((), stats) = SortStats::measure(|| tilesort::tilesort(&mut data));
+    assert!(stats.tile_splits >= 99);
+    assert!(stats.comparisons >= 199);
+    assert_eq!(data, (0..200).collect::<Vec<u32>>());
+}
+
+#[test]
+fn test_strategies_move_differently() {
+    let data: Vec<u32> = (0..3)
+        .flat_map(|t| (0..100).map(move |i| (2 - t) * 100 + i))
+        .collect();
+    let count_moves = |config: SortConfig| {
+        let mut sorted = data.clone();
+        let ((), stats) = SortStats::measure(|| tilesort_with_config(&mut sorted, &config));
+        (stats.element_clones, stats.element_moves)
+    };
+
+    assert_eq!(count_moves(SortConfig::new()), (300, 300));
+    let (clones, moves) = count_moves(SortConfig::new().indirect(true));
+    assert_eq!(clones, 0);
+    assert!(moves > 0 && moves <= 600);
+    let (clones, moves) = count_moves(SortConfig::new().max_aux_memory(0));
+    assert_eq!(clones, 0);
+    assert_eq!(moves, 300 + 200);
+}
+
+#[test]
+fn test_reports_detected_pattern() {
+    use tilesort::InputPattern;
+
+    let detect = |mut data: Vec<u32>, config: SortConfig| {
+        let ((), stats) = SortStats::measure(|| tilesort_with_config(&mut data, &config));
+        assert!(data.windows(2).all(|pair| pair[0] <= pair[1]));
+        stats
+    };
+
+    let sorted = detect((0..5_000).collect(), SortConfig::new());
+    assert_eq!(sorted.pattern, Some(InputPattern::Sorted));
+    assert_eq!(sorted.element_moves, 0);
+
+    let reversed = detect((0..5_000).rev().collect(), SortConfig::new());
+    assert_eq!(reversed.pattern, Some(InputPattern::Reversed));
+    assert_eq!(reversed.element_moves, 5_000);
+
+    let random: Vec<u32> = (0..5_000u32)
+        .map(|i| i.wrapping_mul(2_654_435_761))
+        .collect();
+    let stats = detect(random.clone(), SortConfig::new());
+    assert_eq!(stats.pattern, Some(InputPattern::Random));
+    assert_eq!(stats.tile_splits, 0);
+
+    let stats = detect(random, SortConfig::new().detect_patterns(false));
+    assert_eq!(stats.pattern, None);
+    assert!(stats.tile_splits > 0);
+
+    // Too short to sample
+    assert_eq!(detect(vec![3, 1, 2], SortConfig::new()).pattern, None);
+}
+
+#[test]
+fn test_unstable_placement_stops_at_equal_tiles() {
+    // Two distinct keys in short runs: the index fills with long stretches
+    // of tiles sharing a key, which the unstable search need not cross
+    let mut state = 7u32;
+    let keys: Vec<u16> = (0..50_000)
+        .map(|_| {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            if state >> 16 & 3 == 0 {
+                404
+            } else {
+                200
+            }
+        })
+        .collect();
+    let config = SortConfig::new().detect_patterns(false);
+
+    let mut stable = keys.clone();
+    let ((), stable_stats) = SortStats::measure(|| tilesort_with_config(&mut stable, &config));
+    let mut unstable = keys;
+    let unstable_config = config.unstable();
+    let ((), unstable_stats) =
+        SortStats::measure(|| tilesort_with_config(&mut unstable, &unstable_config));
+
+    assert_eq!(stable, unstable);
+    assert!(
+        unstable_stats.comparisons * 2 < stable_stats.comparisons,
+        "{} vs {}",
+        unstable_stats.comparisons,
+        stable_stats.comparisons
+    );
+}
+
+#[test]
+fn test_find_is_logarithmic() {
+    // 1000 interleaved runs of 100 elements
+    let data: Vec<u32> = (0..100_000u32)
+        .map(|i| (i % 100) * 1_000 + i / 100)
+        .collect();
+    let plan = scan(&data, |&x| x, Order::Ascending);
+    let (found, stats) = SortStats::measure(|| find(&data, &plan, &42_424));
+    assert_eq!(found, Some(424 * 100 + 42));
+    assert!(stats.comparisons <= 40, "{}", stats.comparisons);
+    assert_eq!(stats.element_moves, 0);
+}
+
+#[test]
+fn test_sorted_copy_clones_each_element_once() {
+    let interleaved: Vec<u32> = (0..4_000u32).map(|i| (i % 40) * 100 + i / 40).collect();
+    let descending: Vec<u32> = (0..4_000).rev().collect();
+    let short = vec![3u32, 1, 2];
+    for data in [interleaved, descending, short] {
+        let mut expected = data.clone();
+        expected.sort();
+
+        let (sorted, stats) = SortStats::measure(|| tilesorted(&data));
+        assert_eq!(sorted, expected);
+        assert_eq!(stats.element_clones, data.len() as u64);
+
+        let (sorted, stats) = SortStats::measure(|| tilesorted_by_key(&data, |&x| x));
+        assert_eq!(sorted, expected);
+        assert_eq!(stats.element_clones, data.len() as u64);
+    }
+
+    let data: Vec<u32> = (0..1_000).map(|i| i * 7 % 1_000).collect();
+    let config = SortConfig::new().detect_patterns(false).descending();
+    let (sorted, stats) = SortStats::measure(|| tilesorted_with_config(&data, &config));
+    assert!(sorted.windows(2).all(|pair| pair[0] >= pair[1]));
+    assert_eq!(stats.element_clones, 1_000);
+    assert_eq!(stats.element_moves, 0);
+}
+
+#[test]
+fn test_refs_neither_clone_nor_move() {
+    let data: Vec<u32> = (0..4_000u32).map(|i| (i % 40) * 100 + i / 40).collect();
+    let (sorted, stats) = SortStats::measure(|| tilesort_refs_by_key(&data, |&x| x));
+    assert!(sorted.windows(2).all(|pair| pair[0] <= pair[1]));
+    assert_eq!(stats.element_clones, 0);
+    assert_eq!(stats.element_moves, 0);
+    assert_eq!(stats.key_extractions, data.len() as u64);
+}
+
+#[test]
+fn test_cow_clones_only_unsorted_input() {
+    let sorted: Vec<u32> = (0..5_000).collect();
+    let (_, stats) = SortStats::measure(|| tilesorted_cow(&sorted).len());
+    assert_eq!(stats.element_clones, 0);
+
+    let unsorted: Vec<u32> = (0..5_000).map(|i| (i % 50) * 100 + i / 50).collect();
+    let (_, stats) = SortStats::measure(|| tilesorted_cow(&unsorted).len());
+    assert_eq!(stats.element_clones, 5_000);
+}
+
+#[test]
+fn test_permutation_sort_never_clones() {
+    let mut data: Vec<u32> = (0..4_000u32).map(|i| (i % 40) * 100 + i / 40).collect();
+    let (permutation, stats) = SortStats::measure(|| tilesort_with_permutation(&mut data));
+    assert!(data.windows(2).all(|pair| pair[0] <= pair[1]));
+    assert_eq!(permutation.len(), data.len());
+    assert_eq!(stats.element_clones, 0);
+}
+
+#[test]
+fn test_partitioned_input_is_not_moved() {
+    let mut data: Vec<u32> = (0..1_000).map(|i| u32::from(i >= 600)).collect();
+    let (count, stats) = SortStats::measure(|| stable_partition(&mut data, |&x| x == 0));
+    assert_eq!(count, 600);
+    assert_eq!(stats.element_moves, 0);
+    assert_eq!(stats.element_clones, 0);
+}
+
+#[test]
+fn test_dedup_compares_only_at_tile_boundaries() {
+    // Sorted and distinct: the scan's comparisons find one strictly
+    // increasing tile, so the deduplication compares nothing more
+    let mut data: Vec<u32> = (0..1_000).collect();
+    let (len, stats) = SortStats::measure(|| tilesort_dedup(&mut data));
+    assert_eq!(len, 1_000);
+    assert_eq!(stats.comparisons, 999);
+    assert_eq!(stats.element_clones, 1_000);
+
+    // Ten tiles with duplicates: one comparison where each later tile begins
+    let mut data: Vec<u32> = (0..10)
+        .rev()
+        .flat_map(|t| (0..100).map(move |i| t * 50 + i / 2))
+        .collect();
+    let mut scanned = data.clone();
+    let ((), sort_stats) = SortStats::measure(|| tilesort::tilesort(&mut scanned));
+    let (len, stats) = SortStats::measure(|| tilesort_dedup(&mut data));
+    assert_eq!(&data[..len], (0..500).collect::<Vec<u32>>().as_slice());
+    assert!(
+        stats.comparisons <= sort_stats.comparisons + 9,
+        "{} > {} + 9",
+        stats.comparisons,
+        sort_stats.comparisons
+    );
+}
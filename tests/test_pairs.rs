@@ -0,0 +1,39 @@
+// Integration tests for co-sorting a keys slice and a values slice
+
+use test_log::test;
+
+use tilesort::tilesort_pairs;
+
+#[test]
+fn test_pairs_stable() {
+    let mut keys = vec![2, 1, 2, 1];
+    let mut values = vec!['a', 'b', 'c', 'd'];
+    tilesort_pairs(&mut keys, &mut values);
+    assert_eq!(keys, vec![1, 1, 2, 2]);
+    assert_eq!(values, vec!['b', 'd', 'a', 'c']);
+}
+
+#[test]
+fn test_pairs_values_need_not_clone() {
+    struct Payload(u8);
+
+    let mut keys = vec![9, 7, 8];
+    let mut values = vec![Payload(9), Payload(7), Payload(8)];
+    tilesort_pairs(&mut keys, &mut values);
+    let order: Vec<u8> = values.iter().map(|p| p.0).collect();
+    assert_eq!(order, vec![7, 8, 9]);
+}
+
+#[test]
+fn test_pairs_empty() {
+    let mut keys: Vec<u8> = vec![];
+    let mut values: Vec<u8> = vec![];
+    tilesort_pairs(&mut keys, &mut values);
+    assert!(keys.is_empty());
+}
+
+#[test]
+#[should_panic(expected = "same length")]
+fn test_pairs_length_mismatch() {
+    tilesort_pairs(&mut [1, 2], &mut [1]);
+}
@@ -0,0 +1,172 @@
+// Panic-safety tests: a panicking key extractor, comparator or clone must leave
+// the slice in its original order with no elements leaked or dropped twice.
+
+use std::cell::Cell;
+use std::cmp::Ordering;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use test_log::test;
+
+use tilesort::{tilesort, tilesort_by_key};
+
+thread_local! {
+    static LIVE: Cell<isize> = const { Cell::new(0) };
+    static COMPARISONS_LEFT: Cell<usize> = const { Cell::new(usize::MAX) };
+    static CLONES_LEFT: Cell<usize> = const { Cell::new(usize::MAX) };
+}
+
+/// Element that tracks live instances and can panic in `cmp` or `clone`.
+#[derive(Debug)]
+struct Tracked(i32);
+
+impl Tracked {
+    fn new(value: i32) -> Self {
+        LIVE.with(|live| live.set(live.get() + 1));
+        Tracked(value)
+    }
+}
+
+impl Clone for Tracked {
+    fn clone(&self) -> Self {
+        CLONES_LEFT.with(|left| {
+            if left.get() == 0 {
+                panic!("clone budget exhausted");
+            }
+            left.set(left.get() - 1);
+        });
+        Tracked::new(self.0)
+    }
+}
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        LIVE.with(|live| live.set(live.get() - 1));
+    }
+}
+
+impl PartialEq for Tracked {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Tracked {}
+
+impl PartialOrd for Tracked {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Tracked {
+    fn cmp(&self, other: &Self) -> Ordering {
+        COMPARISONS_LEFT.with(|left| {
+            if left.get() == 0 {
+                panic!("comparison budget exhausted");
+            }
+            left.set(left.get() - 1);
+        });
+        self.0.cmp(&other.0)
+    }
+}
+
+fn reset(comparisons: usize, clones: usize) {
+    COMPARISONS_LEFT.with(|left| left.set(comparisons));
+    CLONES_LEFT.with(|left| left.set(clones));
+}
+
+fn tiled_input() -> Vec<Tracked> {
+    [5, 6, 7, 1, 2, 3, 9, 10, 4, 8]
+        .into_iter()
+        .map(Tracked::new)
+        .collect()
+}
+
+fn values(data: &[Tracked]) -> Vec<i32> {
+    data.iter().map(|t| t.0).collect()
+}
+
+fn live() -> isize {
+    LIVE.with(|live| live.get())
+}
+
+#[test]
+fn test_panicking_comparator_leaves_input_unchanged() {
+    for budget in 0..30 {
+        let mut data = tiled_input();
+        let original = values(&data);
+        let live_before = live();
+
+        reset(budget, usize::MAX);
+        let result = catch_unwind(AssertUnwindSafe(|| tilesort(&mut data)));
+        reset(usize::MAX, usize::MAX);
+
+        if result.is_err() {
+            assert_eq!(values(&data), original, "budget {}", budget);
+        } else {
+            assert_eq!(values(&data), vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
+        }
+        assert_eq!(
+            live(),
+            live_before,
+            "leaked or double-dropped at budget {}",
+            budget
+        );
+    }
+}
+
+#[test]
+fn test_panicking_clone_leaves_input_unchanged() {
+    for budget in 0..12 {
+        let mut data = tiled_input();
+        let original = values(&data);
+        let live_before = live();
+
+        reset(usize::MAX, budget);
+        let result = catch_unwind(AssertUnwindSafe(|| tilesort(&mut data)));
+        reset(usize::MAX, usize::MAX);
+
+        if result.is_err() {
+            assert_eq!(values(&data), original, "budget {}", budget);
+        } else {
+            assert_eq!(values(&data), vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
+        }
+        assert_eq!(
+            live(),
+            live_before,
+            "leaked or double-dropped at budget {}",
+            budget
+        );
+    }
+}
+
+#[test]
+fn test_panicking_key_extractor_leaves_input_unchanged() {
+    let mut data = tiled_input();
+    let original = values(&data);
+    let live_before = live();
+
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        tilesort_by_key(&mut data, |t: &Tracked| {
+            if t.0 == 9 {
+                panic!("bad key");
+            }
+            t.0
+        })
+    }));
+
+    assert!(result.is_err());
+    assert_eq!(values(&data), original);
+    assert_eq!(live(), live_before);
+}
+
+#[test]
+fn test_all_elements_dropped_after_sort() {
+    let live_before = live();
+    {
+        let mut data = tiled_input();
+        tilesort(&mut data);
+        assert_eq!(values(&data), vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
+    }
+    assert_eq!(live(), live_before);
+}
@@ -0,0 +1,132 @@
+// Integration tests for the parallel sort (requires the `rayon` feature)
+#![cfg(feature = "rayon")]
+
+use std::sync::Arc;
+
+use rand::prelude::*;
+use rayon::ThreadPoolBuilder;
+use test_log::test;
+
+use tilesort::{
+    tilesort_by_key_with_config, tilesort_with_config, KeyCost, ParallelTileSortExt, SortConfig,
+    Threads,
+};
+
+#[test]
+fn test_par_tilesort_matches_sequential() {
+    let mut rng = StdRng::seed_from_u64(3);
+    let mut data: Vec<i64> = Vec::new();
+    for _ in 0..200 {
+        let start: i64 = rng.random_range(-100_000..100_000);
+        let len = rng.random_range(1..2_000);
+        data.extend(start..start + len);
+    }
+
+    for config in [SortConfig::new(), SortConfig::new().descending()] {
+        let mut expected = data.clone();
+        tilesort_with_config(&mut expected, &config);
+        let mut actual = data.clone();
+        actual.par_tilesort_with_config(&config);
+        assert_eq!(actual, expected);
+    }
+}
+
+#[test]
+fn test_par_tilesort_by_key_is_stable() {
+    let mut rng = StdRng::seed_from_u64(5);
+    let data: Vec<(u16, usize)> = (0..50_000).map(|i| (rng.random_range(0..64), i)).collect();
+
+    let mut sorted = data.clone();
+    sorted.par_tilesort_by_key(|&(key, _)| key);
+
+    let mut expected = data;
+    expected.sort_by_key(|&(key, _)| key);
+    assert_eq!(sorted, expected);
+}
+
+#[test]
+fn test_par_tilesort_on_subslice() {
+    let mut data = vec![9, 8, 3, 4, 1, 2, 0];
+    data[2..6].par_tilesort();
+    assert_eq!(data, vec![9, 8, 1, 2, 3, 4, 0]);
+}
+
+#[test]
+fn test_par_tilesort_max_threads() {
+    let mut data: Vec<u32> = (1_000..2_000).chain(0..1_000).collect();
+    let config = SortConfig::new().max_threads(2);
+    data.par_tilesort_by_key_with_config(
+        |&x| {
+            assert_eq!(rayon::current_num_threads(), 2);
+            x
+        },
+        &config,
+    );
+    assert_eq!(data, (0..2_000).collect::<Vec<u32>>());
+}
+
+#[test]
+fn test_par_tilesort_on_thread_pool() {
+    let pool = Arc::new(ThreadPoolBuilder::new().num_threads(3).build().unwrap());
+    let config = SortConfig::new().thread_pool(Arc::clone(&pool));
+    assert_eq!(config.threads, Threads::Pool(pool));
+
+    let mut data = vec![(2, 'a'), (1, 'b'), (2, 'c'), (1, 'd')];
+    data.par_tilesort_by_key_with_config(
+        |&(key, _)| {
+            assert_eq!(rayon::current_num_threads(), 3);
+            key
+        },
+        &config,
+    );
+    assert_eq!(data, vec![(1, 'b'), (1, 'd'), (2, 'a'), (2, 'c')]);
+}
+
+#[test]
+fn test_par_tilesort_unstable_is_deterministic() {
+    // Unstable sorts may reorder equal keys, but the same way on every run
+    let mut rng = StdRng::seed_from_u64(13);
+    let data: Vec<(u8, u32)> = (0..20_000).map(|i| (rng.random_range(0..8), i)).collect();
+    let config = SortConfig::new().unstable();
+
+    let mut expected = data.clone();
+    tilesort::tilesort_by_key_with_config(&mut expected, |&(key, _)| key, &config);
+
+    for threads in [1, 2, 3, 8] {
+        for _ in 0..3 {
+            let mut actual = data.clone();
+            actual.par_tilesort_by_key_with_config(
+                |&(key, _)| key,
+                &config.clone().max_threads(threads),
+            );
+            assert_eq!(actual, expected, "{} threads", threads);
+        }
+    }
+}
+
+#[test]
+fn test_par_extract_matches_sequential() {
+    let mut rng = StdRng::seed_from_u64(630);
+    // Strings that must be parsed for their key, in overlapping runs
+    let data: Vec<String> = (0..20_000)
+        .map(|i: u32| format!("{:08}-{}", i / 3 + rng.random_range(0..50), i))
+        .collect();
+    let key = |line: &String| line[..8].parse::<u32>().unwrap();
+
+    for config in [
+        SortConfig::new(),
+        SortConfig::new().descending(),
+        SortConfig::new().max_aux_memory(0),
+        SortConfig::new().key_cost(KeyCost::Auto).max_threads(2),
+    ] {
+        let mut expected = data.clone();
+        tilesort_by_key_with_config(&mut expected, key, &config);
+        let mut actual = data.clone();
+        actual.par_extract_tilesort_by_key_with_config(key, &config);
+        assert_eq!(actual, expected);
+    }
+
+    let mut actual = data.clone();
+    actual.par_extract_tilesort_by_key(key);
+    assert!(actual.windows(2).all(|pair| key(&pair[0]) <= key(&pair[1])));
+}
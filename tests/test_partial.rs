@@ -0,0 +1,92 @@
+// Integration tests for partial sorting and selection
+
+use rand::prelude::*;
+use test_log::test;
+
+use tilesort::{
+    tilesort_partial, tilesort_partial_by_key, tilesort_select_nth, tilesort_select_nth_by_key,
+};
+
+/// Element that cannot be cloned, to show partial sorting only swaps.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct NoClone(u32);
+
+#[test]
+fn test_partial_zero_and_empty() {
+    let mut data = vec![3, 1, 2];
+    tilesort_partial(&mut data, 0);
+    assert_eq!(data, vec![3, 1, 2]);
+
+    let mut empty: Vec<i32> = vec![];
+    tilesort_partial(&mut empty, 5);
+    assert!(empty.is_empty());
+}
+
+#[test]
+fn test_partial_k_larger_than_len_sorts_everything() {
+    let mut data = vec![7, 8, 9, 1, 2, 3, 4];
+    tilesort_partial(&mut data, 100);
+    assert_eq!(data, vec![1, 2, 3, 4, 7, 8, 9]);
+}
+
+#[test]
+fn test_partial_without_clone() {
+    let mut data: Vec<NoClone> = [10, 11, 12, 1, 2, 3].into_iter().map(NoClone).collect();
+    tilesort_partial(&mut data, 2);
+    assert_eq!(&data[..2], &[NoClone(1), NoClone(2)]);
+}
+
+#[test]
+fn test_partial_by_key_keeps_every_element() {
+    let mut data: Vec<i32> = vec![-9, -8, 1, 2, -3, 4];
+    tilesort_partial_by_key(&mut data, 3, |x| x.abs());
+    assert_eq!(&data[..3], &[1, 2, -3]);
+
+    data.sort();
+    assert_eq!(data, vec![-9, -8, -3, 1, 2, 4]);
+}
+
+#[test]
+fn test_partial_matches_full_sort() {
+    let mut rng = StdRng::seed_from_u64(0x70b);
+    for _ in 0..2_000 {
+        let len = rng.random_range(0..60);
+        let k = rng.random_range(0..=len);
+        let data: Vec<u16> = (0..len).map(|_| rng.random_range(0..40)).collect();
+
+        let mut expected = data.clone();
+        expected.sort();
+
+        let mut actual = data.clone();
+        tilesort_partial(&mut actual, k);
+        assert_eq!(&actual[..k], &expected[..k], "input: {:?}, k: {}", data, k);
+
+        // The slice is still a permutation of the input
+        actual.sort();
+        assert_eq!(actual, expected);
+    }
+}
+
+#[test]
+fn test_select_nth() {
+    let data = vec![30, 31, 10, 11, 20, 21];
+    let sorted = [10, 11, 20, 21, 30, 31];
+    for (n, expected) in sorted.iter().enumerate() {
+        assert_eq!(tilesort_select_nth(&data, n), Some(expected));
+    }
+    assert_eq!(tilesort_select_nth(&data, 6), None);
+    assert_eq!(tilesort_select_nth::<i32>(&[], 0), None);
+}
+
+#[test]
+fn test_select_nth_by_key() {
+    let data = vec![(3, 'c'), (1, 'a'), (2, 'b')];
+    assert_eq!(
+        tilesort_select_nth_by_key(&data, 2, |&(k, _)| k),
+        Some(&(3, 'c'))
+    );
+    assert_eq!(
+        tilesort_select_nth_by_key(&data, 0, |&(k, _)| k),
+        Some(&(1, 'a'))
+    );
+}
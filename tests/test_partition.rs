@@ -0,0 +1,73 @@
+// Integration tests for stable partitioning
+
+use rand::prelude::*;
+use test_log::test;
+
+use tilesort::stable_partition;
+
+/// Neither `Clone` nor `Copy`, so the partition can only swap it.
+#[derive(Debug, PartialEq, Eq)]
+struct Item {
+    key: u32,
+    id: usize,
+}
+
+/// What a stable partition must produce: the matching elements, then the
+/// rest, each in input order.
+fn expected(keys: &[u32], pred: impl Fn(u32) -> bool) -> Vec<u32> {
+    let (mut yes, no): (Vec<u32>, Vec<u32>) = keys.iter().partition(|&&key| pred(key));
+    yes.extend(no);
+    yes
+}
+
+#[test]
+fn test_partition_matches_filtering() {
+    let mut rng = StdRng::seed_from_u64(623);
+    let inputs: Vec<Vec<u32>> = vec![
+        vec![],
+        vec![4],
+        vec![5],
+        (0..1_000).collect(),
+        (0..1_000).map(|_| rng.random_range(0..100)).collect(),
+        // Nearly partitioned: long runs on each side
+        (0..1_000)
+            .map(|i| if i % 300 < 280 { 2 } else { 1 })
+            .collect(),
+    ];
+    for keys in inputs {
+        let pred = |key: u32| key % 2 == 0;
+        let mut data = keys.clone();
+        let count = stable_partition(&mut data, |&key| pred(key));
+        assert_eq!(count, keys.iter().filter(|&&key| pred(key)).count());
+        assert_eq!(data, expected(&keys, pred));
+    }
+}
+
+#[test]
+fn test_partition_is_stable() {
+    let mut rng = StdRng::seed_from_u64(6230);
+    let mut items: Vec<Item> = (0..2_000)
+        .map(|id| Item {
+            key: rng.random_range(0..10),
+            id,
+        })
+        .collect();
+    let count = stable_partition(&mut items, |item| item.key < 3);
+    let (small, large) = items.split_at(count);
+    assert!(small.iter().all(|item| item.key < 3));
+    assert!(large.iter().all(|item| item.key >= 3));
+    assert!(small.windows(2).all(|pair| pair[0].id < pair[1].id));
+    assert!(large.windows(2).all(|pair| pair[0].id < pair[1].id));
+}
+
+#[test]
+fn test_predicate_called_once_per_element_in_order() {
+    let mut data: Vec<u32> = (0..100).rev().collect();
+    let mut seen = Vec::new();
+    let count = stable_partition(&mut data, |&x| {
+        seen.push(x);
+        x < 50
+    });
+    assert_eq!(count, 50);
+    assert_eq!(seen, (0..100).rev().collect::<Vec<u32>>());
+}
@@ -0,0 +1,75 @@
+// Integration tests for input pattern detection
+use rand::prelude::*;
+use test_log::test;
+
+use tilesort::{tilesort_by_key_with_config, tilesort_with_config, IdentityKey, SortConfig};
+
+const LEN: usize = 5_000;
+
+/// Inputs shaped like each detected pattern, tagged with input positions.
+fn shaped_inputs() -> Vec<(&'static str, Vec<(u32, usize)>)> {
+    let mut rng = StdRng::seed_from_u64(23);
+    let keys: Vec<(&'static str, Vec<u32>)> = vec![
+        ("sorted", (0..LEN as u32).map(|i| i / 3).collect()),
+        ("reversed", (0..LEN as u32).rev().collect()),
+        (
+            "reversed with ties",
+            (0..LEN as u32).rev().map(|i| i / 2).collect(),
+        ),
+        ("sawtooth", (0..LEN as u32).map(|i| i % 50).collect()),
+        (
+            "few runs",
+            (0..LEN as u32).map(|i| (i + 1_234) % LEN as u32).collect(),
+        ),
+        (
+            "random",
+            (0..LEN).map(|_| rng.random_range(0..500)).collect(),
+        ),
+    ];
+    keys.into_iter()
+        .map(|(name, keys)| (name, keys.into_iter().zip(0..).collect()))
+        .collect()
+}
+
+#[test]
+fn test_every_pattern_sorts_stably() {
+    for (name, data) in shaped_inputs() {
+        for config in [
+            SortConfig::new(),
+            SortConfig::new().descending(),
+            SortConfig::new().max_aux_memory(0),
+            SortConfig::new().detect_patterns(false),
+            SortConfig::new().min_run_len(usize::MAX),
+            SortConfig::new().min_run_len(0).max_tiles(0),
+        ] {
+            let mut expected = data.clone();
+            if config.order.is_descending() {
+                expected.sort_by_key(|&(k, _)| std::cmp::Reverse(k));
+            } else {
+                expected.sort_by_key(|&(k, _)| k);
+            }
+
+            let mut sorted = data.clone();
+            tilesort_by_key_with_config(&mut sorted, |&(k, _)| k, &config);
+            assert_eq!(sorted, expected, "{} with {:?}", name, config);
+
+            // Identity keys are sampled in place
+            let mut keys: Vec<u32> = data.iter().map(|&(k, _)| k).collect();
+            tilesort_with_config(&mut keys, &config);
+            assert!(keys.iter().eq(expected.iter().map(|(k, _)| k)), "{}", name);
+        }
+    }
+}
+
+#[test]
+fn test_unstable_random_input() {
+    let (_, data) = shaped_inputs().pop().expect("random input");
+    let mut keys: Vec<u32> = data.iter().map(|&(k, _)| k).collect();
+    let mut expected = keys.clone();
+    expected.sort_unstable();
+    tilesort::tilesort_by_extractor(&mut keys, IdentityKey);
+    assert_eq!(keys, expected);
+    let mut keys: Vec<u32> = data.iter().map(|&(k, _)| k).collect();
+    tilesort_with_config(&mut keys, &SortConfig::new().unstable());
+    assert_eq!(keys, expected);
+}
@@ -0,0 +1,68 @@
+// Integration tests for in-place sorts that report the permutation applied
+
+use rand::prelude::*;
+use test_log::test;
+
+use tilesort::{tilesort_by_key_with_permutation, tilesort_with_permutation};
+
+/// Neither `Clone` nor `Copy`, so the sort can only swap it.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Row {
+    key: u32,
+    id: usize,
+}
+
+fn inputs() -> Vec<Vec<u32>> {
+    let mut rng = StdRng::seed_from_u64(622);
+    vec![
+        vec![],
+        vec![7],
+        vec![3, 1, 2],
+        (0..5_000).collect(),
+        (0..5_000).rev().collect(),
+        (0..5_000).map(|_| rng.random_range(0..100)).collect(),
+        (0..5_000).map(|i| (i % 50) * 100 + i / 50).collect(),
+    ]
+}
+
+#[test]
+fn test_permutation_maps_old_to_new() {
+    for keys in inputs() {
+        let original = keys.clone();
+        let mut data = keys;
+        let permutation = tilesort_with_permutation(&mut data);
+
+        let mut expected = original.clone();
+        expected.sort();
+        assert_eq!(data, expected);
+        assert_eq!(permutation.len(), data.len());
+        for (old, &new) in permutation.iter().enumerate() {
+            assert_eq!(data[new], original[old]);
+        }
+
+        let mut seen = vec![false; permutation.len()];
+        for &new in &permutation {
+            assert!(!seen[new], "position {} used twice", new);
+            seen[new] = true;
+        }
+    }
+}
+
+#[test]
+fn test_permutation_by_key_is_stable() {
+    for keys in inputs() {
+        let mut rows: Vec<Row> = keys
+            .iter()
+            .enumerate()
+            .map(|(id, &key)| Row { key: key % 10, id })
+            .collect();
+        let permutation = tilesort_by_key_with_permutation(&mut rows, |row| row.key);
+
+        assert!(rows
+            .windows(2)
+            .all(|pair| (pair[0].key, pair[0].id) < (pair[1].key, pair[1].id)));
+        for (old, &new) in permutation.iter().enumerate() {
+            assert_eq!(rows[new].id, old);
+        }
+    }
+}
@@ -0,0 +1,270 @@
+// Integration tests for the separate scan and apply phases
+
+use test_log::test;
+
+use tilesort::{
+    find, find_by_key, partition_point, scan, scan_with_config, IdentityKey, InvalidRuns, Order,
+    PlanStats, SortConfig, SortPlan, StalePlan,
+};
+
+#[test]
+fn test_plan_stats() {
+    let data = vec![1, 2, 3, 4, 10, 11, 5, 6];
+    let plan = scan(&data, |&x| x, Order::Ascending);
+    assert_eq!(
+        plan.stats(),
+        PlanStats {
+            elements: 8,
+            tiles: 3,
+            largest_tile: 4
+        }
+    );
+    assert_eq!(
+        plan.sorted_indices().collect::<Vec<_>>(),
+        vec![0, 1, 2, 3, 6, 7, 4, 5]
+    );
+}
+
+#[test]
+fn test_plan_applies_to_parallel_data() {
+    let keys = vec![30, 10, 20];
+    let mut names = vec!["thirty", "ten", "twenty"];
+    let plan = scan(&keys, |&k| k, Order::Descending);
+    plan.apply(&mut names);
+    assert_eq!(names, vec!["thirty", "twenty", "ten"]);
+}
+
+#[test]
+fn test_plan_honours_config() {
+    let data = vec![(2, 'a'), (1, 'b'), (2, 'c')];
+    let plan = scan_with_config(&data, |&(k, _)| k, &SortConfig::new().indirect(true));
+    let mut sorted = data.clone();
+    plan.apply(&mut sorted);
+    assert_eq!(sorted, vec![(1, 'b'), (2, 'a'), (2, 'c')]);
+}
+
+#[test]
+fn test_plan_empty() {
+    let data: Vec<i32> = vec![];
+    let plan = scan(&data, |&x| x, Order::Ascending);
+    assert!(plan.is_empty());
+    assert_eq!(plan.stats().tiles, 0);
+    plan.apply(&mut Vec::<i32>::new());
+}
+
+#[test]
+#[should_panic(expected = "different length")]
+fn test_plan_length_mismatch() {
+    let plan = scan(&[2, 1], |&x| x, Order::Ascending);
+    plan.apply(&mut [1, 2, 3]);
+}
+
+#[test]
+fn test_extend_matches_full_scan() {
+    let mut data: Vec<(u32, usize)> = (0..2_000).map(|i| ((i * 7) % 500, i as usize)).collect();
+    let mut plan = scan(&data[..1_200], |&(k, _)| k, Order::Ascending);
+    plan.extend(&data, |&(k, _)| k).unwrap();
+    assert_eq!(plan.len(), 2_000);
+
+    let full = scan(&data, |&(k, _)| k, Order::Ascending);
+    let mut expected = data.clone();
+    full.apply(&mut expected);
+    plan.apply(&mut data);
+    assert_eq!(data, expected);
+}
+
+#[test]
+fn test_extend_rejects_changed_prefix() {
+    let mut data = vec![1, 2, 3, 0];
+    let mut plan = scan(&data, |&x| x, Order::Ascending);
+    data[1] = 9;
+    data.push(4);
+    assert_eq!(plan.extend(&data, |&x| x), Err(StalePlan));
+    assert_eq!(plan.len(), 4);
+
+    assert_eq!(plan.extend(&data[..2], |&x| x), Err(StalePlan));
+}
+
+#[test]
+fn test_tile_index_accessors() {
+    let data: Vec<u32> = (0..1_000).map(|i| (i * 37) % 1_000).collect();
+    let plan = scan(&data, |&x| x, Order::Ascending);
+    let index = plan.tile_index();
+    assert_eq!(index.len(), plan.stats().tiles);
+    assert!(!index.is_empty());
+
+    // Tiles cover the data exactly once, and are visited in key order
+    let mut covered = vec![false; data.len()];
+    let mut previous_max = None;
+    for tile in index.iter() {
+        assert!(!tile.is_empty());
+        assert_eq!(tile.range(), tile.start()..tile.start() + tile.len());
+        for i in tile.range() {
+            assert!(!covered[i]);
+            covered[i] = true;
+        }
+        let keys = tile.key_range(&data, IdentityKey);
+        assert!(data[tile.range()].windows(2).all(|w| w[0] <= w[1]));
+        assert!(previous_max <= Some(*keys.start()));
+        previous_max = Some(*keys.end());
+    }
+    assert!(covered.into_iter().all(|c| c));
+
+    let empty = scan(&[] as &[u32], |&x| x, Order::Ascending);
+    assert!(empty.tile_index().is_empty());
+    assert_eq!(empty.tile_index().iter().count(), 0);
+}
+
+/// Input position of the first element with `key` once sorted stably.
+fn first_sorted_position(keys: &[u32], key: u32, descending: bool) -> Option<usize> {
+    let mut positions: Vec<usize> = (0..keys.len()).collect();
+    if descending {
+        positions.sort_by(|&a, &b| keys[b].cmp(&keys[a]));
+    } else {
+        positions.sort_by_key(|&i| keys[i]);
+    }
+    positions.into_iter().find(|&i| keys[i] == key)
+}
+
+#[test]
+fn test_find_matches_sorted_order() {
+    // Overlapping runs with repeated keys, so tiles get split
+    let data: Vec<u32> = (0..600u32).map(|i| (i % 150) * 2 + i / 150 % 2).collect();
+    for order in [Order::Ascending, Order::Descending] {
+        let plan = scan(&data, |&x| x, order);
+        assert!(plan.stats().tiles > 1);
+        for key in 0..310 {
+            let expected = first_sorted_position(&data, key, order == Order::Descending);
+            assert_eq!(find(&data, &plan, &key), expected, "{:?} {}", order, key);
+        }
+    }
+}
+
+#[test]
+fn test_find_by_key() {
+    let records: Vec<(u32, usize)> = (0..500).map(|i| ((i * 7) % 200, i as usize)).collect();
+    let keys: Vec<u32> = records.iter().map(|&(k, _)| k).collect();
+    let plan = scan(&records, |&(k, _)| k, Order::Ascending);
+    for key in 0..210 {
+        assert_eq!(
+            find_by_key(&records, &plan, &key, |&(k, _)| k),
+            first_sorted_position(&keys, key, false)
+        );
+    }
+
+    let empty: [u32; 0] = [];
+    assert_eq!(
+        find(&empty, &scan(&empty, |&x| x, Order::Ascending), &1),
+        None
+    );
+}
+
+#[test]
+#[should_panic(expected = "different length")]
+fn test_find_length_mismatch() {
+    let plan = scan(&[2, 1], |&x| x, Order::Ascending);
+    find(&[1, 2, 3], &plan, &1);
+}
+
+#[test]
+fn test_partition_point_matches_sorted_order() {
+    // Scattered keys give thousands of tiles, spread over several buckets
+    let data: Vec<u32> = (0..5_000u32).map(|i| i * 7_919 % 1_000).collect();
+    for order in [Order::Ascending, Order::Descending] {
+        let plan = scan(&data, |&x| x, order);
+        assert!(plan.stats().tiles > 1_000);
+        let mut sorted = data.clone();
+        sorted.sort();
+        if order == Order::Descending {
+            sorted.reverse();
+        }
+        for bound in (0..=1_010).step_by(7) {
+            let pred = |&x: &u32| {
+                if order == Order::Descending {
+                    x > bound
+                } else {
+                    x < bound
+                }
+            };
+            assert_eq!(
+                partition_point(&data, &plan, pred),
+                sorted.partition_point(pred),
+                "{:?} {}",
+                order,
+                bound
+            );
+        }
+    }
+
+    let empty: [u32; 0] = [];
+    let plan = scan(&empty, |&x| x, Order::Ascending);
+    assert_eq!(partition_point(&empty, &plan, |&x| x < 1), 0);
+}
+
+#[test]
+fn test_from_runs_matches_scan() {
+    // Sorted shards of different lengths, with keys shared between shards
+    let mut data: Vec<(u32, usize)> = Vec::new();
+    let mut runs = Vec::new();
+    for shard in 0..20u32 {
+        let start = data.len();
+        let len = (shard as usize * 37) % 90 + 1;
+        data.extend((0..len as u32).map(|i| ((i * 3 + shard) % 200, 0)));
+        data[start..].sort_by_key(|&(k, _)| k);
+        runs.push(start..data.len());
+    }
+    for (i, element) in data.iter_mut().enumerate() {
+        element.1 = i;
+    }
+
+    for config in [SortConfig::new(), SortConfig::new().descending()] {
+        if config.order == Order::Descending {
+            for run in &runs {
+                data[run.clone()].reverse();
+            }
+        }
+        let plan = SortPlan::from_runs(&data, &runs, |&(k, _)| k, &config).unwrap();
+        let unchecked = SortPlan::from_runs_unchecked(&data, &runs, |&(k, _)| k, &config);
+        let scanned = scan_with_config(&data, |&(k, _)| k, &config);
+
+        let mut expected = data.clone();
+        scanned.apply(&mut expected);
+        for plan in [plan, unchecked] {
+            let mut sorted = data.clone();
+            plan.apply(&mut sorted);
+            assert_eq!(sorted, expected);
+        }
+    }
+}
+
+#[test]
+fn test_from_runs_rejects_bad_runs() {
+    let data = [1, 2, 3, 0, 5];
+    let config = SortConfig::new();
+    let from_runs = |runs: &[std::ops::Range<usize>]| {
+        SortPlan::from_runs(&data, runs, |&x| x, &config).map(|plan| plan.stats().tiles)
+    };
+    assert_eq!(from_runs(&[0..3, 3..5]), Ok(3));
+    assert_eq!(from_runs(&[0..3, 3..3, 3..5]), Ok(3));
+    assert_eq!(
+        from_runs(&[0..3, 4..5]),
+        Err(InvalidRuns::Misaligned { run: 1 })
+    );
+    assert_eq!(
+        from_runs(&[0..3, 3..6]),
+        Err(InvalidRuns::Misaligned { run: 1 })
+    );
+    assert_eq!(from_runs(&[0..2, 2..3]), Err(InvalidRuns::Incomplete));
+    assert_eq!(
+        from_runs(&[0..4, 4..5]),
+        Err(InvalidRuns::Unsorted { run: 0 })
+    );
+    assert_eq!(
+        InvalidRuns::Unsorted { run: 0 }.to_string(),
+        "run 0 is not sorted"
+    );
+
+    let empty: [u32; 0] = [];
+    let plan = SortPlan::from_runs(&empty, &[], |&x| x, &config).unwrap();
+    assert!(plan.is_empty());
+}
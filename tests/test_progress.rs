@@ -0,0 +1,108 @@
+// Integration tests for progress reporting
+
+use test_log::test;
+
+use tilesort::{
+    tilesort_by_key_with_progress, tilesort_with_progress, Eta, EtaEstimator, Progress, SortConfig,
+};
+
+fn tiled_input() -> Vec<u64> {
+    (0..4u64)
+        .rev()
+        .flat_map(|tile| (tile * 10_000)..(tile * 10_000 + 10_000))
+        .collect()
+}
+
+#[test]
+fn test_progress_phases_in_order() {
+    let mut data = tiled_input();
+    let total = data.len();
+    let mut updates = Vec::new();
+    tilesort_with_progress(&mut data, &SortConfig::new(), |p| updates.push(p));
+
+    assert!(data.windows(2).all(|w| w[0] <= w[1]));
+
+    let split = updates
+        .iter()
+        .position(|p| matches!(p, Progress::Restructuring { .. }))
+        .expect("restructure updates");
+    let (scanning, restructuring) = updates.split_at(split);
+
+    assert_eq!(
+        scanning.first(),
+        Some(&Progress::Scanning {
+            processed: 0,
+            total
+        })
+    );
+    assert_eq!(
+        scanning.last(),
+        Some(&Progress::Scanning {
+            processed: total,
+            total
+        })
+    );
+    assert!(restructuring
+        .iter()
+        .all(|p| matches!(p, Progress::Restructuring { .. })));
+
+    let total_bytes = total * std::mem::size_of::<u64>();
+    assert_eq!(
+        restructuring.last(),
+        Some(&Progress::Restructuring {
+            bytes_copied: total_bytes,
+            total_bytes
+        })
+    );
+}
+
+#[test]
+fn test_progress_is_monotonic() {
+    let mut data = tiled_input();
+    let mut percents = Vec::new();
+    tilesort_by_key_with_progress(
+        &mut data,
+        |&x| std::cmp::Reverse(x),
+        &SortConfig::new().descending(),
+        |p| percents.push((matches!(p, Progress::Scanning { .. }), p.percent())),
+    );
+
+    assert!(data.windows(2).all(|w| w[0] <= w[1]));
+    for pair in percents.windows(2) {
+        if pair[0].0 == pair[1].0 {
+            assert!(pair[0].1 <= pair[1].1, "{:?}", pair);
+        }
+    }
+    // Several intermediate updates for 40k elements
+    assert!(percents.len() > 10);
+}
+
+#[test]
+fn test_trivial_input_reports_nothing() {
+    let mut calls = 0;
+    let mut data = vec![1];
+    tilesort_with_progress(&mut data, &SortConfig::new(), |_| calls += 1);
+    assert_eq!(calls, 0);
+}
+
+#[test]
+fn test_eta_estimator_completes_each_phase() {
+    let mut data = tiled_input();
+    let mut eta = EtaEstimator::new();
+    let mut estimates = Vec::new();
+    tilesort_with_progress(&mut data, &SortConfig::new(), |p| {
+        estimates.push((p, eta.update(&p)));
+    });
+
+    assert!(data.windows(2).all(|w| w[0] <= w[1]));
+    // Both phases end complete, with nothing left to wait for
+    for (progress, estimate) in &estimates {
+        if progress.percent() == 100.0 {
+            assert_eq!(*estimate, Some(Eta(std::time::Duration::ZERO)));
+        }
+    }
+    assert!(matches!(
+        estimates.last(),
+        Some((Progress::Restructuring { .. }, Some(_)))
+    ));
+}
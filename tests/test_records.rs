@@ -0,0 +1,66 @@
+// Integration tests for sorting packed fixed-width byte records
+
+use rand::prelude::*;
+use test_log::test;
+
+use tilesort::{sort_records, Order};
+
+const RECORD_LEN: usize = 12;
+
+/// Records of a big-endian `u32` key, a big-endian `u64` sequence number and
+/// nothing else, with keys in overlapping ascending runs.
+fn records(seed: u64, count: usize) -> Vec<u8> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut buf = Vec::with_capacity(count * RECORD_LEN);
+    let mut key = 0u32;
+    for sequence in 0..count as u64 {
+        if rng.random_range(0..40) == 0 {
+            key = rng.random_range(0..1_000);
+        }
+        key += rng.random_range(0..3);
+        buf.extend_from_slice(&key.to_be_bytes());
+        buf.extend_from_slice(&sequence.to_be_bytes());
+    }
+    buf
+}
+
+fn sorted_by_std(buf: &[u8], order: Order) -> Vec<u8> {
+    let mut chunks: Vec<&[u8]> = buf.chunks_exact(RECORD_LEN).collect();
+    match order {
+        Order::Ascending => chunks.sort_by(|a, b| a[..4].cmp(&b[..4])),
+        Order::Descending => chunks.sort_by(|a, b| b[..4].cmp(&a[..4])),
+    }
+    chunks.concat()
+}
+
+#[test]
+fn test_records_match_std_sort() {
+    for count in [0, 1, 2, 10, 3_000] {
+        for order in [Order::Ascending, Order::Descending] {
+            let mut buf = records(628 + count as u64, count);
+            let expected = sorted_by_std(&buf, order);
+            sort_records(&mut buf, RECORD_LEN, 0..4, order);
+            assert_eq!(buf, expected, "{} {:?}", count, order);
+        }
+    }
+}
+
+#[test]
+fn test_key_in_middle_of_record() {
+    // One payload byte, a two-byte key, then another payload byte
+    let mut buf = vec![b'x', 0, 9, b'1', b'y', 0, 2, b'2', b'z', 0, 5, b'3'];
+    sort_records(&mut buf, 4, 1..3, Order::Ascending);
+    assert_eq!(buf, b"y\x00\x022z\x00\x053x\x00\x091");
+}
+
+#[test]
+#[should_panic(expected = "multiple of the record length")]
+fn test_partial_record() {
+    sort_records(&mut [0; 10], 4, 0..2, Order::Ascending);
+}
+
+#[test]
+#[should_panic(expected = "not within a record")]
+fn test_key_outside_record() {
+    sort_records(&mut [0; 8], 4, 2..6, Order::Ascending);
+}
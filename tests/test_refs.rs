@@ -0,0 +1,81 @@
+// Integration tests for sorting into references without cloning
+
+use rand::prelude::*;
+use test_log::test;
+
+use tilesort::{
+    tilesort_refs, tilesort_refs_by_key, tilesort_refs_by_key_with_config,
+    tilesort_refs_with_config, SortConfig,
+};
+
+/// Neither `Clone` nor `Copy`, so only references can be sorted.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Record {
+    key: u32,
+    id: usize,
+}
+
+fn inputs() -> Vec<Vec<u32>> {
+    let mut rng = StdRng::seed_from_u64(620);
+    vec![
+        vec![],
+        vec![7],
+        vec![3, 1, 2],
+        (0..5_000).collect(),
+        (0..5_000).rev().collect(),
+        (0..5_000).map(|_| rng.random_range(0..100)).collect(),
+        (0..5_000).map(|i| (i % 50) * 100 + i / 50).collect(),
+    ]
+}
+
+#[test]
+fn test_refs_match_sorted_copy() {
+    for keys in inputs() {
+        let mut expected = keys.clone();
+        expected.sort();
+        let sorted: Vec<u32> = tilesort_refs(&keys).into_iter().copied().collect();
+        assert_eq!(sorted, expected);
+
+        expected.reverse();
+        let config = SortConfig::new().descending();
+        let sorted: Vec<u32> = tilesort_refs_with_config(&keys, &config)
+            .into_iter()
+            .copied()
+            .collect();
+        assert_eq!(sorted, expected);
+    }
+}
+
+#[test]
+fn test_refs_by_key_are_stable() {
+    for keys in inputs() {
+        let records: Vec<Record> = keys
+            .iter()
+            .enumerate()
+            .map(|(id, &key)| Record { key: key % 10, id })
+            .collect();
+        let sorted = tilesort_refs_by_key(&records, |record| record.key);
+        assert_eq!(sorted.len(), records.len());
+        assert!(sorted
+            .windows(2)
+            .all(|pair| (pair[0].key, pair[0].id) < (pair[1].key, pair[1].id)));
+
+        let config = SortConfig::new().descending();
+        let sorted = tilesort_refs_by_key_with_config(&records, |record| record.key, &config);
+        assert!(sorted.windows(2).all(|pair| pair[0].key > pair[1].key
+            || (pair[0].key == pair[1].key && pair[0].id < pair[1].id)));
+    }
+}
+
+#[test]
+fn test_refs_point_into_input() {
+    let records: Vec<Record> = [5, 3, 9, 1]
+        .into_iter()
+        .enumerate()
+        .map(|(id, key)| Record { key, id })
+        .collect();
+    let sorted = tilesort_refs(&records);
+    for reference in sorted {
+        assert!(std::ptr::eq(reference, &records[reference.id]));
+    }
+}
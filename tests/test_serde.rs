@@ -0,0 +1,74 @@
+// Integration tests for serializing sort plans (requires the `serde` feature)
+#![cfg(feature = "serde")]
+
+use test_log::test;
+
+use tilesort::{scan, scan_with_config, Order, SortConfig, SortPlan};
+
+#[test]
+fn test_plan_round_trip() {
+    let data: Vec<u32> = (500..1_000).chain(0..500).collect();
+    let plan = scan(&data, |&x| x, Order::Ascending);
+
+    let json = serde_json::to_string(&plan).unwrap();
+    let restored: SortPlan = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.stats(), plan.stats());
+
+    let mut sorted = data.clone();
+    restored.apply(&mut sorted);
+    assert_eq!(sorted, (0..1_000).collect::<Vec<u32>>());
+}
+
+#[test]
+fn test_plan_keeps_config() {
+    let data = vec![1, 3, 2];
+    let config = SortConfig::new().descending().indirect(true);
+    let plan = scan_with_config(&data, |&x| x, &config);
+    let restored: SortPlan = serde_json::from_str(&serde_json::to_string(&plan).unwrap()).unwrap();
+
+    let mut sorted = data.clone();
+    restored.apply(&mut sorted);
+    assert_eq!(sorted, vec![3, 2, 1]);
+}
+
+#[test]
+fn test_config_round_trip() {
+    let config = SortConfig::new().descending().unstable();
+    let json = serde_json::to_string(&config).unwrap();
+    assert_eq!(serde_json::from_str::<SortConfig>(&json).unwrap(), config);
+}
+
+#[test]
+fn test_plan_rejects_bad_coverage() {
+    let plan = scan(&[2, 1, 3], |&x| x, Order::Ascending);
+    let json = serde_json::to_string(&plan).unwrap();
+
+    let truncated = json.replace("\"elements\":3", "\"elements\":4");
+    let error = serde_json::from_str::<SortPlan>(&truncated).unwrap_err();
+    assert!(error.to_string().contains("expected 4"), "{}", error);
+
+    let overlapping = json.replace("\"start_index\":1", "\"start_index\":0");
+    assert!(serde_json::from_str::<SortPlan>(&overlapping).is_err());
+}
+
+#[test]
+fn test_plan_rejects_overflowing_tile() {
+    let plan = scan(&[2, 1, 3], |&x| x, Order::Ascending);
+    let json = serde_json::to_string(&plan).unwrap();
+
+    // Wrapping around, the last tile would end at element 1 and the plan
+    // would claim to cover a one-element slice
+    let huge = json
+        .replace(
+            "{\"start_index\":2,\"count\":1}",
+            &format!("{{\"start_index\":2,\"count\":{}}}", usize::MAX),
+        )
+        .replace("\"elements\":3", "\"elements\":1");
+    assert_ne!(huge, json);
+    let error = serde_json::from_str::<SortPlan>(&huge).unwrap_err();
+    assert!(
+        error.to_string().contains("tile end overflows"),
+        "{}",
+        error
+    );
+}
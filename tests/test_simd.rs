@@ -0,0 +1,61 @@
+// Integration tests for the vectorized scan (requires the `simd` feature)
+#![cfg(feature = "simd")]
+
+use rand::prelude::*;
+use test_log::test;
+
+use tilesort::{tilesort_simd, tilesort_simd_with_config, tilesort_with_config, SortConfig};
+
+#[test]
+fn test_simd_matches_scalar_on_tiles() {
+    let mut rng = StdRng::seed_from_u64(7);
+    let mut data: Vec<i32> = Vec::new();
+    for _ in 0..50 {
+        let start: i32 = rng.random_range(-10_000..10_000);
+        let len = rng.random_range(1..300);
+        data.extend(start..start + len);
+    }
+
+    for config in [SortConfig::new(), SortConfig::new().descending()] {
+        let mut expected = data.clone();
+        tilesort_with_config(&mut expected, &config);
+        let mut actual = data.clone();
+        tilesort_simd_with_config(&mut actual, &config);
+        assert_eq!(actual, expected);
+    }
+}
+
+#[test]
+fn test_simd_random_u64() {
+    let mut rng = StdRng::seed_from_u64(11);
+    let mut data: Vec<u64> = (0..5_000).map(|_| rng.random_range(0..100)).collect();
+    let mut expected = data.clone();
+    expected.sort();
+    tilesort_simd(&mut data);
+    assert_eq!(data, expected);
+}
+
+#[test]
+fn test_simd_float_bits() {
+    // Map floats to integers that order like `f64::total_cmp`
+    fn total_order_bits(x: f64) -> i64 {
+        let bits = x.to_bits() as i64;
+        bits ^ ((((bits >> 63) as u64) >> 1) as i64)
+    }
+
+    let floats = [2.5, 3.5, -1.0, 0.0, 0.25, -7.5];
+    let mut keys: Vec<i64> = floats.iter().map(|&x| total_order_bits(x)).collect();
+    tilesort_simd(&mut keys);
+    let mut expected: Vec<i64> = floats.iter().map(|&x| total_order_bits(x)).collect();
+    expected.sort();
+    assert_eq!(keys, expected);
+}
+
+#[test]
+fn test_simd_small_inputs() {
+    let mut empty: Vec<u8> = vec![];
+    tilesort_simd(&mut empty);
+    let mut single = vec![9u8];
+    tilesort_simd(&mut single);
+    assert_eq!(single, vec![9]);
+}
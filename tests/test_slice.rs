@@ -0,0 +1,92 @@
+// Integration tests for the std-parity slice module
+use std::panic::{self, AssertUnwindSafe};
+
+use rand::prelude::*;
+use test_log::test;
+
+use tilesort::slice;
+
+fn random_pairs(seed: u64) -> Vec<(u8, usize)> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    (0..2_000).map(|i| (rng.random_range(0..32), i)).collect()
+}
+
+#[test]
+fn test_matches_std_stable_sorts() {
+    let data = random_pairs(3);
+
+    let mut expected = data.clone();
+    expected.sort();
+    let mut actual = data.clone();
+    slice::sort(&mut actual);
+    assert_eq!(actual, expected);
+
+    // Ignore the tag, so std's stability decides ties
+    let compare = |a: &(u8, usize), b: &(u8, usize)| (a.0 / 4).cmp(&(b.0 / 4)).then(b.0.cmp(&a.0));
+    let mut expected = data.clone();
+    expected.sort_by(compare);
+    let mut actual = data.clone();
+    slice::sort_by(&mut actual, compare);
+    assert_eq!(actual, expected);
+
+    let mut expected = data.clone();
+    expected.sort_by_key(|&(k, _)| k);
+    let mut actual = data.clone();
+    slice::sort_by_key(&mut actual, |&(k, _)| k);
+    assert_eq!(actual, expected);
+
+    let mut actual = data.clone();
+    slice::sort_by_cached_key(&mut actual, |&(k, _)| k.to_string());
+    let mut expected = data;
+    expected.sort_by_cached_key(|&(k, _)| k.to_string());
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn test_unstable_sorts_order_keys() {
+    let data = random_pairs(5);
+    let mut expected: Vec<u8> = data.iter().map(|&(k, _)| k).collect();
+    expected.sort_unstable();
+
+    let mut actual = data.clone();
+    slice::sort_unstable(&mut actual);
+    let mut sorted = data.clone();
+    sorted.sort();
+    assert_eq!(actual, sorted);
+
+    let mut actual = data.clone();
+    slice::sort_unstable_by(&mut actual, |a, b| a.0.cmp(&b.0));
+    assert!(actual.iter().map(|&(k, _)| k).eq(expected.iter().copied()));
+
+    let mut actual = data;
+    slice::sort_unstable_by_key(&mut actual, |&(k, _)| k);
+    assert!(actual.iter().map(|&(k, _)| k).eq(expected.iter().copied()));
+}
+
+#[test]
+fn test_sorts_strings_without_clone_bound() {
+    let mut words: Vec<String> = ["pear", "fig", "banana", "kiwi"]
+        .iter()
+        .map(|w| w.to_string())
+        .collect();
+    slice::sort_by_key(&mut words, |w| w.len());
+    assert_eq!(words, ["fig", "pear", "kiwi", "banana"]);
+}
+
+#[test]
+fn test_panicking_comparator_leaves_data_unchanged() {
+    let original: Vec<i32> = (0..100).rev().collect();
+    let mut data = original.clone();
+    let mut calls = 0;
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        slice::sort_by(&mut data, |a, b| {
+            calls += 1;
+            if calls == 10 {
+                panic!("comparator failed");
+            }
+            a.cmp(b)
+        });
+    }));
+    assert!(result.is_err());
+    assert_eq!(data, original);
+}
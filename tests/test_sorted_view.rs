@@ -0,0 +1,115 @@
+// Integration tests for searching scanned data through a SortedView
+
+use std::cmp::Reverse;
+
+use rand::prelude::*;
+use test_log::test;
+
+use tilesort::{scan, Order};
+
+/// Ascending runs of random lengths, with many repeated keys.
+fn nearly_sorted(seed: u64, len: usize) -> Vec<(u16, usize)> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut data = Vec::with_capacity(len);
+    while data.len() < len {
+        let run = rng.random_range(1..50);
+        let mut key: u16 = rng.random_range(0..500);
+        for _ in 0..run {
+            data.push((key, data.len()));
+            key = key.saturating_add(rng.random_range(0..3));
+        }
+    }
+    data.truncate(len);
+    data
+}
+
+fn sorted_copy(data: &[(u16, usize)], order: Order) -> Vec<(u16, usize)> {
+    let mut sorted = data.to_vec();
+    match order {
+        Order::Ascending => sorted.sort_by_key(|&(k, _)| k),
+        Order::Descending => sorted.sort_by_key(|&(k, _)| Reverse(k)),
+    }
+    sorted
+}
+
+#[test]
+fn test_view_matches_sorted_copy() {
+    for order in [Order::Ascending, Order::Descending] {
+        let data = nearly_sorted(1, 3_000);
+        let plan = scan(&data, |&(k, _)| k, order);
+        let view = plan.view(&data);
+        let sorted = sorted_copy(&data, order);
+
+        assert_eq!(view.len(), data.len());
+        assert!(view.iter().eq(sorted.iter()));
+        for n in [0, 1, 999, 2_999] {
+            assert_eq!(view.get(n), Some(&sorted[n]));
+            assert_eq!(view.input_index(n).map(|i| &data[i]), Some(&sorted[n]));
+        }
+        assert_eq!(view.get(3_000), None);
+    }
+}
+
+#[test]
+fn test_partition_point_and_binary_search() {
+    let data = nearly_sorted(2, 2_000);
+    let plan = scan(&data, |&(k, _)| k, Order::Ascending);
+    let view = plan.view(&data);
+    let sorted = sorted_copy(&data, Order::Ascending);
+
+    for key in 0..600 {
+        let expected = sorted.partition_point(|&(k, _)| k < key);
+        assert_eq!(view.partition_point(|&(k, _)| k < key), expected);
+
+        let found = view.binary_search_by_key(&key, |&(k, _)| k);
+        if sorted.get(expected).map(|&(k, _)| k) == Some(key) {
+            assert_eq!(found, Ok(expected));
+        } else {
+            assert_eq!(found, Err(expected));
+        }
+    }
+}
+
+#[test]
+fn test_range() {
+    for order in [Order::Ascending, Order::Descending] {
+        let data = nearly_sorted(3, 2_000);
+        let plan = scan(&data, |&(k, _)| k, order);
+        let view = plan.view(&data);
+        let sorted = sorted_copy(&data, order);
+        let key = |&(k, _): &(u16, usize)| k;
+
+        let expected: Vec<_> = sorted
+            .iter()
+            .filter(|e| (100..200).contains(&e.0))
+            .collect();
+        let found: Vec<_> = view.range(100..200, key).collect();
+        assert_eq!(found, expected, "{:?}", order);
+
+        let expected: Vec<_> = sorted.iter().filter(|e| e.0 >= 450).collect();
+        assert_eq!(view.range(450.., key).collect::<Vec<_>>(), expected);
+        assert_eq!(view.range(.., key).count(), data.len());
+        #[allow(clippy::reversed_empty_ranges)]
+        let inverted = 300..100;
+        assert_eq!(view.range(inverted, key).count(), 0);
+    }
+}
+
+#[test]
+fn test_empty_view() {
+    let empty: [u32; 0] = [];
+    let plan = scan(&empty, |&x| x, Order::Ascending);
+    let view = plan.view(&empty);
+    assert!(view.is_empty());
+    assert_eq!(view.iter().count(), 0);
+    assert_eq!(view.partition_point(|&x| x < 3), 0);
+    assert_eq!(view.binary_search_by_key(&3, |&x| x), Err(0));
+    assert_eq!(view.range(1..5, |&x| x).count(), 0);
+}
+
+#[test]
+#[should_panic(expected = "different length")]
+fn test_view_length_mismatch() {
+    let plan = scan(&[2, 1], |&x| x, Order::Ascending);
+    plan.view(&[1, 2, 3]);
+}
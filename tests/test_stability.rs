@@ -0,0 +1,133 @@
+// Stability tests: elements with equal keys keep their original relative order
+
+use rand::prelude::*;
+use test_log::test;
+
+use tilesort::{
+    tilesort, tilesort_by_key, tilesort_by_key_reverse, tilesort_with_config, SortConfig,
+};
+
+/// Record with a sort key and its position in the input.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Record {
+    key: u32,
+    position: usize,
+}
+
+/// Generate `tiles` ascending runs of keys drawn from `0..distinct`.
+fn tiled_records(rng: &mut StdRng, tiles: usize, distinct: u32) -> Vec<Record> {
+    let mut keys = Vec::new();
+    for _ in 0..tiles {
+        let len = rng.random_range(1..20);
+        let mut run: Vec<u32> = (0..len).map(|_| rng.random_range(0..distinct)).collect();
+        run.sort();
+        keys.extend(run);
+    }
+    keys.into_iter()
+        .enumerate()
+        .map(|(position, key)| Record { key, position })
+        .collect()
+}
+
+#[test]
+fn test_equal_keys_in_split_tiles() {
+    // The second tile's 2s sort after the first tile's 2s, and its 5s after the 5s
+    let mut data = vec![(1, 'a'), (2, 'b'), (5, 'c'), (2, 'd'), (5, 'e'), (5, 'f')];
+    tilesort_by_key(&mut data, |&(key, _)| key);
+    assert_eq!(
+        data,
+        vec![(1, 'a'), (2, 'b'), (2, 'd'), (5, 'c'), (5, 'e'), (5, 'f')]
+    );
+}
+
+#[test]
+fn test_equal_keys_descending() {
+    let mut data = vec![(5, 'a'), (2, 'b'), (5, 'c'), (2, 'd'), (1, 'e')];
+    tilesort_by_key_reverse(&mut data, |&(key, _)| key);
+    assert_eq!(data, vec![(5, 'a'), (5, 'c'), (2, 'b'), (2, 'd'), (1, 'e')]);
+}
+
+#[test]
+fn test_all_keys_equal() {
+    let mut data: Vec<(u8, usize)> = (0..10).map(|i| (7, i)).collect();
+    let expected = data.clone();
+    tilesort_by_key(&mut data, |&(key, _)| key);
+    assert_eq!(data, expected);
+}
+
+#[test]
+fn test_duplicate_values() {
+    let mut data = vec![3, 3, 5, 1, 3, 3, 5, 2, 3];
+    tilesort(&mut data);
+    assert_eq!(data, vec![1, 2, 3, 3, 3, 3, 3, 5, 5]);
+}
+
+#[test]
+fn test_stable_with_heavy_duplicates() {
+    let mut rng = StdRng::seed_from_u64(0x5eed);
+    for distinct in [1, 2, 3, 5, 10] {
+        for _ in 0..500 {
+            let tiles = rng.random_range(1..12);
+            let mut data = tiled_records(&mut rng, tiles, distinct);
+            let mut expected = data.clone();
+            expected.sort_by_key(|r| r.key);
+
+            tilesort_by_key(&mut data, |r| r.key);
+            assert_eq!(data, expected);
+        }
+    }
+}
+
+#[test]
+fn test_stable_descending_with_heavy_duplicates() {
+    let mut rng = StdRng::seed_from_u64(0xdec);
+    for distinct in [2, 4, 8] {
+        for _ in 0..500 {
+            let tiles = rng.random_range(1..12);
+            let mut data = tiled_records(&mut rng, tiles, distinct);
+            // Turn the ascending runs into descending ones
+            for record in &mut data {
+                record.key = distinct - record.key;
+            }
+            let mut expected = data.clone();
+            expected.sort_by_key(|r| std::cmp::Reverse(r.key));
+
+            tilesort_by_key_reverse(&mut data, |r| r.key);
+            assert_eq!(data, expected);
+        }
+    }
+}
+
+#[test]
+fn test_random_small_inputs_match_std() {
+    let mut rng = StdRng::seed_from_u64(42);
+    for _ in 0..5_000 {
+        let len = rng.random_range(0..40);
+        let data: Vec<u8> = (0..len).map(|_| rng.random_range(0..6)).collect();
+        let mut expected = data.clone();
+        expected.sort();
+
+        let mut actual = data.clone();
+        tilesort_with_config(&mut actual, &SortConfig::new());
+        assert_eq!(actual, expected, "input: {:?}", data);
+    }
+}
+
+#[test]
+fn test_stable_with_fragmented_index() {
+    // Interleaved runs put every element in its own tile, spreading the
+    // index over many storage buckets
+    let n = 20_000;
+    let mut data: Vec<(u32, usize)> = (0..n)
+        .map(|i| (i / 2, 0))
+        .chain((0..n).map(|i| (i / 2, 0)))
+        .collect();
+    for (idx, entry) in data.iter_mut().enumerate() {
+        entry.1 = idx;
+    }
+
+    let mut expected = data.clone();
+    expected.sort_by_key(|&(key, _)| key);
+    tilesort_by_key(&mut data, |&(key, _)| key);
+    assert_eq!(data, expected);
+}
@@ -0,0 +1,53 @@
+// Integration tests for the incremental StreamSorter
+use rand::prelude::*;
+use test_log::test;
+
+use tilesort::{tilesort_by_key_with_config, IdentityKey, SortConfig, StreamSorter};
+
+#[test]
+fn test_stream_matches_slice_sort() {
+    let mut rng = StdRng::seed_from_u64(17);
+    let mut times: Vec<u32> = Vec::new();
+    for _ in 0..40 {
+        let start = rng.random_range(0..1_000);
+        let len = rng.random_range(1..100);
+        times.extend((start..start + len).map(|t| t / 3));
+    }
+    // Tag each event with its arrival position to check stability
+    let data: Vec<(u32, usize)> = times.into_iter().zip(0..).collect();
+
+    for config in [
+        SortConfig::new(),
+        SortConfig::new().descending(),
+        SortConfig::new().unstable(),
+    ] {
+        let mut sorter = StreamSorter::with_config(|&(t, _): &(u32, usize)| t, config.clone());
+        for &event in &data {
+            assert!(sorter.push(event).is_none());
+        }
+        let mut expected = data.clone();
+        tilesort_by_key_with_config(&mut expected, |&(t, _)| t, &config);
+        assert_eq!(sorter.finish(), expected);
+    }
+}
+
+#[test]
+fn test_stream_windows_are_bounded_and_sorted() {
+    let data: Vec<i32> = (0..1_000).rev().collect();
+    let mut sorter = StreamSorter::new(IdentityKey).window(64);
+    let mut batches = Vec::new();
+    for &x in &data {
+        if let Some(batch) = sorter.push(x) {
+            batches.push(batch);
+        }
+        assert!(sorter.len() < 64);
+    }
+    batches.push(sorter.finish());
+
+    assert_eq!(batches.len(), 16);
+    for (batch, chunk) in batches.iter().zip(data.chunks(64)) {
+        let mut expected = chunk.to_vec();
+        expected.sort();
+        assert_eq!(batch, &expected);
+    }
+}
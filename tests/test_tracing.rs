@@ -0,0 +1,133 @@
+// Integration tests for the `tracing` spans (requires the `tracing` feature)
+#![cfg(feature = "tracing")]
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+use tracing_core::span::Current;
+
+use tilesort::{tilesort_with_config, SortConfig};
+
+/// Sort through the tile index even though the inputs are tiny.
+fn tile_index_sort(data: &mut [u32]) {
+    tilesort_with_config(data, &SortConfig::new().insertion_sort_threshold(0));
+}
+
+type Fields = HashMap<&'static str, u64>;
+
+/// Minimal single-threaded subscriber that records every span with its fields.
+#[derive(Default)]
+struct SpanRecorder {
+    spans: Mutex<Vec<(&'static Metadata<'static>, Fields)>>,
+    entered: Mutex<Vec<Id>>,
+}
+
+impl SpanRecorder {
+    fn spans(&self) -> Vec<(&'static str, Fields)> {
+        let spans = self.spans.lock().unwrap();
+        spans
+            .iter()
+            .map(|(metadata, fields)| (metadata.name(), fields.clone()))
+            .collect()
+    }
+}
+
+struct FieldVisitor<'a>(&'a mut Fields);
+
+impl Visit for FieldVisitor<'_> {
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name(), value);
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name(), value as u64);
+    }
+
+    fn record_debug(&mut self, _field: &Field, _value: &dyn std::fmt::Debug) {}
+}
+
+impl Subscriber for SpanRecorder {
+    fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let mut fields = HashMap::new();
+        span.record(&mut FieldVisitor(&mut fields));
+        let mut spans = self.spans.lock().unwrap();
+        spans.push((span.metadata(), fields));
+        Id::from_u64(spans.len() as u64)
+    }
+
+    fn record(&self, span: &Id, values: &Record<'_>) {
+        let mut spans = self.spans.lock().unwrap();
+        let (_, fields) = &mut spans[span.into_u64() as usize - 1];
+        values.record(&mut FieldVisitor(fields));
+    }
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, _event: &Event<'_>) {}
+
+    fn enter(&self, span: &Id) {
+        self.entered.lock().unwrap().push(span.clone());
+    }
+
+    fn exit(&self, _span: &Id) {
+        self.entered.lock().unwrap().pop();
+    }
+
+    fn current_span(&self) -> Current {
+        match self.entered.lock().unwrap().last() {
+            Some(id) => {
+                let metadata = self.spans.lock().unwrap()[id.into_u64() as usize - 1].0;
+                Current::new(id.clone(), metadata)
+            }
+            None => Current::none(),
+        }
+    }
+}
+
+#[test]
+fn test_phase_spans() {
+    let recorder = Arc::new(SpanRecorder::default());
+    let mut data = vec![7, 8, 9, 1, 2, 3, 4, 5, 6];
+
+    tracing::subscriber::with_default(recorder.clone(), || tile_index_sort(&mut data));
+    assert_eq!(data, vec![1, 2, 3, 4, 5, 6, 7, 8, 9]);
+
+    let spans = recorder.spans();
+    let names: Vec<&str> = spans.iter().map(|(name, _)| *name).collect();
+    assert_eq!(names.first(), Some(&"scan_phase"));
+    assert_eq!(names.last(), Some(&"restructure_phase"));
+    assert_eq!(names.iter().filter(|&&n| n == "insert_tile").count(), 2);
+
+    let (_, scan) = &spans[0];
+    assert_eq!(scan["elements"], 9);
+    assert_eq!(scan["reverse"], 0);
+    assert_eq!(scan["tiles"], 2);
+
+    let (_, restructure) = spans.last().unwrap();
+    assert_eq!(restructure["tiles"], 2);
+}
+
+#[test]
+fn test_insert_tile_split_depth() {
+    let recorder = Arc::new(SpanRecorder::default());
+    // The second tile straddles the first and has to be split
+    let mut data = vec![3, 4, 5, 1, 2, 6, 7];
+
+    tracing::subscriber::with_default(recorder.clone(), || tile_index_sort(&mut data));
+    assert_eq!(data, vec![1, 2, 3, 4, 5, 6, 7]);
+
+    let spans = recorder.spans();
+    let depths: Vec<u64> = spans
+        .iter()
+        .filter(|(name, _)| *name == "insert_tile")
+        .map(|(_, fields)| fields["depth"])
+        .collect();
+    assert_eq!(depths, vec![0, 0, 1]);
+}
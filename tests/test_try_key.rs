@@ -0,0 +1,64 @@
+// Integration tests for fallible key extraction
+
+use std::num::ParseIntError;
+
+use test_log::test;
+
+use tilesort::{
+    try_tilesort_by_key, try_tilesort_by_key_with_config, try_tilesorted_by_key, SortConfig,
+};
+
+fn parse_field(line: &&str) -> Result<u32, ParseIntError> {
+    line.split(',').nth(1).unwrap_or("").parse()
+}
+
+#[test]
+fn test_try_sort_success() {
+    let mut data = vec!["c,3", "d,4", "e,5", "a,1", "b,2"];
+    try_tilesort_by_key(&mut data, parse_field).unwrap();
+    assert_eq!(data, vec!["a,1", "b,2", "c,3", "d,4", "e,5"]);
+}
+
+#[test]
+fn test_try_sort_error_leaves_input_unchanged() {
+    let original = vec!["c,3", "d,4", "e,oops", "a,1", "b,2"];
+    let mut data = original.clone();
+    let result = try_tilesort_by_key(&mut data, parse_field);
+    assert!(result.is_err());
+    assert_eq!(data, original);
+}
+
+#[test]
+fn test_try_sort_reports_first_error() {
+    let mut data = vec!["a,1", "b,x", "c,"];
+    let err = try_tilesort_by_key(&mut data, |line: &&str| {
+        parse_field(line).map_err(|_| line.to_string())
+    })
+    .unwrap_err();
+    assert_eq!(err, "b,x");
+}
+
+#[test]
+fn test_try_sort_single_invalid_element() {
+    let mut data = vec!["nope"];
+    assert!(try_tilesort_by_key(&mut data, parse_field).is_err());
+
+    let mut empty: Vec<&str> = vec![];
+    assert!(try_tilesort_by_key(&mut empty, parse_field).is_ok());
+}
+
+#[test]
+fn test_try_sort_with_config_descending() {
+    let mut data = vec!["c,3", "d,4", "a,1", "b,2"];
+    try_tilesort_by_key_with_config(&mut data, parse_field, &SortConfig::new().descending())
+        .unwrap();
+    assert_eq!(data, vec!["d,4", "c,3", "b,2", "a,1"]);
+}
+
+#[test]
+fn test_try_sorted_copy() {
+    let data = vec!["c,3", "a,1", "b,2"];
+    let sorted = try_tilesorted_by_key(&data, parse_field).unwrap();
+    assert_eq!(sorted, vec!["a,1", "b,2", "c,3"]);
+    assert_eq!(data, vec!["c,3", "a,1", "b,2"]);
+}
@@ -0,0 +1,88 @@
+// Integration tests for the unstable sort variants
+
+use rand::prelude::*;
+use test_log::test;
+
+use tilesort::{
+    tilesort_by_key_with_config, tilesort_unstable, tilesort_unstable_by_key, SortConfig,
+};
+
+#[test]
+fn test_unstable_basic() {
+    let mut data = vec![3, 4, 5, 1, 2];
+    tilesort_unstable(&mut data);
+    assert_eq!(data, vec![1, 2, 3, 4, 5]);
+}
+
+#[test]
+fn test_unstable_by_key_sorts_keys() {
+    let mut data = vec![(2, 'a'), (2, 'b'), (3, 'c'), (1, 'd'), (2, 'e')];
+    tilesort_unstable_by_key(&mut data, |&(key, _)| key);
+
+    let keys: Vec<i32> = data.iter().map(|&(key, _)| key).collect();
+    assert_eq!(keys, vec![1, 2, 2, 2, 3]);
+
+    let mut tags: Vec<char> = data.iter().map(|&(_, tag)| tag).collect();
+    tags.sort();
+    assert_eq!(tags, vec!['a', 'b', 'c', 'd', 'e']);
+}
+
+#[test]
+fn test_unstable_with_heavy_duplicates() {
+    let mut rng = StdRng::seed_from_u64(0xa11);
+    for reverse in [false, true] {
+        let config = SortConfig::new().unstable();
+        let config = if reverse { config.descending() } else { config };
+
+        for _ in 0..2_000 {
+            let len = rng.random_range(0..60);
+            let data: Vec<(u8, usize)> = (0..len).map(|i| (rng.random_range(0..4), i)).collect();
+
+            let mut actual = data.clone();
+            tilesort_by_key_with_config(&mut actual, |&(key, _)| key, &config);
+
+            // Keys come out in order...
+            let mut expected_keys: Vec<u8> = data.iter().map(|&(key, _)| key).collect();
+            expected_keys.sort();
+            if reverse {
+                expected_keys.reverse();
+            }
+            let keys: Vec<u8> = actual.iter().map(|&(key, _)| key).collect();
+            assert_eq!(keys, expected_keys, "input: {:?}", data);
+
+            // ...and nothing is lost or duplicated
+            actual.sort();
+            let mut expected = data.clone();
+            expected.sort();
+            assert_eq!(actual, expected);
+        }
+    }
+}
+
+#[test]
+fn test_unstable_with_few_distinct_keys_at_scale() {
+    // Long enough for the tile index to hold many buckets of equal keys
+    let mut rng = StdRng::seed_from_u64(0x5747);
+    let codes = [200u16, 200, 200, 200, 200, 301, 404, 500];
+    for reverse in [false, true] {
+        let config = SortConfig::new().unstable().detect_patterns(false);
+        let config = if reverse { config.descending() } else { config };
+        let data: Vec<(u16, usize)> = (0..40_000)
+            .map(|i| (codes[rng.random_range(0..codes.len())], i))
+            .collect();
+
+        let mut sorted = data.clone();
+        tilesort_by_key_with_config(&mut sorted, |&(code, _)| code, &config);
+
+        let mut expected = data;
+        expected.sort_unstable_by_key(|&(code, _)| code);
+        if reverse {
+            expected.reverse();
+        }
+        let keys = |v: &[(u16, usize)]| v.iter().map(|&(code, _)| code).collect::<Vec<_>>();
+        assert_eq!(keys(&sorted), keys(&expected));
+        sorted.sort_unstable();
+        expected.sort_unstable();
+        assert_eq!(sorted, expected);
+    }
+}
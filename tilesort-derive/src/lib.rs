@@ -0,0 +1,157 @@
+//! Derive macro for tilesort's `TileKey` trait.
+//!
+//! This crate is re-exported by `tilesort` when its `derive` feature is
+//! enabled; depend on `tilesort` rather than on this crate directly.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, ToTokens};
+use syn::spanned::Spanned;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Index, LitInt, Member};
+
+/// Derive `tilesort::TileKey` from annotated fields.
+///
+/// Each field marked with `#[tile_key]` contributes one level of the key.
+/// Supported options:
+///
+/// * `order = N` - priority of the field (lower sorts first). Fields with an
+///   explicit order come before fields without one, which keep their
+///   declaration order.
+/// * `desc` - sort this field in descending order.
+///
+/// Key fields must implement `Ord + Clone`.
+#[proc_macro_derive(TileKey, attributes(tile_key))]
+pub fn derive_tile_key(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// A field participating in the generated key.
+struct KeyField {
+    member: Member,
+    ty: syn::Type,
+    order: Option<u64>,
+    desc: bool,
+    position: usize,
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => &data.fields,
+        _ => {
+            return Err(syn::Error::new(
+                input.span(),
+                "TileKey can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut key_fields = collect_key_fields(fields)?;
+    if key_fields.is_empty() {
+        return Err(syn::Error::new(
+            input.span(),
+            "TileKey requires at least one field marked with #[tile_key]",
+        ));
+    }
+    check_unique_orders(&key_fields)?;
+    key_fields.sort_by_key(|field| (field.order.is_none(), field.order, field.position));
+
+    let mut key_types = Vec::new();
+    let mut key_values = Vec::new();
+    for field in &key_fields {
+        let member = &field.member;
+        let ty = &field.ty;
+        if field.desc {
+            key_types.push(quote!(::std::cmp::Reverse<#ty>));
+            key_values.push(quote!(::std::cmp::Reverse(::std::clone::Clone::clone(&self.#member))));
+        } else {
+            key_types.push(ty.to_token_stream());
+            key_values.push(quote!(::std::clone::Clone::clone(&self.#member)));
+        }
+    }
+
+    let name = &input.ident;
+    let mut generics = input.generics.clone();
+    {
+        let where_clause = generics.make_where_clause();
+        for field in &key_fields {
+            let ty = &field.ty;
+            where_clause
+                .predicates
+                .push(syn::parse_quote!(#ty: ::std::cmp::Ord + ::std::clone::Clone));
+        }
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::tilesort::TileKey for #name #ty_generics #where_clause {
+            type Key = (#(#key_types,)*);
+
+            fn tile_key(&self) -> Self::Key {
+                (#(#key_values,)*)
+            }
+        }
+    })
+}
+
+fn collect_key_fields(fields: &Fields) -> syn::Result<Vec<KeyField>> {
+    let mut key_fields = Vec::new();
+
+    for (position, field) in fields.iter().enumerate() {
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("tile_key")) {
+            let mut order = None;
+            let mut desc = false;
+
+            if !matches!(attr.meta, syn::Meta::Path(_)) {
+                attr.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("desc") {
+                        desc = true;
+                        Ok(())
+                    } else if meta.path.is_ident("asc") {
+                        desc = false;
+                        Ok(())
+                    } else if meta.path.is_ident("order") {
+                        let value: LitInt = meta.value()?.parse()?;
+                        order = Some(value.base10_parse()?);
+                        Ok(())
+                    } else {
+                        Err(meta.error("expected `order = N`, `asc` or `desc`"))
+                    }
+                })?;
+            }
+
+            let member = match &field.ident {
+                Some(ident) => Member::Named(ident.clone()),
+                None => Member::Unnamed(Index::from(position)),
+            };
+
+            key_fields.push(KeyField {
+                member,
+                ty: field.ty.clone(),
+                order,
+                desc,
+                position,
+            });
+        }
+    }
+
+    Ok(key_fields)
+}
+
+fn check_unique_orders(key_fields: &[KeyField]) -> syn::Result<()> {
+    for (i, field) in key_fields.iter().enumerate() {
+        let Some(order) = field.order else { continue };
+        if key_fields[..i]
+            .iter()
+            .any(|other| other.order == Some(order))
+        {
+            return Err(syn::Error::new(
+                field.ty.span(),
+                format!("duplicate #[tile_key(order = {})]", order),
+            ));
+        }
+    }
+    Ok(())
+}